@@ -0,0 +1,31 @@
+//! Resolves the network compiled in by the `embedded-net` feature.
+//!
+//! `PIEBOT_EMBED_NET` names a PIENNQ02 file, absolute or relative to this
+//! crate; `eval::nnue::embedded` includes it via `PIEBOT_EMBEDDED_NET`.
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=PIEBOT_EMBED_NET");
+    if std::env::var_os("CARGO_FEATURE_EMBEDDED_NET").is_none() {
+        return;
+    }
+    let raw = std::env::var("PIEBOT_EMBED_NET").unwrap_or_else(|_| {
+        panic!("the embedded-net feature needs PIEBOT_EMBED_NET=<path to a PIENNQ02 net>")
+    });
+    let mut path = PathBuf::from(raw);
+    if path.is_relative() {
+        path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap()).join(path);
+    }
+    let bytes = std::fs::read(&path)
+        .unwrap_or_else(|e| panic!("read embedded net {}: {e}", path.display()));
+    if !bytes.starts_with(b"PIENNQ02") {
+        panic!("embedded net {} is not a PIENNQ02 file", path.display());
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    println!("cargo:rerun-if-changed={}", path.display());
+    println!("cargo:rustc-env=PIEBOT_EMBEDDED_NET={}", path.display());
+    println!("cargo:rustc-env=PIEBOT_EMBEDDED_NET_NAME={name}");
+}
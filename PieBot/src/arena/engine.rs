@@ -0,0 +1,329 @@
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// How to launch one engine, parsed from a comma-separated
+/// `name=..,cmd=..[,dir=..][,arg=..][,option.NAME=VALUE]` spec. `arg` and
+/// `option.*` may repeat; options are sent with `setoption` after `uciok`,
+/// in the order given. The name defaults to the command's file name.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineSpec {
+    pub name: String,
+    pub cmd: String,
+    pub dir: Option<String>,
+    pub args: Vec<String>,
+    pub options: Vec<(String, String)>,
+}
+
+impl std::str::FromStr for EngineSpec {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut cmd = None;
+        let mut dir = None;
+        let mut args = Vec::new();
+        let mut options = Vec::new();
+        for field in raw.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("engine spec {raw:?}: expected key=value, got {field:?}"))?;
+            match key {
+                "name" => name = Some(value.to_string()),
+                "cmd" => cmd = Some(value.to_string()),
+                "dir" => dir = Some(value.to_string()),
+                "arg" => args.push(value.to_string()),
+                _ => match key.strip_prefix("option.") {
+                    Some(option) if !option.is_empty() => {
+                        options.push((option.to_string(), value.to_string()))
+                    }
+                    _ => return Err(format!("engine spec {raw:?}: unknown key {key:?}")),
+                },
+            }
+        }
+        let cmd = cmd
+            .filter(|cmd| !cmd.is_empty())
+            .ok_or_else(|| format!("engine spec {raw:?}: missing cmd="))?;
+        let name = name.unwrap_or_else(|| {
+            std::path::Path::new(&cmd)
+                .file_name()
+                .map(|f| f.to_string_lossy().into_owned())
+                .unwrap_or_else(|| cmd.clone())
+        });
+        Ok(Self {
+            name,
+            cmd,
+            dir,
+            args,
+            options,
+        })
+    }
+}
+
+/// Why an engine failed to answer. Either way the process is no longer
+/// usable and must be restarted before its next game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineFault {
+    /// The process exited or closed its pipes.
+    Crashed,
+    /// No answer before the deadline.
+    TimedOut,
+}
+
+/// Score from an `info` line, from the engine's (side to move's) view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineScore {
+    Cp(i32),
+    /// Mate in this many moves; negative when the engine is being mated.
+    Mate(i32),
+}
+
+/// Answer to one `go`: the move as sent, plus the last score and depth the
+/// engine reported before it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchReply {
+    pub bestmove: String,
+    pub score: Option<EngineScore>,
+    pub depth: Option<u32>,
+}
+
+/// A running UCI engine. Its stdout is drained by a reader thread, so every
+/// wait can carry a deadline; the process is killed when this is dropped.
+pub struct UciEngine {
+    spec: EngineSpec,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    id_name: Option<String>,
+}
+
+impl UciEngine {
+    /// Spawn the engine, complete the `uci`/`isready` handshake and apply the
+    /// spec's options, all within `timeout`.
+    pub fn start(spec: &EngineSpec, timeout: Duration) -> Result<Self, String> {
+        let mut command = Command::new(&spec.cmd);
+        command
+            .args(&spec.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        if let Some(dir) = &spec.dir {
+            command.current_dir(dir);
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("engine {}: failed to start {:?}: {e}", spec.name, spec.cmd))?;
+        let stdin = child.stdin.take().expect("piped stdin");
+        let stdout = child.stdout.take().expect("piped stdout");
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = Self {
+            spec: spec.clone(),
+            child,
+            stdin,
+            lines,
+            id_name: None,
+        };
+        let deadline = Instant::now() + timeout;
+        let describe = |what: &str, fault: EngineFault| {
+            format!("engine {}: {fault:?} waiting for {what}", spec.name)
+        };
+        engine
+            .send("uci")
+            .map_err(|fault| describe("uciok", fault))?;
+        let mut id_name = None;
+        engine
+            .wait_for(deadline, |line| {
+                if let Some(name) = line.strip_prefix("id name ") {
+                    id_name = Some(name.trim().to_string());
+                }
+                line == "uciok"
+            })
+            .map_err(|fault| describe("uciok", fault))?;
+        engine.id_name = id_name;
+        for (name, value) in &spec.options {
+            engine
+                .send(&format!("setoption name {name} value {value}"))
+                .map_err(|fault| describe("setoption", fault))?;
+        }
+        engine
+            .sync(deadline)
+            .map_err(|fault| describe("readyok", fault))?;
+        Ok(engine)
+    }
+
+    pub fn spec(&self) -> &EngineSpec {
+        &self.spec
+    }
+
+    /// Name the engine reported with `id name`, if any.
+    pub fn id_name(&self) -> Option<&str> {
+        self.id_name.as_deref()
+    }
+
+    /// `ucinewgame` followed by an `isready` round trip.
+    pub fn new_game(&mut self, timeout: Duration) -> Result<(), EngineFault> {
+        self.send("ucinewgame")?;
+        self.sync(Instant::now() + timeout)
+    }
+
+    /// Send `position` and `go` commands and wait up to `timeout` for
+    /// `bestmove`.
+    pub fn go(
+        &mut self,
+        position: &str,
+        go: &str,
+        timeout: Duration,
+    ) -> Result<SearchReply, EngineFault> {
+        self.send(position)?;
+        self.send(go)?;
+        let mut score = None;
+        let mut depth = None;
+        let mut bestmove = None;
+        self.wait_for(Instant::now() + timeout, |line| {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => parse_info(tokens, &mut score, &mut depth),
+                Some("bestmove") => {
+                    bestmove = Some(tokens.next().unwrap_or("").to_string());
+                    return true;
+                }
+                _ => {}
+            }
+            false
+        })?;
+        Ok(SearchReply {
+            bestmove: bestmove.unwrap_or_default(),
+            score,
+            depth,
+        })
+    }
+
+    /// Ask the engine to quit and give it a moment before it is killed.
+    pub fn quit(mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_millis(200);
+        while Instant::now() < deadline {
+            if matches!(self.child.try_wait(), Ok(Some(_))) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), EngineFault> {
+        writeln!(self.stdin, "{command}")
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| EngineFault::Crashed)
+    }
+
+    fn sync(&mut self, deadline: Instant) -> Result<(), EngineFault> {
+        self.send("isready")?;
+        self.wait_for(deadline, |line| line == "readyok")
+    }
+
+    /// Feed output lines to `done` until it returns true.
+    fn wait_for(
+        &mut self,
+        deadline: Instant,
+        mut done: impl FnMut(&str) -> bool,
+    ) -> Result<(), EngineFault> {
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(left) {
+                Ok(line) => {
+                    if done(line.trim()) {
+                        return Ok(());
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => return Err(EngineFault::TimedOut),
+                Err(RecvTimeoutError::Disconnected) => return Err(EngineFault::Crashed),
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn parse_info<'a>(
+    mut tokens: impl Iterator<Item = &'a str>,
+    score: &mut Option<EngineScore>,
+    depth: &mut Option<u32>,
+) {
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => {
+                if let Some(d) = tokens.next().and_then(|t| t.parse().ok()) {
+                    *depth = Some(d);
+                }
+            }
+            "score" => {
+                let kind = tokens.next();
+                let value = tokens.next().and_then(|t| t.parse().ok());
+                match (kind, value) {
+                    (Some("cp"), Some(cp)) => *score = Some(EngineScore::Cp(cp)),
+                    (Some("mate"), Some(moves)) => *score = Some(EngineScore::Mate(moves)),
+                    _ => {}
+                }
+            }
+            // The rest of the line is the PV, which may contain any token.
+            "pv" => return,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_specs_parse_args_and_options_in_order() {
+        let spec: EngineSpec = "cmd=/opt/sf/stockfish,arg=--bench,option.Hash=64,option.Threads=2"
+            .parse()
+            .unwrap();
+        assert_eq!(spec.name, "stockfish");
+        assert_eq!(spec.args, vec!["--bench"]);
+        assert_eq!(
+            spec.options,
+            vec![
+                ("Hash".to_string(), "64".to_string()),
+                ("Threads".to_string(), "2".to_string())
+            ]
+        );
+        assert!("name=x".parse::<EngineSpec>().is_err());
+        assert!("cmd=x,depth=3".parse::<EngineSpec>().is_err());
+    }
+
+    #[test]
+    fn info_lines_keep_the_score_but_not_pv_tokens() {
+        let mut score = None;
+        let mut depth = None;
+        parse_info(
+            "depth 9 seldepth 12 score cp -31 nodes 100 pv e2e4 depth 40".split_whitespace(),
+            &mut score,
+            &mut depth,
+        );
+        assert_eq!((score, depth), (Some(EngineScore::Cp(-31)), Some(9)));
+        parse_info(
+            "depth 10 score mate -3 upperbound".split_whitespace(),
+            &mut score,
+            &mut depth,
+        );
+        assert_eq!((score, depth), (Some(EngineScore::Mate(-3)), Some(10)));
+    }
+}
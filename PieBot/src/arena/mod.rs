@@ -0,0 +1,606 @@
+//! Games between external UCI engines.
+//!
+//! Each engine runs as a subprocess ([`engine::UciEngine`]) and is driven
+//! with `position`/`go wtime btime` under a shared [`TimeControl`]. Games end
+//! by the rules, by the self-play [`Adjudicator`], or by a fault: a flag, a
+//! crash or an illegal move loses for the engine responsible. Moves are kept
+//! in cozy-chess encoding (castling is king-takes-rook) and translated to
+//! standard UCI only on the wire.
+use crate::io::pgn::{EvalComment, PgnGame};
+use crate::search::eval::MATE_SCORE;
+use crate::search::time_manager::{Clock, TimeControl};
+use crate::selfplay::{adjudicate_position, AdjudicationVerdict, Adjudicator, GameTermination};
+use cozy_chess::{Board, Color, File, Move, Piece, Square};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use engine::{EngineFault, EngineScore, EngineSpec, UciEngine};
+
+pub mod engine;
+
+/// Tag written to state files; a state file with another schema is refused.
+pub const STATE_SCHEMA: &str = "piebot-match-state-v1";
+
+/// How a game ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Termination {
+    /// Ended by the rules or by adjudication.
+    Game(GameTermination),
+    /// The side to move ran out of time or never answered.
+    TimeForfeit,
+    /// The side to move's engine exited or closed its pipes.
+    Crash,
+    /// The side to move answered with an illegal or unparsable move.
+    IllegalMove,
+}
+
+impl Termination {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Game(termination) => termination.as_str(),
+            Self::TimeForfeit => "time_forfeit",
+            Self::Crash => "crash",
+            Self::IllegalMove => "illegal_move",
+        }
+    }
+
+    /// Inverse of [`Termination::as_str`].
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "time_forfeit" => Some(Self::TimeForfeit),
+            "crash" => Some(Self::Crash),
+            "illegal_move" => Some(Self::IllegalMove),
+            _ => GameTermination::parse(s).map(Self::Game),
+        }
+    }
+
+    /// Value for the PGN `Termination` tag, from the standard's vocabulary.
+    pub fn pgn_tag(self) -> &'static str {
+        match self {
+            Self::Game(
+                GameTermination::Resigned
+                | GameTermination::AdjudicatedDraw
+                | GameTermination::MaxPlies,
+            ) => "adjudication",
+            Self::Game(_) => "normal",
+            Self::TimeForfeit => "time forfeit",
+            Self::Crash => "abandoned",
+            Self::IllegalMove => "rules infraction",
+        }
+    }
+
+    fn from_fault(fault: EngineFault) -> Self {
+        match fault {
+            EngineFault::Crashed => Self::Crash,
+            EngineFault::TimedOut => Self::TimeForfeit,
+        }
+    }
+}
+
+impl Serialize for Termination {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Termination {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::parse(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown termination {s:?}")))
+    }
+}
+
+/// Clock, fault and adjudication settings shared by every game of a match.
+/// A cp threshold of 0 disables the corresponding adjudication rule.
+#[derive(Clone, Debug)]
+pub struct GameRules {
+    pub time_control: TimeControl,
+    pub timeout_margin: Duration, // wait past the flag before an engine counts as hung
+    pub start_timeout: Duration,  // engine start-up and `isready` round trips
+    pub max_plies: usize,         // draw once this many plies have been played
+    pub resign_cp: f32,
+    pub resign_plies: usize,
+    pub draw_adj_cp: f32,
+    pub draw_adj_plies: usize,
+    pub draw_adj_min_ply: usize,
+}
+
+/// A finished game as played by [`play_game`].
+#[derive(Clone, Debug)]
+pub struct PlayedGame {
+    pub moves: Vec<Move>,
+    /// White-perspective score the mover reported for each move.
+    pub move_value_cp: Vec<Option<i32>>,
+    pub move_depth: Vec<Option<u32>>,
+    /// 1 white win, 0 draw, -1 black win.
+    pub result: i8,
+    pub termination: Termination,
+    /// Side whose engine crashed or hung and must be restarted.
+    pub faulted: Option<Color>,
+}
+
+/// Play one game from `start`. Both engines are reset with `ucinewgame`
+/// first; an engine that fails that loses before a move is played.
+pub fn play_game(
+    white: &mut UciEngine,
+    black: &mut UciEngine,
+    start: &Board,
+    rules: &GameRules,
+) -> PlayedGame {
+    let mut game = PlayedGame {
+        moves: Vec::new(),
+        move_value_cp: Vec::new(),
+        move_depth: Vec::new(),
+        result: 0,
+        termination: Termination::Game(GameTermination::MaxPlies),
+        faulted: None,
+    };
+    let forfeit = |game: &mut PlayedGame, side: Color, termination: Termination| {
+        game.result = if side == Color::White { -1 } else { 1 };
+        game.termination = termination;
+    };
+    for (side, engine) in [(Color::White, &mut *white), (Color::Black, &mut *black)] {
+        if let Err(fault) = engine.new_game(rules.start_timeout) {
+            forfeit(&mut game, side, Termination::from_fault(fault));
+            game.faulted = Some(side);
+            return game;
+        }
+    }
+
+    let position_prefix = if start.same_position(&Board::default()) {
+        "position startpos".to_string()
+    } else {
+        format!("position fen {start}")
+    };
+    let mut uci_moves: Vec<String> = Vec::new();
+    let mut clocks = [
+        Clock::new(rules.time_control),
+        Clock::new(rules.time_control),
+    ];
+    let mut adjudicator = Adjudicator::new(
+        rules.resign_cp,
+        rules.resign_plies,
+        rules.draw_adj_cp,
+        rules.draw_adj_plies,
+        rules.draw_adj_min_ply,
+    );
+    let mut board = start.clone();
+    let mut position_history = vec![board.clone()];
+    loop {
+        if let Some((result, termination)) = adjudicate_position(&board, &position_history) {
+            game.result = result;
+            game.termination = Termination::Game(termination);
+            return game;
+        }
+        if game.moves.len() >= rules.max_plies {
+            game.termination = Termination::Game(GameTermination::MaxPlies);
+            return game;
+        }
+        let side = board.side_to_move();
+        let mover = side as usize;
+        let position = if uci_moves.is_empty() {
+            position_prefix.clone()
+        } else {
+            format!("{position_prefix} moves {}", uci_moves.join(" "))
+        };
+        let mut go = format!(
+            "go wtime {} btime {} winc {} binc {}",
+            clocks[0].remaining().as_millis(),
+            clocks[1].remaining().as_millis(),
+            rules.time_control.increment_ms,
+            rules.time_control.increment_ms
+        );
+        if let Some(moves_to_go) = clocks[mover].moves_to_go() {
+            go.push_str(&format!(" movestogo {moves_to_go}"));
+        }
+        let engine = if side == Color::White {
+            &mut *white
+        } else {
+            &mut *black
+        };
+        let started = Instant::now();
+        let reply = engine.go(
+            &position,
+            &go,
+            clocks[mover].remaining() + rules.timeout_margin,
+        );
+        let elapsed = started.elapsed();
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(fault) => {
+                forfeit(&mut game, side, Termination::from_fault(fault));
+                game.faulted = Some(side);
+                return game;
+            }
+        };
+        if !clocks[mover].charge(elapsed) {
+            forfeit(&mut game, side, Termination::TimeForfeit);
+            return game;
+        }
+        let Some(mv) = uci_to_move(&board, &reply.bestmove) else {
+            forfeit(&mut game, side, Termination::IllegalMove);
+            return game;
+        };
+
+        let white_cp = reply.score.map(|score| {
+            let cp = score_cp(score);
+            if side == Color::White {
+                cp
+            } else {
+                -cp
+            }
+        });
+        uci_moves.push(move_to_uci(&board, mv));
+        game.moves.push(mv);
+        game.move_value_cp.push(white_cp);
+        game.move_depth.push(reply.depth);
+        board.play_unchecked(mv);
+        position_history.push(board.clone());
+        let ply = game.moves.len() - 1;
+        if let Some(verdict) = adjudicator.observe(ply, white_cp.map(|cp| cp as f32)) {
+            // Checkmate and the draw rules outrank an evaluation verdict; the
+            // top of the loop scores those.
+            if adjudicate_position(&board, &position_history).is_none() {
+                let (result, termination) = match verdict {
+                    AdjudicationVerdict::ResignWhiteWins => (1, GameTermination::Resigned),
+                    AdjudicationVerdict::ResignBlackWins => (-1, GameTermination::Resigned),
+                    AdjudicationVerdict::AdjudicatedDraw => (0, GameTermination::AdjudicatedDraw),
+                };
+                game.result = result;
+                game.termination = Termination::Game(termination);
+                return game;
+            }
+        }
+    }
+}
+
+/// One game of a schedule; `white` and `black` index the engine specs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameJob {
+    pub index: usize,
+    /// Index of the start position in the opening suite.
+    pub opening: usize,
+    pub white: usize,
+    pub black: usize,
+}
+
+/// Play `jobs` on `concurrency` workers. Each worker owns its engine
+/// processes, started on first use and restarted after a crash or hang, so
+/// games never share an engine. `on_game` runs on the calling thread for
+/// every finished game, in completion order. An engine that cannot be
+/// started, or an error from `on_game`, stops the run once the games in
+/// flight finish.
+pub fn play_jobs(
+    specs: &[EngineSpec],
+    jobs: &[GameJob],
+    openings: &[Board],
+    rules: &GameRules,
+    concurrency: usize,
+    mut on_game: impl FnMut(MatchGame) -> Result<(), String>,
+) -> Result<(), String> {
+    let next = AtomicUsize::new(0);
+    let abort = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel::<Result<MatchGame, String>>();
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.max(1).min(jobs.len()) {
+            let tx = tx.clone();
+            let (next, abort) = (&next, &abort);
+            scope.spawn(move || {
+                let mut engines: Vec<Option<UciEngine>> = specs.iter().map(|_| None).collect();
+                while !abort.load(Ordering::Relaxed) {
+                    let Some(job) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                        break;
+                    };
+                    let game = play_job(specs, &mut engines, job, openings, rules);
+                    if game.is_err() {
+                        abort.store(true, Ordering::Relaxed);
+                    }
+                    if tx.send(game).is_err() {
+                        break;
+                    }
+                }
+                for engine in engines.into_iter().flatten() {
+                    engine.quit();
+                }
+            });
+        }
+        drop(tx);
+        for game in rx {
+            if let Err(e) = game.and_then(&mut on_game) {
+                abort.store(true, Ordering::Relaxed);
+                return Err(e);
+            }
+        }
+        Ok(())
+    })
+}
+
+fn play_job(
+    specs: &[EngineSpec],
+    engines: &mut [Option<UciEngine>],
+    job: &GameJob,
+    openings: &[Board],
+    rules: &GameRules,
+) -> Result<MatchGame, String> {
+    for player in [job.white, job.black] {
+        if engines[player].is_none() {
+            engines[player] = Some(UciEngine::start(&specs[player], rules.start_timeout)?);
+        }
+    }
+    let mut white = engines[job.white].take().expect("engine started");
+    let mut black = engines[job.black].take().expect("engine started");
+    let start = &openings[job.opening];
+    let played = play_game(&mut white, &mut black, start, rules);
+    let names = [
+        specs[job.white].name.as_str(),
+        specs[job.black].name.as_str(),
+    ];
+    let game = MatchGame::new(job.index, job.opening, names, start, &played);
+    if played.faulted != Some(Color::White) {
+        engines[job.white] = Some(white);
+    }
+    if played.faulted != Some(Color::Black) {
+        engines[job.black] = Some(black);
+    }
+    Ok(game)
+}
+
+/// Centipawns for an engine score, with mates mapped to the search's
+/// mate-distance scale.
+fn score_cp(score: EngineScore) -> i32 {
+    match score {
+        EngineScore::Cp(cp) => cp.clamp(-(MATE_SCORE - 1_025), MATE_SCORE - 1_025),
+        EngineScore::Mate(moves) if moves > 0 => MATE_SCORE - (2 * moves - 1),
+        EngineScore::Mate(moves) => -(MATE_SCORE - 2 * moves.abs()),
+    }
+}
+
+/// Parse a standard UCI move (`e1g1` castling) played from `board`; the
+/// cozy-chess king-takes-rook form is accepted too. `None` unless legal.
+pub fn uci_to_move(board: &Board, uci: &str) -> Option<Move> {
+    let mut mv: Move = uci.parse().ok()?;
+    let side = board.side_to_move();
+    if board.piece_on(mv.from) == Some(Piece::King)
+        && board.colors(side).has(mv.from)
+        && mv.from.rank() == mv.to.rank()
+        && (mv.from.file() as i32 - mv.to.file() as i32).abs() == 2
+    {
+        let rook_file = if mv.to.file() > mv.from.file() {
+            File::H
+        } else {
+            File::A
+        };
+        mv.to = Square::new(rook_file, mv.from.rank());
+    }
+    board.is_legal(mv).then_some(mv)
+}
+
+/// Standard UCI text of the cozy-chess move `mv` played from `board`.
+pub fn move_to_uci(board: &Board, mv: Move) -> String {
+    let side = board.side_to_move();
+    if board.piece_on(mv.from) == Some(Piece::King) && board.colors(side).has(mv.to) {
+        let king_file = if mv.to.file() > mv.from.file() {
+            File::G
+        } else {
+            File::C
+        };
+        return Move {
+            from: mv.from,
+            to: Square::new(king_file, mv.from.rank()),
+            promotion: None,
+        }
+        .to_string();
+    }
+    mv.to_string()
+}
+
+/// A finished game of a match, as kept in the state file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchGame {
+    pub index: usize,
+    /// Index of the start position in the opening suite.
+    pub opening: usize,
+    pub white: String,
+    pub black: String,
+    pub start_fen: String,
+    /// Cozy-chess encoding.
+    pub moves: Vec<String>,
+    pub move_value_cp: Vec<Option<i32>>, // white-perspective, as reported by the mover
+    pub move_depth: Vec<Option<u32>>,
+    pub result: i8, // 1 white win, 0 draw, -1 black win
+    pub termination: Termination,
+}
+
+impl MatchGame {
+    pub fn new(
+        index: usize,
+        opening: usize,
+        [white, black]: [&str; 2],
+        start: &Board,
+        played: &PlayedGame,
+    ) -> Self {
+        Self {
+            index,
+            opening,
+            white: white.to_string(),
+            black: black.to_string(),
+            start_fen: start.to_string(),
+            moves: played.moves.iter().map(Move::to_string).collect(),
+            move_value_cp: played.move_value_cp.clone(),
+            move_depth: played.move_depth.clone(),
+            result: played.result,
+            termination: played.termination,
+        }
+    }
+
+    /// Score of engine `name` in this game: 1, 0.5 or 0; `None` if it did
+    /// not play.
+    pub fn score_of(&self, name: &str) -> Option<f64> {
+        let white_score = f64::from(self.result + 1) / 2.0;
+        if self.white == name {
+            Some(white_score)
+        } else if self.black == name {
+            Some(1.0 - white_score)
+        } else {
+            None
+        }
+    }
+
+    /// PGN for this game; reported scores become `+0.35/12` comments from
+    /// the side that moved.
+    pub fn to_pgn(&self, event: &str) -> PgnGame {
+        let start = Board::from_fen(&self.start_fen, false).unwrap_or_default();
+        let mut pgn = PgnGame::new(start.clone());
+        pgn.set_header("Event", event);
+        pgn.set_header("Site", "?");
+        pgn.set_header("Round", (self.index + 1).to_string());
+        pgn.set_header("White", self.white.as_str());
+        pgn.set_header("Black", self.black.as_str());
+        pgn.set_header("Termination", self.termination.pgn_tag());
+        let mut board = start;
+        for (ply, mv) in self.moves.iter().enumerate() {
+            let Some(mv) = mv.parse().ok().filter(|&mv| board.is_legal(mv)) else {
+                break;
+            };
+            let comment = self.move_value_cp.get(ply).copied().flatten().map(|cp| {
+                let mover_cp = if board.side_to_move() == Color::White {
+                    cp
+                } else {
+                    -cp
+                };
+                EvalComment {
+                    score_cp: mover_cp,
+                    depth: self.move_depth.get(ply).copied().flatten().unwrap_or(0),
+                }
+                .to_string()
+            });
+            pgn.push(mv, comment);
+            board.play_unchecked(mv);
+        }
+        pgn.result = self.result_str().to_string();
+        pgn
+    }
+
+    /// `1-0`, `0-1` or `1/2-1/2`.
+    pub fn result_str(&self) -> &'static str {
+        match self.result {
+            1 => "1-0",
+            -1 => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+}
+
+/// Durable progress of a match: the settings it was started with and every
+/// game finished so far. Saved after each game, so an interrupted match
+/// resumes by skipping the games already here.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatchState {
+    pub schema: String,
+    /// Everything that decides how a game is played; a resumed run must
+    /// present the same value.
+    pub config: serde_json::Value,
+    pub games: Vec<MatchGame>,
+}
+
+impl MatchState {
+    pub fn new(config: serde_json::Value) -> Self {
+        Self {
+            schema: STATE_SCHEMA.to_string(),
+            config,
+            games: Vec::new(),
+        }
+    }
+
+    /// Resume from `path` if it exists. A state file written for a different
+    /// configuration is an error rather than a fresh start, so games played
+    /// under other settings are never mixed in or silently discarded.
+    pub fn load_or_new(path: &Path, config: serde_json::Value) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::new(config));
+        }
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read state file {}: {e}", path.display()))?;
+        let state: Self = serde_json::from_str(&text)
+            .map_err(|e| format!("invalid state file {}: {e}", path.display()))?;
+        if state.schema != STATE_SCHEMA {
+            return Err(format!(
+                "state file {} has schema {:?}, expected {STATE_SCHEMA:?}",
+                path.display(),
+                state.schema
+            ));
+        }
+        if state.config != config {
+            return Err(format!(
+                "state file {} was written for a different match configuration",
+                path.display()
+            ));
+        }
+        Ok(state)
+    }
+
+    pub fn is_done(&self, index: usize) -> bool {
+        self.games.iter().any(|game| game.index == index)
+    }
+
+    /// Record a finished game, keeping games in index order.
+    pub fn push(&mut self, game: MatchGame) {
+        let at = self.games.partition_point(|g| g.index < game.index);
+        self.games.insert(at, game);
+    }
+
+    /// Write atomically: to a temporary file first, then renamed over
+    /// `path`, so a kill mid-write leaves the previous state intact.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+            }
+        }
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = std::path::PathBuf::from(tmp);
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&tmp, text)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| format!("failed to write state file {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn castling_is_standard_on_the_wire_and_king_takes_rook_inside() {
+        let board: Board = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".parse().unwrap();
+        let short = uci_to_move(&board, "e1g1").unwrap();
+        assert_eq!(short.to_string(), "e1h1");
+        assert_eq!(move_to_uci(&board, short), "e1g1");
+        let long = uci_to_move(&board, "e1a1").unwrap();
+        assert_eq!(move_to_uci(&board, long), "e1c1");
+        assert_eq!(uci_to_move(&board, "e1f1").unwrap().to_string(), "e1f1");
+        assert!(uci_to_move(&board, "e1e3").is_none());
+        assert!(uci_to_move(&board, "0000").is_none());
+    }
+
+    #[test]
+    fn terminations_round_trip_through_their_names() {
+        for termination in GameTermination::ALL
+            .into_iter()
+            .map(Termination::Game)
+            .chain([
+                Termination::TimeForfeit,
+                Termination::Crash,
+                Termination::IllegalMove,
+            ])
+        {
+            assert_eq!(Termination::parse(termination.as_str()), Some(termination));
+        }
+    }
+}
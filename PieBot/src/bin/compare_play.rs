@@ -1,15 +1,22 @@
 use clap::Parser;
-use cozy_chess::{BitBoard, Color, Piece, Square};
 use cozy_chess::{Board, Move};
+use piebot::io::pgn::move_to_san;
+use piebot::io::polyglot::{BookSelection, PolyglotBook};
+use piebot::search::config::SearchConfig;
+use piebot::search::time_manager::{Clock, TimeControl, TimeLimits};
+use piebot::stats::{paired_bootstrap, Sprt, SprtConfig};
 use rand::rngs::SmallRng;
 use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
 use serde::Serialize;
 use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::time::Instant;
 
 const PAIRED_OPENING_POLICY: &str = "neutral-pst-topk-v2";
+const BOOK_OPENING_POLICY: &str = "polyglot-weighted-v1";
 const PARALLELISM_SCHEMA: &str = "bounded-pair-workers-v1";
 
 #[derive(Parser, Debug)]
@@ -30,6 +37,11 @@ struct Args {
     #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
     depth: Option<u32>,
 
+    /// Chess clock per engine instead of --movetime, in seconds: `base+inc`
+    /// or `moves/base[+inc]`. Moves are charged by wall time and a flag loses.
+    #[arg(long, conflicts_with = "depth")]
+    tc: Option<TimeControl>,
+
     /// Number of noisy plies at the start of each game (both sides)
     #[arg(long, default_value_t = 12)]
     noise_plies: usize,
@@ -66,10 +78,47 @@ struct Args {
     #[arg(long)]
     openings_file: Option<std::path::PathBuf>,
 
+    /// Optional Polyglot `.bin` book to draw paired openings from (requires --paired-openings).
+    #[arg(long, conflicts_with = "openings_file")]
+    book_file: Option<std::path::PathBuf>,
+
+    /// Maximum book plies per opening; a line ends earlier when the book runs out.
+    #[arg(long, default_value_t = 16)]
+    book_plies: usize,
+
     /// Force both sides to use baseline search implementation (model-only A/B).
     #[arg(long, default_value_t = false)]
     same_search: bool,
 
+    /// Stop as soon as a pentanomial SPRT over opening pairs accepts H0 or
+    /// H1; --games becomes the budget (requires --paired-openings).
+    #[arg(long, default_value_t = false)]
+    sprt: bool,
+
+    /// SPRT null hypothesis, in logistic Elo of experimental over baseline.
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    sprt_elo0: f64,
+
+    /// SPRT alternative hypothesis, in logistic Elo.
+    #[arg(long, default_value_t = 5.0, allow_negative_numbers = true)]
+    sprt_elo1: f64,
+
+    /// SPRT false-positive rate.
+    #[arg(long, default_value_t = 0.05)]
+    sprt_alpha: f64,
+
+    /// SPRT false-negative rate.
+    #[arg(long, default_value_t = 0.05)]
+    sprt_beta: f64,
+
+    /// Resamples for the paired-bootstrap interval in --json-out.
+    #[arg(long, default_value_t = 20_000)]
+    bootstrap_samples: usize,
+
+    /// Confidence level of the paired-bootstrap interval.
+    #[arg(long, default_value_t = 0.95)]
+    bootstrap_confidence: f64,
+
     /// Optional: write summary JSON to this path
     #[arg(long)]
     json_out: Option<String>,
@@ -97,6 +146,10 @@ struct Args {
     base_nnue_file: Option<String>,
     #[arg(long)]
     base_hash_mb: Option<usize>,
+    /// JSON search config for the baseline (see `search::config`); fields
+    /// it leaves out keep their defaults.
+    #[arg(long, value_parser = parse_search_config)]
+    base_config: Option<SearchConfig>,
 
     // Experimental config
     #[arg(long)]
@@ -113,6 +166,11 @@ struct Args {
     exp_nnue_file: Option<String>,
     #[arg(long)]
     exp_hash_mb: Option<usize>,
+    /// JSON search config for the experimental side. While `alphabeta_temp`
+    /// re-exports the baseline (or under --same-search), the two configs are
+    /// the only difference between the searches.
+    #[arg(long, value_parser = parse_search_config)]
+    exp_config: Option<SearchConfig>,
 }
 
 fn parse_positive_usize(raw: &str) -> Result<usize, String> {
@@ -125,6 +183,10 @@ fn parse_positive_usize(raw: &str) -> Result<usize, String> {
     Ok(value)
 }
 
+fn parse_search_config(path: &str) -> Result<SearchConfig, String> {
+    SearchConfig::from_json_file(path)
+}
+
 fn legal_moves(board: &Board) -> Vec<Move> {
     let mut v = Vec::new();
     board.generate_moves(|ml| {
@@ -136,148 +198,6 @@ fn legal_moves(board: &Board) -> Vec<Move> {
     v
 }
 
-fn bb_contains(bb: BitBoard, target: Square) -> bool {
-    for sq in bb {
-        if sq == target {
-            return true;
-        }
-    }
-    false
-}
-
-fn piece_at(board: &Board, sq: Square) -> Option<(Color, Piece)> {
-    for &color in &[Color::White, Color::Black] {
-        let cb = board.colors(color);
-        for &piece in &[
-            Piece::Pawn,
-            Piece::Knight,
-            Piece::Bishop,
-            Piece::Rook,
-            Piece::Queen,
-            Piece::King,
-        ] {
-            if bb_contains(cb & board.pieces(piece), sq) {
-                return Some((color, piece));
-            }
-        }
-    }
-    None
-}
-
-fn is_capture_move(board: &Board, mv: Move) -> bool {
-    piece_at(board, mv.to).is_some()
-}
-
-fn san_for_move(board: &Board, mv: Move) -> String {
-    let Some((moving_color, moving_piece)) = piece_at(board, mv.from) else {
-        return format!("{}", mv);
-    };
-    let from = format!("{}", mv.from);
-    let to = format!("{}", mv.to);
-    let is_castling =
-        moving_piece == Piece::King && piece_at(board, mv.to) == Some((moving_color, Piece::Rook));
-    let mut s = String::new();
-    if is_castling {
-        s.push_str(if to.as_bytes()[0] > from.as_bytes()[0] {
-            "O-O"
-        } else {
-            "O-O-O"
-        });
-    } else {
-        let (piece_char, is_pawn) = match moving_piece {
-            Piece::Knight => ('N', false),
-            Piece::Bishop => ('B', false),
-            Piece::Rook => ('R', false),
-            Piece::Queen => ('Q', false),
-            Piece::King => ('K', false),
-            _ => (' ', true),
-        };
-        let capture =
-            is_capture_move(board, mv) || (is_pawn && from.as_bytes()[0] != to.as_bytes()[0]);
-        // Disambiguation for non-pawn, non-king moves.
-        let mut disamb_file = false;
-        let mut disamb_rank = false;
-        if !is_pawn && moving_piece != Piece::King {
-            let mut candidates: Vec<Move> = Vec::new();
-            board.generate_moves(|ml| {
-                for m in ml {
-                    if m.to == mv.to
-                        && m != mv
-                        && piece_at(board, m.from).map(|(_, p)| p) == Some(moving_piece)
-                    {
-                        candidates.push(m);
-                    }
-                }
-                false
-            });
-            if !candidates.is_empty() {
-                let from_file = from.as_bytes()[0];
-                let from_rank = from.as_bytes()[1];
-                let shares_file = candidates
-                    .iter()
-                    .any(|m| format!("{}", m.from).as_bytes()[0] == from_file);
-                let shares_rank = candidates
-                    .iter()
-                    .any(|m| format!("{}", m.from).as_bytes()[1] == from_rank);
-                if !shares_file {
-                    disamb_file = true;
-                } else if !shares_rank {
-                    disamb_rank = true;
-                } else {
-                    disamb_file = true;
-                    disamb_rank = true;
-                }
-            }
-        }
-        if !is_pawn {
-            s.push(piece_char);
-        }
-        if disamb_file {
-            s.push(from.chars().next().expect("square has a file"));
-        }
-        if disamb_rank {
-            s.push(from.chars().nth(1).expect("square has a rank"));
-        }
-        if is_pawn && capture {
-            s.push(from.chars().next().expect("square has a file"));
-        }
-        if capture {
-            s.push('x');
-        }
-        s.push_str(&to);
-        if is_pawn {
-            if let Some(promo) = mv.promotion {
-                let c = match promo {
-                    Piece::Knight => 'N',
-                    Piece::Bishop => 'B',
-                    Piece::Rook => 'R',
-                    Piece::Queen => 'Q',
-                    _ => 'Q',
-                };
-                s.push('=');
-                s.push(c);
-            }
-        }
-    }
-    // Check or checkmate
-    let mut next = board.clone();
-    next.play_unchecked(mv);
-    let in_check = !(next.checkers()).is_empty();
-    let mut has_legal = false;
-    next.generate_moves(|_| {
-        has_legal = true;
-        true
-    });
-    if in_check {
-        if !has_legal {
-            s.push('#');
-        } else {
-            s.push('+');
-        }
-    }
-    s
-}
-
 fn noisy_choice(order: &[Move], topk: usize, rng: &mut SmallRng) -> Option<Move> {
     if order.is_empty() {
         return None;
@@ -306,6 +226,8 @@ struct GameResultRecord {
     experimental_score: f64,
     plies: usize,
     opening_id: Option<String>,
+    /// The loser ran out of time under --tc.
+    time_forfeit: bool,
 }
 
 impl GameResultRecord {
@@ -334,6 +256,7 @@ impl GameResultRecord {
             experimental_score,
             plies,
             opening_id: opening_id.map(str::to_owned),
+            time_forfeit: false,
         }
     }
 
@@ -517,6 +440,9 @@ fn validate_openings_file_usage(args: &Args) -> Result<(), String> {
     if args.openings_file.is_some() && !args.paired_openings {
         return Err("--openings-file requires --paired-openings".to_string());
     }
+    if args.book_file.is_some() && !args.paired_openings {
+        return Err("--book-file requires --paired-openings".to_string());
+    }
     Ok(())
 }
 
@@ -559,6 +485,57 @@ fn build_paired_openings_from_suite(
         .collect())
 }
 
+/// Walk `book_path` from the start position, choosing weighted book moves
+/// with each pair's seed, for at most `plies` plies. A book without a move
+/// for the start position is a hard error, like an empty suite.
+fn build_paired_openings_from_book(
+    games: usize,
+    book_path: &std::path::Path,
+    plies: usize,
+    base_seed: u64,
+) -> Result<Vec<PairedOpening>, String> {
+    validate_paired_game_count(games, true)?;
+    let book = PolyglotBook::open(book_path)?;
+    if book.moves(&Board::default()).is_empty() {
+        return Err(format!(
+            "book {} has no moves for the start position",
+            book_path.display()
+        ));
+    }
+    Ok((0..games / 2)
+        .map(|pair_index| {
+            let seed = paired_opening_seed(base_seed, pair_index);
+            let mut rng = SmallRng::seed_from_u64(seed);
+            let mut board = Board::default();
+            let mut moves = Vec::with_capacity(plies);
+            let mut positions = vec![format!("{}", board)];
+            while moves.len() < plies {
+                let Some(mv) = book.choose(&board, BookSelection::Weighted, &mut rng) else {
+                    break;
+                };
+                moves.push(format!("{}", mv));
+                board.play_unchecked(mv);
+                positions.push(format!("{}", board));
+            }
+            PairedOpening {
+                pair_index,
+                seed,
+                opening_id: opening_fingerprint(seed, &moves),
+                moves,
+                positions,
+            }
+        })
+        .collect())
+}
+
+fn opening_policy(args: &Args) -> &'static str {
+    if args.book_file.is_some() {
+        BOOK_OPENING_POLICY
+    } else {
+        PAIRED_OPENING_POLICY
+    }
+}
+
 fn opening_start_board(opening: &PairedOpening) -> Board {
     opening
         .positions
@@ -582,6 +559,70 @@ fn experimental_search_threads(args: &Args) -> usize {
     args.exp_threads.unwrap_or(args.threads).max(1)
 }
 
+fn sprt_config(args: &Args) -> Result<Option<SprtConfig>, String> {
+    if !args.sprt {
+        return Ok(None);
+    }
+    if !args.paired_openings {
+        return Err("--sprt tests opening pairs and requires --paired-openings".to_string());
+    }
+    let config = SprtConfig {
+        elo0: args.sprt_elo0,
+        elo1: args.sprt_elo1,
+        alpha: args.sprt_alpha,
+        beta: args.sprt_beta,
+    };
+    config.validate()?;
+    Ok(Some(config))
+}
+
+/// SPRT shared by the match workers. Each completed pair is recorded as it
+/// finishes; once a bound is crossed no new pair starts, while pairs already
+/// in flight finish and are still counted.
+struct SprtMonitor {
+    sprt: Mutex<Sprt>,
+    stopped: AtomicBool,
+}
+
+impl SprtMonitor {
+    fn new(config: SprtConfig) -> Self {
+        Self {
+            sprt: Mutex::new(Sprt::new(config).expect("SPRT config was validated")),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    fn record_pair(&self, games: &[PlayedGame]) {
+        let points = games
+            .iter()
+            .map(|game| game.record.experimental_score)
+            .sum::<f64>();
+        let mut sprt = self.sprt.lock().expect("SPRT lock");
+        let decision = sprt
+            .record_pair(points)
+            .unwrap_or_else(|message| panic!("invalid pair score: {message}"));
+        let (lower, upper) = sprt.config().bounds();
+        eprintln!(
+            "[SPRT] pairs={} llr={:.3} bounds=[{:.3}, {:.3}]",
+            sprt.pentanomial().pairs(),
+            sprt.llr(),
+            lower,
+            upper
+        );
+        if decision.is_some() && !self.stopped.swap(true, Ordering::AcqRel) {
+            eprintln!("[SPRT] {decision:?} accepted; no further pairs will start.");
+        }
+    }
+
+    fn into_inner(self) -> Sprt {
+        self.sprt.into_inner().expect("SPRT lock")
+    }
+}
+
 fn build_match_work_units(games: usize, paired_openings: bool) -> Vec<Vec<usize>> {
     if paired_openings {
         (0..games / 2)
@@ -694,10 +735,27 @@ fn parse_eval_mode_exp(raw: Option<&str>) -> piebot::search::alphabeta_temp::Eva
     }
 }
 
+/// Whether both sides play the same search: the same implementation (or
+/// `--same-search`) with the same `SearchConfig`. Two configs of one
+/// implementation are a real A/B match, not a self-compare.
+fn is_self_compare(same_search: bool, same_engine: bool, configs_differ: bool) -> bool {
+    !configs_differ && (same_search || same_engine)
+}
+
+/// `net_hash` of the network file one side loads (the quantized file wins,
+/// as in the engine builders); `None` for a side without a net file.
+fn side_net_hash(quant_file: Option<&str>, dense_file: Option<&str>) -> Option<String> {
+    let path = quant_file.or(dense_file)?;
+    let bytes =
+        std::fs::read(path).unwrap_or_else(|e| panic!("failed to read NNUE file {}: {}", path, e));
+    Some(piebot::eval::nnue::loader::net_hash(&bytes))
+}
+
 fn build_baseline_engine(args: &Args) -> BaselineEngine {
     let mut s = piebot::search::alphabeta::Searcher::default();
     s.set_tt_capacity_mb(args.base_hash_mb.unwrap_or(64));
     s.set_threads(args.base_threads.unwrap_or(args.threads).max(1));
+    s.set_search_config(args.base_config.clone().unwrap_or_default());
     s.set_order_captures(true);
     s.set_use_history(true);
     s.set_use_killers(true);
@@ -735,6 +793,7 @@ fn build_experimental_engine(args: &Args) -> ExperimentalEngine {
         let mut s = piebot::search::alphabeta::Searcher::default();
         s.set_tt_capacity_mb(args.exp_hash_mb.unwrap_or(64));
         s.set_threads(args.exp_threads.unwrap_or(args.threads).max(1));
+        s.set_search_config(args.exp_config.clone().unwrap_or_default());
         s.set_order_captures(true);
         s.set_use_history(true);
         s.set_use_killers(true);
@@ -776,6 +835,7 @@ fn build_experimental_engine(args: &Args) -> ExperimentalEngine {
     let mut s = piebot::search::alphabeta_temp::Searcher::default();
     s.set_tt_capacity_mb(args.exp_hash_mb.unwrap_or(64));
     s.set_threads(args.exp_threads.unwrap_or(args.threads).max(1));
+    s.set_search_config(args.exp_config.clone().unwrap_or_default());
     s.set_order_captures(true);
     s.set_use_history(true);
     s.set_use_killers(true);
@@ -818,13 +878,15 @@ fn decide_move_baseline(
     board: &Board,
     position_history: &[Board],
     args: &Args,
+    limits: Option<TimeLimits>,
     engine: &mut BaselineEngine,
 ) -> (Option<Move>, u32, u64, f64) {
     engine.searcher.set_position_history(position_history);
     let t0 = Instant::now();
-    let (bm, nodes) = if let Some(depth) = args.depth {
+    let (bm, nodes) = if args.depth.is_some() || limits.is_some() {
         let mut params = piebot::search::alphabeta::SearchParams::default();
-        params.depth = depth.max(1);
+        params.depth = args.depth.map_or(0, |depth| depth.max(1));
+        params.time = limits;
         params.use_tt = true;
         params.order_captures = true;
         params.use_history = true;
@@ -855,15 +917,17 @@ fn decide_move_experimental(
     board: &Board,
     position_history: &[Board],
     args: &Args,
+    limits: Option<TimeLimits>,
     engine: &mut ExperimentalEngine,
 ) -> (Option<Move>, u32, u64, f64) {
     match &mut engine.inner {
         ExperimentalEngineKind::Temp(s) => {
             s.set_position_history(position_history);
             let t0 = Instant::now();
-            let (bm, nodes) = if let Some(depth) = args.depth {
+            let (bm, nodes) = if args.depth.is_some() || limits.is_some() {
                 let mut params = piebot::search::alphabeta_temp::SearchParams::default();
-                params.depth = depth.max(1);
+                params.depth = args.depth.map_or(0, |depth| depth.max(1));
+                params.time = limits;
                 params.use_tt = true;
                 params.order_captures = true;
                 params.use_history = true;
@@ -892,9 +956,10 @@ fn decide_move_experimental(
         ExperimentalEngineKind::Base(s) => {
             s.set_position_history(position_history);
             let t0 = Instant::now();
-            let (bm, nodes) = if let Some(depth) = args.depth {
+            let (bm, nodes) = if args.depth.is_some() || limits.is_some() {
                 let mut params = piebot::search::alphabeta::SearchParams::default();
-                params.depth = depth.max(1);
+                params.depth = args.depth.map_or(0, |depth| depth.max(1));
+                params.time = limits;
                 params.use_tt = true;
                 params.order_captures = true;
                 params.use_history = true;
@@ -964,6 +1029,10 @@ fn play_single_game(
     let mut san_moves = Vec::new();
     let mut baseline = SearchStats::default();
     let mut experimental = SearchStats::default();
+    // [baseline, experimental]; only searched moves are charged, so opening
+    // and noise plies are free.
+    let mut clocks = args.tc.map(|tc| [Clock::new(tc), Clock::new(tc)]);
+    let mut time_forfeit = false;
 
     let result = loop {
         if let Some(res) = is_game_over(&board, &position_history) {
@@ -1002,18 +1071,30 @@ fn play_single_game(
             } else {
                 choose_move_noisy_experimental(&board, exp_engine, args.noise_topk, rng)
             }
-        } else if baseline_to_move {
-            let (mv, depth, nodes, time_s) =
-                decide_move_baseline(&board, &position_history, args, base_engine);
-            if mv.is_some() {
-                baseline.record_move(depth, nodes, time_s);
-            }
-            mv
         } else {
-            let (mv, depth, nodes, time_s) =
-                decide_move_experimental(&board, &position_history, args, exp_engine);
-            if mv.is_some() {
-                experimental.record_move(depth, nodes, time_s);
+            let side = usize::from(!baseline_to_move);
+            let limits = clocks.as_ref().map(|clocks| clocks[side].limits(&board));
+            let started = Instant::now();
+            let mv = if baseline_to_move {
+                let (mv, depth, nodes, time_s) =
+                    decide_move_baseline(&board, &position_history, args, limits, base_engine);
+                if mv.is_some() {
+                    baseline.record_move(depth, nodes, time_s);
+                }
+                mv
+            } else {
+                let (mv, depth, nodes, time_s) =
+                    decide_move_experimental(&board, &position_history, args, limits, exp_engine);
+                if mv.is_some() {
+                    experimental.record_move(depth, nodes, time_s);
+                }
+                mv
+            };
+            if let Some(clocks) = clocks.as_mut() {
+                if !clocks[side].charge(started.elapsed()) {
+                    time_forfeit = true;
+                    break if baseline_to_move { -1.0 } else { 1.0 };
+                }
             }
             mv
         };
@@ -1021,13 +1102,13 @@ fn play_single_game(
         let Some(mv) = mv else {
             break 0.0;
         };
-        san_moves.push(san_for_move(&board, mv));
+        san_moves.push(move_to_san(&board, mv));
         board.play_unchecked(mv);
         position_history.push(board.clone());
         plies += 1;
     };
 
-    let record = if let Some(opening) = paired_opening {
+    let mut record = if let Some(opening) = paired_opening {
         GameResultRecord::new(
             game_index,
             opening.pair_index,
@@ -1039,6 +1120,7 @@ fn play_single_game(
     } else {
         GameResultRecord::unpaired(game_index, baseline_is_white, result, plies)
     };
+    record.time_forfeit = time_forfeit;
     PlayedGame {
         record,
         san_moves,
@@ -1083,14 +1165,20 @@ fn play_paired_match(
     args: &Args,
     openings: &[PairedOpening],
     parallel_games: usize,
+    sprt: Option<&SprtMonitor>,
 ) -> (Vec<PlayedGame>, usize) {
     let work_units = build_match_work_units(args.games, true);
-    let run_serial = || {
-        work_units
-            .iter()
-            .flat_map(|unit| play_paired_work_unit(args, openings, unit))
-            .collect::<Vec<_>>()
+    let play_unit = |unit: &Vec<usize>| {
+        if sprt.is_some_and(SprtMonitor::stopped) {
+            return Vec::new();
+        }
+        let games = play_paired_work_unit(args, openings, unit);
+        if let Some(monitor) = sprt {
+            monitor.record_pair(&games);
+        }
+        games
     };
+    let run_serial = || work_units.iter().flat_map(&play_unit).collect::<Vec<_>>();
     let (mut games, actual_parallel_games) = if parallel_games <= 1 || work_units.len() <= 1 {
         (run_serial(), parallel_games.min(1))
     } else {
@@ -1099,12 +1187,7 @@ fn play_paired_match(
             .build()
         {
             Ok(pool) => (
-                pool.install(|| {
-                    work_units
-                        .par_iter()
-                        .flat_map_iter(|unit| play_paired_work_unit(args, openings, unit))
-                        .collect()
-                }),
+                pool.install(|| work_units.par_iter().flat_map_iter(&play_unit).collect()),
                 parallel_games,
             ),
             Err(error) => {
@@ -1128,15 +1211,33 @@ fn main() {
         eprintln!("error: {message}");
         std::process::exit(2);
     }
+    let sprt_monitor = match sprt_config(&args) {
+        Ok(config) => config.map(SprtMonitor::new),
+        Err(message) => {
+            eprintln!("error: {message}");
+            std::process::exit(2);
+        }
+    };
     let paired_openings = if args.paired_openings {
-        match args.openings_file.as_deref() {
-            Some(suite_path) => build_paired_openings_from_suite(args.games, suite_path, args.seed)
-                .unwrap_or_else(|message| {
-                    eprintln!("error: {message}");
-                    std::process::exit(2);
-                }),
-            None => build_paired_openings(args.games, args.noise_plies, args.noise_topk, args.seed)
-                .expect("paired game count was validated"),
+        match (args.openings_file.as_deref(), args.book_file.as_deref()) {
+            (Some(suite_path), _) => build_paired_openings_from_suite(
+                args.games, suite_path, args.seed,
+            )
+            .unwrap_or_else(|message| {
+                eprintln!("error: {message}");
+                std::process::exit(2);
+            }),
+            (None, Some(book_path)) => {
+                build_paired_openings_from_book(args.games, book_path, args.book_plies, args.seed)
+                    .unwrap_or_else(|message| {
+                        eprintln!("error: {message}");
+                        std::process::exit(2);
+                    })
+            }
+            (None, None) => {
+                build_paired_openings(args.games, args.noise_plies, args.noise_topk, args.seed)
+                    .expect("paired game count was validated")
+            }
         }
     } else {
         Vec::new()
@@ -1144,19 +1245,41 @@ fn main() {
     // Detect if experimental search is identical to baseline (alphabeta_temp reexports alphabeta)
     let tn_base = std::any::type_name::<piebot::search::alphabeta::Searcher>();
     let tn_exp = std::any::type_name::<piebot::search::alphabeta_temp::Searcher>();
-    let self_compare = args.same_search || tn_base == tn_exp;
+    let search_configs = [
+        args.base_config.clone().unwrap_or_default(),
+        args.exp_config.clone().unwrap_or_default(),
+    ];
+    let configs_differ = search_configs[0] != search_configs[1];
+    let self_compare = is_self_compare(args.same_search, tn_base == tn_exp, configs_differ);
     if args.same_search {
         eprintln!(
             "[INFO] same-search mode enabled: both sides use baseline search implementation."
         );
+    } else if tn_base == tn_exp && configs_differ {
+        eprintln!("[INFO] alphabeta_temp reexports alphabeta: comparing two search configs of the baseline implementation.");
     } else if tn_base == tn_exp {
         eprintln!("[WARN] Experimental search equals baseline (alphabeta_temp reexports alphabeta). Comparing baseline against itself.");
     }
+    let base_net = side_net_hash(
+        args.base_nnue_quant_file.as_deref(),
+        args.base_nnue_file.as_deref(),
+    );
+    let exp_net = side_net_hash(
+        args.exp_nnue_quant_file.as_deref(),
+        args.exp_nnue_file.as_deref(),
+    );
+    if base_net.is_some() || exp_net.is_some() {
+        eprintln!(
+            "[INFO] nets: baseline={} experimental={}",
+            base_net.as_deref().unwrap_or("none"),
+            exp_net.as_deref().unwrap_or("none")
+        );
+    }
     if args.paired_openings {
         eprintln!(
             "[INFO] paired-opening mode: {} pairs, policy={}, engines reset every game.",
             paired_openings.len(),
-            PAIRED_OPENING_POLICY
+            opening_policy(&args)
         );
     }
     let available_cores = std::thread::available_parallelism()
@@ -1189,7 +1312,12 @@ fn main() {
 
     let match_started = Instant::now();
     let (played_games, parallel_games) = if args.paired_openings {
-        play_paired_match(&args, &paired_openings, planned_parallel_games)
+        play_paired_match(
+            &args,
+            &paired_openings,
+            planned_parallel_games,
+            sprt_monitor.as_ref(),
+        )
     } else {
         // Preserve legacy unpaired behavior exactly: one RNG stream and engine
         // state that remains warm between sequential games.
@@ -1218,10 +1346,13 @@ fn main() {
         );
     }
     let match_wall_time_s = match_started.elapsed().as_secs_f64();
+    let games_played = played_games.len();
+    let sprt = sprt_monitor.map(SprtMonitor::into_inner);
 
     let mut baseline_points = 0.0f64;
     let mut experimental_points = 0.0f64;
     let mut draws = 0usize;
+    let mut time_forfeits = [0usize; 2];
     let mut baseline_stats = SearchStats::default();
     let mut experimental_stats = SearchStats::default();
     let mut game_results = played_games
@@ -1243,6 +1374,10 @@ fn main() {
         }
         baseline_stats.merge(&game.baseline);
         experimental_stats.merge(&game.experimental);
+        if record.time_forfeit {
+            // The side that lost flagged.
+            time_forfeits[usize::from(result > 0.0)] += 1;
+        }
 
         if let Some(pair_index) = record.pair_index {
             let opening = &paired_openings[pair_index];
@@ -1294,15 +1429,19 @@ fn main() {
             } else {
                 "Baseline"
             };
-            let time_control = args
-                .depth
-                .map(|_| "-".to_string())
-                .unwrap_or_else(|| args.movetime.to_string());
+            let time_control = match (args.tc, args.depth) {
+                (Some(tc), _) => tc.to_string(),
+                (None, Some(_)) => "-".to_string(),
+                (None, None) => args.movetime.to_string(),
+            };
             pgn_buf.push_str(&format!("[Event \"Cozy A/B\"]\n[Site \"Local\"]\n[Round \"{}\"]\n[White \"{}\"]\n[Black \"{}\"]\n[Result \"{}\"]\n[TimeControl \"{}\"]\n",
                                      game_index + 1, white, black, res, time_control));
             if let Some(depth) = args.depth {
                 pgn_buf.push_str(&format!("[PlyDepth \"{}\"]\n", depth.max(1)));
             }
+            if record.time_forfeit {
+                pgn_buf.push_str("[Termination \"time forfeit\"]\n");
+            }
             if let Some(pair_index) = record.pair_index {
                 let opening = &paired_openings[pair_index];
                 pgn_buf.push_str(&format!(
@@ -1310,7 +1449,7 @@ fn main() {
                     opening.pair_index + 1,
                     opening.seed,
                     opening.opening_id,
-                    PAIRED_OPENING_POLICY
+                    opening_policy(&args)
                 ));
             }
             pgn_buf.push('\n');
@@ -1365,8 +1504,34 @@ fn main() {
 
     println!(
         "summary: games={} baseline_pts={} experimental_pts={} draws={}",
-        args.games, baseline_points, experimental_points, draws
+        games_played, baseline_points, experimental_points, draws
     );
+    if let Some(tc) = args.tc {
+        println!(
+            "clock: tc={} time_forfeits baseline={} experimental={}",
+            tc, time_forfeits[0], time_forfeits[1]
+        );
+    }
+    if let Some(sprt) = &sprt {
+        let config = sprt.config();
+        let (lower, upper) = config.bounds();
+        let result = match sprt.stop() {
+            Some(stop) => format!("{:?} at pair {}", stop.decision, stop.pairs),
+            None => "inconclusive".to_string(),
+        };
+        println!(
+            "sprt: elo0={} elo1={} alpha={} beta={} llr={:.3} bounds=[{:.3}, {:.3}] pairs={} result={}",
+            config.elo0,
+            config.elo1,
+            config.alpha,
+            config.beta,
+            sprt.llr(),
+            lower,
+            upper,
+            sprt.pentanomial().pairs(),
+            result
+        );
+    }
     println!(
         "match: wall_time={:.3}s parallel_workers={} schema={}",
         match_wall_time_s, parallel_games, PARALLELISM_SCHEMA
@@ -1389,7 +1554,7 @@ fn main() {
             "engine_reset_per_game": true,
             "parallel_work_unit": "opening-pair",
             "canonical_game_order": true,
-            "opening_policy": PAIRED_OPENING_POLICY,
+            "opening_policy": opening_policy(&args),
             "seed_scheme": "splitmix64-v1",
             "base_seed": args.seed,
             "openings": paired_openings.iter().map(|opening| serde_json::json!({
@@ -1417,13 +1582,39 @@ fn main() {
     } else {
         Vec::new()
     };
+    let pair_deltas = pair_results
+        .iter()
+        .filter_map(|pair| pair["delta_points"].as_f64())
+        .collect::<Vec<_>>();
+    let pair_bootstrap = paired_bootstrap(
+        &pair_deltas,
+        args.bootstrap_samples,
+        args.bootstrap_confidence,
+        args.seed,
+    );
+    let sprt_payload = sprt.as_ref().map(|sprt| {
+        let (lower, upper) = sprt.config().bounds();
+        serde_json::json!({
+            "config": sprt.config(),
+            "elo_model": "logistic",
+            "bounds": {"lower": lower, "upper": upper},
+            "llr": sprt.llr(),
+            "pentanomial": sprt.pentanomial().counts,
+            "pairs": sprt.pentanomial().pairs(),
+            "stop": sprt.stop(),
+            "llr_trajectory": sprt.trajectory(),
+        })
+    });
 
     // Optional machine-readable outputs
     if let Some(path) = args.json_out.as_deref() {
         let payload = serde_json::json!({
-            "games": args.games,
+            "games": games_played,
+            "games_requested": args.games,
             "movetime_ms": args.movetime,
             "fixed_depth": args.depth,
+            "time_control": args.tc.map(|tc| tc.to_string()),
+            "time_forfeits": {"baseline": time_forfeits[0], "experimental": time_forfeits[1]},
             "noise_plies": args.noise_plies,
             "noise_topk": args.noise_topk,
             "threads": args.threads,
@@ -1437,10 +1628,15 @@ fn main() {
             "paired_openings": args.paired_openings,
             "pairing": pairing_payload,
             "self_compare": self_compare,
+            "configs_differ": configs_differ,
             "engines": {"baseline": tn_base, "experimental": tn_exp},
+            "search_configs": {"baseline": search_configs[0], "experimental": search_configs[1]},
+            "nets": {"baseline": base_net, "experimental": exp_net},
             "points": {"baseline": baseline_points, "experimental": experimental_points, "draws": draws},
             "game_results": game_results,
             "pair_results": pair_results,
+            "pair_bootstrap": pair_bootstrap,
+            "sprt": sprt_payload,
             "baseline": {
                 "moves": cnt_base, "nodes": sum_nodes_base, "time_s": sum_time_base,
                 "avg_nps": avg_nps_base, "avg_depth": avg_depth_base
@@ -1457,7 +1653,7 @@ fn main() {
 
     if let Some(path) = args.csv_out.as_deref() {
         // Single-row CSV summary with header
-        let header = "games,movetime_ms,fixed_depth,noise_plies,noise_topk,threads,parallel_games_requested,parallel_games,parallelism_schema,match_wall_time_s,seed,self_compare,base_type,exp_type,baseline_pts,experimental_pts,draws,base_moves,base_nodes,base_time_s,base_avg_nps,base_avg_depth,exp_moves,exp_nodes,exp_time_s,exp_avg_nps,exp_avg_depth,paired_openings,opening_pairs,opening_policy,pair_seeds,opening_ids\n";
+        let header = "games,movetime_ms,fixed_depth,noise_plies,noise_topk,threads,parallel_games_requested,parallel_games,parallelism_schema,match_wall_time_s,seed,self_compare,base_type,exp_type,baseline_pts,experimental_pts,draws,base_moves,base_nodes,base_time_s,base_avg_nps,base_avg_depth,exp_moves,exp_nodes,exp_time_s,exp_avg_nps,exp_avg_depth,paired_openings,opening_pairs,opening_policy,pair_seeds,opening_ids,configs_differ\n";
         let fixed_depth = args.depth.map(|d| d.max(1).to_string()).unwrap_or_default();
         let pair_seeds = paired_openings
             .iter()
@@ -1470,19 +1666,20 @@ fn main() {
             .collect::<Vec<_>>()
             .join(";");
         let opening_policy = if args.paired_openings {
-            PAIRED_OPENING_POLICY
+            opening_policy(&args)
         } else {
             "legacy-engine-ordered-noise"
         };
         let row = format!(
-            "{},{},{},{},{},{},{},{},{},{:.6},{},{},{},{},{:.3},{:.3},{},{},{},{:.6},{:.1},{:.2},{},{},{:.6},{:.1},{:.2},{},{},{},{},{}\n",
-            args.games, args.movetime, fixed_depth, args.noise_plies, args.noise_topk, args.threads,
+            "{},{},{},{},{},{},{},{},{},{:.6},{},{},{},{},{:.3},{:.3},{},{},{},{:.6},{:.1},{:.2},{},{},{:.6},{:.1},{:.2},{},{},{},{},{},{}\n",
+            games_played, args.movetime, fixed_depth, args.noise_plies, args.noise_topk, args.threads,
             args.parallel_games, parallel_games, PARALLELISM_SCHEMA, match_wall_time_s,
             args.seed, self_compare, tn_base, tn_exp,
             baseline_points, experimental_points, draws,
             cnt_base, sum_nodes_base, sum_time_base, avg_nps_base, avg_depth_base,
             cnt_exp, sum_nodes_exp, sum_time_exp, avg_nps_exp, avg_depth_exp,
-            args.paired_openings, paired_openings.len(), opening_policy, pair_seeds, opening_ids
+            args.paired_openings, paired_openings.len(), opening_policy, pair_seeds, opening_ids,
+            configs_differ
         );
         let mut buf = String::new();
         buf.push_str(header);
@@ -1562,6 +1759,67 @@ mod tests {
         "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
     ];
 
+    #[test]
+    fn book_paired_openings_follow_the_book_until_it_runs_out() {
+        let start = Board::default();
+        let mut after_e4 = start.clone();
+        after_e4.play("e2e4".parse().unwrap());
+        let mut bytes = Vec::new();
+        for (board, mv, weight) in [
+            (&start, "e2e4", 1u16),
+            (&start, "d2d4", 1),
+            (&after_e4, "c7c5", 1),
+        ] {
+            let key = piebot::io::polyglot::polyglot_key(board).unwrap();
+            bytes.extend(key.to_be_bytes());
+            bytes.extend(piebot::io::polyglot::encode_move(mv.parse().unwrap()).to_be_bytes());
+            bytes.extend(weight.to_be_bytes());
+            bytes.extend(0u32.to_be_bytes());
+        }
+        let path =
+            std::env::temp_dir().join(format!("piebot_compare_book_{}.bin", std::process::id()));
+        std::fs::write(&path, bytes).expect("write book");
+        let first = build_paired_openings_from_book(16, &path, 6, 3).expect("book openings");
+        let second = build_paired_openings_from_book(16, &path, 6, 3).expect("book openings");
+        let one_ply = build_paired_openings_from_book(2, &path, 1, 3).expect("book openings");
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(first, second);
+        for opening in &first {
+            assert!(
+                opening.moves == ["e2e4", "c7c5"] || opening.moves == ["d2d4"],
+                "{:?}",
+                opening.moves
+            );
+            assert_eq!(opening.positions.len(), opening.moves.len() + 1);
+        }
+        assert!(first.iter().any(|opening| opening.moves.len() == 2));
+        assert!(first.iter().any(|opening| opening.moves.len() == 1));
+        assert_eq!(one_ply[0].moves.len(), 1);
+    }
+
+    #[test]
+    fn book_file_requires_paired_openings_and_excludes_suites() {
+        let unpaired = Args::try_parse_from(["compare_play", "--book-file", "book.bin"]).unwrap();
+        assert!(validate_openings_file_usage(&unpaired).is_err());
+        assert!(Args::try_parse_from([
+            "compare_play",
+            "--paired-openings",
+            "--book-file",
+            "book.bin",
+            "--openings-file",
+            "suite.fen",
+        ])
+        .is_err());
+        let missing = build_paired_openings_from_book(
+            2,
+            std::path::Path::new("/nonexistent/compare_book.bin"),
+            8,
+            1,
+        );
+        assert!(missing.is_err());
+    }
+
     #[test]
     fn suite_paired_openings_are_deterministic_distinct_and_fen_seeded() {
         let path = write_temp_suite("det", &SUITE_FENS.join("\n"));
@@ -1730,8 +1988,8 @@ mod tests {
             build_paired_openings(args.games, args.noise_plies, args.noise_topk, args.seed)
                 .expect("paired openings");
 
-        let (serial, serial_workers) = play_paired_match(&args, &openings, 1);
-        let (parallel, parallel_workers) = play_paired_match(&args, &openings, 2);
+        let (serial, serial_workers) = play_paired_match(&args, &openings, 1, None);
+        let (parallel, parallel_workers) = play_paired_match(&args, &openings, 2, None);
         assert_eq!(serial_workers, 1);
         assert_eq!(parallel_workers, 2);
         let serial_records = serial
@@ -1760,6 +2018,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sprt_stops_a_paired_match_once_a_bound_is_crossed() {
+        // Bounds this loose are crossed by the very first pair whatever it
+        // scores, so the budget of four pairs must not be spent.
+        let args = Args::try_parse_from([
+            "compare_play",
+            "--games",
+            "8",
+            "--paired-openings",
+            "--depth",
+            "1",
+            "--noise-plies",
+            "2",
+            "--max-plies",
+            "6",
+            "--same-search",
+            "--sprt",
+            "--sprt-elo0",
+            "0",
+            "--sprt-elo1",
+            "1000",
+            "--sprt-alpha",
+            "0.45",
+            "--sprt-beta",
+            "0.45",
+        ])
+        .expect("small SPRT match");
+        let openings =
+            build_paired_openings(args.games, args.noise_plies, args.noise_topk, args.seed)
+                .expect("paired openings");
+        let monitor = SprtMonitor::new(sprt_config(&args).unwrap().expect("SPRT enabled"));
+
+        let (games, _) = play_paired_match(&args, &openings, 1, Some(&monitor));
+        let sprt = monitor.into_inner();
+        let stop = sprt.stop().expect("a bound was crossed");
+        assert_eq!(stop.pairs, 1);
+        assert_eq!(games.len(), 2);
+        assert_eq!(sprt.trajectory().len(), 1);
+        let records = games
+            .iter()
+            .map(|game| game.record.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(pair_outcome_payload(&records).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn clocked_games_charge_each_engine_its_own_time() {
+        let args = Args::try_parse_from([
+            "compare_play",
+            "--games",
+            "2",
+            "--paired-openings",
+            "--tc",
+            "0.5+0.01",
+            "--noise-plies",
+            "2",
+            "--max-plies",
+            "6",
+            "--same-search",
+        ])
+        .expect("short clocked match");
+        let openings =
+            build_paired_openings(args.games, args.noise_plies, args.noise_topk, args.seed)
+                .expect("paired openings");
+
+        let (games, _) = play_paired_match(&args, &openings, 1, None);
+        for game in &games {
+            assert!(!game.record.time_forfeit, "{:?}", game.record);
+            assert_eq!(game.record.plies, 6);
+            // Two of six plies are the opening; each side searches two.
+            assert_eq!(game.baseline.moves, 2);
+            assert_eq!(game.experimental.moves, 2);
+        }
+        assert!(Args::try_parse_from(["compare_play", "--tc", "60"]).is_err());
+        assert!(Args::try_parse_from(["compare_play", "--tc", "1+0", "--depth", "3"]).is_err());
+    }
+
+    #[test]
+    fn sprt_requires_paired_openings_and_ordered_hypotheses() {
+        let unpaired = Args::try_parse_from(["compare_play", "--sprt"]).unwrap();
+        assert!(sprt_config(&unpaired).is_err());
+        let inverted = Args::try_parse_from([
+            "compare_play",
+            "--paired-openings",
+            "--sprt",
+            "--sprt-elo0",
+            "5",
+            "--sprt-elo1",
+            "-5",
+        ])
+        .unwrap();
+        assert!(sprt_config(&inverted).is_err());
+        let off = Args::try_parse_from(["compare_play", "--paired-openings"]).unwrap();
+        assert_eq!(sprt_config(&off), Ok(None));
+    }
+
     #[test]
     fn paired_openings_require_an_even_game_count_without_affecting_legacy_mode() {
         assert!(build_paired_openings(3, 8, 5, 91).is_err());
@@ -1899,35 +2253,35 @@ mod tests {
     fn san_formats_cozy_castling_encoding() {
         let board =
             Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", false).expect("valid FEN");
-        assert_eq!("O-O", san_for_move(&board, move_by_uci(&board, "e1h1")));
-        assert_eq!("O-O-O", san_for_move(&board, move_by_uci(&board, "e1a1")));
+        assert_eq!("O-O", move_to_san(&board, move_by_uci(&board, "e1h1")));
+        assert_eq!("O-O-O", move_to_san(&board, move_by_uci(&board, "e1a1")));
 
         let checking_castle =
             Board::from_fen("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", false).expect("valid FEN");
         assert_eq!(
             "O-O-O+",
-            san_for_move(&checking_castle, move_by_uci(&checking_castle, "e1a1"))
+            move_to_san(&checking_castle, move_by_uci(&checking_castle, "e1a1"))
         );
     }
 
     #[test]
     fn san_formats_en_passant_as_capture() {
         let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", false).expect("valid FEN");
-        assert_eq!("exd6", san_for_move(&board, move_by_uci(&board, "e5d6")));
+        assert_eq!("exd6", move_to_san(&board, move_by_uci(&board, "e5d6")));
     }
 
     #[test]
     fn san_uses_file_when_ambiguous_pieces_share_a_rank() {
         let board = Board::from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", false).expect("valid FEN");
-        assert_eq!("Nbd2", san_for_move(&board, move_by_uci(&board, "b1d2")));
-        assert_eq!("Nfd2", san_for_move(&board, move_by_uci(&board, "f1d2")));
+        assert_eq!("Nbd2", move_to_san(&board, move_by_uci(&board, "b1d2")));
+        assert_eq!("Nfd2", move_to_san(&board, move_by_uci(&board, "f1d2")));
     }
 
     #[test]
     fn san_uses_rank_when_ambiguous_pieces_share_a_file() {
         let board = Board::from_fen("4k3/8/8/8/8/1N6/8/1N2K3 w - - 0 1", false).expect("valid FEN");
-        assert_eq!("N1d2", san_for_move(&board, move_by_uci(&board, "b1d2")));
-        assert_eq!("N3d2", san_for_move(&board, move_by_uci(&board, "b3d2")));
+        assert_eq!("N1d2", move_to_san(&board, move_by_uci(&board, "b1d2")));
+        assert_eq!("N3d2", move_to_san(&board, move_by_uci(&board, "b3d2")));
     }
 
     #[test]
@@ -1936,11 +2290,11 @@ mod tests {
             Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", false).expect("valid FEN");
         assert_eq!(
             "a8=Q+",
-            san_for_move(&promotion, move_by_uci(&promotion, "a7a8q"))
+            move_to_san(&promotion, move_by_uci(&promotion, "a7a8q"))
         );
 
         let mate = Board::from_fen("7k/8/5KQ1/8/8/8/8/8 w - - 0 1", false).expect("valid FEN");
-        assert_eq!("Qg7#", san_for_move(&mate, move_by_uci(&mate, "g6g7")));
+        assert_eq!("Qg7#", move_to_san(&mate, move_by_uci(&mate, "g6g7")));
     }
 
     fn first_order_difference_position() -> Board {
@@ -1969,6 +2323,62 @@ mod tests {
         panic!("no candidate position showed a legal-order vs engine-order difference");
     }
 
+    #[test]
+    fn net_hashes_come_from_the_file_each_side_loads() {
+        let dir = std::env::temp_dir().join(format!("compare_play_nets_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let quant = dir.join("q.nnue");
+        let dense = dir.join("d.nnue");
+        std::fs::write(&quant, b"quant bytes").unwrap();
+        std::fs::write(&dense, b"dense bytes").unwrap();
+        let (q, d) = (quant.to_str().unwrap(), dense.to_str().unwrap());
+        let hash = |bytes: &[u8]| Some(piebot::eval::nnue::loader::net_hash(bytes));
+
+        assert_eq!(side_net_hash(Some(q), Some(d)), hash(b"quant bytes"));
+        assert_eq!(side_net_hash(None, Some(d)), hash(b"dense bytes"));
+        assert_eq!(side_net_hash(None, None), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn search_configs_reach_each_side_and_bad_files_are_usage_errors() {
+        let dir = std::env::temp_dir().join(format!("compare_play_configs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let exp = dir.join("exp.json");
+        let typo = dir.join("typo.json");
+        std::fs::write(&exp, r#"{"lmr_divisor": 1.75, "futility_margins_cp": []}"#).unwrap();
+        std::fs::write(&typo, r#"{"lmr_divsor": 1.75}"#).unwrap();
+
+        let args = Args::try_parse_from(["compare_play", "--exp-config", exp.to_str().unwrap()])
+            .expect("a valid config file");
+        assert_eq!(args.base_config, None);
+        let expected = SearchConfig {
+            lmr_divisor: 1.75,
+            futility_margins_cp: Vec::new(),
+            ..SearchConfig::default()
+        };
+        assert_eq!(args.exp_config.as_ref(), Some(&expected));
+        let base = build_baseline_engine(&args);
+        assert_eq!(base.searcher.search_config(), &SearchConfig::default());
+        match build_experimental_engine(&args).inner {
+            ExperimentalEngineKind::Temp(s) => assert_eq!(s.search_config(), &expected),
+            ExperimentalEngineKind::Base(s) => assert_eq!(s.search_config(), &expected),
+        }
+
+        assert!(
+            Args::try_parse_from(["compare_play", "--base-config", typo.to_str().unwrap()])
+                .is_err()
+        );
+        assert!(
+            Args::try_parse_from(["compare_play", "--exp-config", "/missing/piebot.json"]).is_err()
+        );
+        assert!(is_self_compare(false, true, false));
+        assert!(is_self_compare(true, false, false));
+        assert!(!is_self_compare(true, true, true));
+        assert!(!is_self_compare(false, false, false));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn noisy_choice_topk_one_picks_first() {
         let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false).expect("valid FEN");
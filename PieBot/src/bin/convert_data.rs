@@ -0,0 +1,176 @@
+use clap::{Parser, ValueEnum};
+use piebot::selfplay::packed::{
+    shuffle_packed, write_packed_records, PackedFile, PackedPosition, PACKED_EXTENSION,
+};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Jsonl,
+    Packed,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "convert-data",
+    about = "Convert self-play training data between JSONL and PIEPACK shards"
+)]
+struct Args {
+    /// Input file, or a directory of *.jsonl and *.piepack shards
+    #[arg(long)]
+    input: PathBuf,
+    /// Output directory for shard_NNNNNN files
+    #[arg(long)]
+    output: PathBuf,
+    /// Output format
+    #[arg(long, value_enum)]
+    to: Format,
+    #[arg(long, default_value_t = 100_000)]
+    records_per_shard: usize,
+    /// Shuffle the records (packed output only)
+    #[arg(long, default_value_t = false)]
+    shuffle: bool,
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+}
+
+fn input_format(path: &Path) -> Option<Format> {
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("jsonl") => Some(Format::Jsonl),
+        Some(PACKED_EXTENSION) => Some(Format::Packed),
+        _ => None,
+    }
+}
+
+fn collect_inputs(input: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if input.is_file() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(input)? {
+        let path = entry?.path();
+        if input_format(&path).is_some() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Every record of `inputs` as a packed position, in file order.
+fn positions(inputs: &[PathBuf]) -> impl Iterator<Item = Result<PackedPosition, String>> + '_ {
+    inputs
+        .iter()
+        .flat_map(|path| -> Box<dyn Iterator<Item = _>> {
+            let at = move |line: usize, e: String| format!("{}:{line}: {e}", path.display());
+            match input_format(path) {
+                Some(Format::Packed) => match PackedFile::open(path) {
+                    Ok(file) => {
+                        let records: Vec<_> = file.view().iter().collect();
+                        Box::new(records.into_iter())
+                    }
+                    Err(e) => Box::new(std::iter::once(Err(e))),
+                },
+                _ => match File::open(path) {
+                    Ok(f) => Box::new(
+                        BufReader::new(f)
+                            .lines()
+                            .enumerate()
+                            .filter(|(_, line)| {
+                                line.as_ref().map_or(true, |l| !l.trim().is_empty())
+                            })
+                            .map(move |(i, line)| {
+                                let line = line.map_err(|e| at(i + 1, e.to_string()))?;
+                                match serde_json::from_str::<Value>(&line) {
+                                    Ok(Value::Object(map)) => {
+                                        PackedPosition::from_json(&map).map_err(|e| at(i + 1, e))
+                                    }
+                                    _ => Err(at(i + 1, "not a JSON object".to_string())),
+                                }
+                            }),
+                    ),
+                    Err(e) => Box::new(std::iter::once(Err(at(0, e.to_string())))),
+                },
+            }
+        })
+}
+
+fn write_jsonl(inputs: &[PathBuf], out_dir: &Path, per_shard: usize) -> anyhow::Result<usize> {
+    fs::create_dir_all(out_dir)?;
+    let mut shards = 0usize;
+    let mut in_shard = 0usize;
+    let mut writer: Option<BufWriter<File>> = None;
+    for position in positions(inputs) {
+        let position = position.map_err(|e| anyhow::anyhow!(e))?;
+        if writer.is_none() || in_shard >= per_shard.max(1) {
+            if let Some(mut w) = writer.take() {
+                w.flush()?;
+            }
+            let path = out_dir.join(format!("shard_{shards:06}.jsonl"));
+            writer = Some(BufWriter::new(File::create(path)?));
+            shards += 1;
+            in_shard = 0;
+        }
+        let w = writer.as_mut().unwrap();
+        serde_json::to_writer(&mut *w, &position.to_json())?;
+        w.write_all(b"\n")?;
+        in_shard += 1;
+    }
+    if let Some(mut w) = writer {
+        w.flush()?;
+    }
+    Ok(shards)
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let inputs = collect_inputs(&args.input)?;
+    if inputs.is_empty() {
+        anyhow::bail!(
+            "no jsonl or {PACKED_EXTENSION} inputs found at {}",
+            args.input.display()
+        );
+    }
+    if args.shuffle && args.to != Format::Packed {
+        anyhow::bail!("--shuffle needs --to packed");
+    }
+    let shards = match args.to {
+        Format::Jsonl => write_jsonl(&inputs, &args.output, args.records_per_shard)?,
+        Format::Packed if args.shuffle => {
+            // Convert into a staging directory first unless the inputs are
+            // packed already; the shuffle reads raw records.
+            let all_packed = inputs
+                .iter()
+                .all(|p| input_format(p) == Some(Format::Packed));
+            let staging = args.output.join(".convert_staging");
+            let sources = if all_packed {
+                inputs.clone()
+            } else {
+                let records = positions(&inputs).map(|p| p.map(|p| p.encode()));
+                write_packed_records(records, &staging, args.records_per_shard)
+                    .map_err(|e| anyhow::anyhow!(e))?
+            };
+            let out = shuffle_packed(&sources, &args.output, args.records_per_shard, args.seed)
+                .map_err(|e| anyhow::anyhow!(e))?;
+            if !all_packed {
+                fs::remove_dir_all(&staging)?;
+            }
+            out.len()
+        }
+        Format::Packed => {
+            let records = positions(&inputs).map(|p| p.map(|p| p.encode()));
+            write_packed_records(records, &args.output, args.records_per_shard)
+                .map_err(|e| anyhow::anyhow!(e))?
+                .len()
+        }
+    };
+    println!(
+        "Wrote {shards} {:?} shards to {}",
+        args.to,
+        args.output.display()
+    );
+    Ok(())
+}
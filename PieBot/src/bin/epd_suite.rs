@@ -0,0 +1,645 @@
+use clap::Parser;
+use cozy_chess::Move;
+use piebot::eval::nnue::loader::QuantNnue;
+use piebot::io::fen::{parse_epd, parse_fen, EpdRecord};
+use piebot::io::pgn::move_to_san;
+use piebot::search::alphabeta::{EvalMode, SearchInfo, SearchParams, Searcher};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "epd-suite",
+    about = "Solve an EPD test suite (bm/am) and report per-position results"
+)]
+struct Args {
+    /// EPD suite; `{"fen","best"}` JSONL lines from the acceptance suites
+    /// are read as `bm` positions
+    #[arg(long)]
+    suite: PathBuf,
+    /// Depth budget per position
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    depth: Option<u32>,
+    /// Node budget per position
+    #[arg(long)]
+    nodes: Option<u64>,
+    /// Time budget per position in milliseconds
+    #[arg(long)]
+    movetime_ms: Option<u64>,
+    /// Positions solved in parallel, one single-threaded search each
+    #[arg(long, default_value_t = 1)]
+    workers: usize,
+    /// Transposition table per search in MB
+    #[arg(long, default_value_t = 16)]
+    hash_mb: usize,
+    /// Quantized NNUE for the base configuration (PST eval when unset)
+    #[arg(long)]
+    base_nnue_quant_file: Option<PathBuf>,
+    #[arg(long, default_value_t = 100)]
+    base_nnue_blend_percent: u8,
+    /// Quantized NNUE for the experimental configuration; enables A/B mode
+    #[arg(long)]
+    exp_nnue_quant_file: Option<PathBuf>,
+    #[arg(long, default_value_t = 100)]
+    exp_nnue_blend_percent: u8,
+    /// Run the experimental configuration even without an exp net
+    #[arg(long, default_value_t = false)]
+    compare: bool,
+    /// Write the full report as JSON
+    #[arg(long)]
+    json_out: Option<PathBuf>,
+    /// Write one row per position and configuration as CSV
+    #[arg(long)]
+    csv_out: Option<PathBuf>,
+    /// Print every position, not just failures
+    #[arg(long, default_value_t = false)]
+    verbose: bool,
+}
+
+/// One suite position with its expected and avoided moves.
+struct SuitePosition {
+    id: String,
+    fen: String,
+    record: EpdRecord,
+    bm: Vec<Move>,
+    am: Vec<Move>,
+}
+
+impl SuitePosition {
+    fn accepts(&self, mv: &str) -> bool {
+        (self.bm.is_empty() || self.bm.iter().any(|m| m.to_string() == mv))
+            && !self.am.iter().any(|m| m.to_string() == mv)
+    }
+
+    fn san_list(&self, moves: &[Move]) -> Vec<String> {
+        moves
+            .iter()
+            .map(|&mv| move_to_san(&self.record.board, mv))
+            .collect()
+    }
+}
+
+fn parse_suite_line(line: &str) -> Result<EpdRecord, String> {
+    if !line.starts_with('{') {
+        return parse_epd(line);
+    }
+    let legacy: serde_json::Value =
+        serde_json::from_str(line).map_err(|e| format!("invalid JSON suite record: {e}"))?;
+    let field = |name: &str| {
+        legacy
+            .get(name)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| format!("JSON suite record has no {name:?}"))
+    };
+    Ok(EpdRecord {
+        board: parse_fen(field("fen")?)?,
+        ops: vec![("bm".to_string(), vec![field("best")?.to_string()])],
+    })
+}
+
+fn load_suite(path: &Path) -> Result<Vec<SuitePosition>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read suite {}: {e}", path.display()))?;
+    let mut out = Vec::new();
+    for (line_idx, line) in contents.lines().enumerate() {
+        let raw = line.trim();
+        if raw.is_empty() || raw.starts_with('#') {
+            continue;
+        }
+        let at = |e: String| format!("{} line {}: {e}", path.display(), line_idx + 1);
+        let record = parse_suite_line(raw).map_err(at)?;
+        let bm = record.bm().map_err(at)?;
+        let am = record.am().map_err(at)?;
+        if bm.is_empty() && am.is_empty() {
+            return Err(at("position has neither bm nor am".to_string()));
+        }
+        out.push(SuitePosition {
+            id: record
+                .id()
+                .map_or_else(|| format!("#{}", out.len() + 1), str::to_string),
+            fen: record.board.to_string(),
+            record,
+            bm,
+            am,
+        });
+    }
+    if out.is_empty() {
+        return Err(format!("suite {} contains no positions", path.display()));
+    }
+    Ok(out)
+}
+
+/// An engine setup under test.
+struct EngineConfig {
+    label: &'static str,
+    nnue_quant_file: Option<PathBuf>,
+    model: Option<QuantNnue>,
+    blend_percent: u8,
+}
+
+impl EngineConfig {
+    fn load(
+        label: &'static str,
+        path: Option<&PathBuf>,
+        blend_percent: u8,
+    ) -> anyhow::Result<Self> {
+        let model = match path {
+            Some(path) => Some(QuantNnue::load_quantized(path)?),
+            None => None,
+        };
+        Ok(Self {
+            label,
+            nnue_quant_file: path.cloned(),
+            model,
+            blend_percent,
+        })
+    }
+
+    fn searcher(&self, hash_mb: usize) -> Searcher {
+        let mut s = Searcher::default();
+        s.set_tt_capacity_mb(hash_mb.max(1));
+        if let Some(model) = self.model.as_ref() {
+            s.set_use_nnue(true);
+            s.set_eval_mode(EvalMode::Nnue);
+            s.set_eval_blend_percent(self.blend_percent);
+            s.set_nnue_quant_model(model.clone());
+        }
+        s
+    }
+
+    fn eval_name(&self) -> String {
+        match self.nnue_quant_file.as_ref() {
+            Some(path) => format!("nnue:{} ({}%)", path.display(), self.blend_percent),
+            None => "pst".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Budget {
+    depth: Option<u32>,
+    nodes: Option<u64>,
+    movetime_ms: Option<u64>,
+}
+
+impl Budget {
+    fn params(&self) -> SearchParams {
+        SearchParams {
+            // Depth 0 iterates until the node or time budget runs out.
+            depth: self.depth.unwrap_or(0),
+            max_nodes: self.nodes,
+            movetime: self.movetime_ms.map(Duration::from_millis),
+            use_tt: true,
+            order_captures: true,
+            use_history: true,
+            use_killers: true,
+            use_nullmove: true,
+            use_aspiration: true,
+            aspiration_window_cp: 35,
+            use_lmr: true,
+            threads: 1,
+            deterministic: true,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct PositionReport {
+    config: &'static str,
+    index: usize,
+    id: String,
+    fen: String,
+    bm: Vec<String>,
+    am: Vec<String>,
+    best_move: Option<String>,
+    best_move_san: Option<String>,
+    score_cp: i32,
+    depth: u32,
+    nodes: u64,
+    time_ms: u64,
+    solved: bool,
+    /// Iteration from which the best move was correct and stayed correct.
+    solve_depth: Option<u32>,
+    solve_time_ms: Option<u64>,
+    solve_nodes: Option<u64>,
+}
+
+/// Index of the first iteration from which every later best move is
+/// accepted, or `None` when the last one is not.
+fn first_stable_solution(best_moves: &[&str], accepts: impl Fn(&str) -> bool) -> Option<usize> {
+    let mut first = None;
+    for (index, mv) in best_moves.iter().enumerate() {
+        if accepts(mv) {
+            first.get_or_insert(index);
+        } else {
+            first = None;
+        }
+    }
+    first
+}
+
+fn solve_position(
+    config: &EngineConfig,
+    budget: &Budget,
+    hash_mb: usize,
+    index: usize,
+    pos: &SuitePosition,
+) -> PositionReport {
+    let mut searcher = config.searcher(hash_mb);
+    let (tx, rx) = mpsc::channel();
+    searcher.set_info_sender(Some(tx));
+    let started = Instant::now();
+    let result = searcher.search_with_params(&pos.record.board, budget.params());
+    let time_ms = started.elapsed().as_millis() as u64;
+    searcher.set_info_sender(None);
+    let iterations: Vec<SearchInfo> = rx
+        .try_iter()
+        .filter(|info| info.multipv == 1 && !info.pv.is_empty())
+        .collect();
+
+    let solved = result.bestmove.as_deref().is_some_and(|mv| pos.accepts(mv));
+    let best_moves: Vec<&str> = iterations.iter().map(|i| i.pv[0].as_str()).collect();
+    let stable = solved
+        .then(|| first_stable_solution(&best_moves, |mv| pos.accepts(mv)))
+        .flatten();
+    let (solve_depth, solve_time_ms, solve_nodes) = match (solved, stable) {
+        (true, Some(i)) => (
+            Some(iterations[i].depth),
+            Some(iterations[i].time_ms),
+            Some(iterations[i].nodes),
+        ),
+        // An interrupted last iteration changed the move: it counts from there.
+        (true, None) => (Some(result.depth), Some(time_ms), Some(result.nodes)),
+        (false, _) => (None, None, None),
+    };
+    let best_move_san = result.bestmove.as_deref().and_then(|uci| {
+        uci.parse::<Move>()
+            .ok()
+            .filter(|&mv| pos.record.board.is_legal(mv))
+            .map(|mv| move_to_san(&pos.record.board, mv))
+    });
+    PositionReport {
+        config: config.label,
+        index,
+        id: pos.id.clone(),
+        fen: pos.fen.clone(),
+        bm: pos.san_list(&pos.bm),
+        am: pos.san_list(&pos.am),
+        best_move: result.bestmove.clone(),
+        best_move_san,
+        score_cp: result.score_cp,
+        depth: result.depth,
+        nodes: result.nodes,
+        time_ms,
+        solved,
+        solve_depth,
+        solve_time_ms,
+        solve_nodes,
+    }
+}
+
+fn run_suite(
+    pool: &rayon::ThreadPool,
+    config: &EngineConfig,
+    budget: &Budget,
+    hash_mb: usize,
+    positions: &[SuitePosition],
+) -> Vec<PositionReport> {
+    pool.install(|| {
+        positions
+            .par_iter()
+            .enumerate()
+            .map(|(index, pos)| solve_position(config, budget, hash_mb, index, pos))
+            .collect()
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct ConfigSummary {
+    config: &'static str,
+    eval: String,
+    positions: usize,
+    solved: usize,
+    solve_rate: f64,
+    total_nodes: u64,
+    total_time_ms: u64,
+    /// Mean over solved positions.
+    avg_solve_time_ms: Option<f64>,
+    avg_solve_depth: Option<f64>,
+}
+
+fn summarize(config: &EngineConfig, reports: &[PositionReport]) -> ConfigSummary {
+    let solved: Vec<&PositionReport> = reports.iter().filter(|r| r.solved).collect();
+    let mean = |values: Vec<f64>| {
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    ConfigSummary {
+        config: config.label,
+        eval: config.eval_name(),
+        positions: reports.len(),
+        solved: solved.len(),
+        solve_rate: solved.len() as f64 / reports.len().max(1) as f64,
+        total_nodes: reports.iter().map(|r| r.nodes).sum(),
+        total_time_ms: reports.iter().map(|r| r.time_ms).sum(),
+        avg_solve_time_ms: mean(
+            solved
+                .iter()
+                .filter_map(|r| r.solve_time_ms)
+                .map(|t| t as f64)
+                .collect(),
+        ),
+        avg_solve_depth: mean(
+            solved
+                .iter()
+                .filter_map(|r| r.solve_depth)
+                .map(f64::from)
+                .collect(),
+        ),
+    }
+}
+
+/// Positions solved by exactly one of two configurations.
+#[derive(Debug, Default, Serialize)]
+struct Comparison {
+    only_base: Vec<String>,
+    only_exp: Vec<String>,
+}
+
+fn compare(base: &[PositionReport], exp: &[PositionReport]) -> Comparison {
+    let mut out = Comparison::default();
+    for (b, e) in base.iter().zip(exp) {
+        match (b.solved, e.solved) {
+            (true, false) => out.only_base.push(b.id.clone()),
+            (false, true) => out.only_exp.push(e.id.clone()),
+            _ => {}
+        }
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    suite: String,
+    budget: Budget,
+    workers: usize,
+    hash_mb: usize,
+    summaries: Vec<ConfigSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comparison: Option<Comparison>,
+    positions: Vec<&'a PositionReport>,
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_csv(path: &Path, reports: &[&PositionReport]) -> std::io::Result<()> {
+    let mut out = String::from(
+        "config,index,id,fen,bm,am,best_move,best_move_san,score_cp,depth,nodes,time_ms,solved,solve_depth,solve_time_ms,solve_nodes\n",
+    );
+    let opt = |v: Option<String>| v.unwrap_or_default();
+    for r in reports {
+        let row = [
+            r.config.to_string(),
+            r.index.to_string(),
+            csv_field(&r.id),
+            csv_field(&r.fen),
+            csv_field(&r.bm.join(" ")),
+            csv_field(&r.am.join(" ")),
+            opt(r.best_move.clone()),
+            opt(r.best_move_san.clone()),
+            r.score_cp.to_string(),
+            r.depth.to_string(),
+            r.nodes.to_string(),
+            r.time_ms.to_string(),
+            r.solved.to_string(),
+            opt(r.solve_depth.map(|v| v.to_string())),
+            opt(r.solve_time_ms.map(|v| v.to_string())),
+            opt(r.solve_nodes.map(|v| v.to_string())),
+        ];
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    std::fs::write(path, out)
+}
+
+fn print_summary(summary: &ConfigSummary) {
+    println!(
+        "{}: solved {}/{} ({:.1}%) avg_solve_ms={} avg_solve_depth={} nodes={} time_ms={} eval={}",
+        summary.config,
+        summary.solved,
+        summary.positions,
+        summary.solve_rate * 100.0,
+        summary
+            .avg_solve_time_ms
+            .map_or("-".to_string(), |v| format!("{v:.1}")),
+        summary
+            .avg_solve_depth
+            .map_or("-".to_string(), |v| format!("{v:.1}")),
+        summary.total_nodes,
+        summary.total_time_ms,
+        summary.eval
+    );
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    if args.depth.is_none() && args.nodes.is_none() && args.movetime_ms.is_none() {
+        anyhow::bail!("set at least one of --depth, --nodes or --movetime-ms");
+    }
+    let budget = Budget {
+        depth: args.depth,
+        nodes: args.nodes,
+        movetime_ms: args.movetime_ms,
+    };
+    let positions = load_suite(&args.suite).map_err(|e| anyhow::anyhow!(e))?;
+    let mut configs = vec![EngineConfig::load(
+        "base",
+        args.base_nnue_quant_file.as_ref(),
+        args.base_nnue_blend_percent,
+    )?];
+    if args.compare || args.exp_nnue_quant_file.is_some() {
+        configs.push(EngineConfig::load(
+            "exp",
+            args.exp_nnue_quant_file.as_ref(),
+            args.exp_nnue_blend_percent,
+        )?);
+    }
+    let workers = args.workers.max(1);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(workers)
+        .build()?;
+    eprintln!(
+        "Suite {}: {} positions, {} workers, budget {:?}",
+        args.suite.display(),
+        positions.len(),
+        workers,
+        budget
+    );
+
+    let runs: Vec<Vec<PositionReport>> = configs
+        .iter()
+        .map(|config| run_suite(&pool, config, &budget, args.hash_mb, &positions))
+        .collect();
+    for reports in &runs {
+        for r in reports {
+            if args.verbose || !r.solved {
+                println!(
+                    "{} {} {} {}: got {} (bm {} am {}) depth={} solve_depth={} solve_ms={}",
+                    r.config,
+                    if r.solved { "ok  " } else { "FAIL" },
+                    r.index,
+                    r.id,
+                    r.best_move_san.as_deref().unwrap_or("-"),
+                    r.bm.join(" "),
+                    r.am.join(" "),
+                    r.depth,
+                    r.solve_depth.map_or("-".to_string(), |d| d.to_string()),
+                    r.solve_time_ms.map_or("-".to_string(), |t| t.to_string()),
+                );
+            }
+        }
+    }
+    let summaries: Vec<ConfigSummary> = configs
+        .iter()
+        .zip(&runs)
+        .map(|(config, reports)| summarize(config, reports))
+        .collect();
+    for summary in &summaries {
+        print_summary(summary);
+    }
+    let comparison = (runs.len() == 2).then(|| compare(&runs[0], &runs[1]));
+    if let Some(c) = comparison.as_ref() {
+        println!(
+            "only base: {} [{}]; only exp: {} [{}]",
+            c.only_base.len(),
+            c.only_base.join(" "),
+            c.only_exp.len(),
+            c.only_exp.join(" ")
+        );
+    }
+
+    let all: Vec<&PositionReport> = runs.iter().flatten().collect();
+    if let Some(path) = args.csv_out.as_ref() {
+        write_csv(path, &all)?;
+    }
+    if let Some(path) = args.json_out.as_ref() {
+        let report = Report {
+            suite: args.suite.display().to_string(),
+            budget,
+            workers,
+            hash_mb: args.hash_mb,
+            summaries,
+            comparison,
+            positions: all,
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_suite(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "piebot-epd-suite-{}-{name}.epd",
+            std::process::id()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn stable_solution_is_the_start_of_the_final_correct_run() {
+        let accepts = |mv: &str| mv == "a";
+        assert_eq!(
+            first_stable_solution(&["a", "b", "a", "a"], accepts),
+            Some(2)
+        );
+        assert_eq!(first_stable_solution(&["a", "a"], accepts), Some(0));
+        assert_eq!(first_stable_solution(&["a", "b"], accepts), None);
+        assert_eq!(first_stable_solution(&[], accepts), None);
+    }
+
+    #[test]
+    fn suite_reads_epd_and_legacy_json_and_rejects_unscored_lines() {
+        let path = write_suite(
+            "mixed",
+            "# comment\n\
+             7k/8/6K1/8/8/8/8/1Q6 w - - bm Qb8+; id \"mate\";\n\
+             {\"fen\":\"7k/8/6K1/8/8/8/8/1Q6 w - - 0 1\",\"best\":\"b1b8\"}\n\
+             4k3/8/8/8/8/8/8/4K2R w K - am Rh8;\n",
+        );
+        let positions = load_suite(&path).unwrap();
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions[0].id, "mate");
+        assert_eq!(positions[1].id, "#2");
+        assert_eq!(positions[1].san_list(&positions[1].bm), ["Qb8#"]);
+        assert!(positions[2].accepts("e1h1"));
+        assert!(!positions[2].accepts("h1h8"));
+
+        std::fs::write(&path, "4k3/8/8/8/8/8/8/4K2R w K - id \"x\";\n").unwrap();
+        let error = load_suite(&path).err().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(error.contains("line 1"), "{error}");
+        assert!(error.contains("neither bm nor am"), "{error}");
+    }
+
+    #[test]
+    fn suite_run_reports_solve_depth_and_compares_configs() {
+        let path = write_suite(
+            "run",
+            "7k/8/6K1/8/8/8/8/1Q6 w - - bm Qb8#; id \"mate-in-1\";\n\
+             r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - am Qe2; id \"avoid\";\n",
+        );
+        let positions = load_suite(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let budget = Budget {
+            depth: Some(3),
+            nodes: None,
+            movetime_ms: None,
+        };
+        let base = EngineConfig::load("base", None, 100).unwrap();
+        let exp = EngineConfig::load("exp", None, 100).unwrap();
+        let a = run_suite(&pool, &base, &budget, 1, &positions);
+        let b = run_suite(&pool, &exp, &budget, 1, &positions);
+
+        assert_eq!(a.len(), 2);
+        assert!(a[0].solved);
+        assert_eq!(a[0].solve_depth, Some(1));
+        assert!(a[0].best_move_san.as_deref().unwrap().ends_with('#'));
+        assert_eq!(a[0].bm, ["Qb8#"]);
+        assert_eq!(a[1].am, ["Qe2"]);
+
+        let summary = summarize(&base, &a);
+        assert_eq!(summary.positions, 2);
+        assert!(summary.solved >= 1);
+        let c = compare(&a, &b);
+        assert!(c.only_base.is_empty() && c.only_exp.is_empty());
+
+        let csv = std::env::temp_dir().join(format!("piebot-epd-{}.csv", std::process::id()));
+        write_csv(&csv, &a.iter().collect::<Vec<_>>()).unwrap();
+        let text = std::fs::read_to_string(&csv).unwrap();
+        let _ = std::fs::remove_file(&csv);
+        assert_eq!(text.lines().count(), 3);
+        assert!(text
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("base,0,mate-in-1,"));
+    }
+}
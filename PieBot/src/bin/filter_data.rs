@@ -0,0 +1,455 @@
+use clap::Parser;
+use cozy_chess::{Board, Move, Piece};
+use piebot::io::fen::parse_fen;
+use piebot::search::alphabeta::Searcher;
+use piebot::search::eval::eval_cp;
+use piebot::search::zobrist;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const STATS_FILE: &str = "filter_stats.json";
+const STAGING_FILE: &str = ".filter_staging.jsonl";
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "filter-data",
+    about = "Deduplicate and filter self-play/relabel JSONL before training"
+)]
+struct Args {
+    /// Input JSONL file, or a directory of *.jsonl shards
+    #[arg(long)]
+    input: PathBuf,
+    /// Output directory for shard_NNNNNN.jsonl files and filter_stats.json
+    #[arg(long)]
+    output: PathBuf,
+    /// Keep at most this many records per position key (0 = no dedup)
+    #[arg(long, default_value_t = 1)]
+    max_per_key: u32,
+    /// Keep positions where the side to move is in check
+    #[arg(long, default_value_t = false)]
+    keep_in_check: bool,
+    /// Keep positions whose best move is a capture or promotion
+    #[arg(long, default_value_t = false)]
+    keep_tactical_best: bool,
+    /// Drop positions whose quiescence eval differs from the static eval by
+    /// more than this many cp (0 = off)
+    #[arg(long, default_value_t = 60)]
+    max_qsearch_gap_cp: i32,
+    /// Downsample game phases to at most this multiple of the smallest
+    /// non-empty phase (0 = no rebalancing)
+    #[arg(long, default_value_t = 0.0)]
+    phase_ratio: f64,
+    #[arg(long, default_value_t = 100_000)]
+    records_per_shard: usize,
+    /// Seed for the phase downsampling
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+}
+
+/// Game phase by non-pawn material: knights and bishops 1, rooks 2,
+/// queens 4, so the start position is 24.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl Phase {
+    fn of(board: &Board) -> Self {
+        let count = |piece| board.pieces(piece).len();
+        let material = count(Piece::Knight)
+            + count(Piece::Bishop)
+            + 2 * count(Piece::Rook)
+            + 4 * count(Piece::Queen);
+        match material {
+            20.. => Phase::Opening,
+            9..=19 => Phase::Middlegame,
+            _ => Phase::Endgame,
+        }
+    }
+}
+
+/// Why a record was dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum DropReason {
+    Invalid,
+    InCheck,
+    TacticalBest,
+    QsearchGap,
+    Duplicate,
+    PhaseRebalance,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Stats {
+    read: u64,
+    kept: u64,
+    dropped: BTreeMap<DropReason, u64>,
+    /// Records per phase that passed the filters, before rebalancing.
+    phase_filtered: BTreeMap<Phase, u64>,
+    /// Records per phase in the output.
+    phase_kept: BTreeMap<Phase, u64>,
+}
+
+impl Stats {
+    fn drop(&mut self, reason: DropReason) {
+        *self.dropped.entry(reason).or_default() += 1;
+    }
+}
+
+struct Filter {
+    max_per_key: u32,
+    keep_in_check: bool,
+    keep_tactical_best: bool,
+    max_qsearch_gap_cp: i32,
+    seen: HashMap<u64, u32>,
+    searcher: Searcher,
+}
+
+impl Filter {
+    fn new(args: &Args) -> Self {
+        Self {
+            max_per_key: args.max_per_key,
+            keep_in_check: args.keep_in_check,
+            keep_tactical_best: args.keep_tactical_best,
+            max_qsearch_gap_cp: args.max_qsearch_gap_cp,
+            seen: HashMap::new(),
+            searcher: Searcher::default(),
+        }
+    }
+
+    /// The record's phase if it is kept. Dedup runs last, so the per-key
+    /// count only includes positions that passed every other filter.
+    fn check(&mut self, line: &str) -> Result<Phase, DropReason> {
+        let map = match serde_json::from_str(line) {
+            Ok(Value::Object(map)) => map,
+            _ => return Err(DropReason::Invalid),
+        };
+        let board = map
+            .get("fen")
+            .and_then(Value::as_str)
+            .and_then(|fen| parse_fen(fen).ok())
+            .ok_or(DropReason::Invalid)?;
+        if !self.keep_in_check && !board.checkers().is_empty() {
+            return Err(DropReason::InCheck);
+        }
+        if !self.keep_tactical_best {
+            let best = ["target_best_move", "best_move"]
+                .iter()
+                .find_map(|key| map.get(*key).and_then(Value::as_str))
+                .and_then(|text| text.parse::<Move>().ok())
+                .filter(|&mv| board.is_legal(mv));
+            if best.is_some_and(|mv| is_tactical(&board, mv)) {
+                return Err(DropReason::TacticalBest);
+            }
+        }
+        if self.max_qsearch_gap_cp > 0 {
+            let gap = self.searcher.qsearch_eval_cp(&board) - eval_cp(&board);
+            if gap.abs() > self.max_qsearch_gap_cp {
+                return Err(DropReason::QsearchGap);
+            }
+        }
+        if self.max_per_key > 0 {
+            let count = self.seen.entry(zobrist::compute(&board)).or_default();
+            if *count >= self.max_per_key {
+                return Err(DropReason::Duplicate);
+            }
+            *count += 1;
+        }
+        Ok(Phase::of(&board))
+    }
+}
+
+/// Captures (including en passant) and promotions. Castling is
+/// king-takes-rook in cozy-chess, so it is not a capture of an own piece.
+fn is_tactical(board: &Board, mv: Move) -> bool {
+    let en_passant = board.piece_on(mv.from) == Some(Piece::Pawn)
+        && mv.from.file() != mv.to.file()
+        && board.piece_on(mv.to).is_none();
+    mv.promotion.is_some() || board.color_on(mv.to) == Some(!board.side_to_move()) || en_passant
+}
+
+/// How many records to keep per phase: each phase is cut down to `ratio`
+/// times the smallest non-empty one.
+fn phase_quotas(counts: &BTreeMap<Phase, u64>, ratio: f64) -> BTreeMap<Phase, u64> {
+    let smallest = counts
+        .values()
+        .copied()
+        .filter(|&n| n > 0)
+        .min()
+        .unwrap_or(0);
+    let cap = (smallest as f64 * ratio.max(1.0)).floor() as u64;
+    counts
+        .iter()
+        .map(|(&phase, &n)| (phase, n.min(cap)))
+        .collect()
+}
+
+/// Rotating `shard_NNNNNN.jsonl` output.
+struct ShardWriter {
+    dir: PathBuf,
+    per_shard: usize,
+    shards: usize,
+    in_shard: usize,
+    writer: Option<BufWriter<File>>,
+}
+
+impl ShardWriter {
+    fn new(dir: &Path, per_shard: usize) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            per_shard: per_shard.max(1),
+            shards: 0,
+            in_shard: 0,
+            writer: None,
+        }
+    }
+
+    fn write(&mut self, line: &str) -> std::io::Result<()> {
+        if self.writer.is_none() || self.in_shard >= self.per_shard {
+            if let Some(mut w) = self.writer.take() {
+                w.flush()?;
+            }
+            let path = self.dir.join(format!("shard_{:06}.jsonl", self.shards));
+            self.writer = Some(BufWriter::new(File::create(path)?));
+            self.shards += 1;
+            self.in_shard = 0;
+        }
+        let w = self.writer.as_mut().unwrap();
+        w.write_all(line.as_bytes())?;
+        w.write_all(b"\n")?;
+        self.in_shard += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> std::io::Result<usize> {
+        if let Some(mut w) = self.writer {
+            w.flush()?;
+        }
+        Ok(self.shards)
+    }
+}
+
+fn collect_inputs(input: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if input.is_file() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(input)? {
+        let path = entry?.path();
+        if path.extension().and_then(|x| x.to_str()) == Some("jsonl") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn for_each_line<F>(inputs: &[PathBuf], mut f: F) -> anyhow::Result<()>
+where
+    F: FnMut(&str) -> anyhow::Result<()>,
+{
+    for path in inputs {
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                f(&line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let inputs = collect_inputs(&args.input)?;
+    if inputs.is_empty() {
+        anyhow::bail!("no jsonl inputs found at {}", args.input.display());
+    }
+    fs::create_dir_all(&args.output)?;
+    let mut filter = Filter::new(&args);
+    let mut stats = Stats::default();
+    let mut out = ShardWriter::new(&args.output, args.records_per_shard);
+    let rebalance = args.phase_ratio > 0.0;
+
+    // With rebalancing, filtered records wait in a staging file tagged with
+    // their phase until the phase sizes are known.
+    let staging_path = args.output.join(STAGING_FILE);
+    let mut staging = if rebalance {
+        Some(BufWriter::new(File::create(&staging_path)?))
+    } else {
+        None
+    };
+    for_each_line(&inputs, |line| {
+        stats.read += 1;
+        match filter.check(line) {
+            Ok(phase) => {
+                *stats.phase_filtered.entry(phase).or_default() += 1;
+                match staging.as_mut() {
+                    Some(w) => writeln!(w, "{}\t{line}", phase as u8)?,
+                    None => {
+                        out.write(line)?;
+                        *stats.phase_kept.entry(phase).or_default() += 1;
+                    }
+                }
+            }
+            Err(reason) => stats.drop(reason),
+        }
+        Ok(())
+    })?;
+
+    if let Some(w) = staging.take() {
+        w.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        let quotas = phase_quotas(&stats.phase_filtered, args.phase_ratio);
+        let mut rng = SmallRng::seed_from_u64(args.seed);
+        let phases = [Phase::Opening, Phase::Middlegame, Phase::Endgame];
+        let keep: Vec<HashSet<usize>> = phases
+            .iter()
+            .map(|phase| {
+                let n = stats.phase_filtered.get(phase).copied().unwrap_or(0) as usize;
+                let k = quotas.get(phase).copied().unwrap_or(0) as usize;
+                rand::seq::index::sample(&mut rng, n, k)
+                    .into_iter()
+                    .collect()
+            })
+            .collect();
+        let mut seen = [0usize; 3];
+        for_each_line(std::slice::from_ref(&staging_path), |tagged| {
+            let (tag, line) = tagged.split_once('\t').unwrap_or(("", tagged));
+            let phase = tag.parse::<usize>().ok().filter(|&i| i < 3).unwrap_or(0);
+            let index = seen[phase];
+            seen[phase] += 1;
+            if keep[phase].contains(&index) {
+                out.write(line)?;
+                *stats.phase_kept.entry(phases[phase]).or_default() += 1;
+            } else {
+                stats.drop(DropReason::PhaseRebalance);
+            }
+            Ok(())
+        })?;
+        fs::remove_file(&staging_path)?;
+    }
+
+    let shards = out.finish()?;
+    stats.kept = stats.phase_kept.values().sum();
+    let stats_path = args.output.join(STATS_FILE);
+    serde_json::to_writer_pretty(BufWriter::new(File::create(&stats_path)?), &stats)?;
+    eprintln!(
+        "Kept {} of {} records in {shards} shards",
+        stats.kept, stats.read
+    );
+    for (reason, count) in &stats.dropped {
+        eprintln!("  dropped {count:>10} {reason:?}");
+    }
+    eprintln!("Stats written to {}", stats_path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_tactical, phase_quotas, DropReason, Filter, Phase};
+    use cozy_chess::Board;
+    use piebot::search::alphabeta::Searcher;
+    use serde_json::json;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn filter() -> Filter {
+        Filter {
+            max_per_key: 2,
+            keep_in_check: false,
+            keep_tactical_best: false,
+            max_qsearch_gap_cp: 60,
+            seen: HashMap::new(),
+            searcher: Searcher::default(),
+        }
+    }
+
+    fn record(fen: &str, best: &str) -> String {
+        json!({"fen": fen, "target_best_move": best, "result": 0}).to_string()
+    }
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn duplicates_are_capped_per_position_key() {
+        let mut f = filter();
+        let line = record(START, "e2e4");
+        assert_eq!(f.check(&line), Ok(Phase::Opening));
+        // Clocks are not part of the key.
+        let later = record(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3",
+            "e2e4",
+        );
+        assert_eq!(f.check(&later), Ok(Phase::Opening));
+        assert_eq!(f.check(&line), Err(DropReason::Duplicate));
+        assert_eq!(f.check("{\"ply\": 3}"), Err(DropReason::Invalid));
+        assert_eq!(f.check("not json"), Err(DropReason::Invalid));
+    }
+
+    #[test]
+    fn checks_and_tactical_best_moves_are_dropped() {
+        let mut f = filter();
+        let in_check = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        assert_eq!(f.check(&record(in_check, "e1f2")), Err(DropReason::InCheck));
+
+        let exchange = "4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1";
+        assert_eq!(
+            f.check(&record(exchange, "e4d5")),
+            Err(DropReason::TacticalBest)
+        );
+        let promotion = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(
+            f.check(&record(promotion, "a7a8q")),
+            Err(DropReason::TacticalBest)
+        );
+
+        let ep = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", false).unwrap();
+        assert!(is_tactical(&ep, "e5d6".parse().unwrap()));
+        let castle = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1", false).unwrap();
+        assert!(!is_tactical(&castle, "e1h1".parse().unwrap()));
+    }
+
+    #[test]
+    fn unquiet_positions_fail_the_qsearch_gap() {
+        let mut f = filter();
+        // White's queen hangs to the d5 pawn: static eval is a queen down
+        // for Black, quiescence wins it back.
+        let hanging = "7k/8/8/3p4/4Q3/8/8/4K3 b - - 0 1";
+        assert_eq!(
+            f.check(&record(hanging, "h8g8")),
+            Err(DropReason::QsearchGap)
+        );
+        f.max_qsearch_gap_cp = 0;
+        assert_eq!(f.check(&record(hanging, "h8g8")), Ok(Phase::Endgame));
+    }
+
+    #[test]
+    fn phases_follow_material_and_quotas_cap_the_large_ones() {
+        let start = Board::default();
+        assert_eq!(Phase::of(&start), Phase::Opening);
+        let rooks = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1", false).unwrap();
+        assert_eq!(Phase::of(&rooks), Phase::Endgame);
+        let middle =
+            Board::from_fen("r2qk2r/ppp2ppp/8/8/8/8/PPP2PPP/R2QK2R w - - 0 1", false).unwrap();
+        assert_eq!(Phase::of(&middle), Phase::Middlegame);
+
+        let counts = BTreeMap::from([
+            (Phase::Opening, 1000),
+            (Phase::Middlegame, 300),
+            (Phase::Endgame, 100),
+        ]);
+        let quotas = phase_quotas(&counts, 2.0);
+        assert_eq!(quotas[&Phase::Opening], 200);
+        assert_eq!(quotas[&Phase::Middlegame], 200);
+        assert_eq!(quotas[&Phase::Endgame], 100);
+    }
+}
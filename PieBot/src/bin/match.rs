@@ -0,0 +1,345 @@
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use cozy_chess::Board;
+use piebot::arena::engine::EngineSpec;
+use piebot::arena::{play_jobs, GameJob, GameRules, MatchGame, MatchState, Termination};
+use piebot::io::openings::load_fen_suite;
+use piebot::search::time_manager::TimeControl;
+use piebot::stats::{logistic_elo, Pentanomial};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const PGN_EVENT: &str = "PieBot match";
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "match",
+    about = "Play a match between two external UCI engines"
+)]
+struct Args {
+    /// Engine to play, given twice: `name=..,cmd=..[,dir=..][,arg=..][,option.NAME=VALUE]`.
+    /// The first engine is the one the summary scores.
+    #[arg(long = "engine", required = true, num_args = 1)]
+    engines: Vec<EngineSpec>,
+
+    /// Number of games; games 2k and 2k+1 share an opening with colors reversed
+    #[arg(long, default_value_t = 40)]
+    games: usize,
+
+    /// Games played at once, each by its own pair of engine processes
+    #[arg(long, default_value_t = 1, value_parser = parse_positive_usize)]
+    concurrency: usize,
+
+    /// FEN/EPD opening suite, used in order and wrapped around; start position if omitted
+    #[arg(long)]
+    openings_file: Option<PathBuf>,
+
+    /// Clock per engine in seconds: `base+inc` or `moves/base[+inc]`
+    #[arg(long, default_value = "10+0.1")]
+    tc: TimeControl,
+
+    /// How long past its flag an engine may stay silent before it is treated
+    /// as hung and restarted; the move is lost on time either way
+    #[arg(long, default_value_t = 1_000)]
+    timeout_margin_ms: u64,
+
+    /// Time allowed for an engine to start and answer `uciok`/`readyok`
+    #[arg(long, default_value_t = 10_000)]
+    start_timeout_ms: u64,
+
+    /// Plies after which a game is drawn
+    #[arg(long, default_value_t = 400)]
+    max_plies: usize,
+
+    /// Resign once the reported score passes this many cp (white's view) on
+    /// --resign-plies consecutive plies; 0 disables
+    #[arg(long, default_value_t = 1_000.0)]
+    resign_cp: f32,
+
+    #[arg(long, default_value_t = 6)]
+    resign_plies: usize,
+
+    /// Draw once |score| stays within this many cp for --draw-adj-plies
+    /// consecutive plies, from --draw-adj-min-ply on; 0 disables
+    #[arg(long, default_value_t = 10.0)]
+    draw_adj_cp: f32,
+
+    #[arg(long, default_value_t = 16)]
+    draw_adj_plies: usize,
+
+    #[arg(long, default_value_t = 80)]
+    draw_adj_min_ply: usize,
+
+    /// Finished games are saved here after each game; rerunning with the same
+    /// file and settings skips them. --games may grow between runs.
+    #[arg(long)]
+    state_file: Option<PathBuf>,
+
+    /// Write every finished game, in game order, to this PGN file
+    #[arg(long)]
+    pgn_out: Option<PathBuf>,
+
+    /// Write a JSON summary here
+    #[arg(long)]
+    json_out: Option<PathBuf>,
+}
+
+fn parse_positive_usize(raw: &str) -> Result<usize, String> {
+    match raw.parse::<usize>() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(value) => Ok(value),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Everything that changes how a game is played, recorded in the state file
+/// so a resumed run cannot silently switch settings. The game count is left
+/// out on purpose: extending a finished match is a normal resume.
+#[derive(Serialize)]
+struct MatchConfig<'a> {
+    engines: &'a [EngineSpec],
+    openings: Vec<String>,
+    time_control: String,
+    timeout_margin_ms: u64,
+    max_plies: usize,
+    resign_cp: f32,
+    resign_plies: usize,
+    draw_adj_cp: f32,
+    draw_adj_plies: usize,
+    draw_adj_min_ply: usize,
+}
+
+#[derive(Default, Serialize)]
+struct EngineFaults {
+    time_forfeit: usize,
+    crash: usize,
+    illegal_move: usize,
+}
+
+#[derive(Serialize)]
+struct GameSummary<'a> {
+    index: usize,
+    opening: usize,
+    white: &'a str,
+    black: &'a str,
+    result: i8,
+    termination: Termination,
+    plies: usize,
+}
+
+#[derive(Serialize)]
+struct MatchSummary<'a> {
+    engines: [&'a str; 2],
+    games: usize,
+    time_control: String,
+    wins: usize,
+    draws: usize,
+    losses: usize,
+    score: Option<f64>,
+    elo: Option<f64>,
+    pentanomial: Pentanomial,
+    terminations: BTreeMap<&'static str, usize>,
+    faults: BTreeMap<&'a str, EngineFaults>,
+    game_results: Vec<GameSummary<'a>>,
+}
+
+fn summarize<'a>(args: &'a Args, games: &'a [MatchGame]) -> MatchSummary<'a> {
+    let names = [args.engines[0].name.as_str(), args.engines[1].name.as_str()];
+    let (mut wins, mut draws, mut losses) = (0, 0, 0);
+    let mut terminations = BTreeMap::new();
+    let mut faults: BTreeMap<&str, EngineFaults> = names
+        .iter()
+        .map(|&name| (name, EngineFaults::default()))
+        .collect();
+    for game in games {
+        match game.score_of(names[0]) {
+            Some(s) if s > 0.5 => wins += 1,
+            Some(s) if s < 0.5 => losses += 1,
+            _ => draws += 1,
+        }
+        *terminations.entry(game.termination.as_str()).or_insert(0) += 1;
+        let loser = match game.result {
+            1 => game.black.as_str(),
+            -1 => game.white.as_str(),
+            _ => continue,
+        };
+        if let Some(faults) = faults.get_mut(loser) {
+            match game.termination {
+                Termination::TimeForfeit => faults.time_forfeit += 1,
+                Termination::Crash => faults.crash += 1,
+                Termination::IllegalMove => faults.illegal_move += 1,
+                Termination::Game(_) => {}
+            }
+        }
+    }
+    let mut pentanomial = Pentanomial::default();
+    for pair in games.windows(2) {
+        if pair[0].index % 2 == 0 && pair[1].index == pair[0].index + 1 {
+            let points = pair.iter().filter_map(|g| g.score_of(names[0])).sum();
+            pentanomial
+                .add_pair(points)
+                .expect("two game scores make a valid pair score");
+        }
+    }
+    let played = wins + draws + losses;
+    let score = (played > 0).then(|| (wins as f64 + 0.5 * draws as f64) / played as f64);
+    MatchSummary {
+        engines: names,
+        games: played,
+        time_control: args.tc.to_string(),
+        wins,
+        draws,
+        losses,
+        score,
+        elo: score.map(logistic_elo).filter(|elo| elo.is_finite()),
+        pentanomial,
+        terminations,
+        faults,
+        game_results: games
+            .iter()
+            .map(|game| GameSummary {
+                index: game.index,
+                opening: game.opening,
+                white: &game.white,
+                black: &game.black,
+                result: game.result,
+                termination: game.termination,
+                plies: game.moves.len(),
+            })
+            .collect(),
+    }
+}
+
+fn write_pgn(path: &std::path::Path, games: &[MatchGame]) -> Result<()> {
+    let text: String = games.iter().map(|g| g.to_pgn(PGN_EVENT).to_pgn()).collect();
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.engines.len() != 2 {
+        bail!(
+            "a match needs exactly two --engine specs, got {}",
+            args.engines.len()
+        );
+    }
+    if args.engines[0].name == args.engines[1].name {
+        bail!(
+            "both engines are named {:?}; give one a distinct name=",
+            args.engines[0].name
+        );
+    }
+    let openings = match &args.openings_file {
+        Some(path) => load_fen_suite(path).map_err(anyhow::Error::msg)?,
+        None => vec![Board::default()],
+    };
+    let rules = GameRules {
+        time_control: args.tc,
+        timeout_margin: Duration::from_millis(args.timeout_margin_ms),
+        start_timeout: Duration::from_millis(args.start_timeout_ms),
+        max_plies: args.max_plies,
+        resign_cp: args.resign_cp,
+        resign_plies: args.resign_plies,
+        draw_adj_cp: args.draw_adj_cp,
+        draw_adj_plies: args.draw_adj_plies,
+        draw_adj_min_ply: args.draw_adj_min_ply,
+    };
+    let config = serde_json::to_value(MatchConfig {
+        engines: &args.engines,
+        openings: openings.iter().map(Board::to_string).collect(),
+        time_control: args.tc.to_string(),
+        timeout_margin_ms: args.timeout_margin_ms,
+        max_plies: args.max_plies,
+        resign_cp: args.resign_cp,
+        resign_plies: args.resign_plies,
+        draw_adj_cp: args.draw_adj_cp,
+        draw_adj_plies: args.draw_adj_plies,
+        draw_adj_min_ply: args.draw_adj_min_ply,
+    })?;
+    let mut state = match &args.state_file {
+        Some(path) => MatchState::load_or_new(path, config).map_err(anyhow::Error::msg)?,
+        None => MatchState::new(config),
+    };
+    let jobs: Vec<GameJob> = (0..args.games)
+        .filter(|&index| !state.is_done(index))
+        .map(|index| GameJob {
+            index,
+            opening: (index / 2) % openings.len(),
+            white: index % 2,
+            black: 1 - index % 2,
+        })
+        .collect();
+    let resumed = args.games - jobs.len();
+    if resumed > 0 {
+        println!("resuming: {resumed} of {} games already played", args.games);
+    }
+
+    play_jobs(
+        &args.engines,
+        &jobs,
+        &openings,
+        &rules,
+        args.concurrency,
+        |game| {
+            println!(
+                "game {:>4}: {} vs {}: {} ({})",
+                game.index + 1,
+                game.white,
+                game.black,
+                game.result_str(),
+                game.termination.as_str()
+            );
+            state.push(game);
+            match &args.state_file {
+                Some(path) => state.save(path),
+                None => Ok(()),
+            }
+        },
+    )
+    .map_err(anyhow::Error::msg)?;
+
+    let games: Vec<MatchGame> = state
+        .games
+        .iter()
+        .filter(|game| game.index < args.games)
+        .cloned()
+        .collect();
+    if let Some(path) = &args.pgn_out {
+        write_pgn(path, &games)?;
+    }
+    let summary = summarize(&args, &games);
+    println!(
+        "{} vs {}: +{} ={} -{} over {} games, score {}, elo {}",
+        summary.engines[0],
+        summary.engines[1],
+        summary.wins,
+        summary.draws,
+        summary.losses,
+        summary.games,
+        summary
+            .score
+            .map_or("n/a".to_string(), |s| format!("{:.3}", s)),
+        summary
+            .elo
+            .map_or("n/a".to_string(), |e| format!("{:+.1}", e))
+    );
+    for (name, faults) in &summary.faults {
+        if faults.time_forfeit + faults.crash + faults.illegal_move > 0 {
+            println!(
+                "faults {}: {} time forfeits, {} crashes, {} illegal moves",
+                name, faults.time_forfeit, faults.crash, faults.illegal_move
+            );
+        }
+    }
+    if let Some(path) = &args.json_out {
+        let text = serde_json::to_string_pretty(&summary)?;
+        std::fs::write(path, text)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
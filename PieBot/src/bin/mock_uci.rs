@@ -0,0 +1,121 @@
+//! Scriptable stand-in for a UCI engine, for exercising `match` without a
+//! real engine: it plays the first legal move (or a seeded random one),
+//! reports a fixed score, and can be told to misbehave on a given `go`.
+use clap::Parser;
+use cozy_chess::{Board, Move};
+use piebot::arena::{move_to_uci, uci_to_move};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(name = "mock-uci", about = "Minimal scripted UCI engine for tests")]
+struct Args {
+    /// Name reported with `id name`
+    #[arg(long, default_value = "mock")]
+    name: String,
+
+    /// Pick uniformly among legal moves from this seed instead of playing
+    /// the first legal move
+    #[arg(long)]
+    random_seed: Option<u64>,
+
+    /// Score reported in an `info` line before each `bestmove`, side to move's view
+    #[arg(long, allow_negative_numbers = true)]
+    score_cp: Option<i32>,
+
+    /// Sleep this long before answering each `go`
+    #[arg(long, default_value_t = 0)]
+    delay_ms: u64,
+
+    /// Exit without answering the Nth `go` (1-based, counted per process)
+    #[arg(long)]
+    crash_on_go: Option<u64>,
+
+    /// Stop answering from the Nth `go` on
+    #[arg(long)]
+    hang_on_go: Option<u64>,
+
+    /// Answer the Nth `go` with an illegal move
+    #[arg(long)]
+    illegal_on_go: Option<u64>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut rng = args.random_seed.map(SmallRng::seed_from_u64);
+    let mut board = Board::default();
+    let mut gos = 0u64;
+    let stdin = std::io::stdin();
+    let mut out = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        let mut tokens = line.split_whitespace();
+        let reply = match tokens.next() {
+            Some("uci") => format!("id name {}\nid author PieBot tests\nuciok", args.name),
+            Some("isready") => "readyok".to_string(),
+            Some("position") => {
+                board = parse_position(&tokens.collect::<Vec<_>>()).unwrap_or_default();
+                continue;
+            }
+            Some("go") => {
+                gos += 1;
+                if args.crash_on_go == Some(gos) {
+                    std::process::exit(3);
+                }
+                if args.hang_on_go.is_some_and(|n| gos >= n) {
+                    continue;
+                }
+                std::thread::sleep(Duration::from_millis(args.delay_ms));
+                let mut moves: Vec<Move> = Vec::new();
+                board.generate_moves(|ml| {
+                    moves.extend(ml);
+                    false
+                });
+                let bestmove = if args.illegal_on_go == Some(gos) {
+                    "a1a1".to_string()
+                } else {
+                    let mv = match rng.as_mut() {
+                        Some(rng) => moves.choose(rng).copied(),
+                        None => moves.first().copied(),
+                    };
+                    mv.map_or("0000".to_string(), |mv| move_to_uci(&board, mv))
+                };
+                match args.score_cp {
+                    Some(cp) => {
+                        format!("info depth 1 score cp {cp} pv {bestmove}\nbestmove {bestmove}")
+                    }
+                    None => format!("bestmove {bestmove}"),
+                }
+            }
+            Some("quit") => break,
+            _ => continue,
+        };
+        if writeln!(out, "{reply}").and_then(|_| out.flush()).is_err() {
+            break;
+        }
+    }
+}
+
+/// `startpos|fen <6 fields> [moves ...]` with standard UCI castling.
+fn parse_position(tokens: &[&str]) -> Option<Board> {
+    let (mut board, rest) = match *tokens.first()? {
+        "startpos" => (Board::default(), &tokens[1..]),
+        "fen" => {
+            let fen_len = tokens[1..]
+                .iter()
+                .position(|&t| t == "moves")
+                .unwrap_or(tokens.len() - 1);
+            let fen = tokens[1..1 + fen_len].join(" ");
+            (Board::from_fen(&fen, false).ok()?, &tokens[1 + fen_len..])
+        }
+        _ => return None,
+    };
+    for uci in rest.iter().skip_while(|&&t| t == "moves") {
+        let mv = uci_to_move(&board, uci)?;
+        board.play_unchecked(mv);
+    }
+    Some(board)
+}
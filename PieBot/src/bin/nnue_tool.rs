@@ -0,0 +1,423 @@
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand};
+use cozy_chess::Board;
+use piebot::eval::nnue::features::dp_active_indices;
+use piebot::eval::nnue::loader::QuantNnue;
+use piebot::eval::nnue::network::QuantNetwork;
+use piebot::eval::nnue::quant::{quant_accumulators, quantize_dense, quantize_v2, ClipStats};
+use piebot::eval::nnue::train::FloatNnueV2;
+use piebot::eval::nnue::Nnue;
+use piebot::io::openings::load_fen_suite;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "nnue-tool",
+    about = "Inspect, histogram and convert PIENNUE1/PIENNF02/PIENNQ01/PIENNQ02 models"
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the header, payload size check and per-tensor weight stats
+    Inspect { model: PathBuf },
+    /// Print weight histograms, and accumulator histograms over a FEN set
+    Histogram {
+        model: PathBuf,
+        /// FEN/EPD file; adds hidden-layer activation histograms
+        #[arg(long)]
+        fens: Option<PathBuf>,
+        #[arg(long, default_value_t = 16)]
+        bins: usize,
+    },
+    /// Quantize a float model (PIENNUE1 -> PIENNQ01, PIENNF02 -> PIENNQ02)
+    /// or rewrite a quantized one unchanged
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+        /// PIENNUE1 only: percentile of |w| mapped to ±127; larger weights clip
+        #[arg(long, default_value_t = 100.0)]
+        clip_percentile: f32,
+        /// FEN/EPD file; compares the written model's evals with the input's
+        #[arg(long)]
+        verify_fens: Option<PathBuf>,
+    },
+}
+
+enum Model {
+    Dense(Nnue),
+    Float(FloatNnueV2),
+    Quant(QuantNnue),
+}
+
+impl Model {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut magic = [0u8; 8];
+        std::fs::File::open(path)
+            .and_then(|mut f| f.read_exact(&mut magic))
+            .with_context(|| format!("read magic of {}", path.display()))?;
+        match &magic {
+            b"PIENNUE1" => Ok(Self::Dense(Nnue::load(path)?)),
+            b"PIENNF02" => Ok(Self::Float(
+                FloatNnueV2::load(path).map_err(|e| anyhow::anyhow!(e))?,
+            )),
+            b"PIENNQ01" | b"PIENNQ02" => Ok(Self::Quant(QuantNnue::load_quantized(path)?)),
+            _ => bail!(
+                "{}: unknown magic {:?}",
+                path.display(),
+                String::from_utf8_lossy(&magic)
+            ),
+        }
+    }
+
+    fn format(&self) -> &'static str {
+        match self {
+            Self::Dense(_) => "PIENNUE1 (dense float)",
+            Self::Float(_) => "PIENNF02 (arch-v2 float checkpoint)",
+            Self::Quant(q) if q.v2.is_some() => "PIENNQ02 (arch-v2 quantized)",
+            Self::Quant(_) => "PIENNQ01 (legacy quantized)",
+        }
+    }
+
+    /// Payload bytes the header implies; `None` for trainer checkpoints,
+    /// which also carry optimizer state.
+    fn expected_file_len(&self) -> Option<usize> {
+        match self {
+            Self::Dense(m) => {
+                let (n, h) = (m.meta.input_dim, m.meta.hidden_dim);
+                Some(24 + 4 * (h * n + 2 * h + 1))
+            }
+            Self::Float(_) => None,
+            Self::Quant(q) => Some(match q.v2.as_ref() {
+                Some(v2) => {
+                    let (n, h) = (v2.per_perspective_input_dim, v2.hidden_dim);
+                    36 + 2 * n * h + 2 * h + 2 * h + 4
+                }
+                None => {
+                    let (n, h) = (q.meta.input_dim, q.meta.hidden_dim);
+                    32 + h * n + 2 * h + h + 2
+                }
+            }),
+        }
+    }
+
+    fn tensors(&self) -> Vec<(&'static str, Vec<f64>)> {
+        let widen_f32 = |v: &[f32]| v.iter().map(|&x| f64::from(x)).collect();
+        match self {
+            Self::Dense(m) => m
+                .tensors()
+                .into_iter()
+                .map(|(name, v)| (name, widen_f32(v)))
+                .collect(),
+            Self::Float(m) => vec![
+                ("w1", widen_f32(&m.w1)),
+                ("b1", widen_f32(&m.b1)),
+                ("w2", widen_f32(&m.w2)),
+                ("b2", vec![f64::from(m.b2)]),
+            ],
+            Self::Quant(q) => match q.v2.as_ref() {
+                Some(v2) => vec![
+                    ("w1", v2.w1.iter().map(|&x| f64::from(x)).collect()),
+                    ("b1", v2.b1.iter().map(|&x| f64::from(x)).collect()),
+                    ("w2", v2.w2.iter().map(|&x| f64::from(x)).collect()),
+                    ("b2", vec![f64::from(v2.b2)]),
+                ],
+                None => vec![
+                    ("w1", q.w1.iter().map(|&x| f64::from(x)).collect()),
+                    ("b1", q.b1.iter().map(|&x| f64::from(x)).collect()),
+                    ("w2", q.w2.iter().map(|&x| f64::from(x)).collect()),
+                    ("b2", q.b2.iter().map(|&x| f64::from(x)).collect()),
+                ],
+            },
+        }
+    }
+
+    /// Hidden-layer pre-activations for `board` plus the activation's upper
+    /// clamp, if any (1.0 for float SCReLU, QA for PIENNQ02).
+    fn activations(&self, board: &Board) -> anyhow::Result<(Vec<f64>, Option<f64>)> {
+        Ok(match self {
+            Self::Dense(m) => (
+                m.hidden_preactivations(board)
+                    .into_iter()
+                    .map(f64::from)
+                    .collect(),
+                None,
+            ),
+            Self::Float(m) => {
+                let h = m.hidden_dim;
+                let stm = board.side_to_move();
+                let mut out = Vec::with_capacity(2 * h);
+                for perspective in [stm, !stm] {
+                    let mut acc: Vec<f64> = m.b1.iter().map(|&b| f64::from(b)).collect();
+                    for idx in dp_active_indices(board, perspective) {
+                        for (a, &w) in acc.iter_mut().zip(&m.w1[idx * h..(idx + 1) * h]) {
+                            *a += f64::from(w);
+                        }
+                    }
+                    out.extend(acc);
+                }
+                (out, Some(1.0))
+            }
+            Self::Quant(q) => {
+                let acc = quant_accumulators(q, board).with_context(|| {
+                    format!("no HalfKP schema for input_dim {}", q.meta.input_dim)
+                })?;
+                let clamp = q.v2.as_ref().map(|v2| f64::from(v2.qa));
+                (acc.into_iter().map(f64::from).collect(), clamp)
+            }
+        })
+    }
+}
+
+fn inspect(path: &Path) -> anyhow::Result<()> {
+    let model = Model::load(path)?;
+    let file_len = std::fs::metadata(path)?.len() as usize;
+    println!("file: {} ({file_len} bytes)", path.display());
+    println!("format: {}", model.format());
+    match &model {
+        Model::Dense(m) => println!(
+            "version {} input {} hidden {} output {}",
+            m.meta.version, m.meta.input_dim, m.meta.hidden_dim, m.meta.output_dim
+        ),
+        Model::Float(m) => println!("input {} hidden {}", m.input_dim, m.hidden_dim),
+        Model::Quant(q) => match q.v2.as_ref() {
+            Some(v2) => println!(
+                "input {} (per perspective) hidden {} qa {} qb {} scale {}",
+                v2.per_perspective_input_dim, v2.hidden_dim, v2.qa, v2.qb, v2.scale
+            ),
+            None => println!(
+                "version {} input {} hidden {} output {} w1_scale {:e} w2_scale {:e}",
+                q.meta.version,
+                q.meta.input_dim,
+                q.meta.hidden_dim,
+                q.meta.output_dim,
+                q.w1_scale,
+                q.w2_scale
+            ),
+        },
+    }
+    if let Some(expected) = model.expected_file_len() {
+        if expected == file_len {
+            println!("payload: ok");
+        } else {
+            println!(
+                "payload: expected {expected} bytes, file has {} trailing",
+                file_len as i64 - expected as i64
+            );
+        }
+    }
+    println!(
+        "{:<4} {:>10} {:>12} {:>12} {:>12} {:>12} {:>7}",
+        "", "count", "min", "max", "mean", "std", "zero%"
+    );
+    for (name, values) in model.tensors() {
+        let s = Summary::of(&values);
+        println!(
+            "{name:<4} {:>10} {:>12.5} {:>12.5} {:>12.5} {:>12.5} {:>6.2}%",
+            values.len(),
+            s.min,
+            s.max,
+            s.mean,
+            s.std,
+            100.0 * s.zero_share
+        );
+    }
+    Ok(())
+}
+
+struct Summary {
+    min: f64,
+    max: f64,
+    mean: f64,
+    std: f64,
+    zero_share: f64,
+}
+
+impl Summary {
+    fn of(values: &[f64]) -> Self {
+        let n = values.len().max(1) as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        Self {
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            std: var.sqrt(),
+            zero_share: values.iter().filter(|&&v| v == 0.0).count() as f64 / n,
+        }
+    }
+}
+
+fn print_histogram(title: &str, values: &[f64], bins: usize) {
+    println!("{title}: {} values", values.len());
+    if values.is_empty() {
+        return;
+    }
+    let s = Summary::of(values);
+    if s.max == s.min {
+        println!("  all {}", s.min);
+        return;
+    }
+    let bins = bins.max(1);
+    let width = (s.max - s.min) / bins as f64;
+    let mut counts = vec![0usize; bins];
+    for &v in values {
+        counts[(((v - s.min) / width) as usize).min(bins - 1)] += 1;
+    }
+    let peak = counts.iter().copied().max().unwrap_or(1).max(1);
+    for (i, &count) in counts.iter().enumerate() {
+        let lo = s.min + width * i as f64;
+        let bar = "#".repeat((count * 50).div_ceil(peak));
+        println!("  [{lo:>12.5}, {:>12.5}) {count:>10} {bar}", lo + width);
+    }
+}
+
+fn histogram(path: &Path, fens: Option<&Path>, bins: usize) -> anyhow::Result<()> {
+    let model = Model::load(path)?;
+    println!("{}: {}", path.display(), model.format());
+    for (name, values) in model.tensors() {
+        print_histogram(name, &values, bins);
+    }
+    let Some(fens) = fens else {
+        return Ok(());
+    };
+    let boards = load_fen_suite(fens).map_err(|e| anyhow::anyhow!(e))?;
+    let mut values = Vec::new();
+    let mut clamp = None;
+    for board in &boards {
+        let (acc, upper) = model.activations(board)?;
+        values.extend(acc);
+        clamp = upper;
+    }
+    print_histogram(
+        &format!("hidden pre-activations over {} positions", boards.len()),
+        &values,
+        bins,
+    );
+    let n = values.len().max(1) as f64;
+    let share =
+        |pred: &dyn Fn(f64) -> bool| 100.0 * values.iter().filter(|&&v| pred(v)).count() as f64 / n;
+    println!("  inactive (<= 0): {:.2}%", share(&|v| v <= 0.0));
+    if let Some(upper) = clamp {
+        println!("  saturated (>= {upper}): {:.2}%", share(&|v| v >= upper));
+    }
+    if matches!(&model, Model::Quant(q) if q.v2.is_some()) {
+        let (lo, hi) = (f64::from(i16::MIN), f64::from(i16::MAX));
+        println!(
+            "  outside i16 accumulator range: {:.2}%",
+            share(&|v| v < lo || v > hi)
+        );
+    }
+    Ok(())
+}
+
+fn print_clip_stats(stats: &[ClipStats]) {
+    println!(
+        "{:<4} {:>10} {:>10} {:>8} {:>12} {:>14}",
+        "", "values", "clipped", "clip%", "max |w|", "max round err"
+    );
+    for s in stats {
+        println!(
+            "{:<4} {:>10} {:>10} {:>7.3}% {:>12.5} {:>14.3e}",
+            s.tensor,
+            s.values,
+            s.clipped,
+            100.0 * s.clipped_fraction(),
+            s.max_abs,
+            s.max_rounding_error
+        );
+    }
+}
+
+/// White-relative cp of `model`, in whichever precision it holds.
+fn eval_cp(model: &Model, network: Option<&QuantNetwork>, board: &Board) -> f64 {
+    match (model, network) {
+        (_, Some(network)) => f64::from(network.eval_full(board)),
+        (Model::Dense(m), None) => f64::from(m.evaluate(board)),
+        (Model::Float(m), None) => f64::from(m.evaluate(board)),
+        (Model::Quant(_), None) => unreachable!("quantized models evaluate through a network"),
+    }
+}
+
+fn convert(
+    input: &Path,
+    output: &Path,
+    clip_percentile: f32,
+    verify_fens: Option<&Path>,
+) -> anyhow::Result<()> {
+    let model = Model::load(input)?;
+    let quant = match &model {
+        Model::Dense(m) => {
+            let (q, stats) = quantize_dense(m, clip_percentile);
+            println!(
+                "PIENNUE1 -> PIENNQ01: w1_scale {:e} w2_scale {:e}",
+                q.w1_scale, q.w2_scale
+            );
+            print_clip_stats(&stats);
+            q
+        }
+        Model::Float(m) => {
+            let (q, stats) = quantize_v2(m);
+            println!(
+                "PIENNF02 -> PIENNQ02: qa {} qb {} scale {}",
+                q.qa, q.qb, q.scale
+            );
+            print_clip_stats(&stats);
+            QuantNnue::from_v2(q)
+        }
+        Model::Quant(q) => {
+            println!("{}: rewriting unchanged", model.format());
+            q.clone()
+        }
+    };
+    quant.save(output)?;
+    println!("wrote {}", output.display());
+
+    let Some(fens) = verify_fens else {
+        return Ok(());
+    };
+    let written = QuantNnue::load_quantized(output)?;
+    let written_network = QuantNetwork::new(written);
+    let input_network = match &model {
+        Model::Quant(q) => Some(QuantNetwork::new(q.clone())),
+        _ => None,
+    };
+    let boards = load_fen_suite(fens).map_err(|e| anyhow::anyhow!(e))?;
+    let mut diffs: Vec<f64> = boards
+        .iter()
+        .map(|board| {
+            let before = eval_cp(&model, input_network.as_ref(), board);
+            (f64::from(written_network.eval_full(board)) - before).abs()
+        })
+        .collect();
+    if diffs.is_empty() {
+        println!("verify: no positions in {}", fens.display());
+        return Ok(());
+    }
+    diffs.sort_by(f64::total_cmp);
+    let mean = diffs.iter().sum::<f64>() / diffs.len() as f64;
+    let p95 = diffs[((diffs.len() - 1) * 95).div_ceil(100)];
+    println!(
+        "verify over {} positions: |delta cp| mean {mean:.2} p95 {p95:.2} max {:.2}",
+        diffs.len(),
+        diffs[diffs.len() - 1]
+    );
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    match Args::parse().command {
+        Command::Inspect { model } => inspect(&model),
+        Command::Histogram { model, fens, bins } => histogram(&model, fens.as_deref(), bins),
+        Command::Convert {
+            input,
+            output,
+            clip_percentile,
+            verify_fens,
+        } => convert(&input, &output, clip_percentile, verify_fens.as_deref()),
+    }
+}
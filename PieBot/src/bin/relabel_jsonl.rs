@@ -1,7 +1,10 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use cozy_chess::{Board, Color};
 use piebot::eval::nnue::loader::QuantNnue;
+use piebot::io::pgn;
 use piebot::search::alphabeta::{EvalMode, SearchParams, Searcher};
+use piebot::selfplay::packed::{PackedFile, PackedPosition, PackedWriter, PACKED_EXTENSION};
+use piebot::selfplay::{game_jsonl_lines, game_record_from_pgn};
 use rayon::prelude::*;
 use serde_json::Value;
 use std::fs::{self, File};
@@ -16,10 +19,12 @@ const RELABEL_BATCH_LINES: usize = 4096;
     about = "Relabel self-play JSONL with a stronger teacher at higher depth"
 )]
 struct Args {
-    /// Input JSONL file or directory containing *.jsonl shards.
+    /// Input JSONL, PIEPACK or PGN file, or a directory containing *.jsonl
+    /// and *.piepack shards and *.pgn databases. PGN games are converted to
+    /// self-play JSONL records.
     #[arg(long)]
     input: PathBuf,
-    /// Output directory to write relabeled JSONL shards.
+    /// Output directory to write relabeled shards.
     #[arg(long)]
     output: PathBuf,
     /// Teacher search depth for relabeling.
@@ -46,6 +51,29 @@ struct Args {
     /// Eval blend percent (0..100) when NNUE is enabled.
     #[arg(long, default_value_t = 100)]
     nnue_blend_percent: u8,
+    /// Output shard format; by default packed inputs stay packed and
+    /// everything else is written as JSONL. PIEPACK records have no room
+    /// for teacher_top, teacher_pv or teacher_wdl.
+    #[arg(long, value_enum)]
+    output_format: Option<OutputFormat>,
+    /// Teacher MultiPV lines; above 1 the ranked moves are written to
+    /// teacher_top as {"move", "cp"} with White-relative scores.
+    #[arg(long, default_value_t = 1)]
+    multipv: usize,
+    /// Logistic scale for teacher_wdl: the expected score of a position is
+    /// about 1 / (1 + exp(-cp / scale)). teacher_wdl is [win, draw, loss]
+    /// from White's point of view, like value_cp. 0 leaves it out.
+    #[arg(long, default_value_t = 400.0)]
+    wdl_scale_cp: f32,
+    /// Half-width in cp of the draw band of teacher_wdl.
+    #[arg(long, default_value_t = 100.0)]
+    wdl_draw_cp: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Jsonl,
+    Packed,
 }
 
 fn collect_inputs(input: &Path) -> std::io::Result<Vec<PathBuf>> {
@@ -55,7 +83,10 @@ fn collect_inputs(input: &Path) -> std::io::Result<Vec<PathBuf>> {
     let mut files = Vec::new();
     for entry in fs::read_dir(input)? {
         let path = entry?.path();
-        if path.extension().and_then(|x| x.to_str()) == Some("jsonl") {
+        if matches!(
+            path.extension().and_then(|x| x.to_str()),
+            Some("jsonl" | "pgn" | PACKED_EXTENSION)
+        ) {
             files.push(path);
         }
     }
@@ -63,37 +94,207 @@ fn collect_inputs(input: &Path) -> std::io::Result<Vec<PathBuf>> {
     Ok(files)
 }
 
+fn is_pgn(path: &Path) -> bool {
+    path.extension().and_then(|x| x.to_str()) == Some("pgn")
+}
+
+fn is_packed(path: &Path) -> bool {
+    path.extension().and_then(|x| x.to_str()) == Some(PACKED_EXTENSION)
+}
+
+type InputLines = Box<dyn Iterator<Item = anyhow::Result<String>>>;
+
+/// The JSONL records of one input. PGN games are converted on the fly, so a
+/// database is streamed game by game like a JSONL shard; games without
+/// `RunId`/`GameId` tags get ids from the file name and game index.
+fn input_lines(path: &Path) -> anyhow::Result<InputLines> {
+    if is_packed(path) {
+        let file = PackedFile::open(path).map_err(|e| anyhow::anyhow!(e))?;
+        let display = path.display().to_string();
+        let records = file.view().len();
+        return Ok(Box::new((0..records).map(move |index| {
+            let position = file
+                .view()
+                .get(index)
+                .map_err(|e| anyhow::anyhow!("{display}: {e}"))?;
+            Ok(serde_json::to_string(&position.to_json())?)
+        })));
+    }
+    if !is_pgn(path) {
+        let rdr = BufReader::new(File::open(path)?);
+        return Ok(Box::new(rdr.lines().map(|line| Ok(line?))));
+    }
+    let games = pgn::open(path).map_err(|e| anyhow::anyhow!(e))?;
+    let run_id = format!(
+        "pgn:{}",
+        path.file_stem().and_then(|x| x.to_str()).unwrap_or("input")
+    );
+    let display = path.display().to_string();
+    Ok(Box::new(games.enumerate().flat_map(
+        move |(index, game)| match pgn_game_lines(game, &run_id, index) {
+            Ok(lines) => lines.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(e) => vec![Err(anyhow::anyhow!("{display}: game {}: {e}", index + 1))],
+        },
+    )))
+}
+
+fn pgn_game_lines(
+    game: Result<pgn::PgnGame, String>,
+    run_id: &str,
+    index: usize,
+) -> anyhow::Result<Vec<String>> {
+    let game = game.map_err(|e| anyhow::anyhow!(e))?;
+    let record = game_record_from_pgn(&game, run_id, &format!("{run_id}:{index}"));
+    Ok(game_jsonl_lines(&record)?)
+}
+
+/// Where relabeled records go: JSONL lines or packed records.
+enum OutputSink {
+    Jsonl(BufWriter<File>),
+    Packed(PackedWriter<BufWriter<File>>),
+}
+
+impl OutputSink {
+    fn create(path: &Path, format: OutputFormat) -> anyhow::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(match format {
+            OutputFormat::Jsonl => Self::Jsonl(file),
+            OutputFormat::Packed => Self::Packed(PackedWriter::new(file)?),
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Jsonl(w) => {
+                w.write_all(line.as_bytes())?;
+                w.write_all(b"\n")?;
+            }
+            Self::Packed(w) => {
+                let map = match serde_json::from_str(line)? {
+                    Value::Object(map) => map,
+                    _ => anyhow::bail!("cannot pack a non-object record: {line}"),
+                };
+                let position = PackedPosition::from_json(&map).map_err(|e| anyhow::anyhow!(e))?;
+                w.write(&position)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Self::Jsonl(mut w) => w.flush()?,
+            Self::Packed(w) => {
+                w.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Logistic cp -> win/draw/loss model: a side wins with probability
+/// `sigmoid((cp - draw_cp) / scale_cp)`, and the draw band between the two
+/// sides' win curves is the draw probability.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct WdlModel {
+    scale_cp: f32,
+    draw_cp: f32,
+}
+
+impl WdlModel {
+    fn new(scale_cp: f32, draw_cp: f32) -> Option<Self> {
+        (scale_cp > 0.0).then_some(Self {
+            scale_cp,
+            draw_cp: draw_cp.max(0.0),
+        })
+    }
+
+    /// `[win, draw, loss]` for the side the score belongs to.
+    fn wdl(self, cp: f32) -> [f32; 3] {
+        let sigmoid = |x: f32| 1.0 / (1.0 + (-x / self.scale_cp).exp());
+        let win = sigmoid(cp - self.draw_cp);
+        let loss = sigmoid(-cp - self.draw_cp);
+        [win, (1.0 - (win + loss)).max(0.0), loss]
+    }
+}
+
+/// How the teacher searches and which labels it writes.
+#[derive(Clone, Copy, Debug)]
+struct TeacherConfig {
+    params: SearchParams,
+    /// Requested depth; the stamped teacher_depth never exceeds it.
+    depth: u32,
+    wdl: Option<WdlModel>,
+    hash_mb: usize,
+    nnue_blend_percent: u8,
+}
+
+struct TeacherLabel {
+    best: String,
+    /// White-relative, like value_cp.
+    score_white: f32,
+    depth: u32,
+    pv: Vec<String>,
+    /// Ranked MultiPV moves with White-relative scores; empty for a single
+    /// line search.
+    top: Vec<(String, i32)>,
+}
+
 fn teacher_label(
     searcher: &mut Searcher,
     board: &Board,
     params: SearchParams,
-) -> Option<(String, f32, u32)> {
+) -> Option<TeacherLabel> {
     let res = searcher.search_with_params(board, params);
     let best = res.bestmove?;
-    let score_white = if board.side_to_move() == Color::White {
-        res.score_cp as f32
+    let sign = if board.side_to_move() == Color::White {
+        1
+    } else {
+        -1
+    };
+    let top = if params.multipv > 1 {
+        res.lines
+            .iter()
+            .map(|line| (line.mv.clone(), sign * line.score_cp))
+            .collect()
     } else {
-        -(res.score_cp as f32)
+        Vec::new()
     };
-    Some((best, score_white, res.depth))
+    Some(TeacherLabel {
+        best,
+        score_white: (sign * res.score_cp) as f32,
+        depth: res.depth,
+        pv: res.pv,
+        top,
+    })
+}
+
+/// Probabilities rounded to 4 decimals, so the JSON stays readable.
+fn wdl_value(wdl: [f32; 3]) -> Value {
+    Value::from(
+        wdl.iter()
+            .map(|p| (f64::from(*p) * 10_000.0).round() / 10_000.0)
+            .collect::<Vec<_>>(),
+    )
 }
 
 fn build_teacher_search_params(depth: u32, max_nodes: u64) -> SearchParams {
-    let mut p = SearchParams::default();
-    p.depth = depth.max(1);
-    p.max_nodes = (max_nodes > 0).then_some(max_nodes);
-    p.use_tt = true;
-    p.order_captures = true;
-    p.use_history = true;
-    // Batch relabel throughput is better when we parallelize over many positions
-    // and keep each individual teacher search single-threaded.
-    p.threads = 1;
-    p.use_aspiration = true;
-    p.aspiration_window_cp = 50;
-    p.use_lmr = true;
-    p.use_killers = true;
-    p.use_nullmove = true;
-    p
+    SearchParams {
+        depth: depth.max(1),
+        max_nodes: (max_nodes > 0).then_some(max_nodes),
+        use_tt: true,
+        order_captures: true,
+        use_history: true,
+        // Batch relabel throughput is better when we parallelize over many positions
+        // and keep each individual teacher search single-threaded.
+        threads: 1,
+        use_aspiration: true,
+        aspiration_window_cp: 50,
+        use_lmr: true,
+        use_killers: true,
+        use_nullmove: true,
+        ..Default::default()
+    }
 }
 
 fn per_worker_hash_mb(total_hash_mb: usize, workers: usize) -> usize {
@@ -208,8 +409,7 @@ where
 fn process_batch_line(
     task: BatchLine,
     teacher: &mut Searcher,
-    teacher_params: SearchParams,
-    teacher_depth: u32,
+    config: &TeacherConfig,
 ) -> (String, usize) {
     let Some(mut map) = task.parsed else {
         return (task.original, 0usize);
@@ -218,21 +418,43 @@ fn process_batch_line(
         let fen = map.get("fen").and_then(|x| x.as_str());
         if let Some(fen_str) = fen {
             if let Ok(board) = Board::from_fen(fen_str, false) {
-                if let Some((best, cpw, achieved_depth)) =
-                    teacher_label(teacher, &board, teacher_params)
-                {
+                if let Some(label) = teacher_label(teacher, &board, config.params) {
                     // Stamp the depth the search actually completed, not the
                     // requested depth: under a node budget the two differ and
                     // min_teacher_depth filtering must see the honest value.
-                    let stamped_depth = achieved_depth.min(teacher_depth).max(1);
-                    map.insert("target_best_move".to_string(), Value::String(best.clone()));
-                    map.insert("best_move".to_string(), Value::String(best));
+                    let stamped_depth = label.depth.min(config.depth).max(1);
+                    let cpw = label.score_white;
+                    map.insert(
+                        "target_best_move".to_string(),
+                        Value::String(label.best.clone()),
+                    );
+                    map.insert("best_move".to_string(), Value::String(label.best));
                     map.insert("value_cp".to_string(), Value::from(cpw));
                     map.insert("target_value_cp".to_string(), Value::from(cpw));
                     map.insert(
                         "teacher_depth".to_string(),
                         Value::from(stamped_depth as u64),
                     );
+                    map.insert("teacher_pv".to_string(), Value::from(label.pv));
+                    // Drop labels from an earlier relabel pass that this one
+                    // does not produce, so every teacher_* field comes from
+                    // the same search.
+                    match config.wdl {
+                        Some(model) => {
+                            map.insert("teacher_wdl".to_string(), wdl_value(model.wdl(cpw)))
+                        }
+                        None => map.remove("teacher_wdl"),
+                    };
+                    if label.top.is_empty() {
+                        map.remove("teacher_top");
+                    } else {
+                        let top: Vec<Value> = label
+                            .top
+                            .into_iter()
+                            .map(|(mv, cp)| serde_json::json!({"move": mv, "cp": cp}))
+                            .collect();
+                        map.insert("teacher_top".to_string(), Value::from(top));
+                    }
                     let out = serde_json::to_string(&map).unwrap_or(task.original);
                     return (out, 1usize);
                 }
@@ -248,11 +470,8 @@ fn process_batch(
     lines: Vec<String>,
     period: usize,
     remaining_limit: Option<usize>,
-    teacher_params: SearchParams,
-    teacher_depth: u32,
-    hash_mb: usize,
+    config: &TeacherConfig,
     nnue_quant_model: Option<&QuantNnue>,
-    nnue_blend_percent: u8,
 ) -> (Vec<String>, usize) {
     let mut scheduled = 0usize;
     let tasks: Vec<BatchLine> = lines
@@ -289,13 +508,15 @@ fn process_batch(
         batches
             .into_par_iter()
             .map(|batch| {
-                let mut teacher =
-                    build_teacher_searcher(hash_mb, nnue_quant_model, nnue_blend_percent);
+                let mut teacher = build_teacher_searcher(
+                    config.hash_mb,
+                    nnue_quant_model,
+                    config.nnue_blend_percent,
+                );
                 batch
                     .into_iter()
                     .map(|(index, task)| {
-                        let (line, relabeled) =
-                            process_batch_line(task, &mut teacher, teacher_params, teacher_depth);
+                        let (line, relabeled) = process_batch_line(task, &mut teacher, config);
                         (index, line, relabeled)
                     })
                     .collect()
@@ -325,7 +546,10 @@ fn main() -> anyhow::Result<()> {
     fs::create_dir_all(&args.output)?;
     let inputs = collect_inputs(&args.input)?;
     if inputs.is_empty() {
-        anyhow::bail!("no jsonl inputs found at {}", args.input.display());
+        anyhow::bail!(
+            "no jsonl, {PACKED_EXTENSION} or pgn inputs found at {}",
+            args.input.display()
+        );
     }
 
     let mut relabeled = 0usize;
@@ -340,9 +564,18 @@ fn main() -> anyhow::Result<()> {
         None
     };
     let period = args.every.max(1);
-    let teacher_params = build_teacher_search_params(args.depth, args.max_nodes);
     let worker_count = args.threads.max(1);
     let worker_hash_mb = per_worker_hash_mb(args.hash_mb, worker_count);
+    let config = TeacherConfig {
+        params: SearchParams {
+            multipv: args.multipv.max(1),
+            ..build_teacher_search_params(args.depth, args.max_nodes)
+        },
+        depth: args.depth,
+        wdl: WdlModel::new(args.wdl_scale_cp, args.wdl_draw_cp),
+        hash_mb: worker_hash_mb,
+        nnue_blend_percent: args.nnue_blend_percent,
+    };
     let pool = rayon::ThreadPoolBuilder::new()
         .num_threads(worker_count)
         .build()?;
@@ -355,15 +588,21 @@ fn main() -> anyhow::Result<()> {
     );
 
     for in_path in inputs {
-        let out_path = args.output.join(
-            in_path
-                .file_name()
-                .ok_or_else(|| anyhow::anyhow!("bad input filename"))?,
-        );
-        let rdr = BufReader::new(File::open(&in_path)?);
-        let mut wr = BufWriter::new(File::create(&out_path)?);
+        let file_name = in_path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("bad input filename"))?;
+        let format = args.output_format.unwrap_or(if is_packed(&in_path) {
+            OutputFormat::Packed
+        } else {
+            OutputFormat::Jsonl
+        });
+        let out_path = args.output.join(file_name).with_extension(match format {
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Packed => PACKED_EXTENSION,
+        });
+        let mut wr = OutputSink::create(&out_path, format)?;
         let mut batch: Vec<String> = Vec::with_capacity(RELABEL_BATCH_LINES);
-        for line in rdr.lines() {
+        for line in input_lines(&in_path)? {
             let line = line?;
             if line.trim().is_empty() {
                 continue;
@@ -376,16 +615,12 @@ fn main() -> anyhow::Result<()> {
                     std::mem::take(&mut batch),
                     period,
                     remaining_limit,
-                    teacher_params,
-                    args.depth,
-                    worker_hash_mb,
+                    &config,
                     nnue_quant_model.as_ref(),
-                    args.nnue_blend_percent,
                 );
                 relabeled += batch_relabeled;
                 for out_line in out_lines {
-                    wr.write_all(out_line.as_bytes())?;
-                    wr.write_all(b"\n")?;
+                    wr.write_line(&out_line)?;
                 }
             }
         }
@@ -396,19 +631,15 @@ fn main() -> anyhow::Result<()> {
                 std::mem::take(&mut batch),
                 period,
                 remaining_limit,
-                teacher_params,
-                args.depth,
-                worker_hash_mb,
+                &config,
                 nnue_quant_model.as_ref(),
-                args.nnue_blend_percent,
             );
             relabeled += batch_relabeled;
             for out_line in out_lines {
-                wr.write_all(out_line.as_bytes())?;
-                wr.write_all(b"\n")?;
+                wr.write_line(&out_line)?;
             }
         }
-        wr.flush()?;
+        wr.finish()?;
     }
 
     println!("Relabeled records: {}", relabeled);
@@ -418,11 +649,24 @@ fn main() -> anyhow::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::{
-        build_teacher_search_params, build_teacher_searcher, per_worker_hash_mb,
-        process_batch_line, relabel_phase, should_select_for_relabel, worker_batches, BatchLine,
+        build_teacher_search_params, build_teacher_searcher, collect_inputs, input_lines,
+        per_worker_hash_mb, process_batch_line, relabel_phase, should_select_for_relabel,
+        worker_batches, BatchLine, OutputFormat, OutputSink, TeacherConfig, WdlModel,
     };
+    use piebot::search::alphabeta::SearchParams;
+    use piebot::selfplay::packed::{PackedFile, PackedPosition, PackedWriter};
     use serde_json::{json, Value};
 
+    fn config(params: SearchParams, depth: u32) -> TeacherConfig {
+        TeacherConfig {
+            params,
+            depth,
+            wdl: None,
+            hash_mb: 1,
+            nnue_blend_percent: 100,
+        }
+    }
+
     #[test]
     fn teacher_search_params_clamp_depth_and_use_single_search_thread() {
         let p = build_teacher_search_params(0, 0);
@@ -464,7 +708,7 @@ mod tests {
         // position; the stamped teacher_depth must be the depth actually
         // reached so min_teacher_depth filtering stays honest.
         let params = build_teacher_search_params(8, 300);
-        let (line, relabeled) = process_batch_line(task, &mut teacher, params, 8);
+        let (line, relabeled) = process_batch_line(task, &mut teacher, &config(params, 8));
         let output: Value = serde_json::from_str(&line).expect("valid relabeled JSON");
 
         assert_eq!(relabeled, 1);
@@ -492,7 +736,7 @@ mod tests {
         let mut teacher = build_teacher_searcher(1, None, 100);
         let params = build_teacher_search_params(6, 20_000);
         assert_eq!(Some(20_000), params.max_nodes);
-        let (line, relabeled) = process_batch_line(task, &mut teacher, params, 6);
+        let (line, relabeled) = process_batch_line(task, &mut teacher, &config(params, 6));
         let output: Value = serde_json::from_str(&line).expect("valid relabeled JSON");
 
         assert_eq!(relabeled, 1);
@@ -643,8 +887,11 @@ mod tests {
             should_relabel: true,
         };
         let mut teacher = build_teacher_searcher(1, None, 100);
-        let (line, relabeled) =
-            process_batch_line(task, &mut teacher, build_teacher_search_params(1, 0), 6);
+        let (line, relabeled) = process_batch_line(
+            task,
+            &mut teacher,
+            &config(build_teacher_search_params(1, 0), 6),
+        );
         let output: Value = serde_json::from_str(&line).expect("valid relabeled JSON");
 
         assert_eq!(relabeled, 1);
@@ -672,8 +919,11 @@ mod tests {
             should_relabel: false,
         };
         let mut teacher = build_teacher_searcher(1, None, 100);
-        let (line, relabeled) =
-            process_batch_line(task, &mut teacher, build_teacher_search_params(1, 0), 6);
+        let (line, relabeled) = process_batch_line(
+            task,
+            &mut teacher,
+            &config(build_teacher_search_params(1, 0), 6),
+        );
         let output: Value = serde_json::from_str(&line).expect("valid preserved JSON");
 
         assert_eq!(relabeled, 0);
@@ -683,4 +933,162 @@ mod tests {
         assert!(output.get("run_id").is_none());
         assert!(output.get("game_id").is_none());
     }
+
+    #[test]
+    fn pgn_inputs_stream_as_selfplay_jsonl_records() {
+        let dir = std::env::temp_dir().join(format!("piebot-relabel-pgn-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pgn_path = dir.join("db.pgn");
+        std::fs::write(
+            &pgn_path,
+            "[Result \"1-0\"]\n\n1. e4 {+0.30/9} e5 2. Qh5 Nc6 1-0\n\n\
+             [Result \"*\"]\n\n1. d4 *\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("shard.jsonl"), "{}\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let inputs = collect_inputs(&dir).unwrap();
+        let names: Vec<_> = inputs.iter().map(|p| p.file_name().unwrap()).collect();
+        assert_eq!(names, ["db.pgn", "shard.jsonl"]);
+
+        let lines: Vec<Value> = input_lines(&pgn_path)
+            .unwrap()
+            .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+            .collect();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0]["run_id"], "pgn:db");
+        assert_eq!(lines[0]["game_id"], "pgn:db:0");
+        assert_eq!(lines[0]["played_move"], "e2e4");
+        assert_eq!(lines[0]["value_cp"], 30.0);
+        assert_eq!(lines[0]["teacher_depth"], 9);
+        assert_eq!(lines[3]["ply"], 3);
+        assert_eq!(lines[3]["result"], 1);
+        assert_eq!(lines[4]["game_id"], "pgn:db:1");
+        assert_eq!(lines[4]["outcome_valid"], false);
+        assert!(should_select_for_relabel(lines[0].as_object().unwrap(), 1));
+    }
+
+    #[test]
+    fn packed_inputs_read_back_and_write_through_the_packed_sink() {
+        let dir =
+            std::env::temp_dir().join(format!("piebot-relabel-packed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let in_path = dir.join("shard.piepack");
+        let mut position = PackedPosition::new(cozy_chess::Board::default());
+        position.result = 1;
+        position.outcome_valid = true;
+        position.played_move = Some("e2e4".parse().unwrap());
+        let mut writer = PackedWriter::new(std::fs::File::create(&in_path).unwrap()).unwrap();
+        writer.write(&position).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(
+            collect_inputs(&dir).unwrap(),
+            std::slice::from_ref(&in_path)
+        );
+        let lines: Vec<String> = input_lines(&in_path).unwrap().map(Result::unwrap).collect();
+        assert_eq!(lines.len(), 1);
+        let mut record: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(record["played_move"], "e2e4");
+        record["value_cp"] = json!(42.0);
+        record["teacher_depth"] = json!(6);
+
+        let out_path = dir.join("out.piepack");
+        let mut sink = OutputSink::create(&out_path, OutputFormat::Packed).unwrap();
+        sink.write_line(&record.to_string()).unwrap();
+        sink.finish().unwrap();
+        let file = PackedFile::open(&out_path).unwrap();
+        let relabeled = file.view().get(0).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(relabeled.score_cp, Some(42));
+        assert_eq!(relabeled.teacher_depth, Some(6));
+        assert_eq!(relabeled.played_move, position.played_move);
+    }
+
+    #[test]
+    fn wdl_model_is_symmetric_and_sums_to_one() {
+        let model = WdlModel::new(400.0, 100.0).unwrap();
+        let [w, d, l] = model.wdl(0.0);
+        assert!((w - l).abs() < 1e-6);
+        assert!(d > 0.1, "the draw band peaks at 0 cp: {w} {d} {l}");
+        assert!(model.wdl(300.0)[1] < d);
+        for cp in [-900.0, -150.0, 35.0, 600.0, 32_000.0] {
+            let [w, d, l] = model.wdl(cp);
+            assert!((w + d + l - 1.0).abs() < 1e-5);
+            assert_eq!(model.wdl(-cp), [l, d, w]);
+        }
+        assert!(model.wdl(32_000.0)[0] > 0.999);
+        // Without a draw band the win probability is the plain logistic.
+        let plain = WdlModel::new(400.0, 0.0).unwrap();
+        let expected = 1.0 / (1.0 + (-1.0f32).exp());
+        assert!((plain.wdl(400.0)[0] - expected).abs() < 1e-6);
+        assert_eq!(WdlModel::new(0.0, 100.0), None);
+    }
+
+    #[test]
+    fn multipv_teacher_writes_ranked_top_moves_pv_and_wdl() {
+        // Black to move: the ranked scores are stored White-relative, so the
+        // best line has the lowest cp.
+        let original = json!({
+            "fen": "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+            "ply": 2,
+            "run_id": "run-top",
+            "game_id": "game-top",
+            "teacher_top": [{"move": "stale", "cp": 0}]
+        });
+        let task = BatchLine {
+            original: original.to_string(),
+            parsed: original.as_object().cloned(),
+            should_relabel: true,
+        };
+        let mut teacher = build_teacher_searcher(1, None, 100);
+        let mut cfg = config(
+            SearchParams {
+                multipv: 4,
+                ..build_teacher_search_params(3, 0)
+            },
+            3,
+        );
+        cfg.wdl = WdlModel::new(400.0, 100.0);
+        let (line, relabeled) = process_batch_line(task, &mut teacher, &cfg);
+        let output: Value = serde_json::from_str(&line).expect("valid relabeled JSON");
+
+        assert_eq!(relabeled, 1);
+        assert_eq!(output["run_id"], "run-top");
+        let top = output["teacher_top"].as_array().expect("teacher_top");
+        assert_eq!(top.len(), 4);
+        assert_eq!(top[0]["move"], output["best_move"]);
+        let cps: Vec<i64> = top.iter().map(|e| e["cp"].as_i64().unwrap()).collect();
+        assert!(cps.windows(2).all(|w| w[0] <= w[1]), "{cps:?}");
+        assert_eq!(output["value_cp"].as_f64().unwrap() as i64, cps[0]);
+
+        let pv = output["teacher_pv"].as_array().expect("teacher_pv");
+        assert_eq!(pv[0], output["best_move"]);
+        let wdl: Vec<f64> = output["teacher_wdl"]
+            .as_array()
+            .expect("teacher_wdl")
+            .iter()
+            .map(|p| p.as_f64().unwrap())
+            .collect();
+        assert_eq!(wdl.len(), 3);
+        assert!((wdl.iter().sum::<f64>() - 1.0).abs() < 1e-3);
+
+        // A single-line pass drops the MultiPV and WDL labels of the last one.
+        let task = BatchLine {
+            original: line.clone(),
+            parsed: serde_json::from_str(&line).ok(),
+            should_relabel: true,
+        };
+        let (line, _) = process_batch_line(
+            task,
+            &mut teacher,
+            &config(build_teacher_search_params(2, 0), 2),
+        );
+        let output: Value = serde_json::from_str(&line).unwrap();
+        assert!(output.get("teacher_top").is_none());
+        assert!(output.get("teacher_wdl").is_none());
+        assert!(output.get("teacher_pv").is_some());
+    }
 }
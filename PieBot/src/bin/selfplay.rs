@@ -1,9 +1,20 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use piebot::eval::nnue::loader::QuantNnue;
-use piebot::selfplay::{
-    effective_parallel_games, generate_games, write_jsonl_shards, write_shards, SelfPlayParams,
-};
+use piebot::selfplay::opening::OpeningStrategy;
+use piebot::selfplay::stream::{StreamOutputs, StreamingShardWriter, MANIFEST_FILE};
+use piebot::selfplay::{effective_parallel_games, generate_games_streaming, SelfPlayParams};
 use std::path::PathBuf;
+use std::sync::mpsc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OpeningKind {
+    /// Start position or a random --openings entry
+    Suite,
+    /// Random legal plies from there, filtered by a shallow search
+    RandomWalk,
+    /// Weighted moves from --opening-book
+    Book,
+}
 
 #[derive(Parser, Debug)]
 #[command(
@@ -29,6 +40,12 @@ struct Args {
     out: PathBuf,
     #[arg(long)]
     jsonl_out: Option<PathBuf>,
+    /// Also write PIEPACK shards (full positions and targets) to this directory
+    #[arg(long)]
+    packed_out: Option<PathBuf>,
+    /// Also write every game, with teacher evals as comments, to this PGN file
+    #[arg(long)]
+    pgn_out: Option<PathBuf>,
     #[arg(long, default_value_t = 100_000)]
     max_records_per_shard: usize,
     #[arg(long, default_value_t = false)]
@@ -73,6 +90,39 @@ struct Args {
     policy_node_cap: u64,
     #[arg(long, default_value_t = 20_000)]
     bestmove_node_cap: u64,
+    /// Write the policy over every legal move into policy_top instead of the
+    /// top 8
+    #[arg(long, default_value_t = false)]
+    policy_full_distribution: bool,
+    /// How games leave the start position (or --openings entry)
+    #[arg(long, value_enum, default_value_t = OpeningKind::Suite)]
+    opening_strategy: OpeningKind,
+    /// Random plies for --opening-strategy random-walk
+    #[arg(long, default_value_t = 8)]
+    random_opening_plies: usize,
+    /// Redraw random walks whose shallow search is beyond this many cp (0 = off)
+    #[arg(long, default_value_t = 150)]
+    random_opening_max_cp: i32,
+    /// Depth of the random-walk eval filter search
+    #[arg(long, default_value_t = 4)]
+    random_opening_depth: u32,
+    /// Polyglot .bin book or PGN database for --opening-strategy book
+    #[arg(long)]
+    opening_book: Option<PathBuf>,
+    /// Book openings stop after a random 1..=N plies
+    #[arg(long, default_value_t = 12)]
+    book_max_plies: usize,
+    /// Play every opening twice, in games 2k and 2k+1
+    #[arg(long, default_value_t = false)]
+    paired_openings: bool,
+    /// Skip games already in finished shards of an interrupted run with the
+    /// same seed, as recorded in the manifest
+    #[arg(long, default_value_t = false)]
+    resume: bool,
+    /// Progress manifest; defaults to .selfplay_manifest.json in the JSONL,
+    /// packed or binary output directory, whichever is written first
+    #[arg(long)]
+    manifest: Option<PathBuf>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -82,6 +132,21 @@ fn main() -> anyhow::Result<()> {
     } else {
         None
     };
+    let opening_strategy = match a.opening_strategy {
+        OpeningKind::Suite => OpeningStrategy::Suite,
+        OpeningKind::RandomWalk => OpeningStrategy::RandomWalk {
+            plies: a.random_opening_plies,
+            max_cp: a.random_opening_max_cp,
+            depth: a.random_opening_depth,
+        },
+        OpeningKind::Book => OpeningStrategy::Book {
+            path: a
+                .opening_book
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("--opening-strategy book needs --opening-book"))?,
+            max_plies: a.book_max_plies,
+        },
+    };
     let params = SelfPlayParams {
         games: a.games,
         max_plies: a.max_plies,
@@ -110,6 +175,9 @@ fn main() -> anyhow::Result<()> {
         actor_tt_mb: a.actor_tt_mb,
         policy_node_cap: a.policy_node_cap,
         bestmove_node_cap: a.bestmove_node_cap,
+        policy_full_distribution: a.policy_full_distribution,
+        opening_strategy,
+        paired_openings: a.paired_openings,
     };
     let effective_parallel = effective_parallel_games(&params);
     eprintln!(
@@ -124,16 +192,56 @@ fn main() -> anyhow::Result<()> {
         a.dirichlet_epsilon,
         a.nnue_quant_file.is_some()
     );
-    let games = generate_games(&params).map_err(|e| anyhow::anyhow!(e))?;
-    if !a.skip_bin {
-        eprintln!("Writing binary shards to {}", a.out.display());
-        let shards = write_shards(&games, &a.out, a.max_records_per_shard)?;
-        eprintln!("Wrote {} binary shards", shards.len());
-    }
-    if let Some(jsonl_out) = a.jsonl_out.as_ref() {
-        eprintln!("Writing JSONL shards to {}", jsonl_out.display());
-        let shards = write_jsonl_shards(&games, jsonl_out, a.max_records_per_shard)?;
-        eprintln!("Wrote {} JSONL shards", shards.len());
+    let bin_dir = (!a.skip_bin).then(|| a.out.clone());
+    let manifest_path = a.manifest.clone().unwrap_or_else(|| {
+        a.jsonl_out
+            .as_ref()
+            .or(a.packed_out.as_ref())
+            .unwrap_or(&a.out)
+            .join(MANIFEST_FILE)
+    });
+    let manifest_display = manifest_path.display().to_string();
+    let outputs = StreamOutputs {
+        bin_dir,
+        jsonl_dir: a.jsonl_out.clone(),
+        packed_dir: a.packed_out.clone(),
+        pgn_path: a.pgn_out.clone(),
+        manifest_path,
+        max_records_per_shard: a.max_records_per_shard,
+    };
+    let mut writer =
+        StreamingShardWriter::create(outputs, a.seed, a.resume).map_err(|e| anyhow::anyhow!(e))?;
+    let pending: Vec<usize> = (0..a.games).filter(|&i| !writer.is_completed(i)).collect();
+    if a.resume {
+        eprintln!(
+            "Resuming {}: {} games done, {} to play",
+            writer.manifest().run_id,
+            a.games - pending.len(),
+            pending.len()
+        );
     }
+
+    // Games go to the writer as they finish; the bounded channel keeps
+    // finished but unwritten games from piling up in memory.
+    let (tx, rx) = mpsc::sync_channel(effective_parallel.max(1) * 2);
+    let mut written = 0usize;
+    let (generated, wrote) = std::thread::scope(|s| {
+        let producer = s.spawn(|| generate_games_streaming(&params, &pending, tx));
+        let wrote = rx.into_iter().try_for_each(|(game_idx, game)| {
+            writer.write_game(game_idx, &game)?;
+            written += 1;
+            Ok::<_, std::io::Error>(())
+        });
+        (producer.join(), wrote)
+    });
+    wrote?;
+    generated
+        .map_err(|_| anyhow::anyhow!("self-play worker panicked"))?
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let shards = writer.finish()?;
+    eprintln!(
+        "Wrote {written} games in {} shard files (manifest {manifest_display})",
+        shards.len()
+    );
     Ok(())
 }
@@ -0,0 +1,646 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use cozy_chess::Board;
+use piebot::arena::engine::EngineSpec;
+use piebot::arena::{play_jobs, GameJob, GameRules, MatchGame, MatchState};
+use piebot::eval::nnue::loader::QuantNnue;
+use piebot::io::openings::load_fen_suite;
+use piebot::search::time_manager::TimeControl;
+use piebot::stats::{logistic_elo, ml_ratings, paired_bootstrap, Pentanomial, ResultMatrix};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const PGN_EVENT: &str = "PieBot tournament";
+const REPORT_SCHEMA: &str = "piebot-tournament-v1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Format {
+    /// Every player meets every other player
+    RoundRobin,
+    /// The first player meets each of the others; they do not meet each other
+    Gauntlet,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "tournament",
+    about = "Round-robin or gauntlet tournament between PieBot configurations, with ML Elo ratings"
+)]
+struct Args {
+    /// Player, given once per configuration:
+    /// `name=..[,eval=pst|nnue][,quant=PATH][,blend=0-100][,hash=MB][,threads=N]`.
+    /// `cmd=`, `dir=`, `arg=` and `option.NAME=VALUE` are passed through as
+    /// for `match`; cmd defaults to the `uci` binary next to this one.
+    #[arg(long = "player", required = true, num_args = 1)]
+    players: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t = Format::RoundRobin)]
+    format: Format,
+
+    /// Games per pairing, in colour-reversed pairs over the shared openings.
+    /// May grow between runs of one --state-file.
+    #[arg(long, default_value_t = 20)]
+    games_per_pairing: usize,
+
+    /// Games played at once, each by its own engine processes
+    #[arg(long, default_value_t = 1)]
+    concurrency: usize,
+
+    /// FEN/EPD opening suite shared by every pairing, used in order and
+    /// wrapped around; start position if omitted
+    #[arg(long)]
+    openings_file: Option<PathBuf>,
+
+    /// Clock per engine in seconds: `base+inc` or `moves/base[+inc]`
+    #[arg(long, default_value = "10+0.1")]
+    tc: TimeControl,
+
+    #[arg(long, default_value_t = 1_000)]
+    timeout_margin_ms: u64,
+
+    #[arg(long, default_value_t = 10_000)]
+    start_timeout_ms: u64,
+
+    #[arg(long, default_value_t = 400)]
+    max_plies: usize,
+
+    #[arg(long, default_value_t = 1_000.0)]
+    resign_cp: f32,
+
+    #[arg(long, default_value_t = 6)]
+    resign_plies: usize,
+
+    #[arg(long, default_value_t = 10.0)]
+    draw_adj_cp: f32,
+
+    #[arg(long, default_value_t = 16)]
+    draw_adj_plies: usize,
+
+    #[arg(long, default_value_t = 80)]
+    draw_adj_min_ply: usize,
+
+    /// Virtual draws added to every pairing played before rating, so a
+    /// player with a perfect or zero score still gets a finite rating
+    #[arg(long, default_value_t = 1.0)]
+    prior_draws: f64,
+
+    /// Report ratings relative to this player instead of the pool mean
+    #[arg(long)]
+    anchor: Option<String>,
+
+    /// Rating given to the anchor (or to the pool mean without one)
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    anchor_elo: f64,
+
+    /// Resamples for each head-to-head paired-bootstrap interval
+    #[arg(long, default_value_t = 10_000)]
+    bootstrap_samples: usize,
+
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Finished games are saved here after each game; rerunning with the same
+    /// file and settings skips them
+    #[arg(long)]
+    state_file: Option<PathBuf>,
+
+    #[arg(long)]
+    pgn_out: Option<PathBuf>,
+
+    /// Write ratings, the head-to-head table and every game result here
+    #[arg(long)]
+    json_out: Option<PathBuf>,
+}
+
+/// A PieBot configuration and the engine spec that runs it.
+#[derive(Clone, Debug, Serialize)]
+struct Player {
+    #[serde(skip)]
+    spec: EngineSpec,
+    name: String,
+    eval: Option<String>,
+    quant: Option<String>,
+    blend: Option<u8>,
+    hash: Option<usize>,
+    threads: Option<usize>,
+    /// Content hash of the quant net, as the UCI engine reports it.
+    net_hash: Option<String>,
+}
+
+fn parse_player(raw: &str, default_cmd: &str) -> Result<Player> {
+    let mut eval = None;
+    let mut quant = None;
+    let mut blend = None;
+    let mut hash: Option<usize> = None;
+    let mut threads: Option<usize> = None;
+    let mut passthrough = Vec::new();
+    for field in raw.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        let (key, value) = field.split_once('=').unwrap_or((field, ""));
+        let bad = |what: &str| anyhow::anyhow!("player {raw:?}: bad {what} {value:?}");
+        match key {
+            "eval" => match value {
+                "pst" | "nnue" => eval = Some(value.to_string()),
+                _ => return Err(bad("eval mode (expected pst or nnue)")),
+            },
+            "quant" => quant = Some(value.to_string()),
+            "blend" => {
+                blend = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|&b: &u8| b <= 100)
+                        .ok_or_else(|| bad("blend"))?,
+                )
+            }
+            "hash" => hash = Some(value.parse().map_err(|_| bad("hash"))?),
+            "threads" => threads = Some(value.parse().map_err(|_| bad("threads"))?),
+            _ => passthrough.push(field),
+        }
+    }
+    if !passthrough.iter().any(|f| f.starts_with("cmd=")) {
+        passthrough.push(default_cmd);
+    }
+    let mut spec: EngineSpec = passthrough.join(",").parse().map_err(anyhow::Error::msg)?;
+    if !raw.split(',').any(|f| f.trim().starts_with("name=")) {
+        bail!("player {raw:?}: every player needs a name=");
+    }
+    // The engine runs in `dir=`, so it resolves a relative net path there;
+    // hash the file it will actually load.
+    let net_hash = match &quant {
+        Some(path) => Some(
+            QuantNnue::load_quantized_hashed(resolve_in_dir(spec.dir.as_deref(), path))
+                .with_context(|| format!("player {}", spec.name))?
+                .1,
+        ),
+        None => None,
+    };
+    let mut options = Vec::new();
+    if let Some(threads) = threads {
+        options.push(("Threads", threads.to_string()));
+    }
+    if let Some(hash) = hash {
+        options.push(("Hash", hash.to_string()));
+    }
+    if let Some(quant) = &quant {
+        options.push(("NNUEQuantFile", quant.clone()));
+    }
+    if let Some(blend) = blend {
+        options.push(("EvalBlend", blend.to_string()));
+    }
+    if let Some(eval) = &eval {
+        options.push(("UseNNUE", (eval == "nnue").to_string()));
+    }
+    // Explicit option.* entries come last so they win over the shorthands.
+    let explicit = std::mem::take(&mut spec.options);
+    spec.options = options
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .chain(explicit)
+        .collect();
+    Ok(Player {
+        name: spec.name.clone(),
+        spec,
+        eval,
+        quant,
+        blend,
+        hash,
+        threads,
+        net_hash,
+    })
+}
+
+/// `path` as seen from an engine started in `dir`.
+fn resolve_in_dir(dir: Option<&str>, path: &str) -> PathBuf {
+    match dir {
+        Some(dir) => Path::new(dir).join(path),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Pairings in schedule order, as (player, opponent) indices.
+fn pairings(format: Format, players: usize) -> Vec<(usize, usize)> {
+    match format {
+        Format::RoundRobin => (0..players)
+            .flat_map(|i| (i + 1..players).map(move |j| (i, j)))
+            .collect(),
+        Format::Gauntlet => (1..players).map(|j| (0, j)).collect(),
+    }
+}
+
+/// Game `round` of `pairing` has index `round * pairings + pairing`, so
+/// indices do not depend on --games-per-pairing and each round visits every
+/// pairing before the next begins. Rounds 2k and 2k+1 replay opening k with
+/// colours reversed.
+fn schedule(
+    pairings: &[(usize, usize)],
+    games_per_pairing: usize,
+    openings: usize,
+) -> Vec<GameJob> {
+    (0..games_per_pairing)
+        .flat_map(|round| {
+            pairings.iter().enumerate().map(move |(k, &(i, j))| {
+                let (white, black) = if round % 2 == 0 { (i, j) } else { (j, i) };
+                GameJob {
+                    index: round * pairings.len() + k,
+                    opening: (round / 2) % openings,
+                    white,
+                    black,
+                }
+            })
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct RatingRow<'a> {
+    rank: usize,
+    name: &'a str,
+    elo: f64,
+    elo_95_ci: [f64; 2],
+    std_error: f64,
+    games: usize,
+    score_points: f64,
+    score_rate: f64,
+    config: &'a Player,
+}
+
+/// One pairing from `player`'s side, with the keys `scripts/uci_elo_arena.py`
+/// writes in its `summary`, so the same gate code can read either.
+#[derive(Serialize)]
+struct HeadToHead<'a> {
+    player: &'a str,
+    opponent: &'a str,
+    games: usize,
+    wins: usize,
+    draws: usize,
+    losses: usize,
+    score_points: f64,
+    score_rate: Option<f64>,
+    /// `None` when the score is 0% or 100%.
+    elo_difference: Option<f64>,
+    complete_pairs: u64,
+    pentanomial: [u64; 5],
+    termination_counts: BTreeMap<&'static str, usize>,
+    bootstrap_samples: usize,
+    score_95_ci: Option<[f64; 2]>,
+    elo_95_ci: Option<[Option<f64>; 2]>,
+}
+
+#[derive(Serialize)]
+struct GameSummary<'a> {
+    index: usize,
+    opening: usize,
+    white: &'a str,
+    black: &'a str,
+    result: i8,
+    termination: &'static str,
+    plies: usize,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    schema: &'static str,
+    format: Format,
+    time_control: String,
+    games_per_pairing: usize,
+    games: usize,
+    prior_draws: f64,
+    anchor: Option<&'a str>,
+    anchor_elo: f64,
+    ratings: Vec<RatingRow<'a>>,
+    head_to_head: Vec<HeadToHead<'a>>,
+    game_results: Vec<GameSummary<'a>>,
+}
+
+fn finite(elo: f64) -> Option<f64> {
+    elo.is_finite().then_some(elo)
+}
+
+fn head_to_head<'a>(
+    args: &Args,
+    players: &'a [Player],
+    pairings: &[(usize, usize)],
+    games: &[MatchGame],
+) -> Vec<HeadToHead<'a>> {
+    let mut rows = Vec::new();
+    for (k, &(i, j)) in pairings.iter().enumerate() {
+        let (me, them) = (players[i].name.as_str(), players[j].name.as_str());
+        // Game score of `me` by round.
+        let mut by_round = BTreeMap::new();
+        let mut termination_counts = BTreeMap::new();
+        for game in games.iter().filter(|g| g.index % pairings.len() == k) {
+            if let Some(score) = game.score_of(me) {
+                by_round.insert(game.index / pairings.len(), score);
+                *termination_counts
+                    .entry(game.termination.as_str())
+                    .or_insert(0) += 1;
+            }
+        }
+        let scores: Vec<f64> = by_round.values().copied().collect();
+        let mut pentanomial = Pentanomial::default();
+        let mut pair_scores = Vec::new();
+        for (&round, &score) in by_round.iter().filter(|(&round, _)| round % 2 == 0) {
+            if let Some(&reply) = by_round.get(&(round + 1)) {
+                pentanomial
+                    .add_pair(score + reply)
+                    .expect("two game scores make a valid pair score");
+                pair_scores.push((score + reply) / 2.0);
+            }
+        }
+        let points: f64 = scores.iter().sum();
+        let score_rate = (!scores.is_empty()).then(|| points / scores.len() as f64);
+        let interval = paired_bootstrap(&pair_scores, args.bootstrap_samples, 0.95, args.seed);
+        rows.push(HeadToHead {
+            player: me,
+            opponent: them,
+            games: scores.len(),
+            wins: scores.iter().filter(|&&s| s == 1.0).count(),
+            draws: scores.iter().filter(|&&s| s == 0.5).count(),
+            losses: scores.iter().filter(|&&s| s == 0.0).count(),
+            score_points: points,
+            score_rate,
+            elo_difference: score_rate.map(logistic_elo).and_then(finite),
+            complete_pairs: pentanomial.pairs(),
+            pentanomial: pentanomial.counts,
+            termination_counts,
+            bootstrap_samples: args.bootstrap_samples,
+            score_95_ci: interval.map(|ci| [ci.lower, ci.upper]),
+            elo_95_ci: interval.map(|ci| {
+                [
+                    finite(logistic_elo(ci.lower)),
+                    finite(logistic_elo(ci.upper)),
+                ]
+            }),
+        });
+    }
+    rows
+}
+
+fn ratings<'a>(
+    args: &Args,
+    players: &'a [Player],
+    games: &[MatchGame],
+) -> Result<Vec<RatingRow<'a>>> {
+    let index_of = |name: &str| players.iter().position(|p| p.name == name);
+    let mut results = ResultMatrix::new(players.len());
+    for game in games {
+        if let (Some(white), Some(black)) = (index_of(&game.white), index_of(&game.black)) {
+            results.add_game(white, black, f64::from(game.result + 1) / 2.0);
+        }
+    }
+    let anchor = match &args.anchor {
+        Some(name) => {
+            Some(index_of(name).with_context(|| format!("--anchor {name:?} is not a player"))?)
+        }
+        None => None,
+    };
+    let fit = ml_ratings(&results, args.prior_draws).map_err(anyhow::Error::msg)?;
+    let elo = fit.relative_to(anchor);
+    let mut rows: Vec<RatingRow> = players
+        .iter()
+        .enumerate()
+        .map(|(i, player)| {
+            let (lower, upper) = fit.interval(i, anchor, 0.95);
+            let games: f64 = (0..players.len()).map(|j| results.games(i, j)).sum();
+            let points: f64 = (0..players.len()).map(|j| results.points(i, j)).sum();
+            RatingRow {
+                rank: 0,
+                name: &player.name,
+                elo: elo[i] + args.anchor_elo,
+                elo_95_ci: [lower + args.anchor_elo, upper + args.anchor_elo],
+                std_error: fit.std_error(i, anchor),
+                games: games as usize,
+                score_points: points,
+                score_rate: points / games,
+                config: player,
+            }
+        })
+        .collect();
+    rows.sort_by(|a, b| b.elo.total_cmp(&a.elo));
+    for (rank, row) in rows.iter_mut().enumerate() {
+        row.rank = rank + 1;
+    }
+    Ok(rows)
+}
+
+fn print_tables(ratings: &[RatingRow], table: &[HeadToHead], players: &[Player]) {
+    let width = players
+        .iter()
+        .map(|p| p.name.len())
+        .max()
+        .unwrap_or(6)
+        .max(6);
+    println!(
+        "{:>3} {:<width$} {:>8} {:>19} {:>8} {:>6} {:>6}",
+        "#", "PLAYER", "ELO", "95% CI", "POINTS", "GAMES", "%"
+    );
+    for row in ratings {
+        println!(
+            "{:>3} {:<width$} {:>+8.1} [{:>+7.1}, {:>+7.1}] {:>8.1} {:>6} {:>6.1}",
+            row.rank,
+            row.name,
+            row.elo,
+            row.elo_95_ci[0],
+            row.elo_95_ci[1],
+            row.score_points,
+            row.games,
+            100.0 * row.score_rate
+        );
+    }
+    println!();
+    println!("head to head (row player's points / games):");
+    print!("{:<width$}", "");
+    for player in players {
+        print!(" {:>width$}", player.name);
+    }
+    println!();
+    for row_player in players {
+        print!("{:<width$}", row_player.name);
+        for column_player in players {
+            let cell = table.iter().find_map(|h| {
+                if h.player == row_player.name && h.opponent == column_player.name {
+                    Some((h.score_points, h.games))
+                } else if h.opponent == row_player.name && h.player == column_player.name {
+                    Some((h.games as f64 - h.score_points, h.games))
+                } else {
+                    None
+                }
+            });
+            let text = match cell {
+                Some((points, games)) if games > 0 => format!("{points}/{games}"),
+                _ => "-".to_string(),
+            };
+            print!(" {text:>width$}");
+        }
+        println!();
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let default_cmd = std::env::current_exe()
+        .context("locate the tournament binary")?
+        .with_file_name(format!("uci{}", std::env::consts::EXE_SUFFIX));
+    let default_cmd = format!("cmd={}", default_cmd.display());
+    let players = args
+        .players
+        .iter()
+        .map(|raw| parse_player(raw, &default_cmd))
+        .collect::<Result<Vec<_>>>()?;
+    if players.len() < 2 {
+        bail!("a tournament needs at least two --player entries");
+    }
+    for (i, player) in players.iter().enumerate() {
+        if players[..i].iter().any(|p| p.name == player.name) {
+            bail!("two players are named {:?}", player.name);
+        }
+    }
+    if args.games_per_pairing % 2 != 0 {
+        bail!("--games-per-pairing must be even so every opening is played with both colours");
+    }
+    let openings = match &args.openings_file {
+        Some(path) => load_fen_suite(path).map_err(anyhow::Error::msg)?,
+        None => vec![Board::default()],
+    };
+    let rules = GameRules {
+        time_control: args.tc,
+        timeout_margin: Duration::from_millis(args.timeout_margin_ms),
+        start_timeout: Duration::from_millis(args.start_timeout_ms),
+        max_plies: args.max_plies,
+        resign_cp: args.resign_cp,
+        resign_plies: args.resign_plies,
+        draw_adj_cp: args.draw_adj_cp,
+        draw_adj_plies: args.draw_adj_plies,
+        draw_adj_min_ply: args.draw_adj_min_ply,
+    };
+    let specs: Vec<EngineSpec> = players.iter().map(|p| p.spec.clone()).collect();
+    let pairings = pairings(args.format, players.len());
+    let config = serde_json::json!({
+        "format": args.format,
+        "engines": specs,
+        "net_hashes": players.iter().map(|p| p.net_hash.clone()).collect::<Vec<_>>(),
+        "openings": openings.iter().map(Board::to_string).collect::<Vec<_>>(),
+        "time_control": args.tc.to_string(),
+        "timeout_margin_ms": args.timeout_margin_ms,
+        "max_plies": args.max_plies,
+        "resign_cp": args.resign_cp,
+        "resign_plies": args.resign_plies,
+        "draw_adj_cp": args.draw_adj_cp,
+        "draw_adj_plies": args.draw_adj_plies,
+        "draw_adj_min_ply": args.draw_adj_min_ply,
+    });
+    let mut state = match &args.state_file {
+        Some(path) => MatchState::load_or_new(path, config).map_err(anyhow::Error::msg)?,
+        None => MatchState::new(config),
+    };
+    let all_jobs = schedule(&pairings, args.games_per_pairing, openings.len());
+    let jobs: Vec<GameJob> = all_jobs
+        .iter()
+        .filter(|job| !state.is_done(job.index))
+        .copied()
+        .collect();
+    if jobs.len() < all_jobs.len() {
+        println!(
+            "resuming: {} of {} games already played",
+            all_jobs.len() - jobs.len(),
+            all_jobs.len()
+        );
+    }
+    play_jobs(&specs, &jobs, &openings, &rules, args.concurrency, |game| {
+        println!(
+            "game {:>5}: {} vs {}: {} ({})",
+            game.index + 1,
+            game.white,
+            game.black,
+            game.result_str(),
+            game.termination.as_str()
+        );
+        state.push(game);
+        match &args.state_file {
+            Some(path) => state.save(path),
+            None => Ok(()),
+        }
+    })
+    .map_err(anyhow::Error::msg)?;
+
+    let scheduled = all_jobs.len();
+    let games: Vec<MatchGame> = state
+        .games
+        .iter()
+        .filter(|game| game.index < scheduled)
+        .cloned()
+        .collect();
+    if let Some(path) = &args.pgn_out {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text: String = games.iter().map(|g| g.to_pgn(PGN_EVENT).to_pgn()).collect();
+        std::fs::write(path, text)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    let ratings = ratings(&args, &players, &games)?;
+    let table = head_to_head(&args, &players, &pairings, &games);
+    println!();
+    print_tables(&ratings, &table, &players);
+    if let Some(path) = &args.json_out {
+        let report = Report {
+            schema: REPORT_SCHEMA,
+            format: args.format,
+            time_control: args.tc.to_string(),
+            games_per_pairing: args.games_per_pairing,
+            games: games.len(),
+            prior_draws: args.prior_draws,
+            anchor: args.anchor.as_deref(),
+            anchor_elo: args.anchor_elo,
+            ratings,
+            head_to_head: table,
+            game_results: games
+                .iter()
+                .map(|game| GameSummary {
+                    index: game.index,
+                    opening: game.opening,
+                    white: &game.white,
+                    black: &game.black,
+                    result: game.result,
+                    termination: game.termination.as_str(),
+                    plies: game.moves.len(),
+                })
+                .collect(),
+        };
+        let text = serde_json::to_string_pretty(&report)?;
+        std::fs::write(path, text)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quant_paths_are_hashed_relative_to_the_engine_dir() {
+        let dir = std::env::temp_dir().join(format!("tournament_quant_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut bytes = b"PIENNQ01".to_vec();
+        for v in [1u32, 2, 1, 1] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        bytes.extend_from_slice(&1.0f32.to_le_bytes());
+        bytes.extend_from_slice(&1.0f32.to_le_bytes());
+        bytes.extend_from_slice(&[1, 1, 0, 0, 1, 5, 0]);
+        std::fs::write(dir.join("net.nnue"), &bytes).unwrap();
+
+        let raw = format!("name=a,dir={},quant=net.nnue", dir.display());
+        let player = parse_player(&raw, "cmd=piebot").expect("net found in dir");
+        assert_eq!(
+            player.net_hash.as_deref(),
+            Some(piebot::eval::nnue::loader::net_hash(&bytes).as_str())
+        );
+        assert!(parse_player("name=a,quant=net.nnue", "cmd=piebot").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
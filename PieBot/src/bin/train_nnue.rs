@@ -0,0 +1,170 @@
+use clap::Parser;
+use piebot::eval::nnue::quant::quantize_v2;
+use piebot::eval::nnue::train::{
+    collect_data_files, load_samples, AdamConfig, FloatNnueV2, TargetParams, Trainer,
+};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::path::PathBuf;
+use std::time::Instant;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "train-nnue",
+    about = "Train the dual-perspective SCReLU network on the CPU and export PIENNQ02"
+)]
+struct Args {
+    /// Self-play JSONL/PIEPACK file or directory; repeat for several
+    #[arg(long, required = true)]
+    data: Vec<PathBuf>,
+    /// Float checkpoint (weights and Adam state), rewritten after every epoch
+    #[arg(long)]
+    checkpoint: PathBuf,
+    /// Continue from --checkpoint instead of starting from random weights,
+    /// keeping its epoch count and learning-rate schedule
+    #[arg(long, default_value_t = false)]
+    resume: bool,
+    /// Write the final network as PIENNQ02 here
+    #[arg(long)]
+    export: Option<PathBuf>,
+    #[arg(long, default_value_t = 256)]
+    hidden: usize,
+    /// Total epochs; a resumed run trains the ones the checkpoint is missing
+    #[arg(long, default_value_t = 10)]
+    epochs: u64,
+    #[arg(long, default_value_t = 16_384)]
+    batch_size: usize,
+    #[arg(long, default_value_t = 1e-3)]
+    lr: f32,
+    /// Learning rate multiplier applied after every epoch, so epoch `n`
+    /// (from 1) trains at `lr * lr_gamma^(n-1)`
+    #[arg(long, default_value_t = 1.0)]
+    lr_gamma: f32,
+    /// Weight of the teacher eval in the target; the game result gets the rest
+    #[arg(long, default_value_t = 0.5)]
+    eval_weight: f32,
+    /// Logistic scale of the loss, in cp
+    #[arg(long, default_value_t = 400.0)]
+    scale_cp: f32,
+    #[arg(long, default_value_t = 2000.0)]
+    max_teacher_cp: f32,
+    /// Ignore teacher scores from shallower searches
+    #[arg(long, default_value_t = 0)]
+    min_teacher_depth: u8,
+    /// Stop reading data after this many samples (0 = all)
+    #[arg(long, default_value_t = 0)]
+    max_samples: usize,
+    /// Held-out share of the samples for the validation loss
+    #[arg(long, default_value_t = 0.02)]
+    validation_fraction: f64,
+    /// Trainer threads (0 = all cores)
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    if args.threads > 0 {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(args.threads)
+            .build_global()?;
+    }
+    let mut files = Vec::new();
+    for path in &args.data {
+        files.extend(collect_data_files(path)?);
+    }
+    if files.is_empty() {
+        anyhow::bail!("no jsonl or piepack data found");
+    }
+    let targets = TargetParams {
+        eval_weight: args.eval_weight,
+        scale_cp: args.scale_cp,
+        max_teacher_cp: args.max_teacher_cp,
+        min_teacher_depth: args.min_teacher_depth,
+    };
+    let loaded =
+        load_samples(&files, &targets, args.max_samples).map_err(|e| anyhow::anyhow!(e))?;
+    let mut samples = loaded.samples;
+    if samples.is_empty() {
+        anyhow::bail!("no usable samples in {} files", files.len());
+    }
+    let mut rng = SmallRng::seed_from_u64(args.seed);
+    samples.shuffle(&mut rng);
+    let validation_len = ((samples.len() as f64 * args.validation_fraction.clamp(0.0, 0.5))
+        as usize)
+        .min(samples.len() - 1);
+    let mut train = samples.split_off(validation_len);
+    let validation = samples;
+    eprintln!(
+        "Loaded {} train / {} validation samples from {} files ({} records skipped)",
+        train.len(),
+        validation.len(),
+        files.len(),
+        loaded.skipped
+    );
+
+    let adam = AdamConfig {
+        lr: args.lr,
+        ..AdamConfig::default()
+    };
+    let mut trainer = if args.resume {
+        let trainer =
+            Trainer::load_checkpoint(&args.checkpoint, adam).map_err(|e| anyhow::anyhow!(e))?;
+        eprintln!(
+            "Resumed {} at epoch {}, step {} (hidden={})",
+            args.checkpoint.display(),
+            trainer.epoch,
+            trainer.step,
+            trainer.model.hidden_dim
+        );
+        trainer
+    } else {
+        Trainer::new(FloatNnueV2::new_random(args.hidden, args.seed), adam)
+    };
+
+    if trainer.epoch >= args.epochs {
+        eprintln!(
+            "Checkpoint already has {} of {} epochs",
+            trainer.epoch, args.epochs
+        );
+    }
+    trainer.adam.lr = args.lr * args.lr_gamma.powf(trainer.epoch as f32);
+
+    let batch_size = args.batch_size.max(1);
+    for epoch in trainer.epoch + 1..=args.epochs {
+        let started = Instant::now();
+        train.shuffle(&mut rng);
+        let mut loss_sum = 0.0f64;
+        let mut batches = 0usize;
+        for batch in train.chunks(batch_size) {
+            loss_sum += f64::from(trainer.train_batch(batch, args.scale_cp));
+            batches += 1;
+        }
+        let validation_loss = trainer.loss(&validation, args.scale_cp);
+        trainer.epoch = epoch;
+        trainer.save_checkpoint(&args.checkpoint)?;
+        eprintln!(
+            "epoch {epoch}: train loss {:.6}, validation loss {validation_loss:.6}, lr {:.2e}, {:.1}s",
+            loss_sum / batches.max(1) as f64,
+            trainer.adam.lr,
+            started.elapsed().as_secs_f64()
+        );
+        trainer.adam.lr *= args.lr_gamma;
+    }
+
+    if let Some(path) = args.export.as_ref() {
+        let (quant, stats) = quantize_v2(&trainer.model);
+        quant.save(path)?;
+        eprintln!("Exported PIENNQ02 to {}", path.display());
+        for s in stats.iter().filter(|s| s.clipped > 0) {
+            eprintln!(
+                "  {}: {} of {} values clipped (max |w| {:.4})",
+                s.tensor, s.clipped, s.values, s.max_abs
+            );
+        }
+    }
+    Ok(())
+}
@@ -83,3 +83,54 @@ impl Position {
         Ok(pos)
     }
 }
+
+/// The same position as a shakmaty `Chess`, for the tablebase prober and the
+/// Polyglot book key. Both libraries number squares a1 = 0 to h8 = 63, so the
+/// bitboards carry over as they are; castling rights become the squares of
+/// the rooks that may still castle.
+pub fn to_shakmaty(board: &CozyBoard) -> Option<shakmaty::Chess> {
+    use cozy_chess::{Piece, Rank, Square};
+    use shakmaty::{Bitboard, ByColor, ByRole, CastlingMode, FromSetup, Setup};
+
+    let pieces = |piece| Bitboard(board.pieces(piece).0);
+    let colors = |color| Bitboard(board.colors(color).0);
+    let mut castling_rights = Bitboard::EMPTY;
+    for color in [Color::White, Color::Black] {
+        let rights = board.castle_rights(color);
+        let back_rank = Rank::First.relative_to(color);
+        for file in [rights.short, rights.long].into_iter().flatten() {
+            castling_rights.add(shakmaty::Square::new(Square::new(file, back_rank) as u32));
+        }
+    }
+    let stm = board.side_to_move();
+    let ep_square = board
+        .en_passant()
+        .map(|file| shakmaty::Square::new(Square::new(file, Rank::Sixth.relative_to(stm)) as u32));
+    let setup = Setup {
+        board: shakmaty::Board::from_bitboards(
+            ByRole {
+                pawn: pieces(Piece::Pawn),
+                knight: pieces(Piece::Knight),
+                bishop: pieces(Piece::Bishop),
+                rook: pieces(Piece::Rook),
+                queen: pieces(Piece::Queen),
+                king: pieces(Piece::King),
+            },
+            ByColor {
+                white: colors(Color::White),
+                black: colors(Color::Black),
+            },
+        ),
+        turn: match stm {
+            Color::White => shakmaty::Color::White,
+            Color::Black => shakmaty::Color::Black,
+        },
+        castling_rights,
+        ep_square,
+        halfmoves: u32::from(board.halfmove_clock()),
+        fullmoves: std::num::NonZeroU32::new(u32::from(board.fullmove_number()))?,
+        ..Setup::empty()
+    };
+    let mode = CastlingMode::detect(&setup);
+    shakmaty::Chess::from_setup(setup, mode).ok()
+}
@@ -0,0 +1,28 @@
+//! Network compiled into the binary.
+//!
+//! Build with `--features embedded-net` and `PIEBOT_EMBED_NET` pointing at a
+//! PIENNQ02 file (e.g. a net listed in `models/MANIFEST.json`); the UCI
+//! engine then starts with it loaded and `UseNNUE` on. Without the feature
+//! the binary carries no net and everything here returns `None`.
+use crate::eval::nnue::loader::{net_hash, QuantNnue};
+use anyhow::Result;
+
+#[cfg(feature = "embedded-net")]
+const BYTES: Option<&[u8]> = Some(include_bytes!(env!("PIEBOT_EMBEDDED_NET")));
+#[cfg(not(feature = "embedded-net"))]
+const BYTES: Option<&[u8]> = None;
+
+#[cfg(feature = "embedded-net")]
+const NAME: Option<&str> = Some(env!("PIEBOT_EMBEDDED_NET_NAME"));
+#[cfg(not(feature = "embedded-net"))]
+const NAME: Option<&str> = None;
+
+/// File name the embedded net was built from.
+pub fn name() -> Option<&'static str> {
+    NAME
+}
+
+/// Parse the embedded net and compute its `net_hash`.
+pub fn load() -> Option<Result<(QuantNnue, String)>> {
+    BYTES.map(|bytes| Ok((QuantNnue::from_bytes(bytes)?, net_hash(bytes))))
+}
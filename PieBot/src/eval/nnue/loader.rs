@@ -1,6 +1,7 @@
 use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
 
 #[derive(Debug, Clone, Copy)]
@@ -47,8 +48,155 @@ pub struct QuantNnueV2 {
 const Q_MAGIC: &[u8; 8] = b"PIENNQ01"; // Pie NNUE Quant v1
 const Q_MAGIC_V2: &[u8; 8] = b"PIENNQ02"; // Pie NNUE Quant v2 (dual perspective)
 
+/// Hex digits of the SHA-256 kept in a network's content hash.
+pub const NET_HASH_LEN: usize = 12;
+
+/// Content hash identifying a network file: the first `NET_HASH_LEN` hex
+/// digits of its SHA-256, a prefix of the `sha256` that
+/// `models/MANIFEST.json` records for every published net.
+pub fn net_hash(bytes: &[u8]) -> String {
+    let mut hex: String = Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    hex.truncate(NET_HASH_LEN);
+    hex
+}
+
+impl QuantNnueV2 {
+    /// Accumulator quantization: float activations in `[0, 1]` map to
+    /// `[0, QA]`. The head's i16 multiply-accumulate path relies on
+    /// `QA * 128 <= i16::MAX`.
+    pub const QA: i32 = 255;
+    /// Output weight quantization; float weights are kept within `±127 / QB`.
+    pub const QB: i32 = 64;
+    /// Centipawns per unit of float network output.
+    pub const SCALE: i32 = 400;
+
+    /// Write the model as PIENNQ02 (layout in `load_quantized_v2`).
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<()> {
+        let h = self.hidden_dim;
+        if self.w1.len() != self.per_perspective_input_dim * h
+            || self.b1.len() != h
+            || self.w2.len() != 2 * h
+        {
+            bail!("v2 model payload does not match its dims");
+        }
+        w.write_all(Q_MAGIC_V2)?;
+        for v in [1, self.per_perspective_input_dim as u32, h as u32, 1] {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        for v in [self.qa, self.qb, self.scale] {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        let mut bytes = Vec::with_capacity(self.w1.len() * 2);
+        for v in self.w1.iter().chain(&self.b1) {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        bytes.extend(self.w2.iter().map(|&v| v as u8));
+        bytes.extend_from_slice(&self.b2.to_le_bytes());
+        w.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let f = File::create(path)
+            .with_context(|| format!("create quant nnue file: {}", path.display()))?;
+        let mut w = BufWriter::new(f);
+        self.write_to(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
 impl QuantNnue {
+    /// Wrap an arch-v2 model; the legacy fields stay empty.
+    pub fn from_v2(model: QuantNnueV2) -> Self {
+        Self {
+            meta: QuantMeta {
+                version: 1,
+                input_dim: model.per_perspective_input_dim,
+                hidden_dim: model.hidden_dim,
+                output_dim: 1,
+            },
+            w1_scale: 1.0,
+            w2_scale: 1.0,
+            w1: Vec::new(),
+            b1: Vec::new(),
+            w2: Vec::new(),
+            b2: Vec::new(),
+            v2: Some(std::sync::Arc::new(model)),
+        }
+    }
+
+    /// Write the model in the format it was loaded from: PIENNQ02 when `v2`
+    /// is set, otherwise PIENNQ01 (layout in `read_from`).
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<()> {
+        if let Some(v2) = self.v2.as_ref() {
+            return v2.write_to(w);
+        }
+        let m = &self.meta;
+        if m.output_dim != 1
+            || self.w1.len() != m.hidden_dim * m.input_dim
+            || self.b1.len() != m.hidden_dim
+            || self.w2.len() != m.output_dim * m.hidden_dim
+            || self.b2.len() != m.output_dim
+        {
+            bail!("quant model payload does not match its dims");
+        }
+        w.write_all(Q_MAGIC)?;
+        for v in [
+            m.version,
+            m.input_dim as u32,
+            m.hidden_dim as u32,
+            m.output_dim as u32,
+        ] {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        w.write_all(&self.w1_scale.to_le_bytes())?;
+        w.write_all(&self.w2_scale.to_le_bytes())?;
+        let mut bytes: Vec<u8> = self.w1.iter().map(|&v| v as u8).collect();
+        for v in &self.b1 {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        bytes.extend(self.w2.iter().map(|&v| v as u8));
+        for v in &self.b2 {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        w.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let f = File::create(path)
+            .with_context(|| format!("create quant nnue file: {}", path.display()))?;
+        let mut w = BufWriter::new(f);
+        self.write_to(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+
     pub fn load_quantized<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let f = File::open(&path)
+            .with_context(|| format!("open quant nnue file: {}", path.as_ref().display()))?;
+        Self::read_from(&mut BufReader::new(f))
+    }
+
+    /// Load a PIENNQ01/PIENNQ02 file together with its `net_hash`.
+    pub fn load_quantized_hashed<P: AsRef<Path>>(path: P) -> Result<(Self, String)> {
+        let bytes = std::fs::read(&path)
+            .with_context(|| format!("open quant nnue file: {}", path.as_ref().display()))?;
+        Ok((Self::from_bytes(&bytes)?, net_hash(&bytes)))
+    }
+
+    /// Parse a PIENNQ01/PIENNQ02 image held in memory, e.g. an embedded net.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::read_from(&mut &bytes[..])
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self> {
         // Simple quant format for bootstrapping:
         // magic: 8 bytes b"PIENNQ01"
         // u32 version
@@ -58,13 +206,10 @@ impl QuantNnue {
         // i16 b1[hidden]
         // i8  w2[output*hidden]
         // i16 b2[output]
-        let f = File::open(&path)
-            .with_context(|| format!("open quant nnue file: {}", path.as_ref().display()))?;
-        let mut r = BufReader::new(f);
         let mut magic = [0u8; 8];
         r.read_exact(&mut magic).context("read magic")?;
         if &magic == Q_MAGIC_V2 {
-            return Self::load_quantized_v2(&mut r);
+            return Self::load_quantized_v2(r);
         }
         if &magic != Q_MAGIC {
             bail!("bad quant NNUE magic");
@@ -212,31 +357,19 @@ impl QuantNnue {
         let w2: Vec<i8> = w2_bytes.into_iter().map(|b| b as i8).collect();
         let b2 = i32::from_le_bytes(b2_bytes);
 
-        Ok(Self {
-            meta: QuantMeta {
-                version,
-                input_dim,
-                hidden_dim,
-                output_dim,
-            },
-            w1_scale: 1.0,
-            w2_scale: 1.0,
-            w1: Vec::new(),
-            b1: Vec::new(),
-            w2: Vec::new(),
-            b2: Vec::new(),
-            v2: Some(std::sync::Arc::new(QuantNnueV2 {
-                per_perspective_input_dim: input_dim,
-                hidden_dim,
-                qa,
-                qb,
-                scale,
-                w1,
-                b1,
-                w2,
-                b2,
-            })),
-        })
+        let mut model = Self::from_v2(QuantNnueV2 {
+            per_perspective_input_dim: input_dim,
+            hidden_dim,
+            qa,
+            qb,
+            scale,
+            w1,
+            b1,
+            w2,
+            b2,
+        });
+        model.meta.version = version;
+        Ok(model)
     }
 }
 
@@ -1,9 +1,12 @@
 use cozy_chess::{Board, Color, Move, Piece};
 pub mod accumulator;
+pub mod embedded;
 pub mod features;
 pub mod loader;
 pub mod network;
 pub mod quant;
+pub mod simd;
+pub mod train;
 use anyhow::{bail, Context, Result};
 use std::collections::HashSet;
 use std::fs::File;
@@ -32,6 +35,19 @@ pub struct Nnue {
 
 impl Nnue {
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let f = File::open(&path)
+            .with_context(|| format!("open nnue file: {}", path.as_ref().display()))?;
+        Self::read_from(&mut BufReader::new(f))
+    }
+
+    /// Load a PIENNUE1 file together with its `loader::net_hash`.
+    pub fn load_hashed<P: AsRef<Path>>(path: P) -> Result<(Self, String)> {
+        let bytes = std::fs::read(&path)
+            .with_context(|| format!("open nnue file: {}", path.as_ref().display()))?;
+        Ok((Self::read_from(&mut &bytes[..])?, loader::net_hash(&bytes)))
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self> {
         // Format:
         // magic: 8 bytes b"PIENNUE1"
         // u32 version (LE)
@@ -40,9 +56,6 @@ impl Nnue {
         // f32 b1[hidden_dim]
         // f32 w2[output_dim * hidden_dim]
         // f32 b2[output_dim]
-        let f = File::open(&path)
-            .with_context(|| format!("open nnue file: {}", path.as_ref().display()))?;
-        let mut r = BufReader::new(f);
         let mut magic = [0u8; 8];
         r.read_exact(&mut magic).context("read magic")?;
         if &magic != b"PIENNUE1" {
@@ -75,10 +88,10 @@ impl Nnue {
             .checked_mul(hidden_dim)
             .context("dense dimension overflow: output_dim * hidden_dim")?;
 
-        let w1 = read_f32s_exact(&mut r, w1_len, "read dense w1 payload")?;
-        let b1 = read_f32s_exact(&mut r, hidden_dim, "read dense b1 payload")?;
-        let w2 = read_f32s_exact(&mut r, w2_len, "read dense w2 payload")?;
-        let b2 = read_f32s_exact(&mut r, output_dim, "read dense b2 payload")?;
+        let w1 = read_f32s_exact(r, w1_len, "read dense w1 payload")?;
+        let b1 = read_f32s_exact(r, hidden_dim, "read dense b1 payload")?;
+        let w2 = read_f32s_exact(r, w2_len, "read dense w2 payload")?;
+        let b2 = read_f32s_exact(r, output_dim, "read dense b2 payload")?;
         Ok(Self {
             meta: NnueMeta {
                 version,
@@ -107,6 +120,28 @@ impl Nnue {
         self.eval_dense_from_input(&x)
     }
 
+    /// Named weight tensors in file order, for inspection tools.
+    pub fn tensors(&self) -> [(&'static str, &[f32]); 4] {
+        [
+            ("w1", &self.w1),
+            ("b1", &self.b1),
+            ("w2", &self.w2),
+            ("b2", &self.b2),
+        ]
+    }
+
+    /// Hidden-layer sums before the ReLU for `board`.
+    pub fn hidden_preactivations(&self, board: &Board) -> Vec<f32> {
+        let n = self.meta.input_dim;
+        let x = self.features(board);
+        (0..self.meta.hidden_dim)
+            .map(|j| {
+                let row = &self.w1[j * n..(j + 1) * n];
+                self.b1[j] + row.iter().zip(&x).map(|(w, x)| w * x).sum::<f32>()
+            })
+            .collect()
+    }
+
     pub fn refresh_accumulator(&mut self, board: &Board) {
         self.recompute_acc(board);
         self.current_board = Some(board.clone());
@@ -280,7 +315,7 @@ impl Nnue {
     }
 }
 
-fn read_f32s_exact(r: &mut BufReader<File>, n: usize, ctx: &'static str) -> Result<Vec<f32>> {
+fn read_f32s_exact<R: Read>(r: &mut R, n: usize, ctx: &'static str) -> Result<Vec<f32>> {
     let nbytes = n
         .checked_mul(4)
         .context("dense dimension overflow: f32 byte count")?;
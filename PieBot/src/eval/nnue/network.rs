@@ -2,6 +2,7 @@ use crate::eval::nnue::features::{
     dp_active_indices, dp_piece_index, HalfKpSchema, PieceFeatureIndices,
 };
 use crate::eval::nnue::loader::{QuantNnue, QuantNnueV2};
+use crate::eval::nnue::simd::{self, Kernel};
 use cozy_chess::{Board, Color, Move, Piece, Square};
 use std::sync::Arc;
 
@@ -64,6 +65,8 @@ struct V2State {
     wk_idx: usize,
     bk_idx: usize,
     stm: Color,
+    /// Instruction set for accumulator updates and the head.
+    kernel: Kernel,
 }
 
 /// Opaque undo token returned by [`QuantNetwork::apply_move`].
@@ -88,9 +91,7 @@ enum ChangeKind {
         prev_bk: usize,
     },
     /// Side-to-move-only transition (null move) on the arch-v2 backend.
-    NullV2 {
-        prev_stm: Color,
-    },
+    NullV2 { prev_stm: Color },
     /// Nothing to undo. Returned for transitions the active backend does not
     /// model, so callers can stay branch-free.
     Inert,
@@ -143,6 +144,7 @@ impl QuantNetwork {
                 wk_idx: 0,
                 bk_idx: 0,
                 stm: Color::White,
+                kernel: Kernel::detect(),
             };
             let model = Arc::new(model);
             return Self {
@@ -194,10 +196,25 @@ impl QuantNetwork {
                 wk_idx: v2.wk_idx,
                 bk_idx: v2.bk_idx,
                 stm: v2.stm,
+                kernel: v2.kernel,
             }),
         }
     }
 
+    /// Kernel the arch-v2 backend runs on; `None` for legacy models, whose
+    /// scalar paths are left to the autovectorizer.
+    pub fn simd_kernel(&self) -> Option<Kernel> {
+        self.v2.as_ref().map(|v2| v2.kernel)
+    }
+
+    /// Force the arch-v2 kernel, e.g. to compare kernels in benches. Every
+    /// kernel produces identical accumulators and evals.
+    pub fn set_simd_kernel(&mut self, kernel: Kernel) {
+        if let Some(v2) = &mut self.v2 {
+            v2.kernel = kernel;
+        }
+    }
+
     pub fn refresh(&mut self, board: &Board) {
         if let Some(v2) = &mut self.v2 {
             v2.refresh(board);
@@ -501,55 +518,24 @@ impl QuantNetwork {
     }
 }
 
-/// SCReLU dot product: `sum_j clamp(acc[j], 0, QA)^2 * w[j]`.
-///
-/// Partial sums widen to i64 every `SCRELU_CHUNK` terms so the inner loop can
-/// stay in narrow lanes.
-const SCRELU_CHUNK: usize = 128;
-
 /// Largest QA for which `clamp(acc, 0, QA) * w` is exact in i16 across the full
 /// int8 weight range: `qa * 128 <= i16::MAX`. The shipped models quantize at
 /// QA=255, which is exactly this bound.
 const MAX_I16_MADD_QA: i32 = (i16::MAX as i32) / 128;
 
+/// SCReLU dot product: `sum_j clamp(acc[j], 0, QA)^2 * w[j]`, on the i16
+/// fast path whenever QA allows it.
 #[inline]
-fn screlu_dot(acc: &[i16], w: &[i8], qa: i32) -> i64 {
+fn screlu_dot(kernel: Kernel, acc: &[i16], w: &[i8], qa: i32) -> i64 {
     debug_assert_eq!(acc.len(), w.len());
     debug_assert!(qa > 0);
     if qa <= MAX_I16_MADD_QA {
-        screlu_dot_madd(acc, w, qa as i16)
+        simd::screlu_dot_madd(kernel, acc, w, qa as i16)
     } else {
         screlu_dot_wide(acc, w, qa)
     }
 }
 
-/// Fast path, shaped so the autovectorizer emits a widening multiply-accumulate
-/// (`vpmaddwd` on AVX2, `smlal`/`smlal2` on NEON).
-///
-/// The trick is keeping `v * w` in i16: the multiply then runs 16 lanes at a
-/// time instead of the 8 an i32 formulation allows, and the widening i16xi16
-/// -> i32 accumulate is a single instruction. Exactness rests on two bounds:
-///   * `|v * w| <= QA * 128 = 32_640 <= i16::MAX`, guaranteed by the
-///     `MAX_I16_MADD_QA` dispatch above;
-///   * `|v * (v * w)| <= 255 * 32_640 = 8_323_200`, so `SCRELU_CHUNK = 128`
-///     terms reach at most 1.065e9, well inside i32.
-/// Every intermediate is therefore exact, not merely non-panicking, and the
-/// result is bit-identical to `screlu_dot_wide`.
-#[inline]
-fn screlu_dot_madd(acc: &[i16], w: &[i8], qa: i16) -> i64 {
-    let mut total: i64 = 0;
-    for (acc_chunk, w_chunk) in acc.chunks(SCRELU_CHUNK).zip(w.chunks(SCRELU_CHUNK)) {
-        let mut partial: i32 = 0;
-        for (&a, &weight) in acc_chunk.iter().zip(w_chunk) {
-            let v = a.clamp(0, qa);
-            let vw = v * weight as i16;
-            partial += v as i32 * vw as i32;
-        }
-        total += partial as i64;
-    }
-    total
-}
-
 /// Exact for any QA. Everything widens to i64 up front, so this cannot
 /// overflow regardless of quantization scale; it is only reached by models
 /// quantized above `MAX_I16_MADD_QA`, which none currently are.
@@ -604,13 +590,8 @@ impl V2State {
         let model = &*self.model;
         for (p, perspective) in [Color::White, Color::Black].into_iter().enumerate() {
             let dst = &mut self.stack[base + p * h..base + (p + 1) * h];
-            dst.copy_from_slice(&model.b1);
-            for idx in dp_active_indices(board, perspective) {
-                let row = &model.w1[idx * h..(idx + 1) * h];
-                for (value, &weight) in dst.iter_mut().zip(row) {
-                    *value = value.wrapping_add(weight);
-                }
-            }
+            let active = dp_active_indices(board, perspective);
+            simd::update_accumulator(self.kernel, dst, &model.b1, &model.w1, &[], &active);
         }
     }
 
@@ -630,18 +611,15 @@ impl V2State {
     fn eval_full(&self, board: &Board) -> i32 {
         // Full recompute path; used for parity testing.
         let h = self.model.hidden_dim;
-        let mut acc_white = self.model.b1.clone();
-        let mut acc_black = self.model.b1.clone();
+        let mut acc_white = vec![0i16; h];
+        let mut acc_black = vec![0i16; h];
         for (perspective, acc) in [
             (Color::White, &mut acc_white),
             (Color::Black, &mut acc_black),
         ] {
-            for idx in dp_active_indices(board, perspective) {
-                let row = &self.model.w1[idx * h..(idx + 1) * h];
-                for (value, &weight) in acc.iter_mut().zip(row) {
-                    *value = value.wrapping_add(weight);
-                }
-            }
+            let active = dp_active_indices(board, perspective);
+            let model = &*self.model;
+            simd::update_accumulator(self.kernel, acc, &model.b1, &model.w1, &[], &active);
         }
         let stm = board.side_to_move();
         let out_stm = self.head(&acc_white, &acc_black, stm);
@@ -659,8 +637,8 @@ impl V2State {
             Color::White => (acc_white, acc_black),
             Color::Black => (acc_black, acc_white),
         };
-        let sum = screlu_dot(first, &self.model.w2[..h], self.model.qa)
-            + screlu_dot(second, &self.model.w2[h..2 * h], self.model.qa);
+        let sum = screlu_dot(self.kernel, first, &self.model.w2[..h], self.model.qa)
+            + screlu_dot(self.kernel, second, &self.model.w2[h..2 * h], self.model.qa);
         // Weights carry QA^2 * QB; b2 is stored in the same domain.
         let numerator = (sum + self.model.b2 as i64) * self.model.scale as i64;
         (numerator / (qa * qa * self.model.qb as i64)) as i32
@@ -705,35 +683,18 @@ impl V2State {
         for p in 0..2 {
             let src = &parent[p * h..(p + 1) * h];
             let dst = &mut child_slot[p * h..(p + 1) * h];
-            let rem = removed[p].as_slice();
-            let add = added[p].as_slice();
-            // A quiet move is exactly one feature out and one in per
-            // perspective, which is the overwhelming majority of applications.
-            // Fusing the copy with both row reads walks the accumulator once
-            // and lets the two row misses overlap rather than serialize. w1 is
-            // ~84 MB at h1024, so those misses, not the arithmetic, are what
-            // this loop actually costs.
-            if let ([r_idx], [a_idx]) = (rem, add) {
-                let r = &w1[r_idx * h..(r_idx + 1) * h];
-                let a = &w1[a_idx * h..(a_idx + 1) * h];
-                for (((value, &s), &rw), &aw) in dst.iter_mut().zip(src).zip(r).zip(a) {
-                    *value = s.wrapping_sub(rw).wrapping_add(aw);
-                }
-                continue;
-            }
-            dst.copy_from_slice(src);
-            for &idx in rem {
-                let row = &w1[idx * h..(idx + 1) * h];
-                for (value, &weight) in dst.iter_mut().zip(row) {
-                    *value = value.wrapping_sub(weight);
-                }
-            }
-            for &idx in add {
-                let row = &w1[idx * h..(idx + 1) * h];
-                for (value, &weight) in dst.iter_mut().zip(row) {
-                    *value = value.wrapping_add(weight);
-                }
-            }
+            // All removed and added rows land in registers before the child
+            // is stored, so the slot is walked once and the row misses --
+            // w1 is ~84 MB at h1024, so those, not the arithmetic, are what
+            // this costs -- overlap rather than serialize.
+            simd::update_accumulator(
+                self.kernel,
+                dst,
+                src,
+                w1,
+                removed[p].as_slice(),
+                added[p].as_slice(),
+            );
         }
         self.top = top + 1;
     }
@@ -780,7 +741,9 @@ fn square_index(board: &Board, side: Color, piece: Piece) -> usize {
 mod tests {
     use super::{screlu_dot, transpose_w1_feature_major, QuantNetwork};
     use crate::eval::nnue::features::halfkp_v2_dim;
-    use crate::eval::nnue::loader::{QuantMeta, QuantNnue};
+    use crate::eval::nnue::loader::{QuantMeta, QuantNnue, QuantNnueV2};
+    use crate::eval::nnue::simd::{update_accumulator, Kernel};
+    use cozy_chess::Board;
     use std::sync::Arc;
 
     /// Independent, deliberately naive definition of the SCReLU head term.
@@ -838,12 +801,15 @@ mod tests {
             }
         }
 
-        for qa in [1, 2, 64, 127, 254, 255] {
-            assert_eq!(
-                screlu_dot_reference(&acc_vec, &w_vec, qa),
-                screlu_dot(&acc_vec, &w_vec, qa),
-                "qa={qa}",
-            );
+        for kernel in Kernel::available() {
+            for qa in [1, 2, 64, 127, 254, 255] {
+                assert_eq!(
+                    screlu_dot_reference(&acc_vec, &w_vec, qa),
+                    screlu_dot(kernel, &acc_vec, &w_vec, qa),
+                    "{} qa={qa}",
+                    kernel.name(),
+                );
+            }
         }
     }
 
@@ -855,27 +821,57 @@ mod tests {
         let acc: Vec<i16> = (0..2048)
             .map(|_| (lcg(&mut state) % 65536) as u16 as i16)
             .collect();
-        let w: Vec<i8> = (0..2048).map(|_| (lcg(&mut state) % 256) as u8 as i8).collect();
+        let w: Vec<i8> = (0..2048)
+            .map(|_| (lcg(&mut state) % 256) as u8 as i8)
+            .collect();
 
-        assert_eq!(
-            screlu_dot_reference(&acc, &w, 255),
-            screlu_dot(&acc, &w, 255),
-        );
+        for kernel in Kernel::available() {
+            assert_eq!(
+                screlu_dot_reference(&acc, &w, 255),
+                screlu_dot(kernel, &acc, &w, 255),
+                "{}",
+                kernel.name(),
+            );
+        }
+    }
+
+    #[test]
+    fn screlu_dot_matches_reference_at_the_i32_lane_bound() {
+        // Every lane at v = QA against w = -128 is the largest magnitude the
+        // i32 partial sums can see; 4096 lanes spans several widening blocks
+        // of every kernel.
+        let acc = vec![255i16; 4096];
+        let w = vec![i8::MIN; 4096];
+        for kernel in Kernel::available() {
+            assert_eq!(
+                screlu_dot_reference(&acc, &w, 255),
+                screlu_dot(kernel, &acc, &w, 255),
+                "{}",
+                kernel.name(),
+            );
+        }
     }
 
     #[test]
     fn screlu_dot_matches_reference_on_ragged_lengths() {
         let mut state = 0xC0FF_EE00_1234_5678u64;
-        for len in [0usize, 1, 7, 15, 16, 17, 127, 128, 129, 255, 257] {
+        for len in [
+            0usize, 1, 7, 15, 16, 17, 31, 32, 33, 127, 128, 129, 255, 257, 1055,
+        ] {
             let acc: Vec<i16> = (0..len)
                 .map(|_| (lcg(&mut state) % 65536) as u16 as i16)
                 .collect();
-            let w: Vec<i8> = (0..len).map(|_| (lcg(&mut state) % 256) as u8 as i8).collect();
-            assert_eq!(
-                screlu_dot_reference(&acc, &w, 255),
-                screlu_dot(&acc, &w, 255),
-                "len={len}",
-            );
+            let w: Vec<i8> = (0..len)
+                .map(|_| (lcg(&mut state) % 256) as u8 as i8)
+                .collect();
+            for kernel in Kernel::available() {
+                assert_eq!(
+                    screlu_dot_reference(&acc, &w, 255),
+                    screlu_dot(kernel, &acc, &w, 255),
+                    "{} len={len}",
+                    kernel.name(),
+                );
+            }
         }
     }
 
@@ -887,12 +883,143 @@ mod tests {
         // keeps. Without this the overflow would be silent.
         let acc: Vec<i16> = vec![i16::MAX, 5000, 1024, 512, 300, 256, 0, -5];
         let w: Vec<i8> = vec![i8::MIN, 127, -100, 64, -1, 0, 32, 100];
-        for qa in [256, 300, 1024, 4096] {
-            assert_eq!(
-                screlu_dot_reference(&acc, &w, qa),
-                screlu_dot(&acc, &w, qa),
-                "qa={qa}",
+        for kernel in Kernel::available() {
+            for qa in [256, 300, 1024, 4096] {
+                assert_eq!(
+                    screlu_dot_reference(&acc, &w, qa),
+                    screlu_dot(kernel, &acc, &w, qa),
+                    "{} qa={qa}",
+                    kernel.name(),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn accumulator_updates_match_a_naive_sum_on_every_kernel() {
+        // Widths cover full register tiles, single vectors and scalar tails
+        // for both vector kernels; row values reach the i16 extremes so the
+        // wrapping behaviour has to agree too.
+        let mut state = 0xACC0_0000_0000_0001u64;
+        for h in [1usize, 15, 16, 33, 128, 200, 256, 1024, 1061] {
+            let features = 12;
+            let w1: Vec<i16> = (0..features * h)
+                .map(|_| (lcg(&mut state) % 65536) as u16 as i16)
+                .collect();
+            let src: Vec<i16> = (0..h)
+                .map(|_| (lcg(&mut state) % 65536) as u16 as i16)
+                .collect();
+            let cases: [(&[usize], &[usize]); 5] = [
+                (&[], &[]),
+                (&[3], &[7]),
+                (&[0, 11], &[5]),
+                (&[2], &[4, 9, 10]),
+                (&[], &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]),
+            ];
+            for (removed, added) in cases {
+                let mut expected = src.clone();
+                for (j, value) in expected.iter_mut().enumerate() {
+                    for &idx in removed {
+                        *value = value.wrapping_sub(w1[idx * h + j]);
+                    }
+                    for &idx in added {
+                        *value = value.wrapping_add(w1[idx * h + j]);
+                    }
+                }
+                for kernel in Kernel::available() {
+                    let mut dst = vec![0x5555i16; h];
+                    update_accumulator(kernel, &mut dst, &src, &w1, removed, added);
+                    assert_eq!(
+                        dst,
+                        expected,
+                        "{} h={h} removed={removed:?} added={added:?}",
+                        kernel.name(),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn v2_evals_agree_across_kernels_through_a_game() {
+        let hidden_dim = 264;
+        let input_dim = halfkp_v2_dim();
+        let mut state = 0x0123_4567_89AB_CDEFu64;
+        let model = QuantNnue {
+            meta: QuantMeta {
+                version: 1,
+                input_dim,
+                hidden_dim,
+                output_dim: 1,
+            },
+            w1_scale: 1.0,
+            w2_scale: 1.0,
+            w1: Vec::new(),
+            b1: Vec::new(),
+            w2: Vec::new(),
+            b2: Vec::new(),
+            v2: Some(Arc::new(QuantNnueV2 {
+                per_perspective_input_dim: input_dim,
+                hidden_dim,
+                qa: QuantNnueV2::QA,
+                qb: QuantNnueV2::QB,
+                scale: QuantNnueV2::SCALE,
+                w1: (0..input_dim * hidden_dim)
+                    .map(|_| (lcg(&mut state) % 41) as i16 - 20)
+                    .collect(),
+                b1: (0..hidden_dim)
+                    .map(|_| (lcg(&mut state) % 201) as i16 - 50)
+                    .collect(),
+                w2: (0..2 * hidden_dim)
+                    .map(|_| (lcg(&mut state) % 256) as u8 as i8)
+                    .collect(),
+                b2: 12_345,
+            })),
+        };
+        let base = QuantNetwork::new(model);
+        let mut networks: Vec<QuantNetwork> = Kernel::available()
+            .into_iter()
+            .map(|kernel| {
+                let mut net = base.clone_for_search();
+                net.set_simd_kernel(kernel);
+                net
+            })
+            .collect();
+
+        // Quiet moves, captures, castling and a king walk: deltas and pushed
+        // refreshes both run on every kernel.
+        let mut board = Board::default();
+        for net in &mut networks {
+            net.refresh(&board);
+        }
+        let mut changes: Vec<Vec<_>> = networks.iter().map(|_| Vec::new()).collect();
+        for uci in [
+            "e2e4", "d7d5", "e4d5", "d8d5", "g1f3", "c8g4", "f1e2", "b8c6", "e1h1", "e8a8", "f1e1",
+            "c8b8",
+        ] {
+            let mv = uci.parse().unwrap();
+            let mut after = board.clone();
+            after.play(mv);
+            let evals: Vec<i32> = networks
+                .iter_mut()
+                .zip(&mut changes)
+                .map(|(net, undo)| {
+                    undo.push(net.apply_move(&board, mv, &after));
+                    net.eval_current()
+                })
+                .collect();
+            let full = networks[0].eval_full(&after);
+            assert!(
+                evals.iter().all(|&e| e == full),
+                "{uci}: {evals:?} vs {full}"
             );
+            board = after;
+        }
+        for (net, undo) in networks.iter_mut().zip(changes) {
+            for change in undo.into_iter().rev() {
+                net.revert(change);
+            }
+            assert_eq!(net.eval_current(), base.eval_full(&Board::default()));
         }
     }
 
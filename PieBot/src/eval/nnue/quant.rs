@@ -1,4 +1,16 @@
 //! Quantization utilities and scalar kernels.
+//!
+//! Float models become integer ones here: the dense `PIENNUE1` network maps
+//! to `PIENNQ01` with per-tensor scales picked from the weights, and the
+//! arch-v2 float network maps to `PIENNQ02` with the fixed
+//! `QuantNnueV2::{QA, QB, SCALE}` constants. Every conversion reports how
+//! many values saturated and how far rounding moved the rest.
+use crate::eval::nnue::features::{dp_active_indices, HalfKpSchema};
+use crate::eval::nnue::loader::{QuantMeta, QuantNnue, QuantNnueV2};
+use crate::eval::nnue::train::FloatNnueV2;
+use crate::eval::nnue::Nnue;
+use cozy_chess::Board;
+
 #[inline]
 pub fn dot_i8_i16(w_row: &[i8], x: &[i16]) -> i32 {
     // Scalar reference; SIMD-accelerated paths will replace this under feature flags.
@@ -8,3 +20,198 @@ pub fn dot_i8_i16(w_row: &[i8], x: &[i16]) -> i32 {
     }
     acc
 }
+
+/// What quantizing one tensor did to it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipStats {
+    pub tensor: &'static str,
+    pub values: usize,
+    /// Values outside the integer range, saturated to its ends.
+    pub clipped: usize,
+    /// Largest float magnitude before quantization.
+    pub max_abs: f32,
+    /// Largest rounding error among the values that were not clipped, in
+    /// float units.
+    pub max_rounding_error: f32,
+}
+
+impl ClipStats {
+    pub fn clipped_fraction(&self) -> f64 {
+        if self.values == 0 {
+            0.0
+        } else {
+            self.clipped as f64 / self.values as f64
+        }
+    }
+}
+
+/// Quantize `values` to `round(v / step)` saturated to `[min, max]`.
+fn quantize_tensor<T>(
+    tensor: &'static str,
+    values: &[f32],
+    step: f64,
+    min: i64,
+    max: i64,
+    cast: impl Fn(i64) -> T,
+) -> (Vec<T>, ClipStats) {
+    let mut stats = ClipStats {
+        tensor,
+        values: values.len(),
+        clipped: 0,
+        max_abs: 0.0,
+        max_rounding_error: 0.0,
+    };
+    let out = values
+        .iter()
+        .map(|&v| {
+            stats.max_abs = stats.max_abs.max(v.abs());
+            let q = (f64::from(v) / step).round();
+            if q < min as f64 || q > max as f64 {
+                stats.clipped += 1;
+                return cast(q.clamp(min as f64, max as f64) as i64);
+            }
+            let error = (q * step - f64::from(v)).abs() as f32;
+            stats.max_rounding_error = stats.max_rounding_error.max(error);
+            cast(q as i64)
+        })
+        .collect();
+    (out, stats)
+}
+
+/// Magnitude that maps to the end of the integer range: the largest
+/// `|v|`, or with `clip_percentile < 100` that percentile of `|v|`, so a few
+/// outliers saturate instead of coarsening every other weight.
+fn clip_magnitude(values: &[f32], clip_percentile: f32) -> f32 {
+    let mut abs: Vec<f32> = values.iter().map(|v| v.abs()).collect();
+    if abs.is_empty() {
+        return 0.0;
+    }
+    let p = clip_percentile.clamp(0.0, 100.0);
+    let rank = ((abs.len() - 1) as f64 * f64::from(p) / 100.0).round() as usize;
+    let (_, value, _) = abs.select_nth_unstable_by(rank, f32::total_cmp);
+    *value
+}
+
+/// Per-tensor scale putting `magnitude` at `limit`; 1 for an all-zero
+/// tensor, since the loader rejects non-positive scales.
+fn scale_for(magnitude: f32, limit: f32) -> f32 {
+    if magnitude > 0.0 && magnitude.is_finite() {
+        magnitude / limit
+    } else {
+        1.0
+    }
+}
+
+/// Dense `PIENNUE1` -> `PIENNQ01`. `w1` and `b1` share `w1_scale` (the
+/// accumulator is their sum); `b2` lives in the `w1_scale * w2_scale`
+/// domain of the output, so a large output bias is what usually clips.
+pub fn quantize_dense(model: &Nnue, clip_percentile: f32) -> (QuantNnue, Vec<ClipStats>) {
+    let w1_scale = scale_for(clip_magnitude(&model.w1, clip_percentile), 127.0);
+    let w2_scale = scale_for(clip_magnitude(&model.w2, clip_percentile), 127.0);
+    let s1 = f64::from(w1_scale);
+    let s12 = s1 * f64::from(w2_scale);
+    let i8_range = (-127, 127);
+    let i16_range = (i64::from(i16::MIN), i64::from(i16::MAX));
+    let (w1, w1_stats) = quantize_tensor("w1", &model.w1, s1, i8_range.0, i8_range.1, |q| q as i8);
+    let (b1, b1_stats) =
+        quantize_tensor("b1", &model.b1, s1, i16_range.0, i16_range.1, |q| q as i16);
+    let (w2, w2_stats) = quantize_tensor(
+        "w2",
+        &model.w2,
+        f64::from(w2_scale),
+        i8_range.0,
+        i8_range.1,
+        |q| q as i8,
+    );
+    let (b2, b2_stats) =
+        quantize_tensor("b2", &model.b2, s12, i16_range.0, i16_range.1, |q| q as i16);
+    let quant = QuantNnue {
+        meta: QuantMeta {
+            version: model.meta.version,
+            input_dim: model.meta.input_dim,
+            hidden_dim: model.meta.hidden_dim,
+            output_dim: model.meta.output_dim,
+        },
+        w1_scale,
+        w2_scale,
+        w1,
+        b1,
+        w2,
+        b2,
+        v2: None,
+    };
+    (quant, vec![w1_stats, b1_stats, w2_stats, b2_stats])
+}
+
+/// Float arch-v2 -> `PIENNQ02` at `QuantNnueV2::{QA, QB, SCALE}`:
+/// accumulator weights and biases at QA, output weights at QB and the
+/// output bias at `QA^2 * QB`, the engine head's domain.
+pub fn quantize_v2(model: &FloatNnueV2) -> (QuantNnueV2, Vec<ClipStats>) {
+    let qa = f64::from(QuantNnueV2::QA);
+    let qb = f64::from(QuantNnueV2::QB);
+    let i16_range = (i64::from(i16::MIN), i64::from(i16::MAX));
+    let (w1, w1_stats) =
+        quantize_tensor("w1", &model.w1, 1.0 / qa, i16_range.0, i16_range.1, |q| {
+            q as i16
+        });
+    let (b1, b1_stats) =
+        quantize_tensor("b1", &model.b1, 1.0 / qa, i16_range.0, i16_range.1, |q| {
+            q as i16
+        });
+    let (w2, w2_stats) = quantize_tensor("w2", &model.w2, 1.0 / qb, -128, 127, |q| q as i8);
+    let (b2, b2_stats) = quantize_tensor(
+        "b2",
+        std::slice::from_ref(&model.b2),
+        1.0 / (qa * qa * qb),
+        i64::from(i32::MIN),
+        i64::from(i32::MAX),
+        |q| q as i32,
+    );
+    let quant = QuantNnueV2 {
+        per_perspective_input_dim: model.input_dim,
+        hidden_dim: model.hidden_dim,
+        qa: QuantNnueV2::QA,
+        qb: QuantNnueV2::QB,
+        scale: QuantNnueV2::SCALE,
+        w1,
+        b1,
+        w2,
+        b2: b2[0],
+    };
+    (quant, vec![w1_stats, b1_stats, w2_stats, b2_stats])
+}
+
+/// Pre-activation accumulator values of a quantized model for `board`, in
+/// its integer units: the `hidden_dim` sums of a PIENNQ01 model, or both
+/// perspectives of a PIENNQ02 one (side to move first). `None` when a
+/// PIENNQ01 model's input dim is not a HalfKP schema.
+pub fn quant_accumulators(model: &QuantNnue, board: &Board) -> Option<Vec<i32>> {
+    if let Some(v2) = model.v2.as_ref() {
+        let h = v2.hidden_dim;
+        let stm = board.side_to_move();
+        let mut out = Vec::with_capacity(2 * h);
+        for perspective in [stm, !stm] {
+            let mut acc: Vec<i32> = v2.b1.iter().map(|&b| i32::from(b)).collect();
+            for idx in dp_active_indices(board, perspective) {
+                for (a, &w) in acc.iter_mut().zip(&v2.w1[idx * h..(idx + 1) * h]) {
+                    *a += i32::from(w);
+                }
+            }
+            out.extend(acc);
+        }
+        return Some(out);
+    }
+    let schema = HalfKpSchema::from_input_dim(model.meta.input_dim)?;
+    let n = model.meta.input_dim;
+    let active = schema.active_indices(board);
+    let acc = model
+        .b1
+        .iter()
+        .enumerate()
+        .map(|(j, &b)| {
+            let row = &model.w1[j * n..(j + 1) * n];
+            i32::from(b) + active.iter().map(|&i| i32::from(row[i])).sum::<i32>()
+        })
+        .collect();
+    Some(acc)
+}
@@ -0,0 +1,409 @@
+//! Explicit SIMD kernels for the arch-v2 network.
+//!
+//! Two operations dominate a v2 node: rewriting an accumulator as a parent
+//! plus and minus a few `w1` rows (incremental updates, and full refreshes
+//! starting from `b1`), and the SCReLU dot product of the head. Both have a
+//! scalar definition here and hand-written AVX2 / AVX-512BW versions on
+//! x86_64, all bit-identical: accumulator arithmetic wraps in i16 exactly as
+//! the scalar `wrapping_add`/`wrapping_sub` does, and the dot product is
+//! integer-exact.
+//!
+//! A [`Kernel`] names the instruction set to use and can only be obtained
+//! once the CPU is known to support it. With the `simd-avx512` or `simd-avx2`
+//! feature the choice is fixed at build time (and checked once at startup);
+//! otherwise the best kernel is detected at runtime.
+
+/// Instruction set used by the accumulator and SCReLU kernels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Kernel(Isa);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(target_arch = "x86_64"), allow(dead_code))]
+enum Isa {
+    Scalar,
+    Avx2,
+    Avx512,
+}
+
+impl Kernel {
+    /// Portable kernel; always available.
+    pub const SCALAR: Kernel = Kernel(Isa::Scalar);
+
+    /// The AVX2 kernel, if this CPU supports it.
+    pub fn avx2() -> Option<Kernel> {
+        #[cfg(target_arch = "x86_64")]
+        if std::arch::is_x86_feature_detected!("avx2") {
+            return Some(Kernel(Isa::Avx2));
+        }
+        None
+    }
+
+    /// The AVX-512 kernel (F + BW), if this CPU supports it.
+    pub fn avx512() -> Option<Kernel> {
+        #[cfg(target_arch = "x86_64")]
+        if std::arch::is_x86_feature_detected!("avx512f")
+            && std::arch::is_x86_feature_detected!("avx512bw")
+        {
+            return Some(Kernel(Isa::Avx512));
+        }
+        None
+    }
+
+    /// Every kernel this CPU can run, scalar first.
+    pub fn available() -> Vec<Kernel> {
+        [Some(Kernel::SCALAR), Kernel::avx2(), Kernel::avx512()]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Kernel new networks use. `simd-avx512` / `simd-avx2` pin it and panic
+    /// on a CPU without the instructions; without either feature it is the
+    /// widest kernel the CPU supports. Detected once per process.
+    pub fn detect() -> Kernel {
+        static DETECTED: std::sync::OnceLock<Kernel> = std::sync::OnceLock::new();
+        *DETECTED.get_or_init(|| {
+            if cfg!(feature = "simd-avx512") {
+                Kernel::avx512().expect("built with simd-avx512 but this CPU lacks AVX-512F/BW")
+            } else if cfg!(feature = "simd-avx2") {
+                Kernel::avx2().expect("built with simd-avx2 but this CPU lacks AVX2")
+            } else {
+                Kernel::avx512()
+                    .or_else(Kernel::avx2)
+                    .unwrap_or(Kernel::SCALAR)
+            }
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self.0 {
+            Isa::Scalar => "scalar",
+            Isa::Avx2 => "avx2",
+            Isa::Avx512 => "avx512",
+        }
+    }
+}
+
+/// `dst = src - sum(w1 rows in removed) + sum(w1 rows in added)`, wrapping in
+/// i16. Rows are `dst.len()` wide and feature-major in `w1`. A refresh passes
+/// `b1` as `src` and the active features as `added`.
+///
+/// Every row is accumulated into registers before `dst` is written, so an
+/// update walks the accumulator once however many features change.
+pub fn update_accumulator(
+    kernel: Kernel,
+    dst: &mut [i16],
+    src: &[i16],
+    w1: &[i16],
+    removed: &[usize],
+    added: &[usize],
+) {
+    let h = dst.len();
+    assert_eq!(src.len(), h, "accumulator source width");
+    for &idx in removed.iter().chain(added) {
+        assert!((idx + 1) * h <= w1.len(), "feature {idx} outside w1");
+    }
+    match kernel.0 {
+        // SAFETY: a `Kernel` holding `Avx2`/`Avx512` only exists once the CPU
+        // reported those features, and the asserts above bound every row read.
+        #[cfg(target_arch = "x86_64")]
+        Isa::Avx512 => unsafe { x86::update_avx512(dst, src, w1, removed, added) },
+        #[cfg(target_arch = "x86_64")]
+        Isa::Avx2 => unsafe { x86::update_avx2(dst, src, w1, removed, added) },
+        _ => update_scalar(dst, src, w1, removed, added, 0),
+    }
+}
+
+/// Scalar update of lanes `start..`; also the tail of the vector kernels.
+fn update_scalar(
+    dst: &mut [i16],
+    src: &[i16],
+    w1: &[i16],
+    removed: &[usize],
+    added: &[usize],
+    start: usize,
+) {
+    let h = dst.len();
+    let dst = &mut dst[start..];
+    let src = &src[start..];
+    let row = |idx: usize| &w1[idx * h + start..(idx + 1) * h];
+    // A quiet move is exactly one feature out and one in, which is the
+    // overwhelming majority of updates: fusing the copy with both row reads
+    // walks the accumulator once and lets the two row misses overlap.
+    if let ([r_idx], [a_idx]) = (removed, added) {
+        let (r, a) = (row(*r_idx), row(*a_idx));
+        for (((value, &s), &rw), &aw) in dst.iter_mut().zip(src).zip(r).zip(a) {
+            *value = s.wrapping_sub(rw).wrapping_add(aw);
+        }
+        return;
+    }
+    dst.copy_from_slice(src);
+    for &idx in removed {
+        for (value, &weight) in dst.iter_mut().zip(row(idx)) {
+            *value = value.wrapping_sub(weight);
+        }
+    }
+    for &idx in added {
+        for (value, &weight) in dst.iter_mut().zip(row(idx)) {
+            *value = value.wrapping_add(weight);
+        }
+    }
+}
+
+/// Partial sums widen to i64 every `SCRELU_CHUNK` terms so the inner loop can
+/// stay in narrow lanes.
+const SCRELU_CHUNK: usize = 128;
+
+/// SCReLU dot product `sum_j clamp(acc[j], 0, qa)^2 * w[j]` for
+/// `qa * 128 <= i16::MAX`, where `clamp(acc, 0, qa) * w` is exact in i16.
+///
+/// Keeping `v * w` in i16 lets the multiply run 16 (AVX2) or 32 (AVX-512)
+/// lanes at a time, and the widening i16xi16 -> i32 accumulate is a single
+/// `vpmaddwd`. Exactness rests on two bounds:
+///   * `|v * w| <= QA * 128 = 32_640 <= i16::MAX`;
+///   * `|v * (v * w)| <= 255 * 32_640 = 8_323_200`, so `SCRELU_CHUNK = 128`
+///     terms per i32 lane reach at most 1.065e9, well inside i32.
+pub fn screlu_dot_madd(kernel: Kernel, acc: &[i16], w: &[i8], qa: i16) -> i64 {
+    assert_eq!(acc.len(), w.len(), "accumulator and weight widths");
+    debug_assert!(qa > 0 && i32::from(qa) * 128 <= i32::from(i16::MAX));
+    match kernel.0 {
+        // SAFETY: the kernel proves CPU support; lengths are checked above.
+        #[cfg(target_arch = "x86_64")]
+        Isa::Avx512 => unsafe { x86::screlu_dot_avx512(acc, w, qa) },
+        #[cfg(target_arch = "x86_64")]
+        Isa::Avx2 => unsafe { x86::screlu_dot_avx2(acc, w, qa) },
+        _ => screlu_dot_scalar(acc, w, qa),
+    }
+}
+
+/// Shaped so the autovectorizer emits a widening multiply-accumulate
+/// (`vpmaddwd` on AVX2, `smlal`/`smlal2` on NEON) even without a kernel.
+fn screlu_dot_scalar(acc: &[i16], w: &[i8], qa: i16) -> i64 {
+    let mut total: i64 = 0;
+    for (acc_chunk, w_chunk) in acc.chunks(SCRELU_CHUNK).zip(w.chunks(SCRELU_CHUNK)) {
+        let mut partial: i32 = 0;
+        for (&a, &weight) in acc_chunk.iter().zip(w_chunk) {
+            let v = a.clamp(0, qa);
+            let vw = v * weight as i16;
+            partial += v as i32 * vw as i32;
+        }
+        total += partial as i64;
+    }
+    total
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{screlu_dot_scalar, update_scalar, SCRELU_CHUNK};
+    use std::arch::x86_64::*;
+
+    /// Registers held across all rows of one accumulator tile.
+    const TILE_REGS: usize = 8;
+
+    /// # Safety
+    /// Requires AVX2; `src` must be `dst.len()` wide and every row in bounds.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn update_avx2(
+        dst: &mut [i16],
+        src: &[i16],
+        w1: &[i16],
+        removed: &[usize],
+        added: &[usize],
+    ) {
+        const LANES: usize = 16;
+        let h = dst.len();
+        let mut start = 0;
+        if let ([r_idx], [a_idx]) = (removed, added) {
+            // Quiet move: one streaming pass, nothing worth holding.
+            let (r, a) = (w1.as_ptr().add(r_idx * h), w1.as_ptr().add(a_idx * h));
+            while start + LANES <= h {
+                let v = _mm256_loadu_si256(src.as_ptr().add(start).cast());
+                let v = _mm256_sub_epi16(v, _mm256_loadu_si256(r.add(start).cast()));
+                let v = _mm256_add_epi16(v, _mm256_loadu_si256(a.add(start).cast()));
+                _mm256_storeu_si256(dst.as_mut_ptr().add(start).cast(), v);
+                start += LANES;
+            }
+            return update_scalar(dst, src, w1, removed, added, start);
+        }
+        while start + TILE_REGS * LANES <= h {
+            update_tile_avx2::<TILE_REGS>(dst, src, w1, removed, added, start);
+            start += TILE_REGS * LANES;
+        }
+        while start + LANES <= h {
+            update_tile_avx2::<1>(dst, src, w1, removed, added, start);
+            start += LANES;
+        }
+        update_scalar(dst, src, w1, removed, added, start);
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn update_tile_avx2<const R: usize>(
+        dst: &mut [i16],
+        src: &[i16],
+        w1: &[i16],
+        removed: &[usize],
+        added: &[usize],
+        start: usize,
+    ) {
+        const LANES: usize = 16;
+        let h = dst.len();
+        let mut regs = [_mm256_setzero_si256(); R];
+        let base = src.as_ptr().add(start);
+        for (i, reg) in regs.iter_mut().enumerate() {
+            *reg = _mm256_loadu_si256(base.add(i * LANES).cast());
+        }
+        for &idx in removed {
+            let row = w1.as_ptr().add(idx * h + start);
+            for (i, reg) in regs.iter_mut().enumerate() {
+                *reg = _mm256_sub_epi16(*reg, _mm256_loadu_si256(row.add(i * LANES).cast()));
+            }
+        }
+        for &idx in added {
+            let row = w1.as_ptr().add(idx * h + start);
+            for (i, reg) in regs.iter_mut().enumerate() {
+                *reg = _mm256_add_epi16(*reg, _mm256_loadu_si256(row.add(i * LANES).cast()));
+            }
+        }
+        let out = dst.as_mut_ptr().add(start);
+        for (i, reg) in regs.iter().enumerate() {
+            _mm256_storeu_si256(out.add(i * LANES).cast(), *reg);
+        }
+    }
+
+    /// # Safety
+    /// Requires AVX-512F/BW; `src` must be `dst.len()` wide and every row in
+    /// bounds.
+    #[target_feature(enable = "avx512f,avx512bw")]
+    pub(super) unsafe fn update_avx512(
+        dst: &mut [i16],
+        src: &[i16],
+        w1: &[i16],
+        removed: &[usize],
+        added: &[usize],
+    ) {
+        const LANES: usize = 32;
+        let h = dst.len();
+        let mut start = 0;
+        if let ([r_idx], [a_idx]) = (removed, added) {
+            // Quiet move: one streaming pass, nothing worth holding.
+            let (r, a) = (w1.as_ptr().add(r_idx * h), w1.as_ptr().add(a_idx * h));
+            while start + LANES <= h {
+                let v = _mm512_loadu_si512(src.as_ptr().add(start).cast());
+                let v = _mm512_sub_epi16(v, _mm512_loadu_si512(r.add(start).cast()));
+                let v = _mm512_add_epi16(v, _mm512_loadu_si512(a.add(start).cast()));
+                _mm512_storeu_si512(dst.as_mut_ptr().add(start).cast(), v);
+                start += LANES;
+            }
+            return update_scalar(dst, src, w1, removed, added, start);
+        }
+        while start + TILE_REGS * LANES <= h {
+            update_tile_avx512::<TILE_REGS>(dst, src, w1, removed, added, start);
+            start += TILE_REGS * LANES;
+        }
+        while start + LANES <= h {
+            update_tile_avx512::<1>(dst, src, w1, removed, added, start);
+            start += LANES;
+        }
+        update_scalar(dst, src, w1, removed, added, start);
+    }
+
+    #[target_feature(enable = "avx512f,avx512bw")]
+    unsafe fn update_tile_avx512<const R: usize>(
+        dst: &mut [i16],
+        src: &[i16],
+        w1: &[i16],
+        removed: &[usize],
+        added: &[usize],
+        start: usize,
+    ) {
+        const LANES: usize = 32;
+        let h = dst.len();
+        let mut regs = [_mm512_setzero_si512(); R];
+        let base = src.as_ptr().add(start);
+        for (i, reg) in regs.iter_mut().enumerate() {
+            *reg = _mm512_loadu_si512(base.add(i * LANES).cast());
+        }
+        for &idx in removed {
+            let row = w1.as_ptr().add(idx * h + start);
+            for (i, reg) in regs.iter_mut().enumerate() {
+                *reg = _mm512_sub_epi16(*reg, _mm512_loadu_si512(row.add(i * LANES).cast()));
+            }
+        }
+        for &idx in added {
+            let row = w1.as_ptr().add(idx * h + start);
+            for (i, reg) in regs.iter_mut().enumerate() {
+                *reg = _mm512_add_epi16(*reg, _mm512_loadu_si512(row.add(i * LANES).cast()));
+            }
+        }
+        let out = dst.as_mut_ptr().add(start);
+        for (i, reg) in regs.iter().enumerate() {
+            _mm512_storeu_si512(out.add(i * LANES).cast(), *reg);
+        }
+    }
+
+    /// # Safety
+    /// Requires AVX2; `acc` and `w` must have equal lengths.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn screlu_dot_avx2(acc: &[i16], w: &[i8], qa: i16) -> i64 {
+        const LANES: usize = 16;
+        // Each i32 lane sums two products per vector, so SCRELU_CHUNK / 2
+        // vectors keep every lane within the SCRELU_CHUNK bound.
+        const BLOCK: usize = SCRELU_CHUNK / 2 * LANES;
+        let n = acc.len() - acc.len() % LANES;
+        let zero = _mm256_setzero_si256();
+        let qa_v = _mm256_set1_epi16(qa);
+        let mut total = _mm256_setzero_si256();
+        let mut i = 0;
+        while i < n {
+            let end = (i + BLOCK).min(n);
+            let mut sum = _mm256_setzero_si256();
+            while i < end {
+                let a = _mm256_loadu_si256(acc.as_ptr().add(i).cast());
+                let v = _mm256_min_epi16(_mm256_max_epi16(a, zero), qa_v);
+                let wv = _mm256_cvtepi8_epi16(_mm_loadu_si128(w.as_ptr().add(i).cast()));
+                let vw = _mm256_mullo_epi16(v, wv);
+                sum = _mm256_add_epi32(sum, _mm256_madd_epi16(v, vw));
+                i += LANES;
+            }
+            total = _mm256_add_epi64(total, _mm256_cvtepi32_epi64(_mm256_castsi256_si128(sum)));
+            total = _mm256_add_epi64(
+                total,
+                _mm256_cvtepi32_epi64(_mm256_extracti128_si256::<1>(sum)),
+            );
+        }
+        let mut lanes = [0i64; 4];
+        _mm256_storeu_si256(lanes.as_mut_ptr().cast(), total);
+        lanes.iter().sum::<i64>() + screlu_dot_scalar(&acc[n..], &w[n..], qa)
+    }
+
+    /// # Safety
+    /// Requires AVX-512F/BW; `acc` and `w` must have equal lengths.
+    #[target_feature(enable = "avx512f,avx512bw")]
+    pub(super) unsafe fn screlu_dot_avx512(acc: &[i16], w: &[i8], qa: i16) -> i64 {
+        const LANES: usize = 32;
+        const BLOCK: usize = SCRELU_CHUNK / 2 * LANES;
+        let n = acc.len() - acc.len() % LANES;
+        let zero = _mm512_setzero_si512();
+        let qa_v = _mm512_set1_epi16(qa);
+        let mut total = _mm512_setzero_si512();
+        let mut i = 0;
+        while i < n {
+            let end = (i + BLOCK).min(n);
+            let mut sum = _mm512_setzero_si512();
+            while i < end {
+                let a = _mm512_loadu_si512(acc.as_ptr().add(i).cast());
+                let v = _mm512_min_epi16(_mm512_max_epi16(a, zero), qa_v);
+                let wv = _mm512_cvtepi8_epi16(_mm256_loadu_si256(w.as_ptr().add(i).cast()));
+                let vw = _mm512_mullo_epi16(v, wv);
+                sum = _mm512_add_epi32(sum, _mm512_madd_epi16(v, vw));
+                i += LANES;
+            }
+            total = _mm512_add_epi64(total, _mm512_cvtepi32_epi64(_mm512_castsi512_si256(sum)));
+            total = _mm512_add_epi64(
+                total,
+                _mm512_cvtepi32_epi64(_mm512_extracti64x4_epi64::<1>(sum)),
+            );
+        }
+        _mm512_reduce_add_epi64(total) + screlu_dot_scalar(&acc[n..], &w[n..], qa)
+    }
+}
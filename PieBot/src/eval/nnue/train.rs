@@ -0,0 +1,731 @@
+//! CPU trainer for the arch-v2 (PIENNQ02) network.
+//!
+//! The float model mirrors the engine's integer head: each perspective's
+//! accumulator is `b1 + sum(w1[feature])` over [`dp_active_indices`], the
+//! head is `w2 . screlu(stm_acc ++ nstm_acc) + b2`, and one unit of output is
+//! [`QuantNnueV2::SCALE`] centipawns. Exporting multiplies by the same
+//! QA/QB constants `QuantNnueV2` evaluates with, so a trained model
+//! quantizes without any rescaling step.
+//!
+//! Training minimises `(sigmoid(cp / scale_cp) - target)^2`, where the
+//! target blends the teacher score seen through the same sigmoid with the
+//! game result. Only the feature-transformer rows a batch touches get
+//! gradients and Adam updates (lazy Adam, as torch's `SparseAdam`).
+
+use crate::eval::nnue::features::{dp_active_indices, HALFKP_DP_PER_PERSPECTIVE_DIM};
+use crate::eval::nnue::loader::QuantNnueV2;
+use crate::eval::nnue::quant::quantize_v2;
+use crate::selfplay::packed::{PackedFile, PackedPosition, PACKED_EXTENSION};
+use cozy_chess::{Board, Color};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use rayon::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const CHECKPOINT_MAGIC: &[u8; 8] = b"PIENNF02"; // Pie NNUE Float v2 (trainer checkpoint)
+/// Version 2 added the epoch counter after the step. Version 1 weights
+/// still load, but a trainer cannot resume from them.
+const CHECKPOINT_VERSION: u32 = 2;
+
+/// A HalfKP perspective activates about this many features; the initial
+/// feature-transformer spread is divided by its square root so accumulators
+/// start inside SCReLU's live range (see `TorchNnueV2._init_for_screlu`).
+const TYPICAL_ACTIVE_FEATURES: f32 = 30.0;
+
+/// Float arch-v2 weights in the engine's layout.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FloatNnueV2 {
+    pub input_dim: usize,
+    pub hidden_dim: usize,
+    /// Feature-major: `w1[idx * hidden .. (idx + 1) * hidden]`.
+    pub w1: Vec<f32>,
+    pub b1: Vec<f32>,
+    /// Side-to-move half first, `2 * hidden`.
+    pub w2: Vec<f32>,
+    pub b2: f32,
+}
+
+impl FloatNnueV2 {
+    /// A fresh HalfKP dual-perspective network with `hidden_dim` units.
+    pub fn new_random(hidden_dim: usize, seed: u64) -> Self {
+        let input_dim = HALFKP_DP_PER_PERSPECTIVE_DIM;
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let normal = Normal::new(0.0, 0.5 / TYPICAL_ACTIVE_FEATURES.sqrt()).unwrap();
+        let w1 = (0..input_dim * hidden_dim)
+            .map(|_| normal.sample(&mut rng))
+            .collect();
+        // torch.nn.Linear's default init.
+        let bound = 1.0 / ((2 * hidden_dim) as f32).sqrt();
+        let w2 = (0..2 * hidden_dim)
+            .map(|_| rng.gen_range(-bound..bound))
+            .collect();
+        Self {
+            input_dim,
+            hidden_dim,
+            w1,
+            b1: vec![0.5; hidden_dim],
+            w2,
+            b2: 0.0,
+        }
+    }
+
+    fn accumulate(&self, features: &[u16], acc: &mut [f32]) {
+        let h = self.hidden_dim;
+        acc.copy_from_slice(&self.b1);
+        for &idx in features {
+            let row = &self.w1[idx as usize * h..(idx as usize + 1) * h];
+            for (a, &w) in acc.iter_mut().zip(row) {
+                *a += w;
+            }
+        }
+    }
+
+    /// Head output in network units for stm-first accumulators.
+    fn head(&self, stm: &[f32], nstm: &[f32]) -> f32 {
+        let h = self.hidden_dim;
+        let screlu = |a: f32| {
+            let v = a.clamp(0.0, 1.0);
+            v * v
+        };
+        let dot = |acc: &[f32], w: &[f32]| -> f32 {
+            acc.iter().zip(w).map(|(&a, &w)| screlu(a) * w).sum()
+        };
+        dot(stm, &self.w2[..h]) + dot(nstm, &self.w2[h..]) + self.b2
+    }
+
+    /// Side-to-move-relative centipawns for a pair of feature lists.
+    pub fn evaluate_features(&self, stm: &[u16], nstm: &[u16]) -> f32 {
+        let mut acc_stm = vec![0.0; self.hidden_dim];
+        let mut acc_nstm = vec![0.0; self.hidden_dim];
+        self.accumulate(stm, &mut acc_stm);
+        self.accumulate(nstm, &mut acc_nstm);
+        self.head(&acc_stm, &acc_nstm) * QuantNnueV2::SCALE as f32
+    }
+
+    /// White-relative centipawns, matching `QuantNetwork::eval_full`.
+    pub fn evaluate(&self, board: &Board) -> f32 {
+        let (stm, nstm) = stm_features(board);
+        let cp = self.evaluate_features(&stm, &nstm);
+        if board.side_to_move() == Color::White {
+            cp
+        } else {
+            -cp
+        }
+    }
+
+    /// Round to the engine's integer domain with `QuantNnueV2::{QA, QB,
+    /// SCALE}`, saturating at the storage types' limits.
+    pub fn quantize(&self) -> QuantNnueV2 {
+        quantize_v2(self).0
+    }
+
+    /// Keep output weights representable as int8 at QB, so the quantized
+    /// head computes what was trained.
+    fn clamp_output_weights(&mut self) {
+        let limit = 127.0 / QuantNnueV2::QB as f32;
+        for w in &mut self.w2 {
+            *w = w.clamp(-limit, limit);
+        }
+    }
+
+    fn tensors(&self) -> [&[f32]; 4] {
+        [&self.w1, &self.b1, &self.w2, std::slice::from_ref(&self.b2)]
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_all(CHECKPOINT_MAGIC)?;
+        for v in [
+            CHECKPOINT_VERSION,
+            self.input_dim as u32,
+            self.hidden_dim as u32,
+        ] {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        for tensor in self.tensors() {
+            write_f32s(w, tensor)?;
+        }
+        Ok(())
+    }
+
+    /// Read the weights, returning the checkpoint version alongside.
+    fn read_from<R: Read>(r: &mut R) -> Result<(Self, u32), String> {
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic).map_err(|e| e.to_string())?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err("not a PIENNF02 checkpoint".to_string());
+        }
+        let version = read_u32(r)?;
+        if version == 0 || version > CHECKPOINT_VERSION {
+            return Err(format!("unsupported checkpoint version {version}"));
+        }
+        let input_dim = read_u32(r)? as usize;
+        let hidden_dim = read_u32(r)? as usize;
+        if input_dim == 0 || hidden_dim == 0 {
+            return Err(format!(
+                "invalid checkpoint dims: input_dim={input_dim} hidden_dim={hidden_dim}"
+            ));
+        }
+        let w1_len = input_dim
+            .checked_mul(hidden_dim)
+            .ok_or("checkpoint dimension overflow")?;
+        let model = Self {
+            input_dim,
+            hidden_dim,
+            w1: read_f32s(r, w1_len)?,
+            b1: read_f32s(r, hidden_dim)?,
+            w2: read_f32s(r, 2 * hidden_dim)?,
+            b2: read_f32s(r, 1)?[0],
+        };
+        Ok((model, version))
+    }
+
+    /// Load the weights of a trainer checkpoint; optimizer state, if any,
+    /// is ignored.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::read_from(&mut BufReader::new(file))
+            .map(|(model, _)| model)
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+fn write_f32s<W: Write>(w: &mut W, values: &[f32]) -> std::io::Result<()> {
+    let mut bytes = Vec::with_capacity(values.len() * 4);
+    for v in values {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    w.write_all(&bytes)
+}
+
+fn read_u32<R: Read>(r: &mut R) -> Result<u32, String> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b).map_err(|e| e.to_string())?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_f32s<R: Read>(r: &mut R, len: usize) -> Result<Vec<f32>, String> {
+    let mut bytes = vec![0u8; len * 4];
+    r.read_exact(&mut bytes)
+        .map_err(|e| format!("truncated checkpoint: {e}"))?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect())
+}
+
+/// Active features of both perspectives, side to move first.
+fn stm_features(board: &Board) -> (Vec<u16>, Vec<u16>) {
+    let stm = board.side_to_move();
+    let narrow = |v: Vec<usize>| v.into_iter().map(|i| i as u16).collect();
+    (
+        narrow(dp_active_indices(board, stm)),
+        narrow(dp_active_indices(board, !stm)),
+    )
+}
+
+/// How a record's labels become a training target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TargetParams {
+    /// Weight of the teacher score in the target; the game result gets the
+    /// rest. Records with only one of the two use it alone.
+    pub eval_weight: f32,
+    /// Logistic scale mapping centipawns to expected score.
+    pub scale_cp: f32,
+    /// Teacher scores are clamped to this magnitude first.
+    pub max_teacher_cp: f32,
+    /// Teacher scores from shallower searches are ignored.
+    pub min_teacher_depth: u8,
+}
+
+impl Default for TargetParams {
+    fn default() -> Self {
+        Self {
+            eval_weight: 0.5,
+            scale_cp: QuantNnueV2::SCALE as f32,
+            max_teacher_cp: 2000.0,
+            min_teacher_depth: 0,
+        }
+    }
+}
+
+/// One position ready for training.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub stm: Vec<u16>,
+    pub nstm: Vec<u16>,
+    /// Expected score for the side to move, in `[0, 1]`.
+    pub target: f32,
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+impl Sample {
+    /// `None` when the record has neither a usable teacher score nor a
+    /// valid game result.
+    pub fn from_position(position: &PackedPosition, params: &TargetParams) -> Option<Self> {
+        let teacher = position
+            .score_cp
+            .filter(|_| position.teacher_depth.unwrap_or(0) >= params.min_teacher_depth)
+            .map(|cp| {
+                let cp = f32::from(cp).clamp(-params.max_teacher_cp, params.max_teacher_cp);
+                sigmoid(cp / params.scale_cp)
+            });
+        let outcome = position
+            .outcome_valid
+            .then(|| (f32::from(position.result) + 1.0) / 2.0);
+        let white = match (teacher, outcome) {
+            (Some(t), Some(o)) => {
+                let lambda = params.eval_weight.clamp(0.0, 1.0);
+                lambda * t + (1.0 - lambda) * o
+            }
+            (Some(t), None) => t,
+            (None, Some(o)) => o,
+            (None, None) => return None,
+        };
+        let (stm, nstm) = stm_features(&position.board);
+        let target = if position.board.side_to_move() == Color::White {
+            white
+        } else {
+            1.0 - white
+        };
+        Some(Self { stm, nstm, target })
+    }
+}
+
+/// Samples read from self-play data, and how many records were skipped.
+#[derive(Debug, Default)]
+pub struct LoadedSamples {
+    pub samples: Vec<Sample>,
+    pub skipped: usize,
+}
+
+/// `path` itself, or the `*.jsonl` and `*.piepack` files in it, sorted.
+pub fn collect_data_files(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let p = entry?.path();
+        if matches!(
+            p.extension().and_then(|x| x.to_str()),
+            Some("jsonl" | PACKED_EXTENSION)
+        ) {
+            files.push(p);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Read self-play JSONL and PIEPACK files into samples, stopping after
+/// `max_samples` (0 = all). Unparseable records are counted, not fatal.
+pub fn load_samples(
+    files: &[PathBuf],
+    params: &TargetParams,
+    max_samples: usize,
+) -> Result<LoadedSamples, String> {
+    let mut out = LoadedSamples::default();
+    let full = |out: &LoadedSamples| max_samples > 0 && out.samples.len() >= max_samples;
+    let push = |out: &mut LoadedSamples, position: Option<PackedPosition>| match position
+        .and_then(|p| Sample::from_position(&p, params))
+    {
+        Some(sample) => out.samples.push(sample),
+        None => out.skipped += 1,
+    };
+    for path in files {
+        if full(&out) {
+            break;
+        }
+        if path.extension().and_then(|x| x.to_str()) == Some(PACKED_EXTENSION) {
+            let file = PackedFile::open(path)?;
+            for position in file.view().iter() {
+                if full(&out) {
+                    break;
+                }
+                push(&mut out, position.ok());
+            }
+            continue;
+        }
+        let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        for line in BufReader::new(file).lines() {
+            if full(&out) {
+                break;
+            }
+            let line = line.map_err(|e| format!("{}: {e}", path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let position = match serde_json::from_str(&line) {
+                Ok(Value::Object(map)) => PackedPosition::from_json(&map).ok(),
+                _ => None,
+            };
+            push(&mut out, position);
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdamConfig {
+    pub lr: f32,
+    pub beta1: f32,
+    pub beta2: f32,
+    pub eps: f32,
+}
+
+impl Default for AdamConfig {
+    fn default() -> Self {
+        Self {
+            lr: 1e-3,
+            beta1: 0.9,
+            beta2: 0.999,
+            eps: 1e-8,
+        }
+    }
+}
+
+/// First and second moment estimates for one tensor.
+#[derive(Clone, Debug)]
+struct Moments {
+    m: Vec<f32>,
+    v: Vec<f32>,
+}
+
+impl Moments {
+    fn zeros(len: usize) -> Self {
+        Self {
+            m: vec![0.0; len],
+            v: vec![0.0; len],
+        }
+    }
+
+    /// Adam step on `params[range]` with `grad` for that range.
+    fn step(
+        &mut self,
+        params: &mut [f32],
+        offset: usize,
+        grad: &[f32],
+        cfg: &AdamConfig,
+        lr_t: f32,
+    ) {
+        let m = &mut self.m[offset..offset + grad.len()];
+        let v = &mut self.v[offset..offset + grad.len()];
+        let p = &mut params[offset..offset + grad.len()];
+        for i in 0..grad.len() {
+            let g = grad[i];
+            m[i] = cfg.beta1 * m[i] + (1.0 - cfg.beta1) * g;
+            v[i] = cfg.beta2 * v[i] + (1.0 - cfg.beta2) * g * g;
+            p[i] -= lr_t * m[i] / (v[i].sqrt() + cfg.eps);
+        }
+    }
+}
+
+/// Batch gradients. Feature-transformer rows are sparse: only features
+/// active somewhere in the batch have one.
+struct Gradients {
+    loss: f64,
+    rows: HashMap<u16, Vec<f32>>,
+    b1: Vec<f32>,
+    w2: Vec<f32>,
+    b2: f32,
+}
+
+impl Gradients {
+    fn zeros(hidden: usize) -> Self {
+        Self {
+            loss: 0.0,
+            rows: HashMap::new(),
+            b1: vec![0.0; hidden],
+            w2: vec![0.0; 2 * hidden],
+            b2: 0.0,
+        }
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        self.loss += other.loss;
+        for (idx, row) in other.rows {
+            match self.rows.get_mut(&idx) {
+                Some(mine) => mine.iter_mut().zip(&row).for_each(|(a, b)| *a += b),
+                None => {
+                    self.rows.insert(idx, row);
+                }
+            }
+        }
+        self.b1.iter_mut().zip(&other.b1).for_each(|(a, b)| *a += b);
+        self.w2.iter_mut().zip(&other.w2).for_each(|(a, b)| *a += b);
+        self.b2 += other.b2;
+        self
+    }
+}
+
+fn sample_loss(model: &FloatNnueV2, sample: &Sample, scale_cp: f32) -> f32 {
+    let p = sigmoid(model.evaluate_features(&sample.stm, &sample.nstm) / scale_cp);
+    (p - sample.target) * (p - sample.target)
+}
+
+/// Add one sample's loss and gradients to `grads`.
+fn backprop(model: &FloatNnueV2, sample: &Sample, scale_cp: f32, grads: &mut Gradients) {
+    let h = model.hidden_dim;
+    let mut acc = vec![0.0; 2 * h];
+    let (acc_stm, acc_nstm) = acc.split_at_mut(h);
+    model.accumulate(&sample.stm, acc_stm);
+    model.accumulate(&sample.nstm, acc_nstm);
+    let out = model.head(acc_stm, acc_nstm);
+    let units_per_logit = QuantNnueV2::SCALE as f32 / scale_cp;
+    let p = sigmoid(out * units_per_logit);
+    let err = p - sample.target;
+    grads.loss += f64::from(err * err);
+    // d loss / d out through the square and the sigmoid.
+    let g_out = 2.0 * err * p * (1.0 - p) * units_per_logit;
+
+    grads.b2 += g_out;
+    // Reuse the accumulator buffer for d loss / d accumulator.
+    for (j, a) in acc.iter_mut().enumerate() {
+        let v = a.clamp(0.0, 1.0);
+        grads.w2[j] += g_out * v * v;
+        *a = if *a > 0.0 && *a < 1.0 {
+            g_out * model.w2[j] * 2.0 * v
+        } else {
+            0.0
+        };
+    }
+    let (d_stm, d_nstm) = acc.split_at(h);
+    for j in 0..h {
+        grads.b1[j] += d_stm[j] + d_nstm[j];
+    }
+    for (features, d) in [(&sample.stm, d_stm), (&sample.nstm, d_nstm)] {
+        for &idx in features.iter() {
+            let row = grads.rows.entry(idx).or_insert_with(|| vec![0.0; h]);
+            row.iter_mut().zip(d).for_each(|(r, &g)| *r += g);
+        }
+    }
+}
+
+/// A model with its Adam state.
+pub struct Trainer {
+    pub model: FloatNnueV2,
+    pub adam: AdamConfig,
+    /// Optimizer steps taken so far.
+    pub step: u64,
+    /// Training epochs completed so far; the caller advances it.
+    pub epoch: u64,
+    moments: [Moments; 4],
+}
+
+impl Trainer {
+    pub fn new(model: FloatNnueV2, adam: AdamConfig) -> Self {
+        let moments = model.tensors().map(|t| Moments::zeros(t.len()));
+        Self {
+            model,
+            adam,
+            step: 0,
+            epoch: 0,
+            moments,
+        }
+    }
+
+    /// Mean loss of the model on `samples`.
+    pub fn loss(&self, samples: &[Sample], scale_cp: f32) -> f32 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = samples
+            .par_iter()
+            .map(|s| f64::from(sample_loss(&self.model, s, scale_cp)))
+            .sum();
+        (total / samples.len() as f64) as f32
+    }
+
+    /// One Adam step on the mean gradient of `batch`; returns the batch's
+    /// mean loss before the step.
+    pub fn train_batch(&mut self, batch: &[Sample], scale_cp: f32) -> f32 {
+        if batch.is_empty() {
+            return 0.0;
+        }
+        let h = self.model.hidden_dim;
+        let chunk = batch.len().div_ceil(rayon::current_num_threads()).max(64);
+        let model = &self.model;
+        let mut grads = batch
+            .par_chunks(chunk)
+            .map(|part| {
+                let mut g = Gradients::zeros(h);
+                for sample in part {
+                    backprop(model, sample, scale_cp, &mut g);
+                }
+                g
+            })
+            .reduce(|| Gradients::zeros(h), Gradients::merge);
+        let n = batch.len() as f32;
+        let mean = |v: &mut [f32]| v.iter_mut().for_each(|g| *g /= n);
+
+        self.step += 1;
+        let t = self.step as i32;
+        let cfg = self.adam;
+        let lr_t = cfg.lr * (1.0 - cfg.beta2.powi(t)).sqrt() / (1.0 - cfg.beta1.powi(t));
+        let [m_w1, m_b1, m_w2, m_b2] = &mut self.moments;
+        for (&idx, row) in grads.rows.iter_mut() {
+            mean(row);
+            m_w1.step(&mut self.model.w1, idx as usize * h, row, &cfg, lr_t);
+        }
+        mean(&mut grads.b1);
+        m_b1.step(&mut self.model.b1, 0, &grads.b1, &cfg, lr_t);
+        mean(&mut grads.w2);
+        m_w2.step(&mut self.model.w2, 0, &grads.w2, &cfg, lr_t);
+        m_b2.step(
+            std::slice::from_mut(&mut self.model.b2),
+            0,
+            &[grads.b2 / n],
+            &cfg,
+            lr_t,
+        );
+        self.model.clamp_output_weights();
+        (grads.loss / f64::from(n)) as f32
+    }
+
+    /// Write the float weights followed by the step and epoch counters and
+    /// the Adam state, so a run can resume where it stopped.
+    /// `FloatNnueV2::load` reads the weights alone.
+    /// The file is replaced atomically, so an interrupted save keeps the
+    /// previous checkpoint.
+    pub fn save_checkpoint(&self, path: &Path) -> std::io::Result<()> {
+        let mut tmp = path.as_os_str().to_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut w = BufWriter::new(File::create(&tmp)?);
+        self.model.write_to(&mut w)?;
+        w.write_all(&self.step.to_le_bytes())?;
+        w.write_all(&self.epoch.to_le_bytes())?;
+        for moments in &self.moments {
+            write_f32s(&mut w, &moments.m)?;
+            write_f32s(&mut w, &moments.v)?;
+        }
+        w.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Resume from a checkpoint written by `save_checkpoint`.
+    pub fn load_checkpoint(path: &Path, adam: AdamConfig) -> Result<Self, String> {
+        let err = |e: String| format!("{}: {e}", path.display());
+        let file = File::open(path).map_err(|e| err(e.to_string()))?;
+        let mut r = BufReader::new(file);
+        let (model, version) = FloatNnueV2::read_from(&mut r).map_err(err)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(err(format!(
+                "checkpoint version {version} has no epoch counter; \
+                 resuming needs version {CHECKPOINT_VERSION}"
+            )));
+        }
+        let mut trainer = Self::new(model, adam);
+        let mut counters = [0u8; 16];
+        r.read_exact(&mut counters)
+            .map_err(|_| err("checkpoint has no optimizer state".to_string()))?;
+        let (step, epoch) = counters.split_at(8);
+        trainer.step = u64::from_le_bytes(step.try_into().unwrap());
+        trainer.epoch = u64::from_le_bytes(epoch.try_into().unwrap());
+        for moments in &mut trainer.moments {
+            moments.m = read_f32s(&mut r, moments.m.len()).map_err(err)?;
+            moments.v = read_f32s(&mut r, moments.v.len()).map_err(err)?;
+        }
+        Ok(trainer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiny_model(seed: u64) -> FloatNnueV2 {
+        let mut model = FloatNnueV2::new_random(4, seed);
+        // Spread the biases so every unit sits inside SCReLU's live range
+        // and the finite differences see a smooth function.
+        model.b1 = vec![0.2, 0.35, 0.5, 0.65];
+        model
+    }
+
+    #[test]
+    fn gradients_match_finite_differences() {
+        let board = Board::from_fen(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            false,
+        )
+        .unwrap();
+        let (stm, nstm) = stm_features(&board);
+        let sample = Sample {
+            stm,
+            nstm,
+            target: 0.8,
+        };
+        let scale = 400.0;
+        let model = tiny_model(3);
+        let mut grads = Gradients::zeros(4);
+        backprop(&model, &sample, scale, &mut grads);
+        assert!((grads.loss as f32 - sample_loss(&model, &sample, scale)).abs() < 1e-6);
+
+        let numeric = |edit: &dyn Fn(&mut FloatNnueV2, f32)| {
+            let eps = 1e-3;
+            let mut plus = model.clone();
+            edit(&mut plus, eps);
+            let mut minus = model.clone();
+            edit(&mut minus, -eps);
+            (sample_loss(&plus, &sample, scale) - sample_loss(&minus, &sample, scale)) / (2.0 * eps)
+        };
+        // f32 central differences are only good to about a percent.
+        let close = |analytic: f32, numeric: f32| {
+            assert!(
+                (analytic - numeric).abs() <= 1e-2 * analytic.abs().max(1e-2),
+                "analytic {analytic} vs numeric {numeric}"
+            );
+        };
+        close(grads.b2, numeric(&|m, e| m.b2 += e));
+        for j in 0..8 {
+            close(grads.w2[j], numeric(&|m, e| m.w2[j] += e));
+        }
+        for j in 0..4 {
+            close(grads.b1[j], numeric(&|m, e| m.b1[j] += e));
+        }
+        // A feature of each perspective; the shared weights get both.
+        for idx in [sample.stm[0], sample.nstm[3]] {
+            for (j, &g) in grads.rows[&idx].iter().enumerate() {
+                close(g, numeric(&|m, e| m.w1[idx as usize * 4 + j] += e));
+            }
+        }
+    }
+
+    #[test]
+    fn targets_blend_teacher_and_result_from_the_side_to_move() {
+        let mut position =
+            PackedPosition::new(Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1", false).unwrap());
+        let params = TargetParams {
+            eval_weight: 0.25,
+            ..TargetParams::default()
+        };
+        assert_eq!(Sample::from_position(&position, &params), None);
+
+        position.score_cp = Some(400);
+        let teacher_only = Sample::from_position(&position, &params).unwrap();
+        assert!((teacher_only.target - (1.0 - sigmoid(1.0))).abs() < 1e-6);
+
+        position.outcome_valid = true;
+        position.result = 1;
+        let blended = Sample::from_position(&position, &params).unwrap();
+        let white = 0.25 * sigmoid(1.0) + 0.75;
+        assert!((blended.target - (1.0 - white)).abs() < 1e-6);
+        assert_eq!(blended.stm.len(), 1);
+
+        position.teacher_depth = Some(3);
+        let deep_only = TargetParams {
+            min_teacher_depth: 6,
+            ..params
+        };
+        let result_only = Sample::from_position(&position, &deep_only).unwrap();
+        assert_eq!(result_only.target, 0.0);
+    }
+}
@@ -1,4 +1,199 @@
-// Placeholder FEN utilities; we rely on cozy-chess for parsing.
+use crate::io::pgn::san_to_move;
+use cozy_chess::{Board, Move};
+
+// FEN and EPD parsing on top of cozy-chess, with errors that say which
+// field is wrong instead of a bare parse failure.
 pub fn normalize_fen(fen: &str) -> String {
     fen.trim().to_string()
 }
+
+/// Parse a FEN, checking each field. Four-field positions (as in EPD) get
+/// `0 1` clocks.
+pub fn parse_fen(fen: &str) -> Result<Board, String> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    match fields.len() {
+        4 => parse_fields(&fields, "0", "1"),
+        6 => parse_fields(&fields[..4], fields[4], fields[5]),
+        n => Err(format!("FEN {fen:?} has {n} fields, expected 4 or 6")),
+    }
+}
+
+fn parse_fields(fields: &[&str], halfmove: &str, fullmove: &str) -> Result<Board, String> {
+    let placement = fields[0];
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(format!(
+            "piece placement {placement:?} has {} ranks, expected 8",
+            ranks.len()
+        ));
+    }
+    for rank in &ranks {
+        let mut squares = 0u32;
+        for c in rank.chars() {
+            squares += match c {
+                '1'..='8' => c as u32 - '0' as u32,
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => 1,
+                _ => return Err(format!("invalid piece {c:?} in {placement:?}")),
+            };
+        }
+        if squares != 8 {
+            return Err(format!(
+                "rank {rank:?} of {placement:?} covers {squares} squares"
+            ));
+        }
+    }
+    if !matches!(fields[1], "w" | "b") {
+        return Err(format!("invalid side to move {:?}", fields[1]));
+    }
+    let castling = fields[2];
+    if castling != "-" && !castling.chars().all(|c| "KQkqABCDEFGHabcdefgh".contains(c)) {
+        return Err(format!("invalid castling rights {castling:?}"));
+    }
+    let ep = fields[3];
+    let ep_ok = ep == "-"
+        || (ep.len() == 2
+            && matches!(ep.as_bytes()[0], b'a'..=b'h')
+            && matches!(ep.as_bytes()[1], b'3' | b'6'));
+    if !ep_ok {
+        return Err(format!("invalid en passant square {ep:?}"));
+    }
+    halfmove
+        .parse::<u8>()
+        .map_err(|_| format!("invalid halfmove clock {halfmove:?}"))?;
+    match fullmove.parse::<u16>() {
+        Ok(n) if n > 0 => {}
+        _ => return Err(format!("invalid fullmove number {fullmove:?}")),
+    }
+    let fen = format!("{} {halfmove} {fullmove}", fields.join(" "));
+    // Standard castling letters first, Shredder-FEN file letters otherwise.
+    Board::from_fen(&fen, false)
+        .or_else(|_| Board::from_fen(&fen, true))
+        .map_err(|e| format!("illegal position {fen:?}: {e:?}"))
+}
+
+/// One EPD line: a position and its operations, in file order.
+#[derive(Clone, Debug)]
+pub struct EpdRecord {
+    pub board: Board,
+    pub ops: Vec<(String, Vec<String>)>,
+}
+
+impl EpdRecord {
+    /// Operands of the first `opcode` operation.
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.ops
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// Best moves (`bm`); an empty list when the opcode is absent.
+    pub fn bm(&self) -> Result<Vec<Move>, String> {
+        self.moves("bm")
+    }
+
+    /// Moves to avoid (`am`); an empty list when the opcode is absent.
+    pub fn am(&self) -> Result<Vec<Move>, String> {
+        self.moves("am")
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.string_operand("id")
+    }
+
+    pub fn c0(&self) -> Option<&str> {
+        self.string_operand("c0")
+    }
+
+    fn string_operand(&self, opcode: &str) -> Option<&str> {
+        self.operands(opcode)?.first().map(String::as_str)
+    }
+
+    /// Move operands are SAN by the standard; UCI moves are accepted too,
+    /// since hand-written suites often use them.
+    fn moves(&self, opcode: &str) -> Result<Vec<Move>, String> {
+        self.operands(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|text| {
+                san_to_move(&self.board, text).or_else(|e| {
+                    text.parse::<Move>()
+                        .ok()
+                        .filter(|&mv| self.board.is_legal(mv))
+                        .ok_or_else(|| format!("{opcode}: {e}"))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Parse an EPD line: four FEN fields followed by `opcode operand...;`
+/// operations. Quoted operands may contain spaces and semicolons. `hmvc`
+/// and `fmvn` set the position's clocks.
+pub fn parse_epd(line: &str) -> Result<EpdRecord, String> {
+    let line = line.trim();
+    let mut rest = line;
+    let mut fields = Vec::with_capacity(4);
+    for _ in 0..4 {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(format!("EPD {line:?} has fewer than 4 fields"));
+        }
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+
+    let mut ops = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.extend(chars.next()),
+                        Some(c) => text.push(c),
+                        None => return Err(format!("unterminated string in EPD {line:?}")),
+                    }
+                }
+                tokens.push(text);
+            }
+            ';' => {
+                if let Some((op, operands)) = tokens.split_first() {
+                    ops.push((op.clone(), operands.to_vec()));
+                }
+                tokens.clear();
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut text = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ';' || c == '"' {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+                tokens.push(text);
+            }
+        }
+    }
+    if !tokens.is_empty() {
+        return Err(format!(
+            "EPD operation {:?} is missing its ';'",
+            tokens.join(" ")
+        ));
+    }
+
+    let operand = |name: &str, default: &'static str| -> String {
+        ops.iter()
+            .find(|(op, _)| op == name)
+            .and_then(|(_, operands)| operands.first().cloned())
+            .unwrap_or_else(|| default.to_string())
+    };
+    let board = parse_fields(&fields, &operand("hmvc", "0"), &operand("fmvn", "1"))?;
+    Ok(EpdRecord { board, ops })
+}
@@ -1,2 +1,4 @@
 pub mod fen;
 pub mod openings;
+pub mod pgn;
+pub mod polyglot;
@@ -0,0 +1,585 @@
+use crate::search::eval::MATE_SCORE;
+use cozy_chess::{Board, Color, File, Move, Piece, Rank, Square};
+use std::fmt::Write as _;
+use std::io::BufRead;
+use std::path::Path;
+
+const STANDARD_START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+/// Export-format line limit from the PGN standard.
+const MAX_LINE_LEN: usize = 79;
+/// Tags written first, in this order, as the PGN standard's Seven Tag Roster.
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// One move of a game, with the comment that followed it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnMove {
+    /// Cozy-chess move; castling is king-takes-rook.
+    pub mv: Move,
+    pub comment: Option<String>,
+}
+
+/// A game: tag pairs, the start position and the moves played from it.
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    pub headers: Vec<(String, String)>,
+    pub start: Board,
+    pub moves: Vec<PgnMove>,
+    /// `1-0`, `0-1`, `1/2-1/2` or `*`.
+    pub result: String,
+}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        Self::new(Board::default())
+    }
+}
+
+impl PgnGame {
+    pub fn new(start: Board) -> Self {
+        Self {
+            headers: Vec::new(),
+            start,
+            moves: Vec::new(),
+            result: "*".to_string(),
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set a tag, replacing an existing one with the same name.
+    pub fn set_header(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.headers.iter_mut().find(|(key, _)| key == name) {
+            Some(slot) => slot.1 = value,
+            None => self.headers.push((name.to_string(), value)),
+        }
+    }
+
+    /// Append a move, which must be legal in the current final position.
+    pub fn push(&mut self, mv: Move, comment: Option<String>) {
+        self.moves.push(PgnMove { mv, comment });
+    }
+
+    /// The position after every move, starting with `start`.
+    pub fn positions(&self) -> Vec<Board> {
+        let mut board = self.start.clone();
+        let mut out = Vec::with_capacity(self.moves.len() + 1);
+        out.push(board.clone());
+        for m in &self.moves {
+            board.play_unchecked(m.mv);
+            out.push(board.clone());
+        }
+        out
+    }
+
+    /// Game result from White's side: 1, 0 or -1, `None` when unfinished.
+    pub fn white_score(&self) -> Option<i8> {
+        match self.result.as_str() {
+            "1-0" => Some(1),
+            "0-1" => Some(-1),
+            "1/2-1/2" => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Export format: the Seven Tag Roster first (with unknown placeholders),
+    /// then the other tags, `SetUp`/`FEN` for a non-standard start, and the
+    /// movetext wrapped below 80 columns.
+    pub fn to_pgn(&self) -> String {
+        let mut out = String::new();
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.as_str(),
+                "Date" => self.header(name).unwrap_or("????.??.??"),
+                _ => self.header(name).unwrap_or("?"),
+            };
+            write_tag(&mut out, name, value);
+        }
+        let start_fen = self.start.to_string();
+        let custom_start = start_fen != STANDARD_START_FEN;
+        for (name, value) in &self.headers {
+            let skip = SEVEN_TAG_ROSTER.contains(&name.as_str())
+                || (custom_start && (name == "SetUp" || name == "FEN"));
+            if !skip {
+                write_tag(&mut out, name, value);
+            }
+        }
+        if custom_start {
+            write_tag(&mut out, "SetUp", "1");
+            write_tag(&mut out, "FEN", &start_fen);
+        }
+        out.push('\n');
+
+        let mut tokens = Vec::with_capacity(self.moves.len() * 2 + 1);
+        let mut board = self.start.clone();
+        let mut move_number = u32::from(board.fullmove_number());
+        for (index, m) in self.moves.iter().enumerate() {
+            let white = board.side_to_move() == Color::White;
+            if white {
+                tokens.push(format!("{move_number}."));
+            } else if index == 0 {
+                tokens.push(format!("{move_number}..."));
+            }
+            tokens.push(move_to_san(&board, m.mv));
+            if let Some(comment) = m.comment.as_deref() {
+                // Braces cannot be escaped inside a PGN comment.
+                tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+            }
+            board.play_unchecked(m.mv);
+            if !white {
+                move_number += 1;
+            }
+        }
+        tokens.push(self.result.clone());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > MAX_LINE_LEN {
+                out.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                out.push(' ');
+                line_len += 1;
+            }
+            line_len += token.len();
+            out.push_str(&token);
+        }
+        out.push_str("\n\n");
+        out
+    }
+}
+
+fn write_tag(out: &mut String, name: &str, value: &str) {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    let _ = writeln!(out, "[{name} \"{escaped}\"]");
+}
+
+/// Engine evaluation in a move comment, in the `+0.35/12` form used by
+/// cutechess and most GUIs (`+M3/12` for a mate in 3), from the side that
+/// made the move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvalComment {
+    pub score_cp: i32,
+    pub depth: u32,
+}
+
+impl EvalComment {
+    /// Parse the leading `score/depth` of a comment; anything after the
+    /// first whitespace (a move time, say) is ignored.
+    pub fn parse(comment: &str) -> Option<Self> {
+        let head = comment.split_whitespace().next()?;
+        let (score, depth) = head.split_once('/')?;
+        let depth = depth.parse().ok()?;
+        let (negative, magnitude) = match score.as_bytes().first()? {
+            b'-' => (true, &score[1..]),
+            b'+' => (false, &score[1..]),
+            _ => (false, score),
+        };
+        let cp = if let Some(moves) = magnitude.strip_prefix('M') {
+            let moves: i32 = moves.parse().ok()?;
+            MATE_SCORE - (2 * moves - 1).max(0)
+        } else {
+            let pawns: f64 = magnitude.parse().ok()?;
+            (pawns * 100.0).round() as i32
+        };
+        Some(Self {
+            score_cp: if negative { -cp } else { cp },
+            depth,
+        })
+    }
+}
+
+impl std::fmt::Display for EvalComment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.score_cp < 0 { '-' } else { '+' };
+        let magnitude = self.score_cp.unsigned_abs() as i32;
+        if magnitude >= MATE_SCORE - 1_024 {
+            let moves = (MATE_SCORE - magnitude + 1) / 2;
+            write!(f, "{sign}M{moves}/{}", self.depth)
+        } else {
+            write!(
+                f,
+                "{sign}{}.{:02}/{}",
+                magnitude / 100,
+                magnitude % 100,
+                self.depth
+            )
+        }
+    }
+}
+
+fn piece_letter(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+fn piece_from_letter(letter: char) -> Option<Piece> {
+    match letter {
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
+}
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut moves = Vec::with_capacity(64);
+    board.generate_moves(|ml| {
+        moves.extend(ml);
+        false
+    });
+    moves
+}
+
+fn is_castling(board: &Board, mv: Move) -> bool {
+    board.piece_on(mv.from) == Some(Piece::King) && board.colors(board.side_to_move()).has(mv.to)
+}
+
+/// Standard Algebraic Notation for a legal move, with `+`/`#` suffixes.
+pub fn move_to_san(board: &Board, mv: Move) -> String {
+    let Some(piece) = board.piece_on(mv.from) else {
+        return mv.to_string();
+    };
+    let mut san = String::new();
+    if is_castling(board, mv) {
+        san.push_str(if mv.to.file() > mv.from.file() {
+            "O-O"
+        } else {
+            "O-O-O"
+        });
+    } else {
+        let capture = board.piece_on(mv.to).is_some()
+            || (piece == Piece::Pawn && mv.from.file() != mv.to.file());
+        if piece == Piece::Pawn {
+            if capture {
+                san.push(char::from(mv.from.file()));
+            }
+        } else {
+            san.push(piece_letter(piece));
+            let rivals: Vec<Square> = legal_moves(board)
+                .into_iter()
+                .filter(|m| m.to == mv.to && m.from != mv.from)
+                .filter(|m| board.piece_on(m.from) == Some(piece))
+                .map(|m| m.from)
+                .collect();
+            if !rivals.is_empty() {
+                let shares_file = rivals.iter().any(|sq| sq.file() == mv.from.file());
+                let shares_rank = rivals.iter().any(|sq| sq.rank() == mv.from.rank());
+                if !shares_file {
+                    san.push(char::from(mv.from.file()));
+                } else if !shares_rank {
+                    san.push(char::from(mv.from.rank()));
+                } else {
+                    san.push_str(&mv.from.to_string());
+                }
+            }
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&mv.to.to_string());
+        if let Some(promotion) = mv.promotion {
+            san.push('=');
+            san.push(piece_letter(promotion));
+        }
+    }
+    let mut next = board.clone();
+    next.play_unchecked(mv);
+    if !next.checkers().is_empty() {
+        san.push(if legal_moves(&next).is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+/// Parse a SAN move in `board`. Accepts the usual variations: check and
+/// annotation suffixes, `0-0` castling, promotions with or without `=`, and
+/// redundant disambiguation.
+pub fn san_to_move(board: &Board, san: &str) -> Result<Move, String> {
+    let text = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal = legal_moves(board);
+    let castle = match text {
+        "O-O" | "0-0" => Some(true),
+        "O-O-O" | "0-0-0" => Some(false),
+        _ => None,
+    };
+    if let Some(short) = castle {
+        return legal
+            .into_iter()
+            .find(|&mv| is_castling(board, mv) && (mv.to.file() > mv.from.file()) == short)
+            .ok_or_else(|| format!("illegal castling {san:?}"));
+    }
+
+    let mut chars: Vec<char> = text.chars().collect();
+    let piece = match chars.first().copied().and_then(piece_from_letter) {
+        Some(piece) => {
+            chars.remove(0);
+            piece
+        }
+        None => Piece::Pawn,
+    };
+    let mut promotion = None;
+    if let Some(&last) = chars.last() {
+        if let Some(p) = piece_from_letter(last).filter(|_| piece == Piece::Pawn) {
+            promotion = Some(p);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+    if chars.len() < 2 {
+        return Err(format!("invalid SAN {san:?}"));
+    }
+    let to: Square = chars[chars.len() - 2..]
+        .iter()
+        .collect::<String>()
+        .parse()
+        .map_err(|_| format!("invalid SAN {san:?}"))?;
+    let mut from_file = None;
+    let mut from_rank = None;
+    for &c in &chars[..chars.len() - 2] {
+        match c {
+            'a'..='h' => from_file = Some(File::index(c as usize - 'a' as usize)),
+            '1'..='8' => from_rank = Some(Rank::index(c as usize - '1' as usize)),
+            'x' | ':' | '-' => {}
+            _ => return Err(format!("invalid SAN {san:?}")),
+        }
+    }
+
+    let mut matches = legal.into_iter().filter(|&mv| {
+        mv.to == to
+            && !is_castling(board, mv)
+            && board.piece_on(mv.from) == Some(piece)
+            && mv.promotion == promotion
+            && from_file.is_none_or(|f| mv.from.file() == f)
+            && from_rank.is_none_or(|r| mv.from.rank() == r)
+    });
+    match (matches.next(), matches.next()) {
+        (Some(mv), None) => Ok(mv),
+        (None, _) => Err(format!("illegal move {san:?}")),
+        (Some(_), Some(_)) => Err(format!("ambiguous move {san:?}")),
+    }
+}
+
+/// Reads games one at a time from any buffered source, so a large database
+/// never has to fit in memory. Variations and NAGs are skipped; comments
+/// are kept on the move they follow.
+pub struct PgnReader<R> {
+    reader: R,
+    line: String,
+    line_no: usize,
+    // Line that ended the previous game's movetext and starts the next one.
+    pending: Option<String>,
+}
+
+/// Open a PGN file for streaming.
+pub fn open(path: &Path) -> Result<PgnReader<std::io::BufReader<std::fs::File>>, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("failed to open PGN {}: {e}", path.display()))?;
+    Ok(PgnReader::new(std::io::BufReader::new(file)))
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            line_no: 0,
+            pending: None,
+        }
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, String> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+        self.line.clear();
+        let read = self
+            .reader
+            .read_line(&mut self.line)
+            .map_err(|e| format!("failed to read PGN: {e}"))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.line_no += 1;
+        Ok(Some(
+            self.line
+                .trim_end_matches(['\n', '\r'])
+                .trim_start_matches('\u{feff}')
+                .to_string(),
+        ))
+    }
+
+    fn read_game(&mut self) -> Result<Option<PgnGame>, String> {
+        let mut headers = Vec::new();
+        let mut movetext = String::new();
+        let mut started = false;
+        while let Some(line) = self.next_line()? {
+            let trimmed = line.trim();
+            if trimmed.starts_with('%') {
+                continue;
+            }
+            if trimmed.starts_with('[') && movetext.trim().is_empty() {
+                headers.push(
+                    parse_tag(trimmed).ok_or_else(|| {
+                        format!("line {}: malformed tag {trimmed:?}", self.line_no)
+                    })?,
+                );
+                started = true;
+                continue;
+            }
+            if trimmed.starts_with('[') && started && !in_comment(&movetext) {
+                // Tags after movetext: the previous game had no result token.
+                self.pending = Some(line);
+                break;
+            }
+            if trimmed.is_empty() && movetext.trim().is_empty() {
+                continue;
+            }
+            started = true;
+            movetext.push_str(&line);
+            movetext.push('\n');
+            if !in_comment(&movetext) && ends_with_result(trimmed) {
+                break;
+            }
+        }
+        if !started {
+            return Ok(None);
+        }
+        build_game(headers, &movetext).map(Some)
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_game().transpose()
+    }
+}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.trim_end().strip_suffix(']')?;
+    let (name, rest) = inner.split_once(char::is_whitespace)?;
+    let quoted = rest.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            value.extend(chars.next());
+        } else {
+            value.push(c);
+        }
+    }
+    Some((name.to_string(), value))
+}
+
+fn in_comment(movetext: &str) -> bool {
+    movetext.matches('{').count() > movetext.matches('}').count()
+}
+
+fn ends_with_result(line: &str) -> bool {
+    line.split_whitespace()
+        .last()
+        .is_some_and(|token| matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*"))
+}
+
+fn build_game(headers: Vec<(String, String)>, movetext: &str) -> Result<PgnGame, String> {
+    let start = match headers.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => crate::io::fen::parse_fen(fen)?,
+        None => Board::default(),
+    };
+    let mut game = PgnGame::new(start);
+    game.headers = headers;
+    let mut board = game.start.clone();
+    let mut chars = movetext.char_indices().peekable();
+    let mut variation_depth = 0usize;
+    let mut result = None;
+    while let Some(&(at, c)) = chars.peek() {
+        match c {
+            '{' => {
+                let end = movetext[at..]
+                    .find('}')
+                    .map_or(movetext.len(), |offset| at + offset);
+                let comment = movetext[at + 1..end].split_whitespace().collect::<Vec<_>>();
+                if variation_depth == 0 && !comment.is_empty() {
+                    if let Some(last) = game.moves.last_mut() {
+                        let text = comment.join(" ");
+                        last.comment = Some(match last.comment.take() {
+                            Some(previous) => format!("{previous} {text}"),
+                            None => text,
+                        });
+                    }
+                }
+                while chars.peek().is_some_and(|&(i, _)| i <= end) {
+                    chars.next();
+                }
+            }
+            ';' => while chars.next_if(|&(_, c)| c != '\n').is_some() {},
+            '(' => {
+                variation_depth += 1;
+                chars.next();
+            }
+            ')' => {
+                variation_depth = variation_depth.saturating_sub(1);
+                chars.next();
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut end = at;
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '{' | '(' | ')' | ';') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let token = &movetext[at..end];
+                if variation_depth > 0 || token.starts_with('$') {
+                    continue;
+                }
+                if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+                    result = Some(token.to_string());
+                    continue;
+                }
+                // Move numbers may be glued to the move: "12.Nf3", "12...Nf6".
+                let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                if san.is_empty() {
+                    continue;
+                }
+                let mv = san_to_move(&board, san).map_err(|e| {
+                    format!(
+                        "{e} after {} plies of game {:?}",
+                        game.moves.len(),
+                        game.header("Event").unwrap_or("?")
+                    )
+                })?;
+                board.play_unchecked(mv);
+                game.push(mv, None);
+            }
+        }
+    }
+    game.result = result
+        .or_else(|| game.header("Result").map(str::to_string))
+        .unwrap_or_else(|| "*".to_string());
+    Ok(game)
+}
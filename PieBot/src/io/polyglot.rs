@@ -0,0 +1,226 @@
+use crate::board::cozy::to_shakmaty;
+use cozy_chess::{Board, File, Move, Piece, Rank, Square};
+use rand::Rng;
+use shakmaty::zobrist::{Zobrist64, ZobristHash};
+use shakmaty::EnPassantMode;
+use std::collections::HashMap;
+use std::path::Path;
+
+const ENTRY_BYTES: usize = 16;
+
+/// How a move is picked when a position has several book moves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BookSelection {
+    /// At random, in proportion to the entry weights.
+    #[default]
+    Weighted,
+    /// Always the highest-weighted move.
+    Best,
+}
+
+impl std::str::FromStr for BookSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "weighted" => Ok(Self::Weighted),
+            "best" => Ok(Self::Best),
+            other => Err(format!("unknown book selection {other:?}")),
+        }
+    }
+}
+
+/// One book move for a position, already checked to be legal there.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookMove {
+    /// Cozy-chess move; castling is king-takes-rook, as in Polyglot.
+    pub mv: Move,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct RawEntry {
+    key: u64,
+    mv: u16,
+    weight: u16,
+    learn: u32,
+}
+
+/// A Polyglot `.bin` opening book held in memory.
+///
+/// Entries are 16 big-endian bytes: the position key, the move, its weight
+/// and a learn field, sorted by key so all moves for a position are found
+/// with one binary search.
+#[derive(Clone, Debug, Default)]
+pub struct PolyglotBook {
+    entries: Vec<RawEntry>,
+}
+
+impl PolyglotBook {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("failed to read book {}: {e}", path.display()))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("invalid book {}: {e}", path.display()))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(ENTRY_BYTES) {
+            return Err(format!(
+                "{} bytes is not a whole number of {ENTRY_BYTES}-byte entries",
+                bytes.len()
+            ));
+        }
+        let mut entries: Vec<RawEntry> = bytes
+            .chunks_exact(ENTRY_BYTES)
+            .map(|chunk| RawEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                mv: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap()),
+            })
+            .collect();
+        // Books from other tools are sorted already; a stable sort keeps their
+        // move order for equal keys and repairs anything hand-assembled.
+        if !entries.windows(2).all(|w| w[0].key <= w[1].key) {
+            entries.sort_by_key(|e| e.key);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Build a book from the first `max_plies` moves of every game in a PGN
+    /// database, weighting each move by how often it was played.
+    pub fn from_pgn(path: &Path, max_plies: usize) -> Result<Self, String> {
+        let mut counts: HashMap<(u64, u16), u32> = HashMap::new();
+        for (index, game) in crate::io::pgn::open(path)?.enumerate() {
+            let game = game.map_err(|e| format!("{}: game {}: {e}", path.display(), index + 1))?;
+            let mut board = game.start.clone();
+            for m in game.moves.iter().take(max_plies) {
+                let Some(key) = polyglot_key(&board) else {
+                    break;
+                };
+                *counts.entry((key, encode_move(m.mv))).or_default() += 1;
+                board.play_unchecked(m.mv);
+            }
+        }
+        let mut entries: Vec<RawEntry> = counts
+            .into_iter()
+            .map(|((key, mv), count)| RawEntry {
+                key,
+                mv,
+                weight: count.min(u32::from(u16::MAX)) as u16,
+                learn: 0,
+            })
+            .collect();
+        // Most played first within a position, as book tools write them.
+        entries.sort_by_key(|e| (e.key, std::cmp::Reverse(e.weight), e.mv));
+        Ok(Self { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The legal book moves for `board`, in book order. Entries whose move
+    /// is illegal (a key collision or a corrupt entry) are skipped.
+    pub fn moves(&self, board: &Board) -> Vec<BookMove> {
+        let Some(key) = polyglot_key(board) else {
+            return Vec::new();
+        };
+        let start = self.entries.partition_point(|e| e.key < key);
+        self.entries[start..]
+            .iter()
+            .take_while(|e| e.key == key)
+            .filter_map(|e| {
+                let mv = decode_move(e.mv)?;
+                board.is_legal(mv).then_some(BookMove {
+                    mv,
+                    weight: e.weight,
+                    learn: e.learn,
+                })
+            })
+            .collect()
+    }
+
+    /// Pick a book move for `board`. Weighted selection ignores zero-weight
+    /// entries unless every entry has weight zero.
+    pub fn choose<R: Rng>(
+        &self,
+        board: &Board,
+        selection: BookSelection,
+        rng: &mut R,
+    ) -> Option<Move> {
+        let moves = self.moves(board);
+        match selection {
+            // The first of equally weighted moves, as the book lists it.
+            BookSelection::Best => moves.iter().rev().max_by_key(|m| m.weight).map(|m| m.mv),
+            BookSelection::Weighted => {
+                let total: u32 = moves.iter().map(|m| u32::from(m.weight)).sum();
+                if total == 0 {
+                    return moves.first().map(|m| m.mv);
+                }
+                let mut pick = rng.gen_range(0..total);
+                for m in &moves {
+                    let weight = u32::from(m.weight);
+                    if pick < weight {
+                        return Some(m.mv);
+                    }
+                    pick -= weight;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// The Polyglot key of `board`. This is the standard Polyglot Zobrist
+/// scheme, unrelated to `search::zobrist`; the en passant file counts only
+/// when a pawn of the side to move stands next to the double-pushed pawn.
+pub fn polyglot_key(board: &Board) -> Option<u64> {
+    let pos = to_shakmaty(board)?;
+    let Zobrist64(key) = pos.zobrist_hash(EnPassantMode::PseudoLegal);
+    Some(key)
+}
+
+/// Decode a Polyglot move: to-square in bits 0-5, from-square in bits 6-11
+/// and the promotion piece (1 knight .. 4 queen) in bits 12-14. Castling is
+/// stored as the king capturing its own rook, which is also how cozy-chess
+/// represents it.
+pub fn decode_move(raw: u16) -> Option<Move> {
+    let square = |bits: u16| {
+        Square::new(
+            File::index(usize::from(bits & 7)),
+            Rank::index(usize::from((bits >> 3) & 7)),
+        )
+    };
+    let promotion = match (raw >> 12) & 7 {
+        0 => None,
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        4 => Some(Piece::Queen),
+        _ => return None,
+    };
+    Some(Move {
+        from: square(raw >> 6),
+        to: square(raw),
+        promotion,
+    })
+}
+
+/// Inverse of [`decode_move`].
+pub fn encode_move(mv: Move) -> u16 {
+    let square = |sq: Square| (sq.rank() as u16) << 3 | sq.file() as u16;
+    let promotion = match mv.promotion {
+        None => 0,
+        Some(Piece::Knight) => 1,
+        Some(Piece::Bishop) => 2,
+        Some(Piece::Rook) => 3,
+        Some(_) => 4,
+    };
+    square(mv.to) | square(mv.from) << 6 | promotion << 12
+}
@@ -1,10 +1,12 @@
 // Clean slate for NNUE + alpha-beta engine
+pub mod arena;
 pub mod board;
 pub mod eval;
 pub mod io;
 pub mod perft;
 pub mod search;
 pub mod selfplay;
+pub mod stats;
 pub mod test_support;
 pub mod uci;
 
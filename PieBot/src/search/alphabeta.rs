@@ -1,32 +1,17 @@
 use crate::eval::nnue::loader::QuantNnue;
 use crate::eval::nnue::network::{ChangeSet, QuantNetwork};
+use crate::search::config::SearchConfig;
 use crate::search::eval::{eval_cp, material_eval_cp, DRAW_SCORE, MATE_SCORE};
+use crate::search::syzygy::{wdl_score, Tablebases, Wdl, TB_WIN_SCORE};
+use crate::search::time_manager::{TimeLimits, TimeManager};
 use crate::search::tt::{Bound, Entry, Tt};
 use crate::search::zobrist;
 use cozy_chess::{Board, Color, Move, Square};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant};
 const HIST_PROMO_KINDS: usize = 5; // None, N, B, R, Q
 const HIST_SIZE: usize = 64 * 64 * HIST_PROMO_KINDS;
-/// History saturation bound. Without one, `h += depth*depth` grows without
-/// limit: measured in a single 150 ms search the table reached max 11,390-17,109
-/// while the capture ordering band tops out near 10,012, so 54.1% of depth-10
-/// nodes ordered a quiet ahead of a WINNING capture. Gravity keeps the table
-/// inside the band the ordering formula was designed around.
-const HIST_MAX: i32 = 16_384;
-/// Ordering is a flat sum, so the terms have to be scaled against each
-/// other. Measured ceilings: a capture reaches 1000 + mvv(~9000) + see/8
-/// ~= 10,112, while a quiet reaches killer(50) + history(16,384) +
-/// counter(40) = 16,474. A saturated-history quiet therefore outranks the
-/// BEST capture on the board -- measured at 54.1% of depth-10 nodes
-/// ordering a quiet ahead of a winning capture.
-///
-/// Lifting SEE>=0 captures above the quiet ceiling restores the invariant
-/// that a capture which wins material is tried first. Losing captures are
-/// deliberately NOT lifted: a quiet with real history should be searched
-/// before a capture that hangs a piece.
-const GOOD_CAPTURE_PRIORITY: i32 = 20_000;
 
 #[inline]
 fn promo_index(p: Option<cozy_chess::Piece>) -> usize {
@@ -47,6 +32,15 @@ fn move_index(m: Move) -> usize {
     (from * 64 + to) * HIST_PROMO_KINDS + pi
 }
 
+fn legal_move_from_uci(board: &Board, uci: &str) -> Option<Move> {
+    let mut found = None;
+    board.generate_moves(|ml| {
+        found = ml.into_iter().find(|m| format!("{m}") == uci);
+        found.is_some()
+    });
+    found
+}
+
 #[inline]
 fn piece_value_cp(p: cozy_chess::Piece) -> i32 {
     match p {
@@ -88,6 +82,93 @@ pub struct SearchParams {
     pub use_killers: bool,
     pub use_nullmove: bool,
     pub deterministic: bool,
+    /// Number of root lines to rank (UCI `MultiPV`); 0 and 1 both search a
+    /// single principal variation.
+    pub multipv: usize,
+    /// Restrict the root to these moves (UCI `go searchmoves`).
+    pub searchmoves: Option<RootMoveFilter>,
+    /// Clock-managed search: stop between iterations once the soft target is
+    /// used up, and never run past the hard limit. Combines with `movetime`
+    /// by taking whichever deadline comes first.
+    pub time: Option<TimeLimits>,
+}
+
+/// Upper bound on legal moves in any chess position.
+const MAX_ROOT_MOVES: usize = 218;
+
+/// A set of root moves the search is restricted to. Fixed capacity keeps
+/// `SearchParams` `Copy`. Moves that are not legal in the searched position
+/// are ignored, and a filter matching no legal move leaves the search
+/// unrestricted rather than producing no move at all.
+#[derive(Clone, Copy)]
+pub struct RootMoveFilter {
+    moves: [Move; MAX_ROOT_MOVES],
+    len: usize,
+}
+
+impl RootMoveFilter {
+    /// Build a filter from `moves` in cozy-chess encoding (castling is
+    /// king-takes-rook). Duplicates are dropped; moves beyond the capacity
+    /// of any legal position are ignored.
+    pub fn new(moves: &[Move]) -> Self {
+        let placeholder = Move {
+            from: Square::A1,
+            to: Square::A1,
+            promotion: None,
+        };
+        let mut filter = Self {
+            moves: [placeholder; MAX_ROOT_MOVES],
+            len: 0,
+        };
+        for &mv in moves {
+            if filter.len < MAX_ROOT_MOVES && !filter.contains(mv) {
+                filter.moves[filter.len] = mv;
+                filter.len += 1;
+            }
+        }
+        filter
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+
+    pub fn contains(&self, mv: Move) -> bool {
+        self.moves().contains(&mv)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl PartialEq for RootMoveFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.moves() == other.moves()
+    }
+}
+
+impl Eq for RootMoveFilter {}
+
+impl std::fmt::Debug for RootMoveFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.moves().iter().map(|mv| format!("{mv}")))
+            .finish()
+    }
+}
+
+/// One ranked root move from a MultiPV search.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct RootLine {
+    /// Cozy-chess move notation; castling is king-takes-rook.
+    pub mv: String,
+    pub score_cp: i32,
+    /// Line starting with `mv`.
+    pub pv: Vec<String>,
+    /// Iteration this line was last searched at. After an interrupted
+    /// iteration the lower-ranked lines can trail the first one by a ply.
+    pub depth: u32,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -98,6 +179,117 @@ pub struct SearchResult {
     /// Deepest fully completed iteration; can trail the requested depth
     /// when a node budget or deadline interrupts the search.
     pub depth: u32,
+    /// Principal variation in cozy-chess move notation, starting with
+    /// `bestmove`. Empty when no legal move exists.
+    pub pv: Vec<String>,
+    /// Root moves ranked best first, one per MultiPV line; `lines[0]`
+    /// matches `bestmove`. Filled by `search_with_params` only.
+    pub lines: Vec<RootLine>,
+}
+
+/// A root score as UCI reports it: centipawns, or full moves to mate.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InfoScore {
+    Cp(i32),
+    /// Moves to mate; negative when the side to move is the one being mated.
+    Mate(i32),
+}
+
+impl InfoScore {
+    /// Convert a search score, where a mate `n` plies from the root is
+    /// `MATE_SCORE - n`, into the UCI `cp`/`mate` form.
+    pub fn from_score(score: i32) -> Self {
+        if score >= MATE_TT_THRESHOLD {
+            let plies = MATE_SCORE - score;
+            InfoScore::Mate((plies + 1) / 2)
+        } else if score <= -MATE_TT_THRESHOLD {
+            let plies = MATE_SCORE + score;
+            InfoScore::Mate(-(plies / 2))
+        } else {
+            InfoScore::Cp(score)
+        }
+    }
+
+    /// The search score back from the UCI form: a mate in `n` moves is
+    /// `2n - 1` plies away, being mated in `n` is `2n` plies away.
+    pub fn to_score(self) -> i32 {
+        match self {
+            InfoScore::Cp(cp) => cp,
+            InfoScore::Mate(moves) if moves > 0 => MATE_SCORE - (2 * moves - 1),
+            InfoScore::Mate(moves) => -(MATE_SCORE - 2 * moves.abs()),
+        }
+    }
+}
+
+impl std::fmt::Display for InfoScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InfoScore::Cp(cp) => write!(f, "cp {cp}"),
+            InfoScore::Mate(moves) => write!(f, "mate {moves}"),
+        }
+    }
+}
+
+/// Progress for one completed iterative-deepening iteration, published while
+/// the search is still running.
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    /// 1-based rank of this line when several MultiPV lines are searched.
+    pub multipv: usize,
+    pub depth: u32,
+    pub seldepth: u32,
+    pub score: InfoScore,
+    /// Main thread plus every Lazy SMP helper.
+    pub nodes: u64,
+    pub nps: u64,
+    pub time_ms: u64,
+    /// Transposition-table occupancy in permille.
+    pub hashfull: u32,
+    /// Successful tablebase probes, main thread only.
+    pub tbhits: u64,
+    /// Cozy-chess move notation; castling is king-takes-rook.
+    pub pv: Vec<String>,
+}
+
+/// A deadline another thread can install while a search is running. `go
+/// ponder` starts without one; `ponderhit` sets the real time budget so the
+/// search continues in place instead of restarting.
+#[derive(Debug)]
+pub struct SharedDeadline {
+    epoch: Instant,
+    // Milliseconds after `epoch`; `u64::MAX` means no deadline.
+    at_ms: AtomicU64,
+}
+
+impl Default for SharedDeadline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SharedDeadline {
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            at_ms: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// Expire `budget` from now.
+    pub fn set_in(&self, budget: Duration) {
+        let at = self.epoch.elapsed().saturating_add(budget).as_millis();
+        self.at_ms
+            .store(at.min(u128::from(u64::MAX - 1)) as u64, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        self.at_ms.store(u64::MAX, Ordering::Relaxed);
+    }
+
+    fn reached(&self) -> bool {
+        let at = self.at_ms.load(Ordering::Relaxed);
+        at != u64::MAX && self.epoch.elapsed().as_millis() >= u128::from(at)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -111,13 +303,24 @@ enum SearchAbort {
 type SearchScore = Result<i32, SearchAbort>;
 
 const MATE_TT_THRESHOLD: i32 = MATE_SCORE - 1_024;
+/// Mate and tablebase scores both count plies from the root, so both are
+/// stored in the TT relative to the node instead.
+const DECISIVE_TT_THRESHOLD: i32 = TB_WIN_SCORE - 1_024;
 const FIFTY_MOVE_CLAIM_PLIES: u32 = 100;
+/// Tablebase results are stored as if searched this much deeper, so they
+/// are not replaced by ordinary search results for the same node.
+const TB_PROBE_DEPTH_BONUS: u32 = 6;
+/// Default `SyzygyProbeDepth`: interior nodes with as many pieces as the
+/// largest table probed need this much depth left to be probed.
+pub const DEFAULT_TB_PROBE_DEPTH: u32 = 1;
+/// Default `SyzygyProbeLimit`: no cap below the largest table loaded.
+pub const DEFAULT_TB_PROBE_LIMIT: usize = 7;
 
 #[inline]
 fn score_to_tt(score: i32, ply: i32) -> i32 {
-    if score >= MATE_TT_THRESHOLD {
+    if score >= DECISIVE_TT_THRESHOLD {
         score + ply
-    } else if score <= -MATE_TT_THRESHOLD {
+    } else if score <= -DECISIVE_TT_THRESHOLD {
         score - ply
     } else {
         score
@@ -126,9 +329,9 @@ fn score_to_tt(score: i32, ply: i32) -> i32 {
 
 #[inline]
 fn score_from_tt(score: i32, ply: i32) -> i32 {
-    if score >= MATE_TT_THRESHOLD {
+    if score >= DECISIVE_TT_THRESHOLD {
         score - ply
-    } else if score <= -MATE_TT_THRESHOLD {
+    } else if score <= -DECISIVE_TT_THRESHOLD {
         score + ply
     } else {
         score
@@ -140,6 +343,7 @@ pub struct Searcher {
     pub(crate) nodes: u64,
     node_limit: u64,
     deadline: Option<Instant>,
+    shared_deadline: Option<Arc<SharedDeadline>>,
     order_captures: bool,
     use_history: bool,
     threads: usize,
@@ -150,6 +354,7 @@ pub struct Searcher {
     use_lmr: bool,
     use_killers: bool,
     use_nullmove: bool,
+    config: SearchConfig,
     // Optional NNUE evaluator (scalar path for now)
     use_nnue: bool,
     nnue: Option<crate::eval::nnue::Nnue>,
@@ -166,6 +371,21 @@ pub struct Searcher {
     max_seldepth: u32,
     root_history: Vec<Board>,
     search_history: Vec<Board>,
+    info_tx: Option<mpsc::Sender<SearchInfo>>,
+    // Root moves skipped by the current iteration (MultiPV lines already found)
+    root_excluded: Vec<Move>,
+    // Root moves the whole search is restricted to (`go searchmoves`)
+    root_allowed: Option<RootMoveFilter>,
+    // Syzygy tables, shared with every Lazy SMP helper
+    tablebases: Option<Arc<Tablebases>>,
+    // Interior probe gating: least depth left at the largest piece count
+    // probed, and the largest piece count probed at all
+    tb_probe_depth: u32,
+    tb_probe_limit: usize,
+    tb_hits: u64,
+    // Tablebase result at the root of the current search, reported in
+    // place of the search score
+    tb_root_score: Option<i32>,
 }
 
 impl Default for Searcher {
@@ -177,6 +397,7 @@ impl Default for Searcher {
             nodes: 0,
             node_limit: u64::MAX,
             deadline: None,
+            shared_deadline: None,
             order_captures: false,
             use_history: false,
             threads: 1,
@@ -187,6 +408,7 @@ impl Default for Searcher {
             use_lmr: false,
             use_killers: false,
             use_nullmove: false,
+            config: SearchConfig::default(),
             use_nnue: false,
             nnue: None,
             nnue_quant: None,
@@ -199,6 +421,14 @@ impl Default for Searcher {
             max_seldepth: 0,
             root_history: Vec::new(),
             search_history: Vec::new(),
+            info_tx: None,
+            root_excluded: Vec::new(),
+            root_allowed: None,
+            tablebases: None,
+            tb_probe_depth: DEFAULT_TB_PROBE_DEPTH,
+            tb_probe_limit: DEFAULT_TB_PROBE_LIMIT,
+            tb_hits: 0,
+            tb_root_score: None,
         }
     }
 }
@@ -226,6 +456,45 @@ impl Searcher {
         self.external_stop = None;
     }
 
+    /// Install a deadline that can be set after the search has started, on
+    /// top of any `movetime` in `SearchParams`. Lazy SMP helpers share it.
+    pub fn set_shared_deadline(&mut self, deadline: Option<Arc<SharedDeadline>>) {
+        self.shared_deadline = deadline;
+    }
+
+    /// Probe `tables` during search and restrict the root to the moves that
+    /// keep the tablebase result, once few enough pieces are left.
+    pub fn set_tablebases(&mut self, tables: Option<Arc<Tablebases>>) {
+        self.tablebases = tables;
+    }
+
+    /// Interior nodes with as many pieces as the largest table probed (the
+    /// most expensive probes, and the most frequent in an endgame just
+    /// inside the tables) are only probed with at least `depth` plies left;
+    /// smaller endings are always probed.
+    pub fn set_tablebase_probe_depth(&mut self, depth: u32) {
+        self.tb_probe_depth = depth;
+    }
+
+    /// Never probe positions with more than `pieces` pieces, at the root or
+    /// in the search.
+    pub fn set_tablebase_probe_limit(&mut self, pieces: usize) {
+        self.tb_probe_limit = pieces;
+    }
+
+    /// Tablebase probes that returned a result in the last search, main
+    /// thread only.
+    pub fn tb_hits(&self) -> u64 {
+        self.tb_hits
+    }
+
+    /// Publish a `SearchInfo` after every completed iteration of
+    /// `search_with_params`. Send failures are ignored: a listener that went
+    /// away must not abort the search it was watching.
+    pub fn set_info_sender(&mut self, tx: Option<mpsc::Sender<SearchInfo>>) {
+        self.info_tx = tx;
+    }
+
     /// Build a Lazy SMP helper that shares this searcher's transposition table.
     ///
     /// Helpers are fully independent searchers: they run their own iterative
@@ -236,11 +505,17 @@ impl Searcher {
         let mut helper = Searcher::default();
         helper.node_limit = self.node_limit;
         helper.deadline = self.deadline;
+        helper.shared_deadline.clone_from(&self.shared_deadline);
+        helper.root_allowed = self.root_allowed;
+        helper.tablebases.clone_from(&self.tablebases);
+        helper.tb_probe_depth = self.tb_probe_depth;
+        helper.tb_probe_limit = self.tb_probe_limit;
         helper.order_captures = self.order_captures;
         helper.use_history = self.use_history;
         helper.use_killers = self.use_killers;
         helper.use_lmr = self.use_lmr;
         helper.use_nullmove = self.use_nullmove;
+        helper.config.clone_from(&self.config);
         helper.eval_mode = self.eval_mode;
         helper.eval_blend_percent = self.eval_blend_percent;
         helper.use_nnue = self.use_nnue;
@@ -256,24 +531,34 @@ impl Searcher {
     }
 
     /// Drive one Lazy SMP helper's own iterative deepening to exhaustion or
-    /// stop. The return value is its node count; its scores are deliberately
+    /// stop. Its node count is added to `nodes_sink` after every iteration so
+    /// progress reports can include it; its scores are deliberately
     /// discarded -- only what it wrote to the shared TT matters.
-    fn run_lazy_helper(mut helper: Searcher, root: &Board, index: usize, max_depth: u32) -> u64 {
+    fn run_lazy_helper(
+        mut helper: Searcher,
+        root: &Board,
+        index: usize,
+        max_depth: u32,
+        nodes_sink: &AtomicU64,
+    ) {
         // Half the helpers run one iteration ahead so the pool spreads across
         // the schedule instead of every thread redoing the depth the main
         // thread is already on.
         let skew = (index % 2) as u32;
+        let mut published = 0u64;
         for d in 1..=max_depth {
             let target = d + skew;
             if target > max_depth {
                 break;
             }
             helper.prepare_root_state(root);
-            if helper.search_depth_internal(root, target).is_err() {
+            let outcome = helper.search_depth_internal(root, target);
+            nodes_sink.fetch_add(helper.nodes - published, Ordering::Relaxed);
+            published = helper.nodes;
+            if outcome.is_err() {
                 break;
             }
         }
-        helper.nodes
     }
 
     /// How many Lazy SMP helpers may run alongside the main search.
@@ -342,6 +627,13 @@ impl Searcher {
         {
             return Err(SearchAbort::Limit);
         }
+        if self
+            .shared_deadline
+            .as_ref()
+            .is_some_and(|deadline| deadline.reached())
+        {
+            return Err(SearchAbort::Limit);
+        }
         Ok(())
     }
 
@@ -355,7 +647,8 @@ impl Searcher {
 
     fn fallback_result(&mut self, board: &Board) -> SearchResult {
         self.prepare_root_state(board);
-        let moves = self.debug_order_root(board);
+        let mut moves = self.debug_order_root(board);
+        self.filter_root_moves(&mut moves);
         let score_cp = if moves.is_empty() {
             self.eval_terminal(board, 0)
         } else if self.rule_draw(board) {
@@ -367,6 +660,11 @@ impl Searcher {
             bestmove: moves.first().map(|mv| format!("{mv}")),
             score_cp,
             nodes: self.nodes,
+            pv: moves
+                .first()
+                .map(|mv| vec![format!("{mv}")])
+                .unwrap_or_default(),
+            lines: Vec::new(),
         }
     }
 
@@ -392,6 +690,14 @@ impl Searcher {
     pub fn set_use_nullmove(&mut self, on: bool) {
         self.use_nullmove = on;
     }
+    /// Replace the tunable heuristics (LMR, futility, null move, history,
+    /// capture ordering); the default is the accepted baseline.
+    pub fn set_search_config(&mut self, config: SearchConfig) {
+        self.config = config;
+    }
+    pub fn search_config(&self) -> &SearchConfig {
+        &self.config
+    }
     pub fn set_use_aspiration(&mut self, on: bool) {
         self.use_aspiration = on;
     }
@@ -435,6 +741,8 @@ impl Searcher {
         self.last_depth = 0;
         self.max_seldepth = 0;
         self.abort = None;
+        self.root_allowed = None;
+        self.tb_hits = 0;
         self.node_limit = u64::MAX;
         self.deadline = Some(Instant::now() + Duration::from_millis(millis));
         self.prepare_root_state(board);
@@ -463,8 +771,7 @@ impl Searcher {
                 let root = board.clone();
                 let nodes_sink = &helper_nodes;
                 scope.spawn(move || {
-                    let searched = Self::run_lazy_helper(helper, &root, index, max_depth);
-                    nodes_sink.fetch_add(searched, Ordering::Relaxed);
+                    Self::run_lazy_helper(helper, &root, index, max_depth, nodes_sink);
                 });
             }
 
@@ -601,6 +908,8 @@ impl Searcher {
         self.node_limit = u64::MAX;
         self.deadline = None;
         self.abort = None;
+        self.root_allowed = None;
+        self.tb_hits = 0;
         self.last_depth = 0;
         self.max_seldepth = 0;
         self.prepare_root_state(board);
@@ -656,6 +965,18 @@ impl Searcher {
                 bestmove: None,
                 score_cp: self.eval_terminal(board, 0),
                 nodes: self.nodes,
+                pv: Vec::new(),
+                lines: Vec::new(),
+            });
+        }
+        self.filter_root_moves(&mut moves);
+        if moves.is_empty() {
+            return Ok(SearchResult { depth: 0,
+                bestmove: None,
+                score_cp: -MATE_SCORE,
+                nodes: self.nodes,
+                pv: Vec::new(),
+                lines: Vec::new(),
             });
         }
         if self.rule_draw(board) {
@@ -663,6 +984,8 @@ impl Searcher {
                 bestmove: moves.first().map(|mv| format!("{mv}")),
                 score_cp: DRAW_SCORE,
                 nodes: self.nodes,
+                pv: Vec::new(),
+                lines: Vec::new(),
             });
         }
         // TT-first
@@ -781,13 +1104,19 @@ impl Searcher {
         } else {
             Bound::Exact
         };
-        self.tt_put(board, depth, best_score, bestmove, root_bound, 0);
+        // A MultiPV pass that skipped the best moves has not scored this
+        // position; storing it would also demote the real best move.
+        if self.root_excluded.is_empty() {
+            self.tt_put(board, depth, best_score, bestmove, root_bound, 0);
+        }
 
         let bestmove_uci = bestmove.map(|m| format!("{m}"));
         Ok(SearchResult { depth: 0,
             bestmove: bestmove_uci,
             score_cp: best_score,
             nodes: self.nodes,
+            pv: Vec::new(),
+            lines: Vec::new(),
         })
     }
 
@@ -820,19 +1149,30 @@ impl Searcher {
                 self.eval_terminal(board, ply)
             });
         }
+        if let Some((score, bound)) = self.probe_tablebase(board, depth, ply) {
+            let cutoff = match bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                self.tt_put(board, depth + TB_PROBE_DEPTH_BONUS, score, None, bound, ply);
+                return Ok(score);
+            }
+        }
         // Reverse futility: at shallow non-mate-window nodes not in check, a
         // static eval comfortably above beta almost never comes back below it
         // after a real search; return the eval as a fail-soft bound. The
         // margin grows with depth so deeper nodes need a bigger cushion.
         let mut static_eval: Option<i32> = None;
         if self.use_nullmove
-            && depth <= 7
+            && depth <= self.config.reverse_futility_max_depth
             && beta.abs() < MATE_TT_THRESHOLD
             && alpha.abs() < MATE_TT_THRESHOLD
             && board.checkers().is_empty()
         {
             let eval = *static_eval.get_or_insert_with(|| self.eval_current(board));
-            if eval - 90 * depth as i32 >= beta {
+            if eval - self.config.reverse_futility_margin_cp * depth as i32 >= beta {
                 return Ok(eval);
             }
         }
@@ -936,7 +1276,7 @@ impl Searcher {
                 };
                 let see_b = see_raw / 8;
                 let good_cap = if is_cap == 1 && see_raw >= 0 {
-                    GOOD_CAPTURE_PRIORITY
+                    self.config.good_capture_priority
                 } else {
                     0
                 };
@@ -994,10 +1334,11 @@ impl Searcher {
             // whose parent static eval plus a depth-scaled margin still cannot
             // reach alpha is skipped before paying eval-update and child-search
             // costs. The first move is always searched; mate windows and
-            // in-check parents are exempt.
+            // in-check parents are exempt. Depth 0 has already dropped into
+            // qsearch, so depth d uses margin d - 1.
             if self.use_nullmove
                 && idx > 0
-                && depth <= 3
+                && depth as usize <= self.config.futility_margins_cp.len()
                 && !gives_check
                 && alpha.abs() < MATE_TT_THRESHOLD
                 && beta.abs() < MATE_TT_THRESHOLD
@@ -1005,9 +1346,9 @@ impl Searcher {
                 && !self.is_capture(board, m)
                 && m.promotion.is_none()
             {
-                const FUTILITY_MARGIN: [i32; 4] = [0, 120, 200, 280];
+                let margin = self.config.futility_margins_cp[depth as usize - 1];
                 let eval = *static_eval.get_or_insert_with(|| self.eval_current(board));
-                if eval + FUTILITY_MARGIN[depth as usize] <= alpha {
+                if eval + margin <= alpha {
                     continue;
                 }
             }
@@ -1038,12 +1379,12 @@ impl Searcher {
                     .map(|value| -value);
             } else {
                 let r = if self.use_lmr
-                    && depth >= 3
-                    && idx >= 3
+                    && depth >= self.config.lmr_min_depth
+                    && idx >= self.config.lmr_min_move_index
                     && !gives_check
                     && !self.is_capture(board, m)
                 {
-                    Self::lmr_reduction(depth, idx)
+                    self.config.lmr_reduction(depth, idx)
                 } else {
                     0
                 };
@@ -1127,11 +1468,14 @@ impl Searcher {
             // have always gated on Bound::Lower; history now matches them.
             let quiet = !self.is_capture(board, mv) && mv.promotion.is_none();
             if self.use_history && bound == Bound::Lower && quiet {
-                let bonus = ((depth as i32) * (depth as i32)).min(HIST_MAX);
+                let hist_max = self.config.history_max;
+                let bonus = self.config.history_bonus(depth);
+                let malus = self.config.history_malus(depth);
                 if let Some(h) = self.history_table.get_mut(mi) {
                     // Gravity: the increment shrinks as the entry approaches
-                    // HIST_MAX, so the table saturates instead of diverging.
-                    *h += bonus - (*h * bonus / HIST_MAX);
+                    // the history bound, so the table saturates instead of
+                    // diverging.
+                    *h += bonus - (*h * bonus / hist_max);
                 }
                 for &q in &tried_quiets {
                     if q == mv {
@@ -1139,7 +1483,7 @@ impl Searcher {
                     }
                     let qi = move_index(q);
                     if let Some(h) = self.history_table.get_mut(qi) {
-                        *h -= bonus + (*h * bonus / HIST_MAX);
+                        *h -= malus + (*h * malus / hist_max);
                     }
                 }
             }
@@ -1234,43 +1578,9 @@ impl Searcher {
         }
     }
 
-    /// Late-move reduction depth, as ln(depth) * ln(move_index) / 2.25.
-    ///
-    /// The flat reduction of 1 this replaces spent the same search effort on
-    /// the 4th move as on the 40th. A log-log schedule reduces late moves
-    /// harder while staying gentle near the front of the list, where ordering
-    /// is most likely to be right.
-    ///
-    /// Clamped to at least 1 (callers only ask when they intend to reduce)
-    /// and to at most `depth - 2`, so the child keeps a depth of >= 1 and
-    /// never collapses straight into quiescence.
-    fn lmr_reduction(depth: u32, idx: usize) -> u32 {
-        let ld = f64::from(depth).ln();
-        let li = (idx as f64).ln();
-        let raw = (ld * li / 2.25) as u32;
-        raw.clamp(1, depth.saturating_sub(2).max(1))
-    }
-
     fn null_move_reduction(&self, depth: u32, eval: i32, beta: i32) -> u32 {
-        let mut r = if depth <= 4 { 1 } else { 2 };
-        if depth >= 8 {
-            r = 3;
-        }
-        if depth >= 11 {
-            r = 4;
-        }
-        let eval_margin = eval - beta;
-        if eval_margin > 300 {
-            r += 1;
-        }
-        if eval_margin > 600 {
-            r += 1;
-        }
-        if depth <= 12 && r > 2 {
-            r = 2;
-        }
-        r = r.min(depth.saturating_sub(1));
-        r.max(1)
+        let r = self.config.null_move_reduction(depth, eval - beta);
+        r.min(depth.saturating_sub(1)).max(1)
     }
 
     fn should_try_null_move(
@@ -1367,6 +1677,7 @@ impl Searcher {
 mod mate_tt_score_tests {
     use super::{score_from_tt, score_to_tt, Searcher};
     use crate::search::eval::MATE_SCORE;
+    use crate::search::syzygy::{wdl_score, Wdl};
     use crate::search::tt::Bound;
     use cozy_chess::Board;
 
@@ -1383,6 +1694,14 @@ mod mate_tt_score_tests {
         assert_eq!(score_from_tt(loss.score, 8), -MATE_SCORE + 12);
     }
 
+    #[test]
+    fn tablebase_scores_are_adjusted_like_mate_scores() {
+        for wdl in [Wdl::Win, Wdl::Loss] {
+            let stored = score_to_tt(wdl_score(wdl, 3), 3);
+            assert_eq!(score_from_tt(stored, 9), wdl_score(wdl, 9));
+        }
+    }
+
     #[test]
     fn ordinary_centipawn_scores_are_not_ply_adjusted() {
         assert_eq!(score_from_tt(score_to_tt(173, 4), 29), 173);
@@ -1394,6 +1713,32 @@ impl Searcher {
     fn tt_key(board: &Board) -> u64 {
         zobrist::compute(board)
     }
+    /// WDL probe for an interior node. Only positions right after a capture or
+    /// pawn move are probed: there the WDL tables are exact under the
+    /// fifty-move rule, and every other position reaches one of them within
+    /// the search anyway. Wins and losses are bounds, since a real mate found
+    /// by the search scores beyond them; draws are exact. Gated by
+    /// `set_tablebase_probe_depth` and `set_tablebase_probe_limit`.
+    fn probe_tablebase(&mut self, board: &Board, depth: u32, ply: i32) -> Option<(i32, Bound)> {
+        if ply == 0 || board.halfmove_clock() != 0 {
+            return None;
+        }
+        let tables = self.tablebases.as_ref()?;
+        let pieces = board.occupied().len() as usize;
+        let cardinality = tables.max_pieces().min(self.tb_probe_limit);
+        if pieces > cardinality || (pieces == cardinality && depth < self.tb_probe_depth) {
+            return None;
+        }
+        let wdl = tables.probe_wdl_after_zeroing(board)?;
+        self.tb_hits += 1;
+        let bound = match wdl {
+            Wdl::Win => Bound::Lower,
+            Wdl::Loss => Bound::Upper,
+            _ => Bound::Exact,
+        };
+        Some((wdl_score(wdl, ply), bound))
+    }
+
     fn tt_get(&self, board: &Board) -> Option<Entry> {
         self.tt.get(Self::tt_key(board))
     }
@@ -1425,12 +1770,199 @@ impl Searcher {
         self.tt.put(e);
     }
 
+    /// The principal variation behind `result`: its best move followed by the
+    /// best moves the TT holds for each successive position. The walk stops
+    /// at a missing or illegal entry, a position already on the line, or
+    /// `max_len` moves, so a TT that lost part of the line yields a shorter
+    /// but still legal PV.
+    fn principal_variation(
+        &self,
+        board: &Board,
+        result: &SearchResult,
+        max_len: u32,
+    ) -> Vec<String> {
+        let mut pv = Vec::new();
+        let Some(first) = result.bestmove.as_deref() else {
+            return pv;
+        };
+        let mut current = board.clone();
+        let mut seen = vec![zobrist::compute(&current)];
+        let mut next = legal_move_from_uci(&current, first);
+        while let Some(mv) = next {
+            pv.push(format!("{mv}"));
+            current.play_unchecked(mv);
+            let key = zobrist::compute(&current);
+            if pv.len() >= max_len.max(1) as usize || seen.contains(&key) {
+                break;
+            }
+            seen.push(key);
+            next = self
+                .tt
+                .get(key)
+                .and_then(|entry| entry.best)
+                .filter(|&mv| current.is_legal(mv));
+        }
+        pv
+    }
+
+    /// Publish one `SearchInfo` per ranked root line of `result`.
+    fn report_iteration(&self, result: &SearchResult, started: Instant, helper_nodes: &AtomicU64) {
+        let Some(tx) = self.info_tx.as_ref() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        let nodes = self.nodes + helper_nodes.load(Ordering::Relaxed);
+        let micros = elapsed.as_micros().max(1);
+        let hashfull = self.tt.hashfull();
+        for (index, line) in result.lines.iter().enumerate() {
+            let _ = tx.send(SearchInfo {
+                multipv: index + 1,
+                depth: line.depth,
+                seldepth: self.max_seldepth.max(line.depth),
+                score: InfoScore::from_score(self.reported_score(line.score_cp)),
+                nodes,
+                nps: (u128::from(nodes) * 1_000_000 / micros) as u64,
+                time_ms: elapsed.as_millis() as u64,
+                hashfull,
+                tbhits: self.tb_hits,
+                pv: line.pv.clone(),
+            });
+        }
+    }
+
+    /// Legal root moves left after `searchmoves`; the time manager stops at
+    /// once when there is only one.
+    fn root_move_count(&self, board: &Board) -> usize {
+        self.root_moves(board).len()
+    }
+
+    fn root_moves(&self, board: &Board) -> Vec<Move> {
+        let mut moves: Vec<Move> = Vec::with_capacity(64);
+        board.generate_moves(|ml| {
+            moves.extend(ml);
+            false
+        });
+        self.filter_root_moves(&mut moves);
+        moves
+    }
+
+    /// Restrict the root to the moves that keep the tablebase result and
+    /// remember that result as the score to report. The search still picks
+    /// among those moves, so it can find a mate the tables only call a win.
+    fn apply_tablebase_root(&mut self, board: &Board) {
+        self.tb_root_score = None;
+        let Some(tables) = self.tablebases.clone() else {
+            return;
+        };
+        if board.occupied().len() as usize > self.tb_probe_limit {
+            return;
+        }
+        let candidates = self.root_moves(board);
+        if let Some(probe) = tables.rank_root_moves(board, &candidates) {
+            self.tb_hits += 1;
+            self.root_allowed = Some(RootMoveFilter::new(&probe.moves));
+            self.tb_root_score = Some(probe.score);
+        }
+    }
+
+    /// The score to report for a root line: the tablebase result once the
+    /// root was probed, unless the search found a mate.
+    fn reported_score(&self, score_cp: i32) -> i32 {
+        match self.tb_root_score {
+            Some(tb) if score_cp.abs() < MATE_TT_THRESHOLD => tb,
+            _ => score_cp,
+        }
+    }
+
+    /// Drop root moves outside `searchmoves`, then those the current
+    /// iteration must skip: MultiPV lines already ranked at this depth.
+    fn filter_root_moves(&self, moves: &mut Vec<Move>) {
+        if let Some(allowed) = self.root_allowed.as_ref() {
+            if moves.iter().any(|&mv| allowed.contains(mv)) {
+                moves.retain(|&mv| allowed.contains(mv));
+            }
+        }
+        if !self.root_excluded.is_empty() {
+            moves.retain(|mv| !self.root_excluded.contains(mv));
+        }
+    }
+
+    /// One MultiPV iteration: search the root up to `count` times at `depth`,
+    /// each pass excluding the moves already ranked, so every pass finds the
+    /// best of the remaining moves with a full window. Returns the lines
+    /// found, best first, and whether the iteration ran to completion.
+    fn search_multipv_iteration(
+        &mut self,
+        board: &Board,
+        depth: u32,
+        count: usize,
+    ) -> (Vec<RootLine>, Result<(), SearchAbort>) {
+        let mut lines: Vec<RootLine> = Vec::with_capacity(count);
+        let mut outcome = Ok(());
+        self.root_excluded.clear();
+        while lines.len() < count {
+            self.prepare_root_state(board);
+            let result = match self.search_depth_internal(board, depth) {
+                Ok(result) => result,
+                Err(abort) => {
+                    outcome = Err(abort);
+                    break;
+                }
+            };
+            let Some(mv) = result
+                .bestmove
+                .as_deref()
+                .and_then(|uci| legal_move_from_uci(board, uci))
+            else {
+                break;
+            };
+            self.root_excluded.push(mv);
+            lines.push(RootLine {
+                mv: format!("{mv}"),
+                score_cp: result.score_cp,
+                pv: self.principal_variation(board, &result, depth),
+                depth,
+            });
+        }
+        self.root_excluded.clear();
+        // Passes are searched best-first, but search instability can still
+        // score a later pass higher; rank by the scores actually returned.
+        lines.sort_by_key(|line| std::cmp::Reverse(line.score_cp));
+        (lines, outcome)
+    }
+
+    /// Result for a MultiPV iteration. When the iteration was interrupted,
+    /// `fresh` holds only the lines it finished; the remaining slots keep
+    /// the previous iteration's lines for the moves not yet re-searched.
+    fn multipv_result(fresh: Vec<RootLine>, previous: &[RootLine], count: usize) -> SearchResult {
+        let mut lines = fresh;
+        for line in previous {
+            if lines.len() >= count {
+                break;
+            }
+            if !lines.iter().any(|l| l.mv == line.mv) {
+                lines.push(line.clone());
+            }
+        }
+        let best = lines.first().cloned().unwrap_or_default();
+        SearchResult {
+            bestmove: Some(best.mv),
+            score_cp: best.score_cp,
+            pv: best.pv,
+            lines,
+            ..Default::default()
+        }
+    }
+
     pub fn search_with_params(&mut self, board: &Board, params: SearchParams) -> SearchResult {
         // Configure this search
         self.nodes = 0;
         self.last_depth = 0;
         self.max_seldepth = 0;
         self.abort = None;
+        self.root_allowed = params.searchmoves;
+        self.tb_hits = 0;
+        self.apply_tablebase_root(board);
         self.node_limit = params.max_nodes.unwrap_or(u64::MAX);
         if !params.use_tt {
             self.tt = Arc::new(Tt::new());
@@ -1452,7 +1984,20 @@ impl Searcher {
                 *c = usize::MAX;
             }
         }
-        self.deadline = params.movetime.map(|d| Instant::now() + d);
+        let started = Instant::now();
+        let hard_deadline = [
+            params.movetime.map(|d| started + d),
+            params.time.map(|limits| started + limits.hard),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        // Helpers are built below with this deadline; they only ever need the
+        // hard one because the main thread stops them when it returns.
+        self.deadline = hard_deadline;
+        let mut time_manager = params
+            .time
+            .map(|limits| TimeManager::new(limits, self.root_move_count(board)));
         self.prepare_root_state(board);
         let max_depth = if params.depth == 0 { 99 } else { params.depth };
 
@@ -1469,13 +2014,35 @@ impl Searcher {
             let root = board.clone();
             let nodes_sink = &helper_nodes;
             scope.spawn(move || {
-                let searched = Self::run_lazy_helper(helper, &root, index, max_depth);
-                nodes_sink.fetch_add(searched, Ordering::Relaxed);
+                Self::run_lazy_helper(helper, &root, index, max_depth, nodes_sink);
             });
         }
         let mut committed = self.fallback_result(board);
         for d in 1..=max_depth {
+            if let Some(tm) = time_manager.as_ref() {
+                if tm.should_stop(started.elapsed()) {
+                    break;
+                }
+                self.deadline = hard_deadline.map(|hard| hard.min(started + tm.iteration_limit()));
+            }
             self.tt.bump_generation();
+            if params.multipv > 1 {
+                let (fresh, outcome) = self.search_multipv_iteration(board, d, params.multipv);
+                if !fresh.is_empty() {
+                    committed = Self::multipv_result(fresh, &committed.lines, params.multipv);
+                    if outcome.is_ok() {
+                        self.last_depth = d;
+                        if let Some(tm) = time_manager.as_mut() {
+                            tm.on_iteration(d, committed.bestmove.as_deref(), committed.score_cp);
+                        }
+                    }
+                    self.report_iteration(&committed, started, &helper_nodes);
+                }
+                if outcome.is_err() || committed.lines.is_empty() {
+                    break;
+                }
+                continue;
+            }
             self.prepare_root_state(board);
             let iteration = if self.use_aspiration && d > 1 {
                 let window = params.aspiration_window_cp.max(10);
@@ -1483,6 +2050,14 @@ impl Searcher {
                 let beta = committed.score_cp + window;
                 match self.search_depth_window(board, d, alpha, beta) {
                     Ok(result) if result.score_cp <= alpha || result.score_cp >= beta => {
+                        // Do not cut a fail-low re-search off at the soft
+                        // target: the move about to be played was just refuted.
+                        let failed_low = result.score_cp <= alpha;
+                        if let Some(tm) = time_manager.as_mut().filter(|_| failed_low) {
+                            tm.on_fail_low();
+                            let limit = started + tm.iteration_limit();
+                            self.deadline = hard_deadline.map(|hard| hard.min(limit));
+                        }
                         self.prepare_root_state(board);
                         self.search_depth_internal(board, d)
                     }
@@ -1494,7 +2069,22 @@ impl Searcher {
             match iteration {
                 Ok(result) => {
                     committed = result;
+                    committed.pv = self.principal_variation(board, &committed, d);
+                    committed.lines = committed
+                        .bestmove
+                        .iter()
+                        .map(|mv| RootLine {
+                            mv: mv.clone(),
+                            score_cp: committed.score_cp,
+                            pv: committed.pv.clone(),
+                            depth: d,
+                        })
+                        .collect();
                     self.last_depth = d;
+                    if let Some(tm) = time_manager.as_mut() {
+                        tm.on_iteration(d, committed.bestmove.as_deref(), committed.score_cp);
+                    }
+                    self.report_iteration(&committed, started, &helper_nodes);
                 }
                 Err(_) => break,
             }
@@ -1506,6 +2096,10 @@ impl Searcher {
         self.nodes += helper_nodes.load(Ordering::Relaxed);
         committed.nodes = self.nodes;
         committed.depth = self.last_depth;
+        committed.score_cp = self.reported_score(committed.score_cp);
+        for line in &mut committed.lines {
+            line.score_cp = self.reported_score(line.score_cp);
+        }
         committed
     }
 
@@ -1543,6 +2137,18 @@ impl Searcher {
                 bestmove: None,
                 score_cp: self.eval_terminal(board, 0),
                 nodes: self.nodes,
+                pv: Vec::new(),
+                lines: Vec::new(),
+            });
+        }
+        self.filter_root_moves(&mut moves);
+        if moves.is_empty() {
+            return Ok(SearchResult { depth: 0,
+                bestmove: None,
+                score_cp: -MATE_SCORE,
+                nodes: self.nodes,
+                pv: Vec::new(),
+                lines: Vec::new(),
             });
         }
         if self.rule_draw(board) {
@@ -1550,6 +2156,8 @@ impl Searcher {
                 bestmove: moves.first().map(|mv| format!("{mv}")),
                 score_cp: DRAW_SCORE,
                 nodes: self.nodes,
+                pv: Vec::new(),
+                lines: Vec::new(),
             });
         }
         if let Some(en) = self.tt_get(board) {
@@ -1651,6 +2259,8 @@ impl Searcher {
             bestmove: bestmove_uci,
             score_cp: best_score,
             nodes: self.nodes,
+            pv: Vec::new(),
+            lines: Vec::new(),
         })
     }
 
@@ -1711,6 +2321,11 @@ impl Searcher {
         self.tt = Arc::new(tt);
     }
 
+    /// Transposition-table occupancy in permille, for UCI `info hashfull`.
+    pub fn hashfull(&self) -> u32 {
+        self.tt.hashfull()
+    }
+
     pub fn debug_order_root(&self, board: &Board) -> Vec<Move> {
         let mut moves: Vec<Move> = Vec::with_capacity(64);
         board.generate_moves(|ml| {
@@ -1964,11 +2579,15 @@ mod draw_policy_regressions {
 mod lmr_reduction_schedule {
     use super::*;
 
+    fn lmr(depth: u32, idx: usize) -> u32 {
+        SearchConfig::default().lmr_reduction(depth, idx)
+    }
+
     #[test]
     fn never_reduces_below_one_where_the_caller_intends_to_reduce() {
         for depth in 3..=32u32 {
             for idx in 3..64usize {
-                assert!(Searcher::lmr_reduction(depth, idx) >= 1);
+                assert!(lmr(depth, idx) >= 1);
             }
         }
     }
@@ -1979,7 +2598,7 @@ mod lmr_reduction_schedule {
         // in practice >= 1, or a reduced search degenerates into qsearch.
         for depth in 3..=32u32 {
             for idx in 3..64usize {
-                let r = Searcher::lmr_reduction(depth, idx);
+                let r = lmr(depth, idx);
                 assert!(r <= depth - 2, "depth {depth} idx {idx} gave r={r}");
             }
         }
@@ -1989,10 +2608,7 @@ mod lmr_reduction_schedule {
     fn reduces_later_moves_at_least_as_hard_as_earlier_ones() {
         for depth in 3..=32u32 {
             for idx in 4..64usize {
-                assert!(
-                    Searcher::lmr_reduction(depth, idx)
-                        >= Searcher::lmr_reduction(depth, idx - 1)
-                );
+                assert!(lmr(depth, idx) >= lmr(depth, idx - 1));
             }
         }
     }
@@ -2001,14 +2617,14 @@ mod lmr_reduction_schedule {
     fn actually_exceeds_the_flat_reduction_it_replaces() {
         // The point of the change: deep, late moves must reduce by more than
         // the flat 1. If this fails the schedule is a no-op.
-        assert!(Searcher::lmr_reduction(10, 10) > 1);
-        assert!(Searcher::lmr_reduction(20, 30) > Searcher::lmr_reduction(10, 10));
+        assert!(lmr(10, 10) > 1);
+        assert!(lmr(20, 30) > lmr(10, 10));
     }
 
     #[test]
     fn stays_gentle_at_the_front_of_the_move_list() {
         // Early moves are where ordering is most likely correct, so the
         // schedule must not reduce them aggressively.
-        assert_eq!(Searcher::lmr_reduction(8, 3), 1);
+        assert_eq!(lmr(8, 3), 1);
     }
 }
@@ -2,4 +2,7 @@
 //!
 //! Between search experiments this re-exports the accepted baseline so the
 //! next change begins from an exact, buildable reference implementation.
+//! Only structural changes need a fork here: an experiment that moves a
+//! heuristic's numbers (see `search::config`) is a JSON file passed to
+//! `compare_play --exp-config`, played against the unchanged baseline.
 pub use super::alphabeta::*;
@@ -0,0 +1,256 @@
+//! Tunable search heuristics.
+//!
+//! `SearchConfig` holds the numbers the search heuristics are built around:
+//! the late-move reduction schedule, reverse-futility and futility margins,
+//! the null-move reduction, the history bonus and malus, and the band that
+//! lifts winning captures above quiet moves. Its `Default` is the accepted
+//! baseline, so a searcher that is never given a config searches exactly as
+//! before.
+//!
+//! An experiment that only moves one of these numbers is a JSON file rather
+//! than a fork of `alphabeta.rs`: `compare_play --base-config a.json
+//! --exp-config b.json` plays two configurations of the same search against
+//! each other. A file only needs the fields it changes; the rest keep their
+//! defaults. Unknown fields are rejected, so a misspelt key cannot silently
+//! run the baseline.
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// History saturation bound. Without one, `h += depth*depth` grows without
+/// limit: measured in a single 150 ms search the table reached max 11,390-17,109
+/// while the capture ordering band tops out near 10,012, so 54.1% of depth-10
+/// nodes ordered a quiet ahead of a WINNING capture. Gravity keeps the table
+/// inside the band the ordering formula was designed around.
+const HIST_MAX: i32 = 16_384;
+/// Ordering is a flat sum, so the terms have to be scaled against each
+/// other. Measured ceilings: a capture reaches 1000 + mvv(~9000) + see/8
+/// ~= 10,112, while a quiet reaches killer(50) + history(16,384) +
+/// counter(40) = 16,474. A saturated-history quiet therefore outranks the
+/// BEST capture on the board -- measured at 54.1% of depth-10 nodes
+/// ordering a quiet ahead of a winning capture.
+///
+/// Lifting SEE>=0 captures above the quiet ceiling restores the invariant
+/// that a capture which wins material is tried first. Losing captures are
+/// deliberately NOT lifted: a quiet with real history should be searched
+/// before a capture that hangs a piece.
+const GOOD_CAPTURE_PRIORITY: i32 = 20_000;
+/// Largest `history_max` the gravity update can use without overflowing:
+/// it multiplies an entry by a bonus, both bounded by `history_max`.
+const HISTORY_MAX_LIMIT: i32 = 32_768;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    /// Late moves are reduced from this depth on (at least 2, so the
+    /// reduced child never goes below depth 0).
+    pub lmr_min_depth: u32,
+    /// Zero-based index of the first move that may be reduced.
+    pub lmr_min_move_index: usize,
+    /// Divisor of the `ln(depth) * ln(move_index)` reduction schedule;
+    /// smaller reduces harder.
+    pub lmr_divisor: f64,
+    /// Reverse futility applies at depths up to this; 0 disables it.
+    pub reverse_futility_max_depth: u32,
+    /// Reverse-futility margin per ply of remaining depth, in centipawns.
+    pub reverse_futility_margin_cp: i32,
+    /// Futility margin for quiet moves at depth 1, 2, ...; futility pruning
+    /// only runs at depths that have a margin, so `[]` disables it.
+    pub futility_margins_cp: Vec<i32>,
+    /// Null-move reduction at depths 5-7. It is one less at depth 4 and
+    /// below, one more from depth 8 and two more from depth 11.
+    pub null_move_base_r: u32,
+    /// One extra ply of null-move reduction for each full step of static
+    /// eval above beta, up to two steps.
+    pub null_move_eval_step_cp: i32,
+    /// Up to this depth the null-move reduction is capped at
+    /// `null_move_capped_r`.
+    pub null_move_cap_max_depth: u32,
+    /// Largest null-move reduction at or below `null_move_cap_max_depth`.
+    pub null_move_capped_r: u32,
+    /// History saturation bound (see the gravity update in the search).
+    pub history_max: i32,
+    /// Fail-high bonus for the cutoff quiet, as a percentage of depth².
+    pub history_bonus_percent: i32,
+    /// Malus for the quiets searched before the cutoff move, as a
+    /// percentage of depth²; 0 disables the malus.
+    pub history_malus_percent: i32,
+    /// Ordering bonus for captures with SEE >= 0; 0 leaves winning captures
+    /// in the MVV/SEE band below saturated-history quiets.
+    pub good_capture_priority: i32,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            lmr_min_depth: 3,
+            lmr_min_move_index: 3,
+            lmr_divisor: 2.25,
+            reverse_futility_max_depth: 7,
+            reverse_futility_margin_cp: 90,
+            futility_margins_cp: vec![120, 200, 280],
+            null_move_base_r: 2,
+            null_move_eval_step_cp: 300,
+            null_move_cap_max_depth: 12,
+            null_move_capped_r: 2,
+            history_max: HIST_MAX,
+            history_bonus_percent: 100,
+            history_malus_percent: 100,
+            good_capture_priority: GOOD_CAPTURE_PRIORITY,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Read a config from a JSON file; missing fields keep their defaults.
+    pub fn from_json_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read search config {}: {e}", path.display()))?;
+        Self::from_json_str(&text)
+            .map_err(|e| format!("invalid search config {}: {e}", path.display()))
+    }
+
+    pub fn from_json_str(text: &str) -> Result<Self, String> {
+        let config: Self = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reject values the search cannot run with.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.lmr_min_depth < 2 {
+            return Err(format!(
+                "lmr_min_depth must be at least 2, got {}",
+                self.lmr_min_depth
+            ));
+        }
+        if !(self.lmr_divisor.is_finite() && self.lmr_divisor > 0.0) {
+            return Err(format!(
+                "lmr_divisor must be positive, got {}",
+                self.lmr_divisor
+            ));
+        }
+        if self.null_move_base_r == 0 || self.null_move_capped_r == 0 {
+            return Err("null-move reductions must be at least 1".to_string());
+        }
+        if !(1..=HISTORY_MAX_LIMIT).contains(&self.history_max) {
+            return Err(format!(
+                "history_max must be in 1..={HISTORY_MAX_LIMIT}, got {}",
+                self.history_max
+            ));
+        }
+        if self.history_bonus_percent < 0 || self.history_malus_percent < 0 {
+            return Err("history bonus and malus percentages cannot be negative".to_string());
+        }
+        Ok(())
+    }
+
+    /// Late-move reduction depth, as ln(depth) * ln(move_index) / divisor.
+    ///
+    /// The flat reduction of 1 this replaces spent the same search effort on
+    /// the 4th move as on the 40th. A log-log schedule reduces late moves
+    /// harder while staying gentle near the front of the list, where ordering
+    /// is most likely to be right.
+    ///
+    /// Clamped to at least 1 (callers only ask when they intend to reduce)
+    /// and to at most `depth - 2`, so the child keeps a depth of >= 1 and
+    /// never collapses straight into quiescence.
+    pub fn lmr_reduction(&self, depth: u32, idx: usize) -> u32 {
+        let ld = f64::from(depth).ln();
+        let li = (idx as f64).ln();
+        let raw = (ld * li / self.lmr_divisor) as u32;
+        raw.clamp(1, depth.saturating_sub(2).max(1))
+    }
+
+    /// Null-move reduction for a node at `depth` whose static eval exceeds
+    /// beta by `eval_margin`, before clamping to the depth available.
+    pub fn null_move_reduction(&self, depth: u32, eval_margin: i32) -> u32 {
+        let base = self.null_move_base_r;
+        let mut r = match depth {
+            0..=4 => base.saturating_sub(1),
+            5..=7 => base,
+            8..=10 => base + 1,
+            _ => base + 2,
+        };
+        if eval_margin > self.null_move_eval_step_cp {
+            r += 1;
+        }
+        if eval_margin > 2 * self.null_move_eval_step_cp {
+            r += 1;
+        }
+        if depth <= self.null_move_cap_max_depth {
+            r = r.min(self.null_move_capped_r);
+        }
+        r
+    }
+
+    /// Fail-high bonus at `depth`, before the gravity update.
+    pub fn history_bonus(&self, depth: u32) -> i32 {
+        self.history_scaled(depth, self.history_bonus_percent)
+    }
+
+    /// Malus for a quiet that was searched but did not cause the cutoff.
+    pub fn history_malus(&self, depth: u32) -> i32 {
+        self.history_scaled(depth, self.history_malus_percent)
+    }
+
+    fn history_scaled(&self, depth: u32, percent: i32) -> i32 {
+        let depth = i64::from(depth);
+        let scaled = depth * depth * i64::from(percent) / 100;
+        scaled.min(i64::from(self.history_max)) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_files_override_only_the_fields_they_name() {
+        let config = SearchConfig::from_json_str(r#"{"lmr_divisor": 2.0}"#).unwrap();
+        assert_eq!(config.lmr_divisor, 2.0);
+        assert_eq!(
+            SearchConfig {
+                lmr_divisor: 2.25,
+                ..config
+            },
+            SearchConfig::default()
+        );
+    }
+
+    #[test]
+    fn typos_and_unusable_values_are_rejected() {
+        assert!(SearchConfig::from_json_str(r#"{"lmr_divisr": 2.0}"#).is_err());
+        assert!(SearchConfig::from_json_str(r#"{"lmr_min_depth": 1}"#).is_err());
+        assert!(SearchConfig::from_json_str(r#"{"lmr_divisor": 0}"#).is_err());
+        assert!(SearchConfig::from_json_str(r#"{"history_max": 0}"#).is_err());
+        assert!(SearchConfig::from_json_str(r#"{"history_max": 100000}"#).is_err());
+        assert!(SearchConfig::from_json_str(r#"{"null_move_base_r": 0}"#).is_err());
+    }
+
+    #[test]
+    fn default_null_move_reduction_matches_the_tuned_schedule() {
+        let config = SearchConfig::default();
+        // Depth <= 12 is capped at 2 (S6); deeper nodes grow with depth and
+        // with the eval margin over beta.
+        assert_eq!(config.null_move_reduction(3, 0), 1);
+        assert_eq!(config.null_move_reduction(4, 301), 2);
+        assert_eq!(config.null_move_reduction(12, 601), 2);
+        assert_eq!(config.null_move_reduction(13, 0), 4);
+        assert_eq!(config.null_move_reduction(13, 301), 5);
+        assert_eq!(config.null_move_reduction(13, 601), 6);
+    }
+
+    #[test]
+    fn history_bonus_scales_with_depth_squared_and_saturates() {
+        let config = SearchConfig {
+            history_bonus_percent: 50,
+            history_malus_percent: 0,
+            ..SearchConfig::default()
+        };
+        assert_eq!(SearchConfig::default().history_bonus(6), 36);
+        assert_eq!(config.history_bonus(6), 18);
+        assert_eq!(config.history_malus(6), 0);
+        assert_eq!(config.history_bonus(1_000), HIST_MAX);
+    }
+}
@@ -2,10 +2,13 @@ pub mod alphabeta;
 #[cfg(feature = "board-pleco")]
 pub mod alphabeta_pleco;
 pub mod alphabeta_temp;
+pub mod config;
 pub mod draw;
 pub mod eval;
 pub mod safety;
 pub mod see;
+pub mod syzygy;
+pub mod time_manager;
 pub mod tt;
 #[cfg(feature = "board-pleco")]
 pub mod tt_pleco;
@@ -0,0 +1,216 @@
+use crate::board::cozy::to_shakmaty;
+use crate::search::eval::MATE_SCORE;
+use cozy_chess::{Board, Color, GameStatus, Move};
+use shakmaty::Chess;
+use shakmaty_syzygy::Tablebase;
+
+pub use shakmaty_syzygy::{AmbiguousWdl, Wdl};
+
+/// Score of a tablebase win at the root. Wins found in the tables are scored
+/// below every mate score and above anything the evaluation produces, so a
+/// real mate found by the search is still preferred.
+pub const TB_WIN_SCORE: i32 = MATE_SCORE - 2_048;
+
+/// A set of Syzygy WDL/DTZ tables loaded from one or more directories.
+pub struct Tablebases {
+    tables: Tablebase<Chess>,
+    files: usize,
+}
+
+/// The outcome of ranking the root moves with the tables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootProbe {
+    /// Moves that keep the best result reachable under the fifty-move rule.
+    pub moves: Vec<Move>,
+    /// That result, from the side to move, as a search score.
+    pub score: i32,
+    pub wdl: AmbiguousWdl,
+}
+
+impl Tablebases {
+    /// Load every table found in `paths`, a list of directories separated the
+    /// way the platform separates `PATH` entries (`:` on Unix, `;` on
+    /// Windows), as the UCI `SyzygyPath` option expects.
+    pub fn open(paths: &str) -> Result<Self, String> {
+        let mut tables = Tablebase::new();
+        let mut files = 0;
+        for dir in std::env::split_paths(paths) {
+            if dir.as_os_str().is_empty() {
+                continue;
+            }
+            files += tables
+                .add_directory(&dir)
+                .map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        if files == 0 {
+            return Err(format!("no Syzygy tables found in {}", paths));
+        }
+        Ok(Self { tables, files })
+    }
+
+    /// Number of table files loaded.
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        self.tables.max_pieces()
+    }
+
+    /// Whether the tables can answer for `board`: few enough pieces and no
+    /// castling rights, which the tables do not encode.
+    pub fn covers(&self, board: &Board) -> bool {
+        board.occupied().len() as usize <= self.max_pieces()
+            && [Color::White, Color::Black].iter().all(|&c| {
+                let rights = board.castle_rights(c);
+                rights.short.is_none() && rights.long.is_none()
+            })
+    }
+
+    /// WDL of `board` assuming the last move was a capture or pawn move, which
+    /// is the only case where the WDL tables are exact under the fifty-move
+    /// rule.
+    pub fn probe_wdl_after_zeroing(&self, board: &Board) -> Option<Wdl> {
+        if !self.covers(board) {
+            return None;
+        }
+        self.tables
+            .probe_wdl_after_zeroing(&to_shakmaty(board)?)
+            .ok()
+    }
+
+    /// WDL of `board` taking its halfmove clock into account.
+    pub fn probe_wdl(&self, board: &Board) -> Option<AmbiguousWdl> {
+        if !self.covers(board) {
+            return None;
+        }
+        self.tables.probe_wdl(&to_shakmaty(board)?).ok()
+    }
+
+    /// Distance to the next capture or pawn move in plies, positive when the
+    /// side to move wins. Rounded tables may be off by one.
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+        if !self.covers(board) {
+            return None;
+        }
+        let dtz = self.tables.probe_dtz(&to_shakmaty(board)?).ok()?;
+        Some(dtz.ignore_rounding().0)
+    }
+
+    /// Rank `candidates` at the root by the result they keep, with the
+    /// fifty-move rule applied through the halfmove clock. Among winning moves
+    /// only those with the shortest distance to zeroing are kept, so the win
+    /// is converted before the clock runs out; among losing moves those that
+    /// hold out longest. Returns `None` if any candidate cannot be probed.
+    pub fn rank_root_moves(&self, board: &Board, candidates: &[Move]) -> Option<RootProbe> {
+        if candidates.is_empty() || !self.covers(board) {
+            return None;
+        }
+        let mut ranked = Vec::with_capacity(candidates.len());
+        for &mv in candidates {
+            let mut child = board.clone();
+            child.play_unchecked(mv);
+            let (wdl, distance) = match child.status() {
+                GameStatus::Won => (AmbiguousWdl::Win, 0),
+                GameStatus::Drawn => (AmbiguousWdl::Draw, 0),
+                GameStatus::Ongoing => {
+                    let wdl = -self.probe_wdl(&child)?;
+                    // A capture or pawn move zeroes the clock itself; any
+                    // other move adds one ply to the child's DTZ.
+                    let distance = if child.halfmove_clock() == 0 {
+                        1
+                    } else {
+                        self.probe_dtz(&child)?.abs() + 1
+                    };
+                    (wdl, distance)
+                }
+            };
+            ranked.push((mv, wdl, distance));
+        }
+        let best = ranked.iter().map(|&(_, wdl, _)| wdl).max()?;
+        let same: Vec<_> = ranked
+            .into_iter()
+            .filter(|&(_, wdl, _)| wdl == best)
+            .collect();
+        let target = if best.signum() > 0 {
+            same.iter().map(|&(_, _, d)| d).min()?
+        } else if best.signum() < 0 {
+            same.iter().map(|&(_, _, d)| d).max()?
+        } else {
+            0
+        };
+        let moves = same
+            .iter()
+            .filter(|&&(_, _, d)| best.signum() == 0 || d == target)
+            .map(|&(mv, _, _)| mv)
+            .collect();
+        Some(RootProbe {
+            moves,
+            score: root_score(best, target),
+            wdl: best,
+        })
+    }
+}
+
+/// Search score for a WDL probed `ply` plies from the root. Wins and losses
+/// are bounds: the tables know the result but not the distance to mate.
+pub fn wdl_score(wdl: Wdl, ply: i32) -> i32 {
+    match wdl {
+        Wdl::Win => TB_WIN_SCORE - ply,
+        Wdl::CursedWin => 1,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -1,
+        Wdl::Loss => -TB_WIN_SCORE + ply,
+    }
+}
+
+fn root_score(wdl: AmbiguousWdl, distance: i32) -> i32 {
+    match wdl {
+        AmbiguousWdl::Win | AmbiguousWdl::MaybeWin => TB_WIN_SCORE - distance.min(1_000),
+        AmbiguousWdl::CursedWin => 1,
+        AmbiguousWdl::Draw => 0,
+        AmbiguousWdl::BlessedLoss => -1,
+        AmbiguousWdl::Loss | AmbiguousWdl::MaybeLoss => -TB_WIN_SCORE + distance.min(1_000),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::fen::Fen;
+    use shakmaty::{EnPassantMode, Position};
+
+    #[test]
+    fn positions_convert_with_clocks_castling_and_en_passant() {
+        for fen in [
+            "8/8/8/8/8/8/1Q6/K6k b - - 37 80",
+            "8/8/8/3k4/3pP3/8/8/4K3 b - e3 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 3 20",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        ] {
+            let board = Board::from_fen(fen, false).unwrap();
+            let pos = to_shakmaty(&board).expect("convertible");
+            assert_eq!(pos.halfmoves(), u32::from(board.halfmove_clock()));
+            assert_eq!(
+                Fen::from_position(pos, EnPassantMode::Legal).to_string(),
+                board.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn tablebase_scores_rank_between_evaluation_and_mate() {
+        let order = [
+            Wdl::Loss,
+            Wdl::BlessedLoss,
+            Wdl::Draw,
+            Wdl::CursedWin,
+            Wdl::Win,
+        ];
+        let scores: Vec<i32> = order.iter().map(|&wdl| wdl_score(wdl, 3)).collect();
+        assert!(scores.windows(2).all(|w| w[0] < w[1]), "{scores:?}");
+        assert!(wdl_score(Wdl::Win, 3) > wdl_score(Wdl::Win, 5));
+        assert!(wdl_score(Wdl::Win, 0) < MATE_SCORE - 1_024);
+        assert_eq!(wdl_score(Wdl::Loss, 4), -wdl_score(Wdl::Win, 4));
+    }
+}
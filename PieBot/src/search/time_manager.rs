@@ -0,0 +1,332 @@
+use cozy_chess::Board;
+use std::time::Duration;
+
+/// Per-move allowance for GUI and pipe latency, taken off the clock before
+/// any time is allocated.
+pub const MOVE_OVERHEAD_MS: u64 = 10;
+
+/// Hard limit as a multiple of the soft target, before the clock caps below.
+const HARD_SOFT_RATIO: u32 = 3;
+/// Iterations with an unchanged best move before the soft target shrinks.
+const STABLE_ITERATIONS: u32 = 4;
+/// Soft-target scale once the best move has been stable.
+const STABLE_SCALE: f64 = 0.5;
+/// A best-move change from this depth on counts as late.
+const LATE_CHANGE_DEPTH: u32 = 5;
+const LATE_CHANGE_SCALE: f64 = 1.6;
+/// Score drops below this many centipawns are treated as noise.
+const SCORE_DROP_MIN_CP: i32 = 20;
+/// The score-drop extension grows linearly and saturates at this drop,
+/// where it doubles the soft target.
+const SCORE_DROP_FULL_CP: i32 = 150;
+/// An iteration typically costs more than all previous ones together, so one
+/// started after this fraction of the soft target would rarely finish.
+const NEXT_ITERATION_FRACTION: f64 = 0.6;
+
+/// How long one move may take: the search aims for `soft` and never runs
+/// past `hard`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeLimits {
+    pub soft: Duration,
+    pub hard: Duration,
+}
+
+impl TimeLimits {
+    /// Split the side to move's clock into a soft target and a hard limit.
+    ///
+    /// With `moves_to_go` the remaining time is shared evenly across the
+    /// moves left in the control. Otherwise the number of moves still to
+    /// play is estimated from the material on the board, so endgames get
+    /// more time per move, and a reserve is held back against running the
+    /// clock down to the overhead. Either way most of the increment is spent
+    /// and the hard limit keeps enough on the clock for the moves that
+    /// follow.
+    pub fn from_clock(
+        remaining_ms: u64,
+        increment_ms: u64,
+        moves_to_go: Option<u64>,
+        board: &Board,
+    ) -> Self {
+        let (soft_ms, max_hard_ms) = match moves_to_go {
+            Some(moves_to_go) => {
+                let moves_to_go = moves_to_go.max(1);
+                let usable_ms = remaining_ms.saturating_sub(MOVE_OVERHEAD_MS);
+                let base_ms = usable_ms / moves_to_go;
+                let increment_share_ms = increment_ms.saturating_mul(3) / 4;
+                let soft_ms = base_ms
+                    .saturating_add(increment_share_ms)
+                    .max(1)
+                    .min(usable_ms.max(1));
+                let max_hard_ms = if moves_to_go == 1 {
+                    usable_ms
+                } else {
+                    usable_ms / 2
+                };
+                (soft_ms, max_hard_ms)
+            }
+            None => {
+                let reserve_ms = (remaining_ms / 20).clamp(50, 1_000);
+                let usable_ms = remaining_ms.saturating_sub(reserve_ms).max(1);
+                let non_king_pieces = (board.occupied().len() as u64).saturating_sub(2);
+                let estimated_moves = match non_king_pieces {
+                    24.. => 24,
+                    12..=23 => 20,
+                    6..=11 => 16,
+                    _ => 12,
+                };
+                let base_ms = usable_ms / estimated_moves;
+                let increment_share_ms = increment_ms.saturating_mul(4) / 5;
+                let soft_ms = base_ms
+                    .saturating_add(increment_share_ms)
+                    .max(1)
+                    .min((usable_ms / 3).max(1));
+                (soft_ms, usable_ms / 2)
+            }
+        };
+        let hard_ms = soft_ms
+            .saturating_mul(u64::from(HARD_SOFT_RATIO))
+            .min(max_hard_ms)
+            .max(soft_ms);
+        Self {
+            soft: Duration::from_millis(soft_ms),
+            hard: Duration::from_millis(hard_ms),
+        }
+    }
+}
+
+/// A chess-clock time control in cutechess notation, seconds throughout:
+/// `base+inc` (sudden death with an increment) or `moves/base[+inc]`
+/// (`base` added back every `moves` moves).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base_ms: u64,
+    pub increment_ms: u64,
+    pub moves_per_period: Option<u32>,
+}
+
+impl std::str::FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let seconds = |field: &str, what: &str| -> Result<u64, String> {
+            let value: f64 = field
+                .trim()
+                .parse()
+                .map_err(|_| format!("time control {raw:?}: {what} {field:?} is not a number"))?;
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("time control {raw:?}: {what} must be non-negative"));
+            }
+            Ok((value * 1000.0).round() as u64)
+        };
+        let (moves_per_period, rest) = match raw.split_once('/') {
+            Some((moves, rest)) => {
+                let moves = moves
+                    .trim()
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&moves| moves > 0)
+                    .ok_or_else(|| format!("time control {raw:?}: bad move count {moves:?}"))?;
+                (Some(moves), rest)
+            }
+            None => (None, raw),
+        };
+        let (base, increment) = match rest.split_once('+') {
+            Some((base, increment)) => (base, Some(increment)),
+            None => (rest, None),
+        };
+        let base_ms = seconds(base, "base")?;
+        if base_ms == 0 {
+            return Err(format!("time control {raw:?}: base time must be positive"));
+        }
+        let increment_ms = increment.map_or(Ok(0), |inc| seconds(inc, "increment"))?;
+        if moves_per_period.is_none() && increment.is_none() {
+            return Err(format!(
+                "time control {raw:?}: expected base+inc or moves/base"
+            ));
+        }
+        Ok(Self {
+            base_ms,
+            increment_ms,
+            moves_per_period,
+        })
+    }
+}
+
+impl std::fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = |ms: u64| ms as f64 / 1000.0;
+        if let Some(moves) = self.moves_per_period {
+            write!(f, "{moves}/")?;
+        }
+        write!(f, "{}", secs(self.base_ms))?;
+        if self.increment_ms > 0 || self.moves_per_period.is_none() {
+            write!(f, "+{}", secs(self.increment_ms))?;
+        }
+        Ok(())
+    }
+}
+
+/// One player's clock under a [`TimeControl`].
+///
+/// [`Clock::limits`] hands the clock to [`TimeLimits::from_clock`], exactly
+/// what the UCI engine does with `go wtime/btime`, so games played against a
+/// `Clock` exercise the shipped time allocation.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining_ms: u64,
+    period_moves: u32,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Self {
+            control,
+            remaining_ms: control.base_ms,
+            period_moves: 0,
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        Duration::from_millis(self.remaining_ms)
+    }
+
+    /// Moves left in the current period of a `moves/base` control.
+    pub fn moves_to_go(&self) -> Option<u64> {
+        self.control
+            .moves_per_period
+            .map(|moves| u64::from(moves - self.period_moves))
+    }
+
+    /// Time allocation for a move from `board` on this clock.
+    pub fn limits(&self, board: &Board) -> TimeLimits {
+        TimeLimits::from_clock(
+            self.remaining_ms,
+            self.control.increment_ms,
+            self.moves_to_go(),
+            board,
+        )
+    }
+
+    /// Charge a move that took `elapsed` of wall time. Returns `false` when
+    /// the flag fell, i.e. the move was not made in the time remaining;
+    /// otherwise credits the increment and, at the end of a period, the next
+    /// period's base time.
+    pub fn charge(&mut self, elapsed: Duration) -> bool {
+        let spent_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+        if spent_ms > self.remaining_ms {
+            self.remaining_ms = 0;
+            return false;
+        }
+        self.remaining_ms = self.remaining_ms - spent_ms + self.control.increment_ms;
+        if let Some(moves) = self.control.moves_per_period {
+            self.period_moves += 1;
+            if self.period_moves == moves {
+                self.period_moves = 0;
+                self.remaining_ms += self.control.base_ms;
+            }
+        }
+        true
+    }
+}
+
+/// Decides between iterations whether the search has used enough of its
+/// time, and how long the iteration in progress may run.
+///
+/// The soft target is scaled from what the search reports: it shrinks once
+/// the best move has been stable for several iterations and grows when the
+/// score drops or the best move changes late. It never exceeds the hard
+/// limit, and neither does an iteration whose root failed low: until the
+/// re-search resolves, the iteration may run all the way to `hard`.
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+    limits: TimeLimits,
+    root_moves: usize,
+    iterations: u32,
+    best: Option<String>,
+    stable_iterations: u32,
+    last_score: Option<i32>,
+    scale: f64,
+    fail_low: bool,
+}
+
+impl TimeManager {
+    /// `root_moves` is the number of moves the search may choose from.
+    pub fn new(limits: TimeLimits, root_moves: usize) -> Self {
+        Self {
+            limits,
+            root_moves,
+            iterations: 0,
+            best: None,
+            stable_iterations: 0,
+            last_score: None,
+            scale: 1.0,
+            fail_low: false,
+        }
+    }
+
+    pub fn limits(&self) -> TimeLimits {
+        self.limits
+    }
+
+    /// Record a completed iteration and rescale the soft target.
+    pub fn on_iteration(&mut self, depth: u32, bestmove: Option<&str>, score_cp: i32) {
+        let changed = self.best.is_some() && self.best.as_deref() != bestmove;
+        if changed || self.best.is_none() {
+            self.stable_iterations = 1;
+        } else {
+            self.stable_iterations += 1;
+        }
+        self.best = bestmove.map(str::to_string);
+        self.iterations += 1;
+        self.fail_low = false;
+
+        // A falling score means the position is not settled, however long
+        // the best move has held, so the drop overrides the stability cut.
+        let drop = self
+            .last_score
+            .map_or(0, |previous| previous.saturating_sub(score_cp));
+        let mut scale = 1.0;
+        if drop >= SCORE_DROP_MIN_CP {
+            scale *= 1.0 + f64::from(drop.min(SCORE_DROP_FULL_CP)) / f64::from(SCORE_DROP_FULL_CP);
+        } else if self.stable_iterations >= STABLE_ITERATIONS {
+            scale *= STABLE_SCALE;
+        }
+        if changed && depth >= LATE_CHANGE_DEPTH {
+            scale *= LATE_CHANGE_SCALE;
+        }
+        self.scale = scale;
+        self.last_score = Some(score_cp);
+    }
+
+    /// The root failed low in the iteration in progress.
+    pub fn on_fail_low(&mut self) {
+        self.fail_low = true;
+    }
+
+    /// The scaled soft target, capped at the hard limit.
+    pub fn soft_target(&self) -> Duration {
+        self.limits.soft.mul_f64(self.scale).min(self.limits.hard)
+    }
+
+    /// How long after the start the iteration in progress may run.
+    pub fn iteration_limit(&self) -> Duration {
+        if self.fail_low {
+            self.limits.hard
+        } else {
+            self.soft_target()
+        }
+    }
+
+    /// Whether to return the current best move rather than start another
+    /// iteration.
+    pub fn should_stop(&self, elapsed: Duration) -> bool {
+        if self.iterations == 0 {
+            return false;
+        }
+        if self.root_moves <= 1 {
+            return true;
+        }
+        elapsed >= self.soft_target().mul_f64(NEXT_ITERATION_FRACTION)
+    }
+}
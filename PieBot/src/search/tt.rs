@@ -1,5 +1,5 @@
-use cozy_chess::Move;
-use std::sync::Mutex;
+use cozy_chess::{Move, Piece, Square};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Bound {
@@ -8,6 +8,10 @@ pub enum Bound {
     Upper,
 }
 
+/// A probe result or a store request. Stored entries are packed, so `depth`
+/// saturates at 255, `score` at the `i16` range (mate scores stay well inside
+/// it) and `gen` comes back as the low 8 bits of the generation the entry
+/// was written in; the value passed to `put` is ignored.
 #[derive(Clone, Copy, Debug)]
 pub struct Entry {
     pub key: u64,
@@ -19,26 +23,126 @@ pub struct Entry {
 }
 
 const DEFAULT_WAYS: usize = 4;
+const ENTRY_BYTES: usize = 16;
 
-#[derive(Default, Clone, Copy)]
-struct Slot(Option<Entry>);
+// Packed data word layout, low bits first. A bound code of zero marks an
+// empty slot, so every stored entry has a non-zero data word.
+const SCORE_SHIFT: u32 = 0; // 16 bits, i16
+const DEPTH_SHIFT: u32 = 16; // 8 bits
+const MOVE_SHIFT: u32 = 24; // 16 bits: from | to << 6 | promotion << 12
+const BOUND_SHIFT: u32 = 40; // 2 bits: 1 exact, 2 lower, 3 upper
+const GEN_SHIFT: u32 = 42; // 8 bits
 
+/// One 16-byte entry. `check` holds `key ^ data`, so a reader that sees a
+/// data word from one write and a check word from another (a torn entry
+/// from a concurrent store) fails the key test and treats it as a miss;
+/// no lock is needed on either side.
 #[derive(Default)]
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    fn load(&self) -> (u64, u64) {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.check.load(Ordering::Relaxed) ^ data;
+        (key, data)
+    }
+
+    fn store(&self, key: u64, data: u64) {
+        self.data.store(data, Ordering::Relaxed);
+        self.check.store(key ^ data, Ordering::Relaxed);
+    }
+}
+
+/// Four slots filling exactly one cache line, so a probe touches one line.
+#[derive(Default)]
+#[repr(C, align(64))]
 struct Bucket {
     slots: [Slot; DEFAULT_WAYS],
 }
 
+fn encode_move(mv: Option<Move>) -> u64 {
+    let Some(mv) = mv else {
+        return 0;
+    };
+    let promotion = match mv.promotion {
+        None => 0,
+        Some(Piece::Knight) => 1,
+        Some(Piece::Bishop) => 2,
+        Some(Piece::Rook) => 3,
+        Some(_) => 4,
+    };
+    // from == to never occurs in a real move, so zero is free for `None`.
+    mv.from as u64 | (mv.to as u64) << 6 | promotion << 12
+}
+
+fn decode_move(bits: u64) -> Option<Move> {
+    if bits == 0 {
+        return None;
+    }
+    let promotion = match (bits >> 12) & 7 {
+        0 => None,
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        _ => Some(Piece::Queen),
+    };
+    Some(Move {
+        from: Square::index((bits & 63) as usize),
+        to: Square::index(((bits >> 6) & 63) as usize),
+        promotion,
+    })
+}
+
+fn pack(e: &Entry, gen: u32) -> u64 {
+    let score = e.score.clamp(i16::MIN as i32, i16::MAX as i32) as i16 as u16 as u64;
+    let depth = e.depth.min(u8::MAX as u32) as u64;
+    let bound = match e.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    score << SCORE_SHIFT
+        | depth << DEPTH_SHIFT
+        | encode_move(e.best) << MOVE_SHIFT
+        | bound << BOUND_SHIFT
+        | u64::from(gen & 0xff) << GEN_SHIFT
+}
+
+fn unpack(key: u64, data: u64) -> Option<Entry> {
+    let bound = match (data >> BOUND_SHIFT) & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+    Some(Entry {
+        key,
+        depth: ((data >> DEPTH_SHIFT) & 0xff) as u32,
+        score: ((data >> SCORE_SHIFT) & 0xffff) as u16 as i16 as i32,
+        best: decode_move((data >> MOVE_SHIFT) & 0xffff),
+        bound,
+        gen: ((data >> GEN_SHIFT) & 0xff) as u32,
+    })
+}
+
+fn packed_depth(data: u64) -> u32 {
+    ((data >> DEPTH_SHIFT) & 0xff) as u32
+}
+
 #[derive(Default)]
 pub struct Tt {
-    buckets: Vec<Mutex<Bucket>>,
-    gen: std::sync::atomic::AtomicU32,
+    buckets: Vec<Bucket>,
+    gen: AtomicU32,
 }
 
 impl Tt {
     pub fn new() -> Self {
         Self {
             buckets: Vec::new(),
-            gen: std::sync::atomic::AtomicU32::new(0),
+            gen: AtomicU32::new(0),
         }
     }
 
@@ -52,8 +156,9 @@ impl Tt {
     pub fn clear(&mut self) {
         self.ensure_init();
         for b in &self.buckets {
-            let mut g = b.lock().unwrap();
-            *g = Bucket::default();
+            for slot in &b.slots {
+                slot.store(0, 0);
+            }
         }
     }
 
@@ -67,91 +172,188 @@ impl Tt {
             return None;
         }
         let idx = self.bucket_index(key);
-        let g = self.buckets[idx].lock().unwrap();
-        for slot in &g.slots {
-            if let Some(e) = slot.0 {
-                if e.key == key {
-                    return Some(e);
-                }
+        self.buckets[idx].slots.iter().find_map(|slot| {
+            let (stored_key, data) = slot.load();
+            if stored_key == key {
+                unpack(key, data)
+            } else {
+                None
             }
-        }
-        None
+        })
     }
 
     pub fn len(&self) -> usize {
-        if self.buckets.is_empty() {
+        self.buckets
+            .iter()
+            .flat_map(|b| &b.slots)
+            .filter(|slot| slot.data.load(Ordering::Relaxed) != 0)
+            .count()
+    }
+
+    /// Occupancy in permille, estimated from the first 1000 slots the way
+    /// UCI `info hashfull` expects.
+    pub fn hashfull(&self) -> u32 {
+        let sample = self.buckets.len().min(1000 / DEFAULT_WAYS);
+        if sample == 0 {
             return 0;
         }
-        let mut count = 0;
-        for b in &self.buckets {
-            let g = b.lock().unwrap();
-            for s in &g.slots {
-                if s.0.is_some() {
-                    count += 1;
-                }
-            }
-        }
-        count
+        let used = self.buckets[..sample]
+            .iter()
+            .flat_map(|b| &b.slots)
+            .filter(|slot| slot.data.load(Ordering::Relaxed) != 0)
+            .count();
+        (used * 1000 / (sample * DEFAULT_WAYS)) as u32
     }
 
     pub fn set_capacity_entries(&mut self, cap: usize) {
         let entries = cap.max(DEFAULT_WAYS);
-        let buckets = (entries + DEFAULT_WAYS - 1) / DEFAULT_WAYS;
+        let buckets = entries.div_ceil(DEFAULT_WAYS);
         self.buckets.clear();
-        self.buckets
-            .resize_with(buckets, || Mutex::new(Bucket::default()));
+        self.buckets.resize_with(buckets, Bucket::default);
     }
 
     pub fn set_capacity_mb(&mut self, mb: usize) {
-        // Heuristic: ~64 bytes per entry
-        let entries = ((mb.saturating_mul(1024) * 1024) / 64).max(DEFAULT_WAYS);
+        let entries = ((mb.saturating_mul(1024) * 1024) / ENTRY_BYTES).max(DEFAULT_WAYS);
         self.set_capacity_entries(entries);
     }
 
+    /// Store without locking. Two threads storing into one bucket at once
+    /// can overwrite each other's choice of slot; that only loses an entry,
+    /// and torn slots are rejected on read by the key check.
     pub fn put(&self, e: Entry) {
-        // Safety: we only mutate internal bucket; external API remains &self
         if self.buckets.is_empty() {
             return;
         }
         let idx = self.bucket_index(e.key);
-        let mut g = self.buckets[idx].lock().unwrap();
-        let cur_gen = self.gen.load(std::sync::atomic::Ordering::Relaxed);
-        let mut e = e;
-        e.gen = cur_gen;
+        let slots = &self.buckets[idx].slots;
+        let cur_gen = self.gen.load(Ordering::Relaxed);
+        let data = pack(&e, cur_gen);
         // Replace same key if deeper
-        for slot in &mut g.slots {
-            if let Some(cur) = slot.0 {
-                if cur.key == e.key {
-                    if e.depth >= cur.depth {
-                        slot.0 = Some(e);
-                    }
-                    return;
+        for slot in slots {
+            let (key, cur) = slot.load();
+            if cur != 0 && key == e.key {
+                if e.depth >= packed_depth(cur) {
+                    slot.store(e.key, data);
                 }
-            }
-        }
-        // Empty slot first
-        for slot in &mut g.slots {
-            if slot.0.is_none() {
-                slot.0 = Some(e);
                 return;
             }
         }
-        // Replace lowest depth
-        let mut victim = 0usize;
-        let mut best_key = (u32::MAX, u32::MAX);
-        for (i, slot) in g.slots.iter().enumerate() {
-            if let Some(cur) = slot.0 {
-                let key = (cur.depth, cur.gen); // lexicographic: prefer evicting lowest depth, then oldest gen
-                if key < best_key {
-                    best_key = key;
-                    victim = i;
-                }
-            }
+        // Empty slot first
+        if let Some(slot) = slots.iter().find(|s| s.data.load(Ordering::Relaxed) == 0) {
+            slot.store(e.key, data);
+            return;
         }
-        g.slots[victim].0 = Some(e);
+        // Replace lowest depth, then the oldest generation. Generations wrap
+        // at 8 bits, so age is measured relative to the current one.
+        let victim = slots
+            .iter()
+            .min_by_key(|slot| {
+                let cur = slot.data.load(Ordering::Relaxed);
+                let age = (cur_gen.wrapping_sub(((cur >> GEN_SHIFT) & 0xff) as u32)) & 0xff;
+                (packed_depth(cur), std::cmp::Reverse(age))
+            })
+            .expect("buckets are never empty");
+        victim.store(e.key, data);
     }
 
     pub fn bump_generation(&self) {
-        let _ = self.gen.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let _ = self.gen.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_and_buckets_have_the_packed_sizes() {
+        assert_eq!(std::mem::size_of::<Slot>(), ENTRY_BYTES);
+        assert_eq!(std::mem::size_of::<Bucket>(), 64);
+        assert_eq!(std::mem::align_of::<Bucket>(), 64);
+    }
+
+    #[test]
+    fn packed_fields_round_trip() {
+        let mut tt = Tt::new();
+        tt.set_capacity_entries(4);
+        let promotion = Move {
+            from: Square::G7,
+            to: Square::H8,
+            promotion: Some(Piece::Knight),
+        };
+        for (key, score, best, bound) in [
+            (7, -29_990, Some(promotion), Bound::Upper),
+            (8, 30_100, None, Bound::Lower),
+            (0, 0, Some("a2a4".parse().unwrap()), Bound::Exact),
+        ] {
+            tt.put(Entry {
+                key,
+                depth: 300,
+                score,
+                best,
+                bound,
+                gen: 0,
+            });
+            let e = tt.get(key).expect("stored entry");
+            assert_eq!(
+                (e.score, e.best, e.bound, e.depth),
+                (score, best, bound, 255)
+            );
+        }
+    }
+
+    #[test]
+    fn a_torn_entry_reads_as_a_miss() {
+        let mut tt = Tt::new();
+        tt.set_capacity_entries(4);
+        tt.put(Entry {
+            key: 42,
+            depth: 3,
+            score: 10,
+            best: None,
+            bound: Bound::Exact,
+            gen: 0,
+        });
+        // Simulate a concurrent store that replaced the data word but not
+        // yet the check word.
+        let slot = &tt.buckets[0].slots[0];
+        slot.data.fetch_xor(1 << DEPTH_SHIFT, Ordering::Relaxed);
+        assert!(tt.get(42).is_none());
+    }
+
+    #[test]
+    fn aging_survives_generation_wraparound() {
+        let mut tt = Tt::new();
+        tt.set_capacity_entries(4);
+        for _ in 0..250 {
+            tt.bump_generation();
+        }
+        for key in 1..=4u64 {
+            tt.put(Entry {
+                key,
+                depth: 5,
+                score: 0,
+                best: None,
+                bound: Bound::Exact,
+                gen: 0,
+            });
+            // Crosses the 8-bit wrap between the second and third store.
+            for _ in 0..3 {
+                tt.bump_generation();
+            }
+        }
+        tt.put(Entry {
+            key: 99,
+            depth: 5,
+            score: 0,
+            best: None,
+            bound: Bound::Exact,
+            gen: 0,
+        });
+        assert!(
+            tt.get(1).is_none(),
+            "oldest entry not evicted across the wrap"
+        );
+        assert!(tt.get(4).is_some());
     }
 }
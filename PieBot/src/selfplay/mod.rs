@@ -1,4 +1,5 @@
 use crate::eval::nnue::loader::QuantNnue;
+use crate::io::pgn::{EvalComment, PgnGame};
 use crate::search::alphabeta::{EvalMode, SearchParams, Searcher};
 use crate::search::zobrist;
 use cozy_chess::{Board, Color, GameStatus, Move};
@@ -9,6 +10,13 @@ use rayon::prelude::*;
 use std::fs::{create_dir_all, File};
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use opening::{OpeningInfo, OpeningStrategy, Openings};
+
+pub mod opening;
+pub mod packed;
+pub mod stream;
 
 #[derive(Clone)]
 pub struct SelfPlayParams {
@@ -37,12 +45,16 @@ pub struct SelfPlayParams {
     pub draw_adj_plies: usize,  // consecutive quiet plies before adjudicating a draw
     pub draw_adj_min_ply: usize, // earliest ply index a draw adjudication may fire
     pub actor_tt_mb: usize,      // 0 = legacy 4096-entry table; >0 = real TT in MB
-    pub policy_node_cap: u64,    // per-move policy-scoring node budget
+    pub policy_node_cap: u64,    // node budget of the MultiPV policy search
     pub bestmove_node_cap: u64,  // best-move search node budget
+    pub policy_full_distribution: bool, // policy_top lists every legal move, not the top 8
+    pub opening_strategy: OpeningStrategy, // how games leave the start/suite position
+    pub paired_openings: bool,           // games 2k and 2k+1 share an opening
 }
 
-/// Search budget for per-move policy scoring; extracted so the actor's
-/// node caps are configuration, not constants buried in the game loop.
+/// Search budget for the MultiPV search that scores every root move for
+/// the policy; extracted so the actor's node caps are configuration, not
+/// constants buried in the game loop.
 pub fn policy_search_params(params: &SelfPlayParams, depth: u32) -> SearchParams {
     let mut p = base_actor_search_params(params, depth);
     p.max_nodes = Some(params.policy_node_cap.max(1));
@@ -86,6 +98,7 @@ pub struct GameRecord {
     pub result: i8,                               // 1 white win, 0 draw, -1 black win
     pub outcome_valid: bool, // false when generation stopped without a chess result
     pub termination: GameTermination,
+    pub opening: Option<OpeningInfo>, // how the start position was chosen
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
@@ -116,6 +129,24 @@ impl GameTermination {
             Self::AdjudicatedDraw => "adjudicated_draw",
         }
     }
+
+    /// Every termination, in the order their packed codes use.
+    pub const ALL: [Self; 9] = [
+        Self::Checkmate,
+        Self::Stalemate,
+        Self::FiftyMove,
+        Self::InsufficientMaterial,
+        Self::ThreefoldRepetition,
+        Self::MaxPlies,
+        Self::NoMove,
+        Self::Resigned,
+        Self::AdjudicatedDraw,
+    ];
+
+    /// Inverse of [`GameTermination::as_str`].
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == s)
+    }
 }
 
 /// Verdict produced by the ply-by-ply adjudication state machine.
@@ -211,33 +242,52 @@ struct MoveChoice {
 }
 
 pub fn generate_games(params: &SelfPlayParams) -> Result<Vec<GameRecord>, String> {
-    let openings = load_openings(params)?;
-    if params.games == 0 {
-        return Ok(Vec::new());
-    }
+    let game_indices: Vec<usize> = (0..params.games).collect();
+    let (tx, rx) = mpsc::sync_channel(params.games.max(1));
+    generate_games_streaming(params, &game_indices, tx)?;
+    let mut games: Vec<(usize, GameRecord)> = rx.into_iter().collect();
+    games.sort_by_key(|(game_idx, _)| *game_idx);
+    Ok(games.into_iter().map(|(_, game)| game).collect())
+}
 
-    let parallel_games = effective_parallel_games(params);
-    if parallel_games <= 1 || params.games <= 1 {
-        return Ok((0..params.games)
-            .map(|game_idx| generate_single_game(params, &openings, game_idx))
-            .collect());
-    }
+/// Play the games `game_indices` and send each one, tagged with its index,
+/// as soon as it finishes. Games finish out of order when several run in
+/// parallel; each game's moves depend only on the seed and its index. Stops
+/// early, without error, once the receiver hangs up.
+pub fn generate_games_streaming(
+    params: &SelfPlayParams,
+    game_indices: &[usize],
+    tx: mpsc::SyncSender<(usize, GameRecord)>,
+) -> Result<(), String> {
+    let openings = Openings::load(params)?;
+    let play = |tx: &mut mpsc::SyncSender<(usize, GameRecord)>, game_idx: usize| {
+        tx.send((game_idx, generate_single_game(params, &openings, game_idx)))
+            .map_err(|_| ())
+    };
 
-    let games = match rayon::ThreadPoolBuilder::new()
-        .num_threads(parallel_games)
-        .build()
-    {
-        Ok(pool) => pool.install(|| {
-            (0..params.games)
-                .into_par_iter()
-                .map(|game_idx| generate_single_game(params, &openings, game_idx))
-                .collect()
+    let parallel_games = effective_parallel_games(params).min(game_indices.len());
+    let pool = if parallel_games > 1 {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(parallel_games)
+            .build()
+            .ok()
+    } else {
+        None
+    };
+    let _ = match pool {
+        Some(pool) => pool.install(|| {
+            game_indices
+                .par_iter()
+                .try_for_each_with(tx, |tx, &game_idx| play(tx, game_idx))
         }),
-        Err(_) => (0..params.games)
-            .map(|game_idx| generate_single_game(params, &openings, game_idx))
-            .collect(),
+        None => {
+            let mut tx = tx;
+            game_indices
+                .iter()
+                .try_for_each(|&game_idx| play(&mut tx, game_idx))
+        }
     };
-    Ok(games)
+    Ok(())
 }
 
 pub fn effective_parallel_games(params: &SelfPlayParams) -> usize {
@@ -257,7 +307,7 @@ pub fn effective_parallel_games(params: &SelfPlayParams) -> usize {
 
 fn generate_single_game(
     params: &SelfPlayParams,
-    openings: &[Board],
+    openings: &Openings,
     game_idx: usize,
 ) -> GameRecord {
     let game_seed = game_seed(params.seed, game_idx);
@@ -268,12 +318,7 @@ fn generate_single_game(
     } else {
         None
     };
-    let mut board = if !openings.is_empty() {
-        let idx = (mix_u64(game_seed ^ 0xA5A5_A5A5_A5A5_A5A5) as usize) % openings.len();
-        openings[idx].clone()
-    } else {
-        Board::default()
-    };
+    let (mut board, opening) = openings.pick(params, game_idx);
     let mut record = GameRecord {
         game_id: game_id(&run_id, game_idx),
         run_id,
@@ -286,6 +331,7 @@ fn generate_single_game(
         result: 0,
         outcome_valid: false,
         termination: GameTermination::MaxPlies,
+        opening: Some(opening),
     };
     let resign_allowed = !resign_disabled_for_game(params.no_resign_fraction, game_seed);
     let mut adjudicator = Adjudicator::new(
@@ -488,21 +534,27 @@ fn select_engine_move(
         if moves.is_empty() {
             return None;
         }
-        // Score each child with a slightly reduced depth
-        let pol_depth = if params.depth > 1 {
-            params.depth - 1
-        } else {
-            1
-        };
-        let mut scores: Vec<f32> = Vec::with_capacity(moves.len());
-        for &m in &moves {
-            let mut child = board.clone();
-            child.play_unchecked(m);
-            let p = policy_search_params(params, pol_depth);
-            let r = searcher.search_with_params(&child, p);
-            let score_from_parent = -(r.score_cp as f32);
-            scores.push(score_from_parent);
+        // One MultiPV search scores every root move exactly under a shared
+        // budget; moves the last iteration did not reach keep their score
+        // from the one before. Only a search cut off inside depth 1 leaves
+        // moves unscored, so finish depth 1 without the budget then.
+        let mut p = policy_search_params(params, params.depth.max(1));
+        p.multipv = moves.len();
+        let mut res = searcher.search_with_params(board, p);
+        if res.lines.len() < moves.len() {
+            p.depth = 1;
+            p.max_nodes = None;
+            p.movetime = None;
+            res = searcher.search_with_params(board, p);
         }
+        let scores: Vec<f32> = moves
+            .iter()
+            .map(|m| {
+                let uci = m.to_string();
+                let line = res.lines.iter().find(|l| l.mv == uci);
+                line.expect("MultiPV scores every legal root move").score_cp as f32
+            })
+            .collect();
         // Softmax with temperature
         // Anneal temperature linearly over first temperature_moves plies
         let tau = if use_temp && params.temperature_moves > 1 {
@@ -579,7 +631,11 @@ fn select_engine_move(
                 .partial_cmp(&clean_probs[a])
                 .unwrap_or(std::cmp::Ordering::Equal)
         });
-        let keep = order.len().min(8);
+        let keep = if params.policy_full_distribution {
+            order.len()
+        } else {
+            order.len().min(8)
+        };
         let mut policy_top = Vec::with_capacity(keep);
         for &idx in &order[..keep] {
             policy_top.push((format!("{}", moves[idx]), clean_probs[idx]));
@@ -594,7 +650,7 @@ fn select_engine_move(
             played_mv: moves[picked_idx],
             target_best_mv: Some(moves[best_idx]),
             value_cp: Some(best_cp_white),
-            teacher_depth: Some(params.depth.max(1)),
+            teacher_depth: Some(res.depth.max(1)),
             policy_top,
         });
     }
@@ -627,13 +683,6 @@ fn select_engine_move(
     })
 }
 
-fn load_openings(params: &SelfPlayParams) -> Result<Vec<Board>, String> {
-    match params.openings_path {
-        Some(ref p) => crate::io::openings::load_fen_suite(p),
-        None => Ok(Vec::new()),
-    }
-}
-
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct RecordBin {
@@ -706,6 +755,8 @@ struct JsonlSelfPlayRecord<'a> {
     best_move: &'a str,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     policy_top: Vec<JsonPolicyTopEntry<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    opening: Option<&'a OpeningInfo>,
 }
 
 #[derive(serde::Serialize)]
@@ -734,76 +785,218 @@ pub fn write_jsonl_shards<P: AsRef<Path>>(
     };
 
     for g in games {
-        let mut board = Board::from_fen(&g.start_fen, false).unwrap_or_default();
-        for (ply, mv_str) in g.moves.iter().enumerate() {
+        for_each_jsonl_record(g, |rec| {
             if writer.is_none() || rec_in_shard >= max_records_per_shard {
                 writer = Some(start_new_shard(shard_index)?);
                 shard_index += 1;
                 rec_in_shard = 0;
             }
             let w = writer.as_mut().unwrap();
-            let value_cp = g.move_value_cp.get(ply).copied().flatten();
-            let teacher_depth = if value_cp.is_some() {
-                g.move_teacher_depth.get(ply).copied().flatten()
-            } else {
-                None
-            };
-            let target_best = g
-                .move_target_best
-                .get(ply)
-                .and_then(|s| s.as_deref())
-                .unwrap_or(mv_str.as_str());
-            let mut policy_top = Vec::new();
-            if let Some(items) = g.move_policy_top.get(ply) {
-                policy_top.reserve(items.len());
-                for (mv, p) in items {
-                    policy_top.push(JsonPolicyTopEntry {
-                        mv: mv.as_str(),
-                        p: *p,
-                    });
-                }
-            }
-            let rec = JsonlSelfPlayRecord {
-                run_id: g.run_id.as_str(),
-                game_id: g.game_id.as_str(),
-                fen: format!("{}", board),
-                ply,
-                result: g.result,
-                result_q: g.result as f32,
-                outcome_valid: g.outcome_valid,
-                termination: g.termination,
-                value_cp,
-                teacher_depth,
-                played_move: mv_str.as_str(),
-                target_best_move: target_best,
-                best_move: target_best,
-                policy_top,
-            };
-            serde_json::to_writer(&mut *w, &rec)?;
+            serde_json::to_writer(&mut *w, rec)?;
             w.write_all(b"\n")?;
             rec_in_shard += 1;
+            Ok(())
+        })?;
+    }
+    if let Some(mut w) = writer {
+        w.flush()?;
+    }
+    Ok(out_paths)
+}
+
+/// The JSONL records of one game, one per ply, as `write_jsonl_shards`
+/// writes them.
+pub fn game_jsonl_lines(game: &GameRecord) -> std::io::Result<Vec<String>> {
+    let mut lines = Vec::with_capacity(game.moves.len());
+    for_each_jsonl_record(game, |rec| {
+        lines.push(serde_json::to_string(rec)?);
+        Ok(())
+    })?;
+    Ok(lines)
+}
+
+fn for_each_jsonl_record<F>(g: &GameRecord, mut emit: F) -> std::io::Result<()>
+where
+    F: FnMut(&JsonlSelfPlayRecord) -> std::io::Result<()>,
+{
+    let mut board = Board::from_fen(&g.start_fen, false).unwrap_or_default();
+    for (ply, mv_str) in g.moves.iter().enumerate() {
+        let value_cp = g.move_value_cp.get(ply).copied().flatten();
+        let teacher_depth = if value_cp.is_some() {
+            g.move_teacher_depth.get(ply).copied().flatten()
+        } else {
+            None
+        };
+        let target_best = g
+            .move_target_best
+            .get(ply)
+            .and_then(|s| s.as_deref())
+            .unwrap_or(mv_str.as_str());
+        let mut policy_top = Vec::new();
+        if let Some(items) = g.move_policy_top.get(ply) {
+            policy_top.reserve(items.len());
+            for (mv, p) in items {
+                policy_top.push(JsonPolicyTopEntry {
+                    mv: mv.as_str(),
+                    p: *p,
+                });
+            }
+        }
+        let rec = JsonlSelfPlayRecord {
+            run_id: g.run_id.as_str(),
+            game_id: g.game_id.as_str(),
+            fen: format!("{}", board),
+            ply,
+            result: g.result,
+            result_q: g.result as f32,
+            outcome_valid: g.outcome_valid,
+            termination: g.termination,
+            value_cp,
+            teacher_depth,
+            played_move: mv_str.as_str(),
+            target_best_move: target_best,
+            best_move: target_best,
+            policy_top,
+            opening: g.opening.as_ref(),
+        };
+        emit(&rec)?;
 
-            let mut chosen = None;
-            board.generate_moves(|ml| {
-                for m in ml {
-                    if format!("{}", m) == *mv_str {
-                        chosen = Some(m);
-                        break;
-                    }
+        let mut chosen = None;
+        board.generate_moves(|ml| {
+            for m in ml {
+                if format!("{}", m) == *mv_str {
+                    chosen = Some(m);
+                    break;
                 }
-                chosen.is_some()
-            });
-            if let Some(m) = chosen {
-                board.play_unchecked(m);
+            }
+            chosen.is_some()
+        });
+        if let Some(m) = chosen {
+            board.play_unchecked(m);
+        } else {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Convert a generated game to PGN. Teacher values become `+0.35/12`
+/// comments from the side that moved; the run and game ids are kept as tags
+/// so PGN and JSONL output can be joined.
+pub fn game_to_pgn(game: &GameRecord, round: usize) -> PgnGame {
+    let start = Board::from_fen(&game.start_fen, false).unwrap_or_default();
+    let mut pgn = PgnGame::new(start.clone());
+    pgn.set_header("Event", "PieBot self-play");
+    pgn.set_header("Site", "?");
+    pgn.set_header("Round", round.to_string());
+    pgn.set_header("White", "PieBot");
+    pgn.set_header("Black", "PieBot");
+    pgn.set_header("RunId", game.run_id.as_str());
+    pgn.set_header("GameId", game.game_id.as_str());
+    pgn.set_header("Termination", game.termination.as_str());
+    let mut board = start;
+    for (ply, mv_str) in game.moves.iter().enumerate() {
+        let Some(mv) = parse_legal_move(&board, mv_str) else {
+            break;
+        };
+        let comment = game.move_value_cp.get(ply).copied().flatten().map(|cp| {
+            let mover_cp = if board.side_to_move() == Color::White {
+                cp
             } else {
-                break;
+                -cp
+            };
+            let depth = game.move_teacher_depth.get(ply).copied().flatten();
+            EvalComment {
+                score_cp: mover_cp.round() as i32,
+                depth: depth.unwrap_or(0),
             }
+            .to_string()
+        });
+        pgn.push(mv, comment);
+        board.play_unchecked(mv);
+    }
+    pgn.result = match (game.outcome_valid, game.result) {
+        (false, _) => "*",
+        (true, 1) => "1-0",
+        (true, -1) => "0-1",
+        (true, _) => "1/2-1/2",
+    }
+    .to_string();
+    pgn
+}
+
+/// Write all games to one PGN file, rounds numbered from 1.
+pub fn write_pgn<P: AsRef<Path>>(games: &[GameRecord], path: P) -> std::io::Result<()> {
+    if let Some(parent) = path.as_ref().parent() {
+        if !parent.as_os_str().is_empty() {
+            create_dir_all(parent)?;
         }
     }
-    if let Some(mut w) = writer {
-        w.flush()?;
+    let mut w = BufWriter::new(File::create(path)?);
+    for (index, game) in games.iter().enumerate() {
+        w.write_all(game_to_pgn(game, index + 1).to_pgn().as_bytes())?;
     }
-    Ok(out_paths)
+    w.flush()
+}
+
+/// Convert a PGN game into a `GameRecord` so databases can go through the
+/// same JSONL path as self-play. `+0.35/12` comments become teacher values;
+/// the `RunId`/`GameId` tags written by [`game_to_pgn`] win over the ids
+/// passed in. The termination comes from the final position, then the
+/// `Termination` tag, then the result.
+pub fn game_record_from_pgn(pgn: &PgnGame, run_id: &str, game_id: &str) -> GameRecord {
+    let n = pgn.moves.len();
+    let mut record = GameRecord {
+        run_id: pgn.header("RunId").unwrap_or(run_id).to_string(),
+        game_id: pgn.header("GameId").unwrap_or(game_id).to_string(),
+        start_fen: pgn.start.to_string(),
+        moves: Vec::with_capacity(n),
+        move_target_best: vec![None; n],
+        move_value_cp: Vec::with_capacity(n),
+        move_teacher_depth: Vec::with_capacity(n),
+        move_policy_top: vec![Vec::new(); n],
+        result: pgn.white_score().unwrap_or(0),
+        outcome_valid: pgn.white_score().is_some(),
+        termination: GameTermination::MaxPlies,
+        opening: None,
+    };
+    let mut board = pgn.start.clone();
+    let mut history = vec![board.clone()];
+    for m in &pgn.moves {
+        let eval = m.comment.as_deref().and_then(EvalComment::parse);
+        let white_cp = eval.map(|e| {
+            if board.side_to_move() == Color::White {
+                e.score_cp as f32
+            } else {
+                -(e.score_cp as f32)
+            }
+        });
+        record.moves.push(m.mv.to_string());
+        record.move_value_cp.push(white_cp);
+        record
+            .move_teacher_depth
+            .push(eval.map(|e| e.depth).filter(|&d| d > 0));
+        board.play_unchecked(m.mv);
+        history.push(board.clone());
+    }
+    record.termination = adjudicate_position(&board, &history)
+        .map(|(_, termination)| termination)
+        .or_else(|| pgn.header("Termination").and_then(GameTermination::parse))
+        .unwrap_or(match pgn.white_score() {
+            Some(0) => GameTermination::AdjudicatedDraw,
+            Some(_) => GameTermination::Resigned,
+            None => GameTermination::MaxPlies,
+        });
+    record
+}
+
+fn parse_legal_move(board: &Board, mv_str: &str) -> Option<Move> {
+    let mut chosen = None;
+    board.generate_moves(|ml| {
+        chosen = ml.into_iter().find(|m| m.to_string() == mv_str);
+        chosen.is_some()
+    });
+    chosen
 }
 
 pub fn write_shards<P: AsRef<Path>>(
@@ -834,12 +1027,7 @@ pub fn write_shards<P: AsRef<Path>>(
                 rec_in_shard = 0;
             }
             let w = writer.as_mut().unwrap();
-            let mut buf = [0u8; RECORD_SIZE];
-            buf[0..8].copy_from_slice(&r.key.to_le_bytes());
-            buf[8] = r.result as u8;
-            buf[9] = r.stm;
-            // pad zeros for 10..=11
-            w.write_all(&buf)?;
+            w.write_all(&encode_record_bin(&r))?;
             rec_in_shard += 1;
         }
     }
@@ -850,6 +1038,15 @@ pub fn write_shards<P: AsRef<Path>>(
     Ok(out_paths)
 }
 
+fn encode_record_bin(r: &RecordBin) -> [u8; RECORD_SIZE] {
+    let mut buf = [0u8; RECORD_SIZE];
+    buf[0..8].copy_from_slice(&r.key.to_le_bytes());
+    buf[8] = r.result as u8;
+    buf[9] = r.stm;
+    // pad zeros for 10..=11
+    buf
+}
+
 pub fn read_shard<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<RecordBin>> {
     let mut f = BufReader::new(File::open(path)?);
     let mut magic = [0u8; 8];
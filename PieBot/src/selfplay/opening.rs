@@ -0,0 +1,236 @@
+//! Where self-play games start.
+//!
+//! Every game starts from a base position (the start position, or a random
+//! entry of the `openings_path` suite) and may then walk a few opening plies
+//! away from it, either uniformly at random or through a book. The choice is
+//! recorded as an [`OpeningInfo`] so the JSONL output says which opening a
+//! position came from.
+
+use super::{build_selfplay_searcher, game_seed, mix_u64, SelfPlayParams};
+use crate::io::polyglot::{BookSelection, PolyglotBook};
+use crate::search::alphabeta::SearchParams;
+use cozy_chess::{Board, GameStatus, Move};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::path::{Path, PathBuf};
+
+/// Give up looking for a random walk inside the eval window after this many
+/// tries and keep the most balanced one seen.
+const RANDOM_WALK_ATTEMPTS: usize = 32;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum OpeningStrategy {
+    /// The base position as is.
+    #[default]
+    Suite,
+    /// `plies` uniformly random legal moves. With `max_cp > 0`, walks whose
+    /// `depth`-ply search leaves the side to move outside `±max_cp` are
+    /// rejected and redrawn.
+    RandomWalk {
+        plies: usize,
+        max_cp: i32,
+        depth: u32,
+    },
+    /// Weighted book moves to a random depth in `1..=max_plies`. `path` is a
+    /// Polyglot `.bin` book, or a `.pgn` database whose first `max_plies`
+    /// moves are turned into one.
+    Book { path: PathBuf, max_plies: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpeningSource {
+    Startpos,
+    Suite,
+    RandomWalk,
+    Book,
+}
+
+/// How a game's start position was chosen.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct OpeningInfo {
+    pub source: OpeningSource,
+    /// Line of the base position in the openings suite.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suite_index: Option<usize>,
+    /// File name of the book.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub book: Option<String>,
+    /// Opening moves played from the base position to the game's start.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub moves: Vec<String>,
+    /// Shallow search score of a random walk, from the side to move.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eval_cp: Option<i32>,
+    /// With paired openings, the index of the pair this game belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pair: Option<usize>,
+}
+
+/// The suite and book for a run, loaded once and shared by all games.
+pub(crate) struct Openings {
+    suite: Vec<Board>,
+    book: Option<(PolyglotBook, String)>,
+}
+
+impl Openings {
+    pub(crate) fn load(params: &SelfPlayParams) -> Result<Self, String> {
+        let suite = match params.openings_path {
+            Some(ref p) => crate::io::openings::load_fen_suite(p)?,
+            None => Vec::new(),
+        };
+        let book = match &params.opening_strategy {
+            OpeningStrategy::Book { path, max_plies } => {
+                let book = load_book(path, *max_plies)?;
+                if book.is_empty() {
+                    return Err(format!("opening book {} has no entries", path.display()));
+                }
+                let name = path
+                    .file_name()
+                    .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+                Some((book, name))
+            }
+            _ => None,
+        };
+        Ok(Self { suite, book })
+    }
+
+    /// The start position of game `game_idx`. With `paired_openings`, games
+    /// `2k` and `2k + 1` get the same opening; one engine plays both sides,
+    /// so the pair covers the opening from each side's point of view.
+    pub(crate) fn pick(&self, params: &SelfPlayParams, game_idx: usize) -> (Board, OpeningInfo) {
+        let (opening_idx, pair) = if params.paired_openings {
+            (game_idx & !1, Some(game_idx / 2))
+        } else {
+            (game_idx, None)
+        };
+        let seed = game_seed(params.seed, opening_idx);
+        let mut info = OpeningInfo {
+            source: OpeningSource::Startpos,
+            suite_index: None,
+            book: None,
+            moves: Vec::new(),
+            eval_cp: None,
+            pair,
+        };
+        let base = if self.suite.is_empty() {
+            Board::default()
+        } else {
+            let idx = (mix_u64(seed ^ 0xA5A5_A5A5_A5A5_A5A5) as usize) % self.suite.len();
+            info.source = OpeningSource::Suite;
+            info.suite_index = Some(idx);
+            self.suite[idx].clone()
+        };
+        let mut rng = SmallRng::seed_from_u64(mix_u64(seed ^ 0x4F50_454E_494E_4753)); // "OPENINGS"
+        let (board, moves) = match &params.opening_strategy {
+            OpeningStrategy::Suite => (base, Vec::new()),
+            &OpeningStrategy::RandomWalk {
+                plies,
+                max_cp,
+                depth,
+            } => {
+                info.source = OpeningSource::RandomWalk;
+                let (board, moves, eval_cp) =
+                    random_walk(params, &base, plies, max_cp, depth, &mut rng);
+                info.eval_cp = eval_cp;
+                (board, moves)
+            }
+            OpeningStrategy::Book { max_plies, .. } => {
+                let (book, name) = self.book.as_ref().expect("book loaded with the strategy");
+                info.source = OpeningSource::Book;
+                info.book = Some(name.clone());
+                let target = rng.gen_range(1..=(*max_plies).max(1));
+                book_walk(book, &base, target, &mut rng)
+            }
+        };
+        info.moves = moves.iter().map(Move::to_string).collect();
+        (board, info)
+    }
+}
+
+fn load_book(path: &Path, max_plies: usize) -> Result<PolyglotBook, String> {
+    if path.extension().and_then(|x| x.to_str()) == Some("pgn") {
+        PolyglotBook::from_pgn(path, max_plies)
+    } else {
+        PolyglotBook::open(path)
+    }
+}
+
+fn book_walk(
+    book: &PolyglotBook,
+    base: &Board,
+    plies: usize,
+    rng: &mut SmallRng,
+) -> (Board, Vec<Move>) {
+    let mut board = base.clone();
+    let mut moves = Vec::new();
+    while moves.len() < plies {
+        let Some(mv) = book.choose(&board, BookSelection::Weighted, rng) else {
+            break;
+        };
+        board.play_unchecked(mv);
+        moves.push(mv);
+    }
+    (board, moves)
+}
+
+fn random_walk(
+    params: &SelfPlayParams,
+    base: &Board,
+    plies: usize,
+    max_cp: i32,
+    depth: u32,
+    rng: &mut SmallRng,
+) -> (Board, Vec<Move>, Option<i32>) {
+    let mut searcher = (max_cp > 0).then(|| build_selfplay_searcher(params));
+    let mut best: Option<(Board, Vec<Move>, Option<i32>)> = None;
+    for _ in 0..RANDOM_WALK_ATTEMPTS {
+        let Some((board, moves)) = random_plies(base, plies, rng) else {
+            continue;
+        };
+        let Some(searcher) = searcher.as_mut() else {
+            return (board, moves, None);
+        };
+        let p = SearchParams {
+            depth: depth.max(1),
+            use_tt: true,
+            order_captures: true,
+            use_history: true,
+            threads: 1,
+            deterministic: true,
+            ..Default::default()
+        };
+        let score = searcher.search_with_params(&board, p).score_cp;
+        if score.abs() <= max_cp {
+            return (board, moves, Some(score));
+        }
+        if best
+            .as_ref()
+            .is_none_or(|(_, _, s)| s.is_some_and(|s| score.abs() < s.abs()))
+        {
+            best = Some((board, moves, Some(score)));
+        }
+    }
+    best.unwrap_or_else(|| (base.clone(), Vec::new(), None))
+}
+
+/// `plies` random legal moves from `base`, or `None` if the game ends on
+/// the way.
+fn random_plies(base: &Board, plies: usize, rng: &mut SmallRng) -> Option<(Board, Vec<Move>)> {
+    let mut board = base.clone();
+    let mut moves = Vec::with_capacity(plies);
+    for _ in 0..plies {
+        let mut legal = Vec::new();
+        board.generate_moves(|ml| {
+            legal.extend(ml);
+            false
+        });
+        if legal.is_empty() {
+            return None;
+        }
+        let mv = legal[rng.gen_range(0..legal.len())];
+        board.play_unchecked(mv);
+        moves.push(mv);
+    }
+    (board.status() == GameStatus::Ongoing).then_some((board, moves))
+}
@@ -0,0 +1,604 @@
+//! Packed binary training records (`PIEPACK`, version 1).
+//!
+//! A file is a 16-byte header followed by fixed 40-byte records, so a
+//! reader can index any record straight out of a byte slice (a memory map,
+//! say) without parsing the rest of the file. Record layout, little-endian:
+//!
+//! | bytes  | field                                                       |
+//! |--------|-------------------------------------------------------------|
+//! | 0..8   | occupancy bitboard, bit `i` for square `i` (a1 = 0)         |
+//! | 8..24  | one nibble per occupied square in square order, low first   |
+//! | 24     | flags: bit 0 black to move, bit 1 outcome valid, bit 2 score |
+//! | 25     | halfmove clock                                              |
+//! | 26..28 | fullmove number                                             |
+//! | 28..30 | teacher score, centipawns from White's side                 |
+//! | 30     | game result from White's side: 1, 0 or -1                   |
+//! | 31     | teacher depth, 0 when unknown                               |
+//! | 32..34 | ply within the game                                         |
+//! | 34..36 | teacher best move, Polyglot encoding, 0 when none           |
+//! | 36..38 | played move, Polyglot encoding, 0 when none                 |
+//! | 38     | game termination code, 255 when unknown                     |
+//! | 39     | reserved, 0                                                 |
+//!
+//! Piece nibbles are `color * 6 + piece` (pawn .. king), plus 12/13 for a
+//! white/black rook that still has its castling right and 14 for a pawn
+//! that can be taken en passant. Castling rooks are stored by square, so
+//! Chess960 positions round-trip too.
+
+use super::{GameRecord, GameTermination};
+use crate::io::polyglot::{decode_move, encode_move};
+use cozy_chess::{Board, BoardBuilder, Color, Move, Piece, Rank, Square};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde_json::{Map, Value};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub const PACKED_MAGIC: &[u8; 8] = b"PIEPACK\0";
+pub const PACKED_VERSION: u16 = 1;
+pub const PACKED_HEADER_SIZE: usize = 16;
+pub const PACKED_RECORD_SIZE: usize = 40;
+/// File extension used for packed shards.
+pub const PACKED_EXTENSION: &str = "piepack";
+
+const FLAG_BLACK_TO_MOVE: u8 = 1;
+const FLAG_OUTCOME_VALID: u8 = 1 << 1;
+const FLAG_HAS_SCORE: u8 = 1 << 2;
+const CASTLING_ROOK: [u8; 2] = [12, 13];
+const EN_PASSANT_PAWN: u8 = 14;
+const NO_TERMINATION: u8 = u8::MAX;
+
+/// One training position with its targets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackedPosition {
+    pub board: Board,
+    /// Teacher score in centipawns from White's side.
+    pub score_cp: Option<i16>,
+    /// 1 white win, 0 draw, -1 black win.
+    pub result: i8,
+    pub outcome_valid: bool,
+    pub ply: u16,
+    pub best_move: Option<Move>,
+    pub played_move: Option<Move>,
+    pub teacher_depth: Option<u8>,
+    pub termination: Option<GameTermination>,
+}
+
+impl PackedPosition {
+    /// A position with no targets beyond an unknown result.
+    pub fn new(board: Board) -> Self {
+        Self {
+            board,
+            score_cp: None,
+            result: 0,
+            outcome_valid: false,
+            ply: 0,
+            best_move: None,
+            played_move: None,
+            teacher_depth: None,
+            termination: None,
+        }
+    }
+
+    pub fn encode(&self) -> [u8; PACKED_RECORD_SIZE] {
+        let mut out = [0u8; PACKED_RECORD_SIZE];
+        out[..24].copy_from_slice(&pack_board(&self.board));
+        let mut flags = 0;
+        if self.board.side_to_move() == Color::Black {
+            flags |= FLAG_BLACK_TO_MOVE;
+        }
+        if self.outcome_valid {
+            flags |= FLAG_OUTCOME_VALID;
+        }
+        if self.score_cp.is_some() {
+            flags |= FLAG_HAS_SCORE;
+        }
+        out[24] = flags;
+        out[25] = self.board.halfmove_clock();
+        out[26..28].copy_from_slice(&self.board.fullmove_number().to_le_bytes());
+        out[28..30].copy_from_slice(&self.score_cp.unwrap_or(0).to_le_bytes());
+        out[30] = self.result as u8;
+        out[31] = self.teacher_depth.unwrap_or(0);
+        out[32..34].copy_from_slice(&self.ply.to_le_bytes());
+        out[34..36].copy_from_slice(&self.best_move.map_or(0, encode_move).to_le_bytes());
+        out[36..38].copy_from_slice(&self.played_move.map_or(0, encode_move).to_le_bytes());
+        out[38] = self.termination.map_or(NO_TERMINATION, termination_code);
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != PACKED_RECORD_SIZE {
+            return Err(format!(
+                "packed record is {} bytes, expected {PACKED_RECORD_SIZE}",
+                bytes.len()
+            ));
+        }
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let flags = bytes[24];
+        let stm = if flags & FLAG_BLACK_TO_MOVE != 0 {
+            Color::Black
+        } else {
+            Color::White
+        };
+        let board = unpack_board(&bytes[..24], stm, bytes[25], u16_at(26))?;
+        let legal_move = |raw: u16| -> Result<Option<Move>, String> {
+            if raw == 0 {
+                return Ok(None);
+            }
+            match decode_move(raw).filter(|&mv| board.is_legal(mv)) {
+                Some(mv) => Ok(Some(mv)),
+                None => Err(format!("packed move {raw:#06x} is illegal in {board}")),
+            }
+        };
+        let termination = match bytes[38] {
+            NO_TERMINATION => None,
+            code => Some(
+                *GameTermination::ALL
+                    .get(usize::from(code))
+                    .ok_or_else(|| format!("unknown termination code {code}"))?,
+            ),
+        };
+        Ok(Self {
+            score_cp: (flags & FLAG_HAS_SCORE != 0).then(|| u16_at(28) as i16),
+            result: bytes[30] as i8,
+            outcome_valid: flags & FLAG_OUTCOME_VALID != 0,
+            teacher_depth: (bytes[31] != 0).then_some(bytes[31]),
+            ply: u16_at(32),
+            best_move: legal_move(u16_at(34))?,
+            played_move: legal_move(u16_at(36))?,
+            termination,
+            board,
+        })
+    }
+
+    /// Build a record from a self-play JSONL object. Needs `fen`; the move
+    /// fields must be legal cozy-chess moves when present.
+    pub fn from_json(map: &Map<String, Value>) -> Result<Self, String> {
+        let fen = map
+            .get("fen")
+            .and_then(Value::as_str)
+            .ok_or("record has no fen")?;
+        let board = crate::io::fen::parse_fen(fen)?;
+        let legal_move = |key: &str| -> Result<Option<Move>, String> {
+            let Some(text) = map.get(key).and_then(Value::as_str) else {
+                return Ok(None);
+            };
+            text.parse::<Move>()
+                .ok()
+                .filter(|&mv| board.is_legal(mv))
+                .map(Some)
+                .ok_or_else(|| format!("{key} {text:?} is illegal in {fen}"))
+        };
+        let best_move = match legal_move("target_best_move")? {
+            Some(mv) => Some(mv),
+            None => legal_move("best_move")?,
+        };
+        let score_cp = map
+            .get("value_cp")
+            .and_then(Value::as_f64)
+            .map(|cp| cp.round().clamp(f64::from(i16::MIN), f64::from(i16::MAX)) as i16);
+        let int = |key: &str| map.get(key).and_then(Value::as_i64);
+        Ok(Self {
+            score_cp,
+            result: int("result").unwrap_or(0).clamp(-1, 1) as i8,
+            // Records from before the field only existed for finished games.
+            outcome_valid: map
+                .get("outcome_valid")
+                .and_then(Value::as_bool)
+                .unwrap_or(true),
+            ply: int("ply").unwrap_or(0).clamp(0, i64::from(u16::MAX)) as u16,
+            best_move,
+            played_move: legal_move("played_move")?,
+            teacher_depth: int("teacher_depth").map(|d| d.clamp(1, 255) as u8),
+            termination: map
+                .get("termination")
+                .and_then(Value::as_str)
+                .and_then(GameTermination::parse),
+            board,
+        })
+    }
+
+    /// The self-play JSONL fields this record carries; run and game ids and
+    /// policy samples are not stored in the packed format.
+    pub fn to_json(&self) -> Map<String, Value> {
+        let mut map = Map::new();
+        map.insert("fen".to_string(), Value::from(self.board.to_string()));
+        map.insert("ply".to_string(), Value::from(self.ply));
+        map.insert("result".to_string(), Value::from(self.result));
+        map.insert("result_q".to_string(), Value::from(f32::from(self.result)));
+        map.insert("outcome_valid".to_string(), Value::from(self.outcome_valid));
+        if let Some(termination) = self.termination {
+            map.insert("termination".to_string(), Value::from(termination.as_str()));
+        }
+        if let Some(cp) = self.score_cp {
+            map.insert("value_cp".to_string(), Value::from(f32::from(cp)));
+        }
+        if let Some(depth) = self.teacher_depth {
+            map.insert("teacher_depth".to_string(), Value::from(depth));
+        }
+        if let Some(mv) = self.played_move {
+            map.insert("played_move".to_string(), Value::from(mv.to_string()));
+        }
+        if let Some(mv) = self.best_move {
+            map.insert("target_best_move".to_string(), Value::from(mv.to_string()));
+            map.insert("best_move".to_string(), Value::from(mv.to_string()));
+        }
+        map
+    }
+}
+
+fn termination_code(termination: GameTermination) -> u8 {
+    GameTermination::ALL
+        .iter()
+        .position(|&t| t == termination)
+        .map_or(NO_TERMINATION, |i| i as u8)
+}
+
+const PIECES: [Piece; 6] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+fn back_rank(color: Color) -> Rank {
+    match color {
+        Color::White => Rank::First,
+        Color::Black => Rank::Eighth,
+    }
+}
+
+/// Occupancy plus piece nibbles; see the module docs.
+pub fn pack_board(board: &Board) -> [u8; 24] {
+    let mut out = [0u8; 24];
+    let mut special = [None; Square::NUM];
+    for color in [Color::White, Color::Black] {
+        let rights = board.castle_rights(color);
+        for file in [rights.short, rights.long].into_iter().flatten() {
+            let square = Square::new(file, back_rank(color));
+            special[square as usize] = Some(CASTLING_ROOK[color as usize]);
+        }
+    }
+    if let Some(file) = board.en_passant() {
+        // The pawn that just moved two squares, from the side not to move.
+        let rank = match board.side_to_move() {
+            Color::White => Rank::Fifth,
+            Color::Black => Rank::Fourth,
+        };
+        special[Square::new(file, rank) as usize] = Some(EN_PASSANT_PAWN);
+    }
+    out[..8].copy_from_slice(&board.occupied().0.to_le_bytes());
+    for (index, square) in board.occupied().into_iter().enumerate() {
+        let code = special[square as usize].unwrap_or_else(|| {
+            let color = board.color_on(square).expect("occupied square");
+            let piece = board.piece_on(square).expect("occupied square");
+            color as u8 * 6 + piece as u8
+        });
+        out[8 + index / 2] |= code << (4 * (index % 2));
+    }
+    out
+}
+
+fn unpack_board(bytes: &[u8], stm: Color, halfmove: u8, fullmove: u16) -> Result<Board, String> {
+    let occupied = u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"));
+    if occupied.count_ones() > 32 {
+        return Err(format!("packed board has {} pieces", occupied.count_ones()));
+    }
+    let mut builder = BoardBuilder::empty();
+    builder.side_to_move = stm;
+    builder.halfmove_clock = halfmove;
+    builder.fullmove_number = fullmove;
+    let mut castling_rooks = Vec::new();
+    let mut index = 0;
+    for square in Square::ALL {
+        if occupied & (1 << square as u64) == 0 {
+            continue;
+        }
+        let code = (bytes[8 + index / 2] >> (4 * (index % 2))) & 0x0f;
+        index += 1;
+        let (piece, color) = match code {
+            0..=11 => (
+                PIECES[usize::from(code % 6)],
+                Color::ALL[usize::from(code / 6)],
+            ),
+            12 | 13 => {
+                let color = Color::ALL[usize::from(code - 12)];
+                castling_rooks.push((color, square));
+                (Piece::Rook, color)
+            }
+            EN_PASSANT_PAWN => {
+                let color = !stm;
+                let behind = match color {
+                    Color::White => Rank::Third,
+                    Color::Black => Rank::Sixth,
+                };
+                builder.en_passant = Some(Square::new(square.file(), behind));
+                (Piece::Pawn, color)
+            }
+            _ => return Err(format!("invalid piece code {code} on {square}")),
+        };
+        *builder.square_mut(square) = Some((piece, color));
+    }
+    for (color, rook) in castling_rooks {
+        let king_file = Square::ALL
+            .into_iter()
+            .filter(|&sq| sq.rank() == back_rank(color))
+            .find(|&sq| builder.square(sq) == Some((Piece::King, color)))
+            .map(Square::file)
+            .ok_or_else(|| format!("castling rook on {rook} without a king on its back rank"))?;
+        let rights = builder.castle_rights_mut(color);
+        if rook.file() > king_file {
+            rights.short = Some(rook.file());
+        } else {
+            rights.long = Some(rook.file());
+        }
+    }
+    builder
+        .build()
+        .map_err(|e| format!("packed board is invalid: {e:?}"))
+}
+
+pub(crate) fn header() -> [u8; PACKED_HEADER_SIZE] {
+    let mut out = [0u8; PACKED_HEADER_SIZE];
+    out[..8].copy_from_slice(PACKED_MAGIC);
+    out[8..10].copy_from_slice(&PACKED_VERSION.to_le_bytes());
+    out[10..12].copy_from_slice(&(PACKED_RECORD_SIZE as u16).to_le_bytes());
+    out
+}
+
+/// Records of a packed file, borrowed from its bytes and decoded on demand.
+#[derive(Clone, Copy, Debug)]
+pub struct PackedView<'a> {
+    records: &'a [u8],
+}
+
+impl<'a> PackedView<'a> {
+    /// Check the header and record size. The bytes can come from anywhere,
+    /// including a memory-mapped file.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, String> {
+        if bytes.len() < PACKED_HEADER_SIZE || &bytes[..8] != PACKED_MAGIC {
+            return Err("not a PIEPACK file".to_string());
+        }
+        let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+        let record_size = usize::from(u16::from_le_bytes([bytes[10], bytes[11]]));
+        if version != PACKED_VERSION || record_size != PACKED_RECORD_SIZE {
+            return Err(format!(
+                "unsupported PIEPACK version {version} with {record_size}-byte records"
+            ));
+        }
+        let records = &bytes[PACKED_HEADER_SIZE..];
+        if !records.len().is_multiple_of(PACKED_RECORD_SIZE) {
+            return Err(format!(
+                "PIEPACK body of {} bytes is not a whole number of records",
+                records.len()
+            ));
+        }
+        Ok(Self { records })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len() / PACKED_RECORD_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The undecoded bytes of record `index`.
+    pub fn raw(&self, index: usize) -> &'a [u8] {
+        &self.records[index * PACKED_RECORD_SIZE..(index + 1) * PACKED_RECORD_SIZE]
+    }
+
+    pub fn get(&self, index: usize) -> Result<PackedPosition, String> {
+        PackedPosition::decode(self.raw(index)).map_err(|e| format!("record {index}: {e}"))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Result<PackedPosition, String>> + 'a {
+        let view = *self;
+        (0..view.len()).map(move |index| view.get(index))
+    }
+}
+
+/// A packed file read into memory.
+pub struct PackedFile {
+    bytes: Vec<u8>,
+}
+
+impl PackedFile {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        PackedView::parse(&bytes).map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(Self { bytes })
+    }
+
+    pub fn view(&self) -> PackedView<'_> {
+        PackedView::parse(&self.bytes).expect("checked in open")
+    }
+}
+
+/// Streams records after a PIEPACK header.
+pub struct PackedWriter<W: Write> {
+    inner: W,
+    records: usize,
+}
+
+impl<W: Write> PackedWriter<W> {
+    pub fn new(mut inner: W) -> std::io::Result<Self> {
+        inner.write_all(&header())?;
+        Ok(Self { inner, records: 0 })
+    }
+
+    pub fn write(&mut self, position: &PackedPosition) -> std::io::Result<()> {
+        self.write_raw(&position.encode())
+    }
+
+    /// Copy an already encoded record, as from [`PackedView::raw`].
+    pub fn write_raw(&mut self, record: &[u8]) -> std::io::Result<()> {
+        debug_assert_eq!(record.len(), PACKED_RECORD_SIZE);
+        self.inner.write_all(record)?;
+        self.records += 1;
+        Ok(())
+    }
+
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// One record per ply, with the same targets as the JSONL shards.
+pub fn game_to_packed(game: &GameRecord) -> Vec<PackedPosition> {
+    let mut out = Vec::with_capacity(game.moves.len());
+    let mut board = Board::from_fen(&game.start_fen, false).unwrap_or_default();
+    for (ply, mv_str) in game.moves.iter().enumerate() {
+        let Some(played) = mv_str.parse::<Move>().ok().filter(|&m| board.is_legal(m)) else {
+            break;
+        };
+        let score_cp = game.move_value_cp.get(ply).copied().flatten();
+        let best_move = game
+            .move_target_best
+            .get(ply)
+            .and_then(|s| s.as_deref())
+            .and_then(|s| s.parse::<Move>().ok())
+            .filter(|&m| board.is_legal(m))
+            .unwrap_or(played);
+        out.push(PackedPosition {
+            board: board.clone(),
+            score_cp: score_cp.map(|cp| cp.round().clamp(-32_768.0, 32_767.0) as i16),
+            result: game.result,
+            outcome_valid: game.outcome_valid,
+            ply: ply.min(usize::from(u16::MAX)) as u16,
+            best_move: Some(best_move),
+            played_move: Some(played),
+            teacher_depth: score_cp
+                .and(game.move_teacher_depth.get(ply).copied().flatten())
+                .map(|d| d.clamp(1, 255) as u8),
+            termination: Some(game.termination),
+        });
+        board.play_unchecked(played);
+    }
+    out
+}
+
+fn shard_path(out_dir: &Path, index: usize) -> PathBuf {
+    out_dir.join(format!("shard_{index:06}.{PACKED_EXTENSION}"))
+}
+
+/// Write records into `shard_NNNNNN.piepack` files of at most
+/// `max_records_per_shard` records each, stopping at the first error.
+pub fn write_packed_records<I>(
+    records: I,
+    out_dir: &Path,
+    max_records_per_shard: usize,
+) -> Result<Vec<PathBuf>, String>
+where
+    I: IntoIterator<Item = Result<[u8; PACKED_RECORD_SIZE], String>>,
+{
+    let io_err = |e: std::io::Error| format!("{}: {e}", out_dir.display());
+    std::fs::create_dir_all(out_dir).map_err(io_err)?;
+    let max_records_per_shard = max_records_per_shard.max(1);
+    let mut paths = Vec::new();
+    let mut writer: Option<PackedWriter<BufWriter<std::fs::File>>> = None;
+    for record in records {
+        let record = record?;
+        if writer
+            .as_ref()
+            .is_none_or(|w| w.records() >= max_records_per_shard)
+        {
+            if let Some(w) = writer.take() {
+                w.finish().map_err(io_err)?;
+            }
+            let path = shard_path(out_dir, paths.len());
+            let file = std::fs::File::create(&path).map_err(io_err)?;
+            writer = Some(PackedWriter::new(BufWriter::new(file)).map_err(io_err)?);
+            paths.push(path);
+        }
+        writer
+            .as_mut()
+            .unwrap()
+            .write_raw(&record)
+            .map_err(io_err)?;
+    }
+    if let Some(w) = writer {
+        w.finish().map_err(io_err)?;
+    }
+    Ok(paths)
+}
+
+/// Packed counterpart of `write_jsonl_shards`.
+pub fn write_packed_shards<P: AsRef<Path>>(
+    games: &[GameRecord],
+    out_dir: P,
+    max_records_per_shard: usize,
+) -> Result<Vec<PathBuf>, String> {
+    let records = games
+        .iter()
+        .flat_map(|g| game_to_packed(g).into_iter().map(|p| Ok(p.encode())));
+    write_packed_records(records, out_dir.as_ref(), max_records_per_shard)
+}
+
+/// Shuffle the records of `inputs` into new shards of `records_per_shard`
+/// records. Records are first scattered at random into one bucket file per
+/// output shard, then each bucket is shuffled in memory, so memory use is
+/// bounded by the shard size rather than the data set.
+pub fn shuffle_packed(
+    inputs: &[PathBuf],
+    out_dir: &Path,
+    records_per_shard: usize,
+    seed: u64,
+) -> Result<Vec<PathBuf>, String> {
+    let io_err = |e: std::io::Error| e.to_string();
+    let records_per_shard = records_per_shard.max(1);
+    let mut total = 0usize;
+    for path in inputs {
+        total += PackedFile::open(path)?.view().len();
+    }
+    std::fs::create_dir_all(out_dir).map_err(io_err)?;
+    let buckets = total.div_ceil(records_per_shard).max(1);
+    let bucket_path = |i: usize| out_dir.join(format!(".shuffle_bucket_{i:06}"));
+    let mut rng = SmallRng::seed_from_u64(seed);
+    {
+        let mut writers = (0..buckets)
+            .map(|i| std::fs::File::create(bucket_path(i)).map(BufWriter::new))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io_err)?;
+        for path in inputs {
+            let file = PackedFile::open(path)?;
+            let view = file.view();
+            for index in 0..view.len() {
+                let bucket = rng.gen_range(0..buckets);
+                writers[bucket].write_all(view.raw(index)).map_err(io_err)?;
+            }
+        }
+        for w in &mut writers {
+            w.flush().map_err(io_err)?;
+        }
+    }
+
+    let mut out = Vec::with_capacity(buckets);
+    for i in 0..buckets {
+        let bytes = std::fs::read(bucket_path(i)).map_err(io_err)?;
+        let mut records: Vec<&[u8]> = bytes.chunks_exact(PACKED_RECORD_SIZE).collect();
+        records.shuffle(&mut rng);
+        let path = shard_path(out_dir, i);
+        let mut w = PackedWriter::new(BufWriter::new(
+            std::fs::File::create(&path).map_err(io_err)?,
+        ))
+        .map_err(io_err)?;
+        for record in records {
+            w.write_raw(record).map_err(io_err)?;
+        }
+        w.finish().map_err(io_err)?;
+        std::fs::remove_file(bucket_path(i)).map_err(io_err)?;
+        out.push(path);
+    }
+    Ok(out)
+}
@@ -0,0 +1,272 @@
+//! Crash-safe shard output for long self-play runs.
+//!
+//! Games are written as they finish instead of after the whole run. Every
+//! enabled format writes into `shard_NNNNNN.<ext>.tmp`; once the open shards
+//! hold `max_records_per_shard` positions they are closed together at a game
+//! boundary: flushed, fsynced and renamed into place, after which the
+//! manifest is rewritten (also atomically) with the indices of every game in
+//! a finished shard. A crash therefore loses at most the games of the open
+//! shards, and `--resume` replays exactly those.
+
+use super::packed::{self, game_to_packed, PACKED_EXTENSION};
+use super::{
+    encode_record_bin, flatten_game_to_records, game_jsonl_lines, game_to_pgn, run_id, GameRecord,
+    SHARD_MAGIC,
+};
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = ".selfplay_manifest.json";
+const MANIFEST_VERSION: u32 = 1;
+
+/// Where a streaming run writes; `None` disables a format.
+#[derive(Clone, Debug, Default)]
+pub struct StreamOutputs {
+    pub bin_dir: Option<PathBuf>,
+    pub jsonl_dir: Option<PathBuf>,
+    pub packed_dir: Option<PathBuf>,
+    /// Games are appended to this PGN file as their shards close; a crash
+    /// between the append and the manifest update repeats those games.
+    pub pgn_path: Option<PathBuf>,
+    pub manifest_path: PathBuf,
+    pub max_records_per_shard: usize,
+}
+
+/// Progress of a run, as saved after every shard rotation.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub run_id: String,
+    pub seed: u64,
+    /// Index of the next shard to write in every format.
+    pub next_shard: usize,
+    /// Games whose records are all in finished shards, ascending.
+    pub completed_games: BTreeSet<usize>,
+}
+
+impl Manifest {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            run_id: run_id(seed),
+            seed,
+            next_shard: 0,
+            completed_games: BTreeSet::new(),
+        }
+    }
+
+    /// The saved manifest, or `None` if there is none yet.
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        };
+        let manifest: Self =
+            serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+        if manifest.version != MANIFEST_VERSION {
+            return Err(format!(
+                "{}: unsupported manifest version {}",
+                path.display(),
+                manifest.version
+            ));
+        }
+        Ok(Some(manifest))
+    }
+
+    fn save(&self, path: &Path) -> std::io::Result<()> {
+        let tmp = tmp_path(path);
+        let mut f = File::create(&tmp)?;
+        serde_json::to_writer_pretty(&mut f, self)?;
+        f.sync_all()?;
+        fs::rename(&tmp, path)?;
+        sync_dir(path)
+    }
+}
+
+struct OpenShard {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl OpenShard {
+    fn create(dir: &Path, index: usize, ext: &str, header: &[u8]) -> std::io::Result<Self> {
+        let path = dir.join(format!("shard_{index:06}.{ext}"));
+        let mut writer = BufWriter::new(File::create(tmp_path(&path))?);
+        writer.write_all(header)?;
+        Ok(Self { path, writer })
+    }
+
+    fn close(self) -> std::io::Result<PathBuf> {
+        let file = self.writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        fs::rename(tmp_path(&self.path), &self.path)?;
+        sync_dir(&self.path)?;
+        Ok(self.path)
+    }
+}
+
+/// Receives finished games and writes them to rotating shards.
+pub struct StreamingShardWriter {
+    outputs: StreamOutputs,
+    manifest: Manifest,
+    bin: Option<OpenShard>,
+    jsonl: Option<OpenShard>,
+    packed: Option<OpenShard>,
+    pgn: String,
+    open_games: Vec<usize>,
+    open_records: usize,
+    shards: Vec<PathBuf>,
+}
+
+impl StreamingShardWriter {
+    /// Start a run for `seed`. With `resume`, continue the run recorded in
+    /// the manifest, if any, which must belong to the same seed; a fresh run
+    /// replaces any earlier manifest and truncates the PGN output.
+    pub fn create(outputs: StreamOutputs, seed: u64, resume: bool) -> Result<Self, String> {
+        let io_err = |e: std::io::Error| e.to_string();
+        let saved = Manifest::load(&outputs.manifest_path)?.filter(|_| resume);
+        let resumed = saved.is_some();
+        let manifest = match saved {
+            Some(saved) => {
+                if saved.seed != seed || saved.run_id != run_id(seed) {
+                    return Err(format!(
+                        "{} belongs to run {} (seed {}), not seed {seed}",
+                        outputs.manifest_path.display(),
+                        saved.run_id,
+                        saved.seed
+                    ));
+                }
+                saved
+            }
+            None => Manifest::new(seed),
+        };
+        for dir in [&outputs.bin_dir, &outputs.jsonl_dir, &outputs.packed_dir]
+            .into_iter()
+            .flatten()
+        {
+            fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        if let Some(parent) = outputs.manifest_path.parent() {
+            fs::create_dir_all(parent).map_err(io_err)?;
+        }
+        if !resumed {
+            if let Some(pgn) = outputs.pgn_path.as_ref() {
+                File::create(pgn).map_err(io_err)?;
+            }
+            manifest.save(&outputs.manifest_path).map_err(io_err)?;
+        }
+        Ok(Self {
+            outputs,
+            manifest,
+            bin: None,
+            jsonl: None,
+            packed: None,
+            pgn: String::new(),
+            open_games: Vec::new(),
+            open_records: 0,
+            shards: Vec::new(),
+        })
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Whether `game_idx` is already in a finished shard.
+    pub fn is_completed(&self, game_idx: usize) -> bool {
+        self.manifest.completed_games.contains(&game_idx)
+    }
+
+    pub fn write_game(&mut self, game_idx: usize, game: &GameRecord) -> std::io::Result<()> {
+        let shard = self.manifest.next_shard;
+        if let Some(dir) = self.outputs.bin_dir.as_ref() {
+            if self.bin.is_none() {
+                self.bin = Some(OpenShard::create(dir, shard, "bin", SHARD_MAGIC)?);
+            }
+            let w = &mut self.bin.as_mut().unwrap().writer;
+            for r in flatten_game_to_records(game) {
+                w.write_all(&encode_record_bin(&r))?;
+            }
+        }
+        if let Some(dir) = self.outputs.jsonl_dir.as_ref() {
+            if self.jsonl.is_none() {
+                self.jsonl = Some(OpenShard::create(dir, shard, "jsonl", &[])?);
+            }
+            let w = &mut self.jsonl.as_mut().unwrap().writer;
+            for line in game_jsonl_lines(game)? {
+                w.write_all(line.as_bytes())?;
+                w.write_all(b"\n")?;
+            }
+        }
+        if let Some(dir) = self.outputs.packed_dir.as_ref() {
+            if self.packed.is_none() {
+                let header = packed::header();
+                self.packed = Some(OpenShard::create(dir, shard, PACKED_EXTENSION, &header)?);
+            }
+            let w = &mut self.packed.as_mut().unwrap().writer;
+            for position in game_to_packed(game) {
+                w.write_all(&position.encode())?;
+            }
+        }
+        if self.outputs.pgn_path.is_some() {
+            self.pgn.push_str(&game_to_pgn(game, game_idx + 1).to_pgn());
+            self.pgn.push('\n');
+        }
+        self.open_games.push(game_idx);
+        self.open_records += game.moves.len();
+        if self.open_records >= self.outputs.max_records_per_shard.max(1) {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    /// Close the open shards and return every shard this writer finished.
+    pub fn finish(mut self) -> std::io::Result<Vec<PathBuf>> {
+        self.rotate()?;
+        Ok(self.shards)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.open_games.is_empty() {
+            return Ok(());
+        }
+        for shard in [self.bin.take(), self.jsonl.take(), self.packed.take()]
+            .into_iter()
+            .flatten()
+        {
+            self.shards.push(shard.close()?);
+        }
+        if let Some(path) = self.outputs.pgn_path.as_ref() {
+            let mut f = OpenOptions::new().create(true).append(true).open(path)?;
+            f.write_all(self.pgn.as_bytes())?;
+            f.sync_all()?;
+            self.pgn.clear();
+        }
+        self.manifest.next_shard += 1;
+        self.manifest
+            .completed_games
+            .extend(self.open_games.drain(..));
+        self.open_records = 0;
+        self.manifest.save(&self.outputs.manifest_path)
+    }
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Make a rename durable by syncing its directory; not possible on Windows.
+fn sync_dir(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
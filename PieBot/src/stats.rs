@@ -0,0 +1,570 @@
+//! Match statistics over colour-reversed opening pairs.
+//!
+//! A pair (the same opening played once with each colour) is the unit of
+//! evidence: its two games share the opening's bias, so pair scores are far
+//! less noisy than game scores and are what both the SPRT and the bootstrap
+//! consume. Scores are from the point of view of the engine under test.
+//!
+//! Tournaments of more than two engines are rated jointly by
+//! [`ml_ratings`], a maximum-likelihood fit over the whole result matrix.
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+/// Expected score of a player `elo` logistic Elo above its opponent.
+pub fn logistic_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Inverse of [`logistic_score`]; infinite at scores of 0 and 1.
+pub fn logistic_elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Counts of pair outcomes by points scored over the two games:
+/// `counts[i]` holds the pairs that scored `i / 2` points (0, 0.5, ... 2).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Pentanomial {
+    pub counts: [u64; 5],
+}
+
+impl Pentanomial {
+    /// Record one pair worth `points` (0..=2 in steps of 0.5).
+    pub fn add_pair(&mut self, points: f64) -> Result<(), String> {
+        let slot = points * 2.0;
+        if !(0.0..=4.0).contains(&slot) || slot.fract() != 0.0 {
+            return Err(format!("pair score {points} is not one of 0, 0.5, .. 2"));
+        }
+        self.counts[slot as usize] += 1;
+        Ok(())
+    }
+
+    pub fn pairs(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Mean score per game over all pairs, in `[0, 1]`.
+    pub fn score(&self) -> Option<f64> {
+        let pairs = self.pairs();
+        if pairs == 0 {
+            return None;
+        }
+        let points: f64 = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| n as f64 * i as f64 / 4.0)
+            .sum();
+        Some(points / pairs as f64)
+    }
+
+    /// Generalized log-likelihood ratio of "score = logistic_score(elo1)"
+    /// against "score = logistic_score(elo0)".
+    ///
+    /// Each hypothesis is the maximum-likelihood pentanomial distribution
+    /// with that mean (the construction fishtest uses), so the ratio needs no
+    /// draw model. Empty outcome classes get a small pseudo-count first:
+    /// otherwise a short run without, say, a lost pair would rule such pairs
+    /// out entirely and the variance, and with it the LLR, would blow up.
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let pairs = self.pairs();
+        if pairs == 0 {
+            return 0.0;
+        }
+        let regularized: Vec<f64> = self
+            .counts
+            .iter()
+            .map(|&n| if n == 0 { 1e-3 } else { n as f64 })
+            .collect();
+        let total: f64 = regularized.iter().sum();
+        let probs: Vec<f64> = regularized.iter().map(|n| n / total).collect();
+        let p0 = mle_with_mean(&probs, logistic_score(elo0));
+        let p1 = mle_with_mean(&probs, logistic_score(elo1));
+        let per_pair: f64 = probs
+            .iter()
+            .zip(p0.iter().zip(&p1))
+            .map(|(p, (q0, q1))| p * (q1 / q0).ln())
+            .sum();
+        pairs as f64 * per_pair
+    }
+}
+
+/// Pair score of outcome class `i`.
+fn class_score(i: usize) -> f64 {
+    i as f64 / 4.0
+}
+
+/// Distribution on the five pair outcomes closest (in likelihood) to `probs`
+/// whose mean is `mean`: `q_i = p_i / (1 + lambda * (x_i - mean))`, with
+/// `lambda` found by bisection. Every `p_i` is positive and the outcomes span
+/// `[0, 1]`, so a root exists for any `mean` strictly inside that range.
+fn mle_with_mean(probs: &[f64], mean: f64) -> Vec<f64> {
+    let mean = mean.clamp(1e-9, 1.0 - 1e-9);
+    // 1 + lambda * (x - mean) must stay positive for x = 0 and x = 1.
+    let mut lo = -1.0 / (1.0 - mean);
+    let mut hi = 1.0 / mean;
+    let gradient = |lambda: f64| -> f64 {
+        probs
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let d = class_score(i) - mean;
+                p * d / (1.0 + lambda * d)
+            })
+            .sum()
+    };
+    // The gradient falls monotonically from +inf at `lo` to -inf at `hi`.
+    for _ in 0..200 {
+        let mid = 0.5 * (lo + hi);
+        if gradient(mid) > 0.0 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    let lambda = 0.5 * (lo + hi);
+    probs
+        .iter()
+        .enumerate()
+        .map(|(i, p)| p / (1.0 + lambda * (class_score(i) - mean)))
+        .collect()
+}
+
+/// Hypotheses and error rates of a sequential probability ratio test.
+/// Elo values are logistic, engine under test minus reference.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct SprtConfig {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl SprtConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.elo0.is_finite() && self.elo1.is_finite() && self.elo0 < self.elo1) {
+            return Err(format!(
+                "SPRT needs finite elo0 < elo1 (got {} and {})",
+                self.elo0, self.elo1
+            ));
+        }
+        for (name, rate) in [("alpha", self.alpha), ("beta", self.beta)] {
+            if !(rate > 0.0 && rate < 0.5) {
+                return Err(format!("SPRT {name} must be in (0, 0.5), got {rate}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// `(lower, upper)` LLR bounds: crossing `lower` accepts H0, crossing
+    /// `upper` accepts H1.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum SprtDecision {
+    /// The engine under test is no better than `elo0`.
+    H0,
+    /// The engine under test is at least `elo1` better.
+    H1,
+}
+
+/// Point at which the test first crossed a bound.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct SprtStop {
+    pub decision: SprtDecision,
+    pub pairs: u64,
+    pub llr: f64,
+}
+
+/// Pentanomial SPRT fed one opening pair at a time. The first bound crossing
+/// is kept as the decision; pairs recorded after it (games already in flight
+/// when a match stops) still count towards the totals and trajectory.
+#[derive(Clone, Debug)]
+pub struct Sprt {
+    config: SprtConfig,
+    pentanomial: Pentanomial,
+    trajectory: Vec<f64>,
+    stop: Option<SprtStop>,
+}
+
+impl Sprt {
+    pub fn new(config: SprtConfig) -> Result<Self, String> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            pentanomial: Pentanomial::default(),
+            trajectory: Vec::new(),
+            stop: None,
+        })
+    }
+
+    /// Add one pair worth `points` and return the decision once one is
+    /// reached.
+    pub fn record_pair(&mut self, points: f64) -> Result<Option<SprtDecision>, String> {
+        self.pentanomial.add_pair(points)?;
+        let llr = self.llr();
+        self.trajectory.push(llr);
+        if self.stop.is_none() {
+            let (lower, upper) = self.config.bounds();
+            let decision = if llr >= upper {
+                Some(SprtDecision::H1)
+            } else if llr <= lower {
+                Some(SprtDecision::H0)
+            } else {
+                None
+            };
+            self.stop = decision.map(|decision| SprtStop {
+                decision,
+                pairs: self.pentanomial.pairs(),
+                llr,
+            });
+        }
+        Ok(self.decision())
+    }
+
+    pub fn llr(&self) -> f64 {
+        self.pentanomial.llr(self.config.elo0, self.config.elo1)
+    }
+
+    pub fn decision(&self) -> Option<SprtDecision> {
+        self.stop.map(|stop| stop.decision)
+    }
+
+    pub fn stop(&self) -> Option<SprtStop> {
+        self.stop
+    }
+
+    pub fn config(&self) -> SprtConfig {
+        self.config
+    }
+
+    pub fn pentanomial(&self) -> &Pentanomial {
+        &self.pentanomial
+    }
+
+    /// LLR after each recorded pair, in recording order.
+    pub fn trajectory(&self) -> &[f64] {
+        &self.trajectory
+    }
+}
+
+/// Percentile bootstrap interval of a mean.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct BootstrapInterval {
+    pub mean: f64,
+    pub lower: f64,
+    pub upper: f64,
+    pub confidence: f64,
+    pub samples: usize,
+    pub seed: u64,
+}
+
+/// Bootstrap interval of the mean of `values` (one per opening pair):
+/// `samples` resamples with replacement from a generator seeded by `seed`,
+/// with the `(1 - confidence) / 2` tails cut off by linear interpolation
+/// between order statistics. `None` for an empty sample.
+pub fn paired_bootstrap(
+    values: &[f64],
+    samples: usize,
+    confidence: f64,
+    seed: u64,
+) -> Option<BootstrapInterval> {
+    if values.is_empty() || samples == 0 {
+        return None;
+    }
+    let n = values.len();
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut means: Vec<f64> = (0..samples)
+        .map(|_| (0..n).map(|_| values[rng.gen_range(0..n)]).sum::<f64>() / n as f64)
+        .collect();
+    means.sort_unstable_by(f64::total_cmp);
+    let tail = (1.0 - confidence.clamp(0.0, 1.0)) / 2.0;
+    Some(BootstrapInterval {
+        mean: values.iter().sum::<f64>() / n as f64,
+        lower: percentile(&means, tail),
+        upper: percentile(&means, 1.0 - tail),
+        confidence,
+        samples,
+        seed,
+    })
+}
+
+/// Linear-interpolated percentile of ascending `sorted` at `probability`.
+fn percentile(sorted: &[f64], probability: f64) -> f64 {
+    let rank = probability * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Quantile function of the standard normal distribution (Acklam's rational
+/// approximation, relative error below 1.2e-9), for `p` in `(0, 1)`.
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.02425;
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p <= 0.0 {
+        f64::NEG_INFINITY
+    } else if p >= 1.0 {
+        f64::INFINITY
+    } else if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Games and points between every pair of players of a tournament.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultMatrix {
+    players: usize,
+    games: Vec<f64>,  // games[i * players + j]: games between i and j
+    points: Vec<f64>, // points[i * players + j]: points i scored against j
+}
+
+impl ResultMatrix {
+    pub fn new(players: usize) -> Self {
+        Self {
+            players,
+            games: vec![0.0; players * players],
+            points: vec![0.0; players * players],
+        }
+    }
+
+    pub fn players(&self) -> usize {
+        self.players
+    }
+
+    /// Record one game; `white_score` is 1, 0.5 or 0.
+    pub fn add_game(&mut self, white: usize, black: usize, white_score: f64) {
+        let n = self.players;
+        self.games[white * n + black] += 1.0;
+        self.games[black * n + white] += 1.0;
+        self.points[white * n + black] += white_score;
+        self.points[black * n + white] += 1.0 - white_score;
+    }
+
+    pub fn games(&self, player: usize, opponent: usize) -> f64 {
+        self.games[player * self.players + opponent]
+    }
+
+    pub fn points(&self, player: usize, opponent: usize) -> f64 {
+        self.points[player * self.players + opponent]
+    }
+}
+
+/// Maximum-likelihood logistic Elo ratings, centred on a pool mean of zero,
+/// with their covariance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MlRatings {
+    elo: Vec<f64>,
+    covariance: Vec<f64>, // players x players, in Elo squared
+}
+
+impl MlRatings {
+    pub fn elo(&self) -> &[f64] {
+        &self.elo
+    }
+
+    /// Ratings shifted so that `anchor`, if given, is at zero.
+    pub fn relative_to(&self, anchor: Option<usize>) -> Vec<f64> {
+        let offset = anchor.map_or(0.0, |a| self.elo[a]);
+        self.elo.iter().map(|elo| elo - offset).collect()
+    }
+
+    /// Standard error of `player`'s rating: against the pool mean, or
+    /// against `anchor` (whose own error is then zero).
+    pub fn std_error(&self, player: usize, anchor: Option<usize>) -> f64 {
+        let n = self.elo.len();
+        let cov = |i: usize, j: usize| self.covariance[i * n + j];
+        let variance = match anchor {
+            Some(a) => cov(player, player) + cov(a, a) - 2.0 * cov(player, a),
+            None => cov(player, player),
+        };
+        variance.max(0.0).sqrt()
+    }
+
+    /// Two-sided Wald interval of `player`'s rating at `confidence`, on the
+    /// scale of [`MlRatings::relative_to`].
+    pub fn interval(&self, player: usize, anchor: Option<usize>, confidence: f64) -> (f64, f64) {
+        let elo = self.relative_to(anchor)[player];
+        let half = normal_quantile(0.5 + confidence / 2.0) * self.std_error(player, anchor);
+        (elo - half, elo + half)
+    }
+}
+
+/// Fit Bradley-Terry ratings to `results` by maximum likelihood, draws
+/// counting as half a win and half a loss (the Ordo convention).
+///
+/// `prior_draws` virtual draws are added to every pairing that was played,
+/// as BayesElo's prior does: without them a player who won or lost every
+/// game has no finite rating. The covariance is the inverse of the observed
+/// Fisher information, pseudo-inverted because only rating differences are
+/// identified. Every player must have games, and the pairings must connect
+/// all players, or the ratings are not comparable.
+pub fn ml_ratings(results: &ResultMatrix, prior_draws: f64) -> Result<MlRatings, String> {
+    let n = results.players();
+    if n < 2 {
+        return Err("ratings need at least two players".to_string());
+    }
+    let mut games = vec![0.0; n * n];
+    let mut wins = vec![0.0; n];
+    for i in 0..n {
+        for j in 0..n {
+            let played = results.games(i, j);
+            if i != j && played > 0.0 {
+                games[i * n + j] = played + prior_draws.max(0.0);
+                wins[i] += results.points(i, j) + 0.5 * prior_draws.max(0.0);
+            }
+        }
+    }
+    if let Some(idle) = (0..n).find(|&i| (0..n).all(|j| games[i * n + j] == 0.0)) {
+        return Err(format!("player {idle} has no games"));
+    }
+    let mut reached = vec![false; n];
+    let mut stack = vec![0];
+    reached[0] = true;
+    while let Some(i) = stack.pop() {
+        for j in 0..n {
+            if games[i * n + j] > 0.0 && !reached[j] {
+                reached[j] = true;
+                stack.push(j);
+            }
+        }
+    }
+    if reached.contains(&false) {
+        return Err("the pairings do not connect every player".to_string());
+    }
+    if let Some(extreme) = (0..n).find(|&i| {
+        let total: f64 = (0..n).map(|j| games[i * n + j]).sum();
+        wins[i] <= 0.0 || wins[i] >= total
+    }) {
+        return Err(format!(
+            "player {extreme} scored 0% or 100%; its rating is unbounded without a prior"
+        ));
+    }
+
+    // Minorization-maximization (Hunter 2004): each step raises the
+    // likelihood and the fixed point is the maximum.
+    let mut gamma = vec![1.0f64; n];
+    for _ in 0..100_000 {
+        let mut next: Vec<f64> = (0..n)
+            .map(|i| {
+                let denominator: f64 = (0..n)
+                    .filter(|&j| games[i * n + j] > 0.0)
+                    .map(|j| games[i * n + j] / (gamma[i] + gamma[j]))
+                    .sum();
+                wins[i] / denominator
+            })
+            .collect();
+        let log_mean = next.iter().map(|g| g.ln()).sum::<f64>() / n as f64;
+        next.iter_mut().for_each(|g| *g /= log_mean.exp());
+        let change = next
+            .iter()
+            .zip(&gamma)
+            .map(|(a, b)| (a.ln() - b.ln()).abs())
+            .fold(0.0, f64::max);
+        gamma = next;
+        if change < 1e-12 {
+            break;
+        }
+    }
+
+    let elo_per_nat = 400.0 / std::f64::consts::LN_10;
+    let elo: Vec<f64> = gamma.iter().map(|g| g.ln() * elo_per_nat).collect();
+    // Fisher information of the log-strengths is a weighted graph
+    // Laplacian; adding J/n makes it invertible without changing the
+    // pseudo-inverse on the subspace of centred ratings.
+    let mut information = vec![1.0 / n as f64; n * n];
+    for i in 0..n {
+        for j in (0..n).filter(|&j| j != i) {
+            let p = gamma[i] / (gamma[i] + gamma[j]);
+            let weight = games[i * n + j] * p * (1.0 - p);
+            information[i * n + j] -= weight;
+            information[i * n + i] += weight;
+        }
+    }
+    let inverse = invert(&information, n)?;
+    let covariance = inverse
+        .iter()
+        .map(|v| (v - 1.0 / n as f64) * elo_per_nat * elo_per_nat)
+        .collect();
+    Ok(MlRatings { elo, covariance })
+}
+
+/// Gauss-Jordan inverse of the row-major `n x n` matrix `m`.
+fn invert(m: &[f64], n: usize) -> Result<Vec<f64>, String> {
+    let mut a = m.to_vec();
+    let mut inv: Vec<f64> = (0..n * n)
+        .map(|k| if k / n == k % n { 1.0 } else { 0.0 })
+        .collect();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&r, &s| a[r * n + col].abs().total_cmp(&a[s * n + col].abs()))
+            .expect("non-empty range");
+        if a[pivot * n + col].abs() < 1e-12 {
+            return Err("rating information matrix is singular".to_string());
+        }
+        for k in 0..n {
+            a.swap(col * n + k, pivot * n + k);
+            inv.swap(col * n + k, pivot * n + k);
+        }
+        let scale = a[col * n + col];
+        for k in 0..n {
+            a[col * n + k] /= scale;
+            inv[col * n + k] /= scale;
+        }
+        for row in (0..n).filter(|&row| row != col) {
+            let factor = a[row * n + col];
+            if factor != 0.0 {
+                for k in 0..n {
+                    a[row * n + k] -= factor * a[col * n + k];
+                    inv[row * n + k] -= factor * inv[col * n + k];
+                }
+            }
+        }
+    }
+    Ok(inv)
+}
@@ -1,4 +1,6 @@
 use crate::eval::nnue::loader::QuantNnue;
+use crate::search::alphabeta::{EvalMode, Searcher};
+use crate::search::config::SearchConfig;
 use anyhow::{Context, Result};
 
 pub struct TestNnueConfig {
@@ -31,6 +33,52 @@ pub fn load_test_nnue_config_from_env() -> Result<TestNnueConfig> {
     })
 }
 
+/// Search heuristics from the JSON file named by `PIEBOT_TEST_SEARCH_CONFIG`,
+/// or `None` to run the defaults.
+pub fn load_test_search_config_from_env() -> Result<Option<SearchConfig>> {
+    let Some(path) = std::env::var("PIEBOT_TEST_SEARCH_CONFIG")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+    else {
+        return Ok(None);
+    };
+    SearchConfig::from_json_file(&path)
+        .map(Some)
+        .map_err(anyhow::Error::msg)
+        .with_context(|| format!("load PIEBOT_TEST_SEARCH_CONFIG={path}"))
+}
+
+/// What the acceptance bins build each searcher from: the test NNUE and
+/// the search heuristics named in the environment.
+pub struct TestSearchSetup {
+    pub nnue: TestNnueConfig,
+    pub search_config: SearchConfig,
+}
+
+pub fn load_test_search_setup_from_env() -> Result<TestSearchSetup> {
+    Ok(TestSearchSetup {
+        nnue: load_test_nnue_config_from_env()?,
+        search_config: load_test_search_config_from_env()?.unwrap_or_default(),
+    })
+}
+
+impl TestSearchSetup {
+    /// A fresh searcher with a 128 MB TT, these heuristics and, when one is
+    /// configured, the test NNUE.
+    pub fn searcher(&self) -> Searcher {
+        let mut s = Searcher::default();
+        s.set_tt_capacity_mb(128);
+        s.set_search_config(self.search_config.clone());
+        if let Some(model) = self.nnue.quant_model.as_ref() {
+            s.set_use_nnue(true);
+            s.set_eval_mode(EvalMode::Nnue);
+            s.set_eval_blend_percent(self.nnue.blend_percent);
+            s.set_nnue_quant_model(model.clone());
+        }
+        s
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::load_test_nnue_config_from_env;
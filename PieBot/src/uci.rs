@@ -1,14 +1,28 @@
 #[cfg(not(feature = "board-pleco"))]
 use crate::board::cozy::Position;
 #[cfg(not(feature = "board-pleco"))]
+use crate::eval::nnue::embedded;
+#[cfg(not(feature = "board-pleco"))]
 use crate::eval::nnue::loader::QuantNnue;
 #[cfg(not(feature = "board-pleco"))]
 use crate::eval::nnue::Nnue;
 #[cfg(not(feature = "board-pleco"))]
-use crate::search::alphabeta::{SearchParams, SearchResult, Searcher};
+use crate::io::polyglot::{BookSelection, PolyglotBook};
+#[cfg(not(feature = "board-pleco"))]
+use crate::search::alphabeta::{
+    InfoScore, RootLine, RootMoveFilter, SearchInfo, SearchParams, SearchResult, Searcher,
+    SharedDeadline, DEFAULT_TB_PROBE_DEPTH, DEFAULT_TB_PROBE_LIMIT,
+};
+#[cfg(not(feature = "board-pleco"))]
+#[cfg(not(feature = "board-pleco"))]
+use crate::search::syzygy::Tablebases;
+#[cfg(not(feature = "board-pleco"))]
+use crate::search::time_manager::{TimeLimits, TimeManager};
 #[cfg(not(feature = "board-pleco"))]
 use cozy_chess::{Color, Piece, Square};
 #[cfg(not(feature = "board-pleco"))]
+use rand::{rngs::SmallRng, SeedableRng};
+#[cfg(not(feature = "board-pleco"))]
 use std::collections::VecDeque;
 use std::io::{self, BufRead};
 #[cfg(not(feature = "board-pleco"))]
@@ -18,7 +32,7 @@ use std::sync::{mpsc, Arc};
 #[cfg(not(feature = "board-pleco"))]
 use std::thread;
 #[cfg(not(feature = "board-pleco"))]
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[cfg(not(feature = "board-pleco"))]
 const ENGINE_NAME: &str = "PieBot NNUE";
@@ -27,7 +41,31 @@ const DEFAULT_HASH_MB: usize = 64;
 #[cfg(not(feature = "board-pleco"))]
 const DEFAULT_GO_MOVETIME_MS: u64 = 1_000;
 #[cfg(not(feature = "board-pleco"))]
-const MOVE_OVERHEAD_MS: u64 = 10;
+const MAX_MULTIPV: usize = 256;
+/// Full moves answered from the opening book when `OwnBook` is on.
+#[cfg(not(feature = "board-pleco"))]
+const DEFAULT_BOOK_DEPTH: u32 = 20;
+
+/// `NNUEQuantFile` value that selects the net compiled into the binary.
+#[cfg(not(feature = "board-pleco"))]
+const EMBEDDED_NET_OPTION: &str = "<embedded>";
+
+/// Tokens that end a `searchmoves` list.
+#[cfg(not(feature = "board-pleco"))]
+const GO_KEYWORDS: &[&str] = &[
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
 
 #[cfg(not(feature = "board-pleco"))]
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -41,13 +79,16 @@ struct GoOptions {
     binc_ms: Option<u64>,
     moves_to_go: Option<u64>,
     infinite: bool,
+    ponder: bool,
+    /// Standard UCI notation, as sent by the GUI.
+    searchmoves: Vec<String>,
 }
 
 #[cfg(not(feature = "board-pleco"))]
 impl GoOptions {
     fn parse(args: &str) -> Self {
         let mut options = Self::default();
-        let mut tokens = args.split_whitespace();
+        let mut tokens = args.split_whitespace().peekable();
         while let Some(token) = tokens.next() {
             match token {
                 "depth" => {
@@ -87,50 +128,31 @@ impl GoOptions {
                         .map(|moves| moves.max(1));
                 }
                 "infinite" => options.infinite = true,
+                "ponder" => options.ponder = true,
+                "searchmoves" => {
+                    while let Some(mv) = tokens.next_if(|token| !GO_KEYWORDS.contains(token)) {
+                        options.searchmoves.push(mv.to_string());
+                    }
+                }
                 _ => {}
             }
         }
         options
     }
 
-    fn allocated_time_ms(&self, side_to_move: Color) -> Option<u64> {
-        if let Some(movetime_ms) = self.movetime_ms {
-            return Some(movetime_ms.max(1));
-        }
-        let (remaining_ms, increment_ms) = match side_to_move {
-            Color::White => (self.wtime_ms?, self.winc_ms.unwrap_or(0)),
-            Color::Black => (self.btime_ms?, self.binc_ms.unwrap_or(0)),
-        };
-        let moves_to_go = self.moves_to_go.unwrap_or(30).max(1);
-        let usable_ms = remaining_ms.saturating_sub(MOVE_OVERHEAD_MS);
-        let base_ms = usable_ms / moves_to_go;
-        let increment_share_ms = increment_ms.saturating_mul(3) / 4;
-        let requested_ms = base_ms.saturating_add(increment_share_ms).max(1);
-        Some(requested_ms.min(usable_ms.max(1)))
-    }
-
-    fn allocated_time_for(&self, board: &cozy_chess::Board) -> Option<u64> {
-        if self.movetime_ms.is_some() || self.moves_to_go.is_some() {
-            return self.allocated_time_ms(board.side_to_move());
-        }
-
+    /// Soft and hard limits from the side to move's clock, or `None`
+    /// without a clock for that side.
+    fn time_limits_for(&self, board: &cozy_chess::Board) -> Option<TimeLimits> {
         let (remaining_ms, increment_ms) = match board.side_to_move() {
             Color::White => (self.wtime_ms?, self.winc_ms.unwrap_or(0)),
             Color::Black => (self.btime_ms?, self.binc_ms.unwrap_or(0)),
         };
-        let reserve_ms = (remaining_ms / 20).clamp(50, 1_000);
-        let usable_ms = remaining_ms.saturating_sub(reserve_ms).max(1);
-        let non_king_pieces = (board.occupied().len() as u64).saturating_sub(2);
-        let estimated_moves = match non_king_pieces {
-            24.. => 24,
-            12..=23 => 20,
-            6..=11 => 16,
-            _ => 12,
-        };
-        let base_ms = usable_ms / estimated_moves;
-        let increment_share_ms = increment_ms.saturating_mul(4) / 5;
-        let requested_ms = base_ms.saturating_add(increment_share_ms).max(1);
-        Some(requested_ms.min((usable_ms / 3).max(1)))
+        Some(TimeLimits::from_clock(
+            remaining_ms,
+            increment_ms,
+            self.moves_to_go,
+            board,
+        ))
     }
 }
 
@@ -154,18 +176,16 @@ fn search_params_for_go(
     params.use_nullmove = true;
     params.deterministic = params.threads == 1;
 
-    let allocated_ms = if options.infinite {
-        None
-    } else {
-        options.allocated_time_for(board).or_else(|| {
-            if options.depth.is_none() && options.nodes.is_none() {
-                Some(DEFAULT_GO_MOVETIME_MS)
-            } else {
-                None
-            }
-        })
-    };
-    params.movetime = allocated_ms.map(Duration::from_millis);
+    if options.infinite {
+        return params;
+    }
+    if let Some(movetime_ms) = options.movetime_ms {
+        params.movetime = Some(Duration::from_millis(movetime_ms.max(1)));
+    } else if let Some(limits) = options.time_limits_for(board) {
+        params.time = Some(limits);
+    } else if options.depth.is_none() && options.nodes.is_none() {
+        params.movetime = Some(Duration::from_millis(DEFAULT_GO_MOVETIME_MS));
+    }
     params
 }
 
@@ -203,6 +223,20 @@ fn normalize_uci_move(position: &Position, uci: &str) -> String {
         .to_string()
 }
 
+/// Root filter for `go searchmoves`, in cozy-chess encoding. Illegal or
+/// unparsable moves are dropped; `None` when nothing legal remains.
+#[cfg(not(feature = "board-pleco"))]
+fn searchmoves_filter(position: &Position, searchmoves: &[String]) -> Option<RootMoveFilter> {
+    let board = position.board();
+    let moves: Vec<cozy_chess::Move> = searchmoves
+        .iter()
+        .filter_map(|raw| normalize_uci_move(position, raw).parse().ok())
+        .filter(|&mv| board.is_legal(mv))
+        .collect();
+    let filter = RootMoveFilter::new(&moves);
+    (!filter.is_empty()).then_some(filter)
+}
+
 #[cfg(not(feature = "board-pleco"))]
 fn format_uci_move(position: &Position, cozy_move: &str) -> String {
     castling_translation(position, cozy_move, false)
@@ -210,6 +244,47 @@ fn format_uci_move(position: &Position, cozy_move: &str) -> String {
         .to_string()
 }
 
+/// Translate a cozy-chess PV to standard UCI notation. Castling encoding
+/// depends on the position each move is played from, so the line is
+/// replayed; an unplayable move truncates it.
+#[cfg(not(feature = "board-pleco"))]
+fn format_uci_pv(position: &Position, pv: &[String]) -> Vec<String> {
+    let mut current = position.clone();
+    let mut out = Vec::with_capacity(pv.len());
+    for mv in pv {
+        let formatted = format_uci_move(&current, mv);
+        if current.make_move_uci(mv).is_err() {
+            break;
+        }
+        out.push(formatted);
+    }
+    out
+}
+
+#[cfg(not(feature = "board-pleco"))]
+fn format_info_line(position: &Position, info: &SearchInfo) -> String {
+    let mut line = format!(
+        "info depth {} seldepth {} multipv {} score {} nodes {} nps {} time {} hashfull {}",
+        info.depth,
+        info.seldepth,
+        info.multipv,
+        info.score,
+        info.nodes,
+        info.nps,
+        info.time_ms,
+        info.hashfull
+    );
+    if info.tbhits > 0 {
+        line.push_str(&format!(" tbhits {}", info.tbhits));
+    }
+    let pv = format_uci_pv(position, &info.pv);
+    if !pv.is_empty() {
+        line.push_str(" pv ");
+        line.push_str(&pv.join(" "));
+    }
+    line
+}
+
 #[cfg(not(feature = "board-pleco"))]
 fn apply_uci_moves(mut position: Position, moves: &[String]) -> Result<Position, String> {
     for raw_move in moves {
@@ -433,8 +508,19 @@ pub struct UciEngine {
     searcher: Searcher,
     hash_mb: usize,
     threads: usize,
+    multipv: usize,
+    ponder: bool,
     use_nnue: bool,
-    nnue_loaded: bool,
+    /// The loaded network, if any; `UseNNUE` only takes effect with one.
+    net: Option<ActiveNet>,
+    /// Why the embedded net could not be loaded, reported on `uci`.
+    embedded_net_error: Option<String>,
+    own_book: bool,
+    book: Option<PolyglotBook>,
+    /// Last full move number answered from the book.
+    book_depth: u32,
+    book_selection: BookSelection,
+    book_rng: SmallRng,
 }
 
 #[cfg(not(feature = "board-pleco"))]
@@ -444,17 +530,138 @@ impl Default for UciEngine {
     }
 }
 
+/// Where the active network came from and its content hash, so games and
+/// logs can be traced to an entry in `models/MANIFEST.json`.
+#[cfg(not(feature = "board-pleco"))]
+struct ActiveNet {
+    source: String,
+    hash: String,
+}
+
 #[cfg(not(feature = "board-pleco"))]
 struct SearchOutcome {
     searcher: Searcher,
     position: Position,
     result: SearchResult,
+    elapsed: Duration,
 }
 
 #[cfg(not(feature = "board-pleco"))]
 struct ActiveSearch {
     stop: Arc<AtomicBool>,
     outcome_rx: mpsc::Receiver<SearchOutcome>,
+    info_rx: mpsc::Receiver<SearchInfo>,
+    position: Position,
+    deadline: Arc<SharedDeadline>,
+    /// Set while a `go ponder` search waits for `ponderhit` or `stop`.
+    pondering: bool,
+    /// The limit of the original `go`, started on `ponderhit`.
+    ponderhit_limit: Option<PonderLimit>,
+    /// A ponder search that finished before `ponderhit`; UCI forbids
+    /// printing its `bestmove` until the GUI resolves the ponder.
+    finished: Option<SearchOutcome>,
+}
+
+#[cfg(not(feature = "board-pleco"))]
+enum PonderLimit {
+    Movetime(Duration),
+    Clock(PonderClock),
+}
+
+/// The clock of a `go ponder` search. Nothing counts down while pondering;
+/// from `ponderhit` on, the UCI loop runs a `TimeManager` for the clock of
+/// the original `go` and moves the shared deadline after every iteration.
+///
+/// Pondering was search effort spent on the position now on the board, so
+/// the soft target is measured from `go`: a long ponder leaves little or
+/// nothing of it. The hard limit guards the real clock, which only started
+/// on `ponderhit`.
+#[cfg(not(feature = "board-pleco"))]
+struct PonderClock {
+    go_at: Instant,
+    manager: TimeManager,
+    hit_at: Option<Instant>,
+}
+
+#[cfg(not(feature = "board-pleco"))]
+impl PonderClock {
+    fn new(limits: TimeLimits, root_moves: usize) -> Self {
+        Self {
+            go_at: Instant::now(),
+            manager: TimeManager::new(limits, root_moves),
+            hit_at: None,
+        }
+    }
+
+    /// Feed a finished iteration to the time manager. Iterations reported
+    /// while pondering count too: they are the stability history the
+    /// manager scales its target with.
+    fn on_iteration(&mut self, info: &SearchInfo) {
+        let bestmove = info.pv.first().map(String::as_str);
+        self.manager
+            .on_iteration(info.depth, bestmove, info.score.to_score());
+    }
+
+    /// How much longer the search may run at `now`; zero stops it. Only
+    /// meaningful once `ponderhit` has started the clock.
+    fn remaining(&self, now: Instant) -> Duration {
+        let Some(hit_at) = self.hit_at else {
+            return Duration::MAX;
+        };
+        if self.manager.should_stop(now.duration_since(self.go_at)) {
+            return Duration::ZERO;
+        }
+        let hard = (hit_at + self.manager.limits().hard).saturating_duration_since(now);
+        let soft = (self.go_at + self.manager.iteration_limit()).saturating_duration_since(now);
+        hard.min(soft)
+    }
+}
+
+#[cfg(not(feature = "board-pleco"))]
+impl ActiveSearch {
+    /// Print every iteration report the worker has published so far. After
+    /// `ponderhit` each report also reschedules the ponder clock's deadline.
+    fn flush_info(&mut self) {
+        let mut iterated = false;
+        while let Ok(info) = self.info_rx.try_recv() {
+            println!("{}", format_info_line(&self.position, &info));
+            if let Some(PonderLimit::Clock(clock)) = self.ponderhit_limit.as_mut() {
+                if info.multipv <= 1 {
+                    clock.on_iteration(&info);
+                    iterated = true;
+                }
+            }
+        }
+        if let Some(PonderLimit::Clock(clock)) = self.ponderhit_limit.as_ref() {
+            if iterated && clock.hit_at.is_some() {
+                self.deadline.set_in(clock.remaining(Instant::now()));
+            }
+        }
+    }
+
+    /// `ponderhit`: the predicted move was played, so the search keeps
+    /// running and now counts down the clock from the original `go`.
+    fn ponderhit(&mut self) {
+        if !self.pondering {
+            return;
+        }
+        self.pondering = false;
+        match self.ponderhit_limit.as_mut() {
+            Some(PonderLimit::Movetime(movetime)) => self.deadline.set_in(*movetime),
+            Some(PonderLimit::Clock(clock)) => {
+                let now = Instant::now();
+                clock.hit_at = Some(now);
+                self.deadline.set_in(clock.remaining(now));
+            }
+            None => {}
+        }
+    }
+
+    /// Stop the search and release a held ponder result.
+    fn halt(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.pondering = false;
+    }
 }
 
 #[cfg(not(feature = "board-pleco"))]
@@ -462,25 +669,94 @@ impl UciEngine {
     pub fn new() -> Self {
         let mut searcher = Searcher::default();
         searcher.set_tt_capacity_mb(DEFAULT_HASH_MB);
-        Self {
+        let mut engine = Self {
             pos: Position::startpos(),
             searcher,
             hash_mb: DEFAULT_HASH_MB,
             threads: 1,
+            multipv: 1,
+            ponder: false,
             use_nnue: false,
-            nnue_loaded: false,
+            net: None,
+            embedded_net_error: None,
+            own_book: false,
+            book: None,
+            book_depth: DEFAULT_BOOK_DEPTH,
+            book_selection: BookSelection::default(),
+            book_rng: SmallRng::from_entropy(),
+        };
+        if embedded::name().is_some() {
+            engine.use_nnue = true;
+            if let Some(Err(error)) = engine.load_embedded_net() {
+                engine.use_nnue = false;
+                engine.embedded_net_error = Some(error);
+            }
         }
+        engine
+    }
+
+    /// Load the net compiled in with the `embedded-net` feature; `None` when
+    /// the binary carries none, otherwise the `info string` to report.
+    fn load_embedded_net(&mut self) -> Option<Result<String, String>> {
+        Some(match embedded::load()? {
+            Ok((model, hash)) => {
+                let source = format!("embedded {}", embedded::name().unwrap_or_default());
+                Ok(self.activate_quant_net(model, source, hash))
+            }
+            Err(error) => Err(format!("info string failed to load embedded NNUE: {error}")),
+        })
+    }
+
+    fn activate_quant_net(&mut self, model: QuantNnue, source: String, hash: String) -> String {
+        self.searcher.set_nnue_network(None);
+        self.searcher.set_nnue_quant_model(model);
+        self.activate_net(source, hash)
+    }
+
+    /// Record a freshly installed net and report it as an `info string`.
+    fn activate_net(&mut self, source: String, hash: String) -> String {
+        self.searcher.set_use_nnue(self.use_nnue);
+        let message = format!("info string NNUE net {hash} loaded from {source}");
+        self.net = Some(ActiveNet { source, hash });
+        message
     }
 
     fn cmd_uci(&self) {
-        println!("id name {ENGINE_NAME}");
+        match self.net.as_ref() {
+            Some(net) => println!("id name {ENGINE_NAME} {}", net.hash),
+            None => println!("id name {ENGINE_NAME}"),
+        }
         println!("id author PieBot Team");
         println!("option name Threads type spin default 1 min 1 max 512");
         println!("option name Hash type spin default 64 min 1 max 16384");
-        println!("option name UseNNUE type check default false");
+        println!("option name MultiPV type spin default 1 min 1 max {MAX_MULTIPV}");
+        println!("option name Ponder type check default false");
+        let embedded = embedded::name().is_some();
+        println!("option name UseNNUE type check default {embedded}");
         println!("option name NNUEFile type string default ");
-        println!("option name NNUEQuantFile type string default ");
+        if embedded {
+            println!("option name NNUEQuantFile type string default {EMBEDDED_NET_OPTION}");
+        } else {
+            println!("option name NNUEQuantFile type string default ");
+        }
         println!("option name EvalBlend type spin default 100 min 0 max 100");
+        println!("option name SyzygyPath type string default <empty>");
+        println!(
+            "option name SyzygyProbeDepth type spin default {DEFAULT_TB_PROBE_DEPTH} min 1 max 100"
+        );
+        println!(
+            "option name SyzygyProbeLimit type spin default {DEFAULT_TB_PROBE_LIMIT} min 0 max 7"
+        );
+        println!("option name OwnBook type check default false");
+        println!("option name BookFile type string default <empty>");
+        println!("option name BookDepth type spin default {DEFAULT_BOOK_DEPTH} min 1 max 500");
+        println!("option name BookSelection type combo default weighted var weighted var best");
+        if let Some(net) = self.net.as_ref() {
+            println!("info string NNUE net {} from {}", net.hash, net.source);
+        }
+        if let Some(error) = self.embedded_net_error.as_ref() {
+            println!("{error}");
+        }
         println!("uciok");
     }
 
@@ -508,16 +784,26 @@ impl UciEngine {
                 }
                 None
             }
+            "ponder" => {
+                self.ponder = matches!(value.to_lowercase().as_str(), "true" | "1" | "on" | "yes");
+                None
+            }
+            "multipv" => {
+                if let Ok(n) = value.parse::<usize>() {
+                    self.multipv = n.clamp(1, MAX_MULTIPV);
+                }
+                None
+            }
             "usennue" => {
                 let on = matches!(value.to_lowercase().as_str(), "true" | "1" | "on" | "yes");
                 self.use_nnue = on;
-                self.searcher.set_use_nnue(on && self.nnue_loaded);
+                self.searcher.set_use_nnue(on && self.net.is_some());
                 None
             }
             "nnuefile" => {
                 // Attempt to load the dense-f32 dev format (PIENNUE1)
-                match Nnue::load(value) {
-                    Ok(nn) => {
+                match Nnue::load_hashed(value) {
+                    Ok((nn, hash)) => {
                         if crate::eval::nnue::features::HalfKpSchema::from_input_dim(
                             nn.meta.input_dim,
                         )
@@ -532,15 +818,20 @@ impl UciEngine {
                         }
                         self.searcher.clear_nnue_quant();
                         self.searcher.set_nnue_network(Some(nn));
-                        self.nnue_loaded = true;
-                        self.searcher.set_use_nnue(self.use_nnue);
-                        None
+                        Some(self.activate_net(value.to_string(), hash))
                     }
                     Err(error) => Some(format!("info string failed to load NNUEFile: {error}")),
                 }
             }
-            "nnuequantfile" => match QuantNnue::load_quantized(value) {
-                Ok(model) => {
+            "nnuequantfile" if value == EMBEDDED_NET_OPTION => match self.load_embedded_net() {
+                Some(Ok(message)) | Some(Err(message)) => Some(message),
+                None => Some(
+                    "info string failed to load NNUEQuantFile: this build has no embedded net"
+                        .to_string(),
+                ),
+            },
+            "nnuequantfile" => match QuantNnue::load_quantized_hashed(value) {
+                Ok((model, hash)) => {
                     if crate::eval::nnue::features::HalfKpSchema::from_input_dim(
                         model.meta.input_dim,
                     )
@@ -553,11 +844,7 @@ impl UciEngine {
                             model.meta.input_dim
                         ));
                     }
-                    self.searcher.set_nnue_network(None);
-                    self.searcher.set_nnue_quant_model(model);
-                    self.nnue_loaded = true;
-                    self.searcher.set_use_nnue(self.use_nnue);
-                    None
+                    Some(self.activate_quant_net(model, value.to_string(), hash))
                 }
                 Err(error) => Some(format!("info string failed to load NNUEQuantFile: {error}")),
             },
@@ -567,6 +854,68 @@ impl UciEngine {
                 }
                 None
             }
+            "ownbook" => {
+                self.own_book =
+                    matches!(value.to_lowercase().as_str(), "true" | "1" | "on" | "yes");
+                None
+            }
+            "bookfile" => {
+                if value.is_empty() || value == "<empty>" {
+                    self.book = None;
+                    return None;
+                }
+                match PolyglotBook::open(std::path::Path::new(value)) {
+                    Ok(book) => {
+                        let loaded = format!("info string loaded book with {} entries", book.len());
+                        self.book = Some(book);
+                        Some(loaded)
+                    }
+                    Err(error) => Some(format!("info string failed to load BookFile: {error}")),
+                }
+            }
+            "bookdepth" => {
+                if let Ok(depth) = value.parse::<u32>() {
+                    self.book_depth = depth.clamp(1, 500);
+                }
+                None
+            }
+            "bookselection" => {
+                if let Ok(selection) = value.parse() {
+                    self.book_selection = selection;
+                }
+                None
+            }
+            "syzygypath" => {
+                if value.is_empty() || value == "<empty>" {
+                    self.searcher.set_tablebases(None);
+                    return None;
+                }
+                match Tablebases::open(value) {
+                    Ok(tables) => {
+                        let loaded = format!(
+                            "info string loaded {} Syzygy tables, up to {} pieces",
+                            tables.files(),
+                            tables.max_pieces()
+                        );
+                        self.searcher.set_tablebases(Some(Arc::new(tables)));
+                        Some(loaded)
+                    }
+                    Err(error) => Some(format!("info string failed to load SyzygyPath: {error}")),
+                }
+            }
+            "syzygyprobedepth" => {
+                if let Ok(depth) = value.parse::<u32>() {
+                    self.searcher.set_tablebase_probe_depth(depth.clamp(1, 100));
+                }
+                None
+            }
+            "syzygyprobelimit" => {
+                if let Ok(pieces) = value.parse::<usize>() {
+                    self.searcher
+                        .set_tablebase_probe_limit(pieces.min(DEFAULT_TB_PROBE_LIMIT));
+                }
+                None
+            }
             _ => None,
         }
     }
@@ -635,44 +984,152 @@ impl UciEngine {
         }
     }
 
+    /// Answer `go` from the opening book when it has a move for the current
+    /// position. Searches that must run (`infinite`, `ponder`) and book moves
+    /// outside `searchmoves` fall through to a normal search.
+    fn book_move(&mut self, args: &str) -> Option<String> {
+        if !self.own_book {
+            return None;
+        }
+        let options = GoOptions::parse(args);
+        if options.infinite || options.ponder {
+            return None;
+        }
+        let board = self.pos.board();
+        if u32::from(board.fullmove_number()) > self.book_depth {
+            return None;
+        }
+        let mv = self
+            .book
+            .as_ref()?
+            .choose(board, self.book_selection, &mut self.book_rng)?;
+        if !options.searchmoves.is_empty()
+            && !searchmoves_filter(&self.pos, &options.searchmoves).is_some_and(|f| f.contains(mv))
+        {
+            return None;
+        }
+        Some(format_uci_move(&self.pos, &mv.to_string()))
+    }
+
+    fn cmd_go(&mut self, args: &str) -> Option<ActiveSearch> {
+        if let Some(mv) = self.book_move(args) {
+            println!("info string book move {mv}");
+            println!("bestmove {mv}");
+            return None;
+        }
+        Some(self.start_search(args))
+    }
+
     fn start_search(&mut self, args: &str) -> ActiveSearch {
         let options = GoOptions::parse(args);
-        let params = search_params_for_go(&options, self.pos.board(), self.threads);
+        let mut params = SearchParams {
+            multipv: self.multipv,
+            searchmoves: searchmoves_filter(&self.pos, &options.searchmoves),
+            ..search_params_for_go(&options, self.pos.board(), self.threads)
+        };
+        // A ponder search runs until `ponderhit` or `stop`; the clock only
+        // starts once the predicted move has actually been played.
+        let mut ponderhit_limit = None;
+        if options.ponder {
+            let root_moves = match &params.searchmoves {
+                Some(filter) => filter.moves().len(),
+                None => {
+                    let mut count = 0;
+                    self.pos.board().generate_moves(|moves| {
+                        count += moves.len();
+                        false
+                    });
+                    count
+                }
+            };
+            ponderhit_limit = params.movetime.take().map(PonderLimit::Movetime).or(params
+                .time
+                .take()
+                .map(|limits| PonderLimit::Clock(PonderClock::new(limits, root_moves))));
+        }
         let position = self.pos.clone();
         let mut searcher = std::mem::take(&mut self.searcher);
         searcher.set_position_history(position.history());
 
         let stop = Arc::new(AtomicBool::new(false));
         searcher.set_stop_flag(Some(stop.clone()));
+        let deadline = Arc::new(SharedDeadline::new());
+        searcher.set_shared_deadline(Some(deadline.clone()));
+        let (info_tx, info_rx) = mpsc::channel();
+        searcher.set_info_sender(Some(info_tx));
         let (outcome_tx, outcome_rx) = mpsc::channel();
+        let worker_position = position.clone();
         thread::Builder::new()
             .name("piebot-uci-search".to_string())
             .spawn(move || {
+                let position = worker_position;
+                let started = Instant::now();
                 let result = searcher.search_with_params(position.board(), params);
+                let elapsed = started.elapsed();
                 searcher.clear_stop_flag();
+                searcher.set_shared_deadline(None);
+                searcher.set_info_sender(None);
                 let _ = outcome_tx.send(SearchOutcome {
                     searcher,
                     position,
                     result,
+                    elapsed,
                 });
             })
             .expect("failed to start UCI search worker");
 
-        ActiveSearch { stop, outcome_rx }
+        ActiveSearch {
+            stop,
+            outcome_rx,
+            info_rx,
+            position,
+            deadline,
+            pondering: options.ponder,
+            ponderhit_limit,
+            finished: None,
+        }
     }
 
     fn finish_search(&mut self, outcome: SearchOutcome) {
         self.searcher = outcome.searcher;
         let result = outcome.result;
-        println!(
-            "info depth {} seldepth {} score cp {} nodes {}",
-            self.searcher.last_depth(),
-            self.searcher.last_seldepth(),
-            result.score_cp,
-            result.nodes
-        );
+        // The final report covers the whole search, including the partial
+        // iteration a stop or deadline interrupted.
+        let micros = outcome.elapsed.as_micros().max(1);
+        let lines = if result.lines.is_empty() {
+            vec![RootLine {
+                score_cp: result.score_cp,
+                pv: result.pv.clone(),
+                depth: self.searcher.last_depth(),
+                ..Default::default()
+            }]
+        } else {
+            result.lines.clone()
+        };
+        for (index, line) in lines.into_iter().enumerate() {
+            let summary = SearchInfo {
+                multipv: index + 1,
+                depth: line.depth,
+                seldepth: self.searcher.last_seldepth().max(line.depth),
+                score: InfoScore::from_score(line.score_cp),
+                nodes: result.nodes,
+                nps: (u128::from(result.nodes) * 1_000_000 / micros) as u64,
+                time_ms: outcome.elapsed.as_millis() as u64,
+                hashfull: self.searcher.hashfull(),
+                tbhits: self.searcher.tb_hits(),
+                pv: line.pv,
+            };
+            println!("{}", format_info_line(&outcome.position, &summary));
+        }
         if let Some(best) = result.bestmove {
-            println!("bestmove {}", format_uci_move(&outcome.position, &best));
+            let best = format_uci_move(&outcome.position, &best);
+            let pv = format_uci_pv(&outcome.position, &result.pv);
+            match pv.get(1) {
+                Some(reply) if self.ponder && pv[0] == best => {
+                    println!("bestmove {best} ponder {reply}")
+                }
+                _ => println!("bestmove {best}"),
+            }
         } else {
             println!("bestmove 0000");
         }
@@ -705,25 +1162,37 @@ impl UciEngine {
         let mut quitting = false;
 
         loop {
-            if let Some(search) = active.as_ref() {
-                match search.outcome_rx.try_recv() {
-                    Ok(outcome) => {
-                        active = None;
-                        self.finish_search(outcome);
-                        if quitting {
-                            break;
+            if let Some(search) = active.as_mut() {
+                search.flush_info();
+                if search.finished.is_none() {
+                    match search.outcome_rx.try_recv() {
+                        Ok(outcome) => {
+                            // The worker publishes its last report before its
+                            // outcome, so drain again to keep them in order.
+                            search.flush_info();
+                            search.finished = Some(outcome);
                         }
-                        continue;
+                        Err(mpsc::TryRecvError::Disconnected) => {
+                            active = None;
+                            println!("info string search worker terminated unexpectedly");
+                            println!("bestmove 0000");
+                            if quitting {
+                                break;
+                            }
+                            continue;
+                        }
+                        Err(mpsc::TryRecvError::Empty) => {}
                     }
-                    Err(mpsc::TryRecvError::Disconnected) => {
+                }
+                if !search.pondering {
+                    if let Some(outcome) = search.finished.take() {
                         active = None;
-                        println!("info string search worker terminated unexpectedly");
-                        println!("bestmove 0000");
+                        self.finish_search(outcome);
                         if quitting {
                             break;
                         }
+                        continue;
                     }
-                    Err(mpsc::TryRecvError::Empty) => {}
                 }
             }
 
@@ -732,8 +1201,8 @@ impl UciEngine {
                     Ok(line) => line,
                     Err(mpsc::RecvTimeoutError::Timeout) => continue,
                     Err(mpsc::RecvTimeoutError::Disconnected) => {
-                        if let Some(search) = active.as_ref() {
-                            search.stop.store(true, Ordering::Relaxed);
+                        if let Some(search) = active.as_mut() {
+                            search.halt();
                             quitting = true;
                             continue;
                         }
@@ -753,11 +1222,12 @@ impl UciEngine {
                 continue;
             }
 
-            if let Some(search) = active.as_ref() {
+            if let Some(search) = active.as_mut() {
                 match line.as_str() {
-                    "stop" => search.stop.store(true, Ordering::Relaxed),
+                    "stop" => search.halt(),
+                    "ponderhit" => search.ponderhit(),
                     "quit" => {
-                        search.stop.store(true, Ordering::Relaxed);
+                        search.halt();
                         quitting = true;
                     }
                     "isready" if pending.is_empty() => self.cmd_isready(),
@@ -766,7 +1236,7 @@ impl UciEngine {
                     _ => {
                         // State-changing commands must be applied to the real
                         // searcher after it returns from the worker thread.
-                        search.stop.store(true, Ordering::Relaxed);
+                        search.halt();
                         pending.push_back(line);
                     }
                 }
@@ -797,11 +1267,11 @@ impl UciEngine {
                 continue;
             }
             if line == "go" {
-                active = Some(self.start_search(""));
+                active = self.cmd_go("");
                 continue;
             }
             if let Some(rest) = line.strip_prefix("go ") {
-                active = Some(self.start_search(rest));
+                active = self.cmd_go(rest);
                 continue;
             }
             if line == "stop" {
@@ -864,6 +1334,11 @@ mod tests {
         file.write_all(&output_bias.to_le_bytes()).unwrap();
     }
 
+    fn assert_loaded(message: Option<String>) {
+        let message = message.expect("a loaded net is reported to the GUI");
+        assert!(message.starts_with("info string NNUE net "), "{message}");
+    }
+
     #[test]
     fn engine_defaults_match_advertised_hash_and_identity() {
         let engine = UciEngine::new();
@@ -924,8 +1399,78 @@ mod tests {
         )
         .unwrap();
 
-        assert_eq!(go.allocated_time_for(&white), Some(2749));
-        assert_eq!(go.allocated_time_for(&black), Some(999));
+        let soft_ms = |board| {
+            go.time_limits_for(board)
+                .map(|limits| limits.soft.as_millis() as u64)
+        };
+        assert_eq!(soft_ms(&white), Some(2749));
+        assert_eq!(soft_ms(&black), Some(999));
+    }
+
+    #[test]
+    fn match_clocks_allocate_exactly_like_go() {
+        // compare_play's --tc clocks and a GUI's wtime/btime must reach the
+        // same allocation, or A/B results would not carry over to play.
+        use crate::search::time_manager::Clock;
+        let board = cozy_chess::Board::default();
+        for (tc, go) in [
+            ("60+0.5", "wtime 60000 btime 60000 winc 500 binc 500"),
+            ("40/90", "wtime 90000 btime 90000 movestogo 40"),
+        ] {
+            let clock = Clock::new(tc.parse().unwrap());
+            assert_eq!(
+                Some(clock.limits(&board)),
+                GoOptions::parse(go).time_limits_for(&board),
+                "{tc}"
+            );
+        }
+    }
+
+    #[test]
+    fn go_ponder_keeps_the_clock_for_ponderhit() {
+        let go = GoOptions::parse("ponder wtime 60000 btime 30000 winc 1000 binc 0 movestogo 30");
+        assert!(go.ponder);
+        assert!(!go.infinite);
+        let board = cozy_chess::Board::default();
+        let params = search_params_for_go(&go, &board, 1);
+        let limits = params.time.expect("clock search is time managed");
+        assert_eq!(limits.soft, Duration::from_millis(2749));
+    }
+
+    #[test]
+    fn ponderhit_counts_the_ponder_time_against_the_soft_target() {
+        let limits = TimeLimits {
+            soft: Duration::from_millis(1_000),
+            hard: Duration::from_millis(3_000),
+        };
+        let info = SearchInfo {
+            multipv: 1,
+            depth: 12,
+            seldepth: 18,
+            score: InfoScore::Cp(25),
+            nodes: 1,
+            nps: 1,
+            time_ms: 1,
+            hashfull: 0,
+            tbhits: 0,
+            pv: vec!["e2e4".to_string()],
+        };
+        let now = Instant::now() + Duration::from_secs(10);
+        let mut clock = PonderClock::new(limits, 20);
+        clock.on_iteration(&info);
+        assert_eq!(
+            clock.remaining(now),
+            Duration::MAX,
+            "no clock before ponderhit"
+        );
+
+        clock.hit_at = Some(now);
+        clock.go_at = now - Duration::from_millis(200);
+        assert_eq!(clock.remaining(now), Duration::from_millis(800));
+
+        // Pondered past the soft target: move as soon as the GUI confirms.
+        clock.go_at = now - Duration::from_secs(5);
+        assert_eq!(clock.remaining(now), Duration::ZERO);
     }
 
     #[test]
@@ -935,8 +1480,8 @@ mod tests {
         let ending =
             cozy_chess::Board::from_fen("8/8/8/3k4/8/4K3/4P3/8 w - - 0 50", false).unwrap();
 
-        let opening_ms = go.allocated_time_for(&opening).unwrap();
-        let ending_ms = go.allocated_time_for(&ending).unwrap();
+        let opening_ms = go.time_limits_for(&opening).unwrap().soft.as_millis() as u64;
+        let ending_ms = go.time_limits_for(&ending).unwrap().soft.as_millis() as u64;
         assert!((2_500..=3_500).contains(&opening_ms), "{opening_ms}");
         assert!(
             ending_ms > opening_ms,
@@ -956,7 +1501,18 @@ mod tests {
             .expect("invalid model load should be visible to the GUI");
 
         assert!(message.starts_with("info string failed to load NNUEQuantFile:"));
-        assert!(!engine.nnue_loaded);
+        assert!(engine.net.is_none());
+    }
+
+    #[test]
+    fn missing_syzygy_path_reports_a_uci_info_string() {
+        let mut engine = UciEngine::new();
+        let message = engine
+            .apply_setoption("SyzygyPath", "/definitely/missing/piebot-syzygy")
+            .expect("a failed load should be visible to the GUI");
+
+        assert!(message.starts_with("info string failed to load SyzygyPath:"));
+        assert_eq!(engine.apply_setoption("SyzygyPath", "<empty>"), None);
     }
 
     #[test]
@@ -969,10 +1525,7 @@ mod tests {
         write_dense_model(&incompatible_dense_path, 12, -29.0);
 
         let mut engine = UciEngine::new();
-        assert_eq!(
-            engine.apply_setoption("NNUEQuantFile", valid_path.to_str().unwrap()),
-            None
-        );
+        assert_loaded(engine.apply_setoption("NNUEQuantFile", valid_path.to_str().unwrap()));
         engine.apply_setoption("UseNNUE", "true");
         let score_before = engine.searcher.qsearch_eval_cp(engine.pos.board());
 
@@ -995,7 +1548,7 @@ mod tests {
             score_after_rejections, score_before,
             "active model must be preserved"
         );
-        assert!(engine.nnue_loaded);
+        assert!(engine.net.is_some());
     }
 
     #[test]
@@ -1006,10 +1559,7 @@ mod tests {
         write_dense_model(&dense_path, halfkp_dim(), -29.0);
 
         let mut engine = UciEngine::new();
-        assert_eq!(
-            engine.apply_setoption("NNUEQuantFile", quant_path.to_str().unwrap()),
-            None
-        );
+        assert_loaded(engine.apply_setoption("NNUEQuantFile", quant_path.to_str().unwrap()));
         engine.apply_setoption("UseNNUE", "true");
         assert_eq!(
             engine.searcher.qsearch_eval_cp(engine.pos.board()),
@@ -1017,10 +1567,7 @@ mod tests {
             "quantized fixture should be active"
         );
 
-        assert_eq!(
-            engine.apply_setoption("NNUEFile", dense_path.to_str().unwrap()),
-            None
-        );
+        assert_loaded(engine.apply_setoption("NNUEFile", dense_path.to_str().unwrap()));
         let score_after_dense_load = engine.searcher.qsearch_eval_cp(engine.pos.board());
 
         let _ = std::fs::remove_file(quant_path);
@@ -1039,23 +1586,43 @@ mod tests {
         write_dense_model(&dense_path, halfkp_v2_dim(), -19.0);
 
         let mut engine = UciEngine::new();
-        assert_eq!(
-            engine.apply_setoption("NNUEQuantFile", quant_path.to_str().unwrap()),
-            None
-        );
+        assert_loaded(engine.apply_setoption("NNUEQuantFile", quant_path.to_str().unwrap()));
         engine.apply_setoption("UseNNUE", "true");
         assert_eq!(engine.searcher.qsearch_eval_cp(engine.pos.board()), 37);
 
-        assert_eq!(
-            engine.apply_setoption("NNUEFile", dense_path.to_str().unwrap()),
-            None
-        );
+        assert_loaded(engine.apply_setoption("NNUEFile", dense_path.to_str().unwrap()));
         assert_eq!(engine.searcher.qsearch_eval_cp(engine.pos.board()), -19);
 
         let _ = std::fs::remove_file(quant_path);
         let _ = std::fs::remove_file(dense_path);
     }
 
+    #[test]
+    fn loaded_nets_are_identified_by_content_hash() {
+        let quant_path = quant_model_path("hashed_quant");
+        write_quant_model(&quant_path, halfkp_dim(), 11);
+        let expected = crate::eval::nnue::loader::net_hash(&std::fs::read(&quant_path).unwrap());
+
+        let mut engine = UciEngine::new();
+        let message = engine
+            .apply_setoption("NNUEQuantFile", quant_path.to_str().unwrap())
+            .unwrap();
+        let _ = std::fs::remove_file(&quant_path);
+        assert_eq!(expected.len(), crate::eval::nnue::loader::NET_HASH_LEN);
+        assert!(message.contains(&expected), "{message}");
+        let net = engine.net.as_ref().unwrap();
+        assert_eq!(net.hash, expected);
+        assert_eq!(net.source, quant_path.to_str().unwrap());
+
+        if embedded::name().is_none() {
+            let message = engine
+                .apply_setoption("NNUEQuantFile", EMBEDDED_NET_OPTION)
+                .unwrap();
+            assert!(message.contains("no embedded net"), "{message}");
+            assert_eq!(engine.net.as_ref().unwrap().hash, expected);
+        }
+    }
+
     #[test]
     fn standard_uci_castling_is_translated_to_and_from_cozy_encoding() {
         let start = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")
@@ -1068,4 +1635,78 @@ mod tests {
         assert_eq!(castled.board().piece_on(Square::G1), Some(Piece::King));
         assert_eq!(castled.board().piece_on(Square::F1), Some(Piece::Rook));
     }
+
+    #[test]
+    fn searchmoves_run_until_the_next_keyword_and_translate_castling() {
+        let go = GoOptions::parse("searchmoves e1g1 a1b1 e2e4 zz wtime 1000 depth 3");
+        assert_eq!(go.searchmoves, vec!["e1g1", "a1b1", "e2e4", "zz"]);
+        assert_eq!(go.wtime_ms, Some(1000));
+        assert_eq!(go.depth, Some(3));
+
+        let start = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")
+            .expect("valid castling position");
+        let filter = searchmoves_filter(&start, &go.searchmoves).expect("legal moves remain");
+        let moves: Vec<String> = filter.moves().iter().map(|mv| format!("{mv}")).collect();
+        assert_eq!(moves, vec!["e1h1", "a1b1"]);
+        assert!(searchmoves_filter(&start, &["e2e4".to_string()]).is_none());
+    }
+
+    #[test]
+    fn pv_castling_is_translated_move_by_move_and_truncated_when_unplayable() {
+        let start = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")
+            .expect("valid castling position");
+        let pv = ["e1h1", "e8a8", "f1f8", "h1h2"].map(str::to_string);
+
+        assert_eq!(format_uci_pv(&start, &pv), vec!["e1g1", "e8c8", "f1f8"]);
+    }
+
+    fn write_book(name: &str, board: &cozy_chess::Board, moves: &[(&str, u16)]) -> PathBuf {
+        let key = crate::io::polyglot::polyglot_key(board).expect("book key");
+        let mut bytes = Vec::new();
+        for &(mv, weight) in moves {
+            bytes.extend(key.to_be_bytes());
+            bytes.extend(crate::io::polyglot::encode_move(mv.parse().unwrap()).to_be_bytes());
+            bytes.extend(weight.to_be_bytes());
+            bytes.extend(0u32.to_be_bytes());
+        }
+        let path = quant_model_path(name).with_extension("bin");
+        std::fs::write(&path, bytes).expect("write book");
+        path
+    }
+
+    #[test]
+    fn own_book_answers_go_with_uci_castling_within_book_depth() {
+        let mut engine = UciEngine::new();
+        engine.cmd_position("fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 12");
+        let path = write_book(
+            "castling_book",
+            engine.pos.board(),
+            &[("a1b1", 1), ("e1h1", 4)],
+        );
+        let loaded = engine.apply_setoption("BookFile", path.to_str().unwrap());
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(
+            loaded.as_deref(),
+            Some("info string loaded book with 2 entries")
+        );
+
+        assert_eq!(engine.book_move("movetime 50"), None, "OwnBook is off");
+        engine.apply_setoption("OwnBook", "true");
+        engine.apply_setoption("BookSelection", "best");
+        assert_eq!(engine.book_move("movetime 50").as_deref(), Some("e1g1"));
+        assert_eq!(engine.book_move("infinite"), None);
+        assert_eq!(
+            engine.book_move("searchmoves a1b1").as_deref(),
+            None,
+            "the best book move is outside searchmoves"
+        );
+        engine.apply_setoption("BookDepth", "11");
+        assert_eq!(engine.book_move("movetime 50"), None);
+
+        let message = engine
+            .apply_setoption("BookFile", "/definitely/missing/piebot-book.bin")
+            .expect("a failed load should be visible to the GUI");
+        assert!(message.starts_with("info string failed to load BookFile:"));
+        assert!(engine.book.is_some(), "the loaded book is kept");
+    }
 }
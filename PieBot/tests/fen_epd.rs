@@ -0,0 +1,68 @@
+use piebot::io::fen::{parse_epd, parse_fen};
+
+#[test]
+fn parse_fen_accepts_full_and_four_field_positions() {
+    let board = parse_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+    assert_eq!(board.side_to_move(), cozy_chess::Color::Black);
+    let epd = parse_fen("8/8/8/8/8/8/1Q6/K6k w - -").unwrap();
+    assert_eq!(epd.halfmove_clock(), 0);
+    assert_eq!(epd.fullmove_number(), 1);
+}
+
+#[test]
+fn parse_fen_names_the_bad_field() {
+    for (fen, expected) in [
+        ("8/8/8/8/8/8/8 w - - 0 1", "7 ranks"),
+        ("8/8/8/8/8/8/8/K6k7 w - - 0 1", "squares"),
+        ("8/8/8/8/8/8/8/K5xk w - - 0 1", "invalid piece"),
+        ("8/8/8/8/8/8/8/K6k x - - 0 1", "side to move"),
+        ("8/8/8/8/8/8/8/K6k w KX - 0 1", "castling"),
+        ("8/8/8/8/8/8/8/K6k w - e4 0 1", "en passant"),
+        ("8/8/8/8/8/8/8/K6k w - - x 1", "halfmove"),
+        ("8/8/8/8/8/8/8/K6k w - - 0 0", "fullmove"),
+        ("8/8/8/8/8/8/8/K6k w -", "3 fields"),
+        ("8/8/8/8/8/8/8/K7 w - - 0 1", "illegal position"),
+    ] {
+        let error = parse_fen(fen).unwrap_err();
+        assert!(error.contains(expected), "{fen}: {error}");
+    }
+}
+
+#[test]
+fn epd_opcodes_parse_with_san_and_quoted_operands() {
+    let record = parse_epd(
+        r#"r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; am g4; id "ruy; or italian"; c0 "two \"good\" moves"; hmvc 2; fmvn 3;"#,
+    )
+    .unwrap();
+    let bm: Vec<String> = record.bm().unwrap().iter().map(|m| m.to_string()).collect();
+    assert_eq!(bm, ["f1b5", "f1c4"]);
+    let am: Vec<String> = record.am().unwrap().iter().map(|m| m.to_string()).collect();
+    assert_eq!(am, ["g2g4"]);
+    assert_eq!(record.id(), Some("ruy; or italian"));
+    assert_eq!(record.c0(), Some("two \"good\" moves"));
+    assert_eq!(record.board.halfmove_clock(), 2);
+    assert_eq!(record.board.fullmove_number(), 3);
+}
+
+#[test]
+fn epd_moves_accept_uci_and_absent_opcodes_are_empty() {
+    let record = parse_epd("4k3/8/8/8/8/8/8/4K2R w K - bm e1h1; id \"castle\";").unwrap();
+    assert_eq!(record.bm().unwrap()[0].to_string(), "e1h1");
+    assert!(record.am().unwrap().is_empty());
+    assert_eq!(record.c0(), None);
+    let bare = parse_epd("4k3/8/8/8/8/8/8/4K2R w K -").unwrap();
+    assert!(bare.ops.is_empty());
+}
+
+#[test]
+fn epd_errors_are_reported() {
+    assert!(parse_epd("4k3/8/8/8 w").is_err());
+    assert!(parse_epd("4k3/8/8/8/8/8/8/4K2R w K - bm Kd1")
+        .unwrap_err()
+        .contains("missing its ';'"));
+    assert!(parse_epd("4k3/8/8/8/8/8/8/4K2R w K - id \"open;")
+        .unwrap_err()
+        .contains("unterminated"));
+    let record = parse_epd("4k3/8/8/8/8/8/8/4K2R w K - bm Qh5;").unwrap();
+    assert!(record.bm().unwrap_err().contains("bm"));
+}
@@ -96,3 +96,30 @@ fn quant_scales_are_applied_in_eval() {
     let sc = s.qsearch_eval_cp(&b);
     assert_eq!(sc, 1);
 }
+
+#[test]
+fn net_hash_is_a_sha256_prefix_of_the_file() {
+    use piebot::eval::nnue::loader::{net_hash, QuantNnue, NET_HASH_LEN};
+    assert_eq!(net_hash(b"abc"), "ba7816bf8f01");
+    assert_eq!(NET_HASH_LEN, 12);
+
+    let path = "target/nnue_quant_hashed.nnue";
+    let mut f = File::create(path).unwrap();
+    f.write_all(b"PIENNQ01").unwrap();
+    for v in [1u32, 12, 2, 1] {
+        f.write_all(&v.to_le_bytes()).unwrap();
+    }
+    f.write_all(&1.0f32.to_le_bytes()).unwrap();
+    f.write_all(&1.0f32.to_le_bytes()).unwrap();
+    f.write_all(&[1u8; 24]).unwrap();
+    f.write_all(&[0u8; 4]).unwrap();
+    f.write_all(&[2u8; 2]).unwrap();
+    f.write_all(&7i16.to_le_bytes()).unwrap();
+    drop(f);
+
+    let bytes = std::fs::read(path).unwrap();
+    let (model, hash) = QuantNnue::load_quantized_hashed(path).unwrap();
+    assert_eq!(hash, net_hash(&bytes));
+    assert_eq!(model.b2, vec![7]);
+    assert_eq!(QuantNnue::from_bytes(&bytes).unwrap().w1, model.w1);
+}
@@ -0,0 +1,177 @@
+use cozy_chess::Board;
+use piebot::eval::nnue::features::HALFKP_DIM;
+use piebot::eval::nnue::loader::{QuantMeta, QuantNnue};
+use piebot::eval::nnue::network::QuantNetwork;
+use piebot::eval::nnue::quant::{quant_accumulators, quantize_dense, quantize_v2};
+use piebot::eval::nnue::train::FloatNnueV2;
+use piebot::eval::nnue::Nnue;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const FENS: [&str; 4] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "4k3/8/8/3p4/4Q3/8/8/4K3 b - - 0 1",
+    "8/5pk1/8/8/8/8/1P3K2/8 w - - 0 1",
+];
+
+fn fresh_dir(name: &str) -> PathBuf {
+    let dir = Path::new("target").join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Deterministic values in `[-1, 1)`.
+fn noise(seed: u64) -> impl FnMut() -> f32 {
+    let mut state = seed;
+    move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((state >> 40) as f32 / (1u64 << 23) as f32) - 1.0
+    }
+}
+
+fn write_dense(path: &Path, hidden: usize, w1_amp: f32, w2_amp: f32, b2: f32) {
+    let mut next = noise(17);
+    let mut f = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    f.write_all(b"PIENNUE1").unwrap();
+    for v in [1u32, HALFKP_DIM as u32, hidden as u32, 1] {
+        f.write_all(&v.to_le_bytes()).unwrap();
+    }
+    let mut floats = Vec::new();
+    floats.extend((0..hidden * HALFKP_DIM).map(|_| next() * w1_amp));
+    floats.extend((0..hidden).map(|_| 0.5 + next() * 0.1));
+    floats.extend((0..hidden).map(|_| next() * w2_amp));
+    floats.push(b2);
+    for v in floats {
+        f.write_all(&v.to_le_bytes()).unwrap();
+    }
+}
+
+fn legacy_model() -> QuantNnue {
+    let hidden = 4;
+    let mut next = noise(3);
+    QuantNnue {
+        meta: QuantMeta {
+            version: 1,
+            input_dim: HALFKP_DIM,
+            hidden_dim: hidden,
+            output_dim: 1,
+        },
+        w1_scale: 0.25,
+        w2_scale: 0.5,
+        w1: (0..hidden * HALFKP_DIM)
+            .map(|_| (next() * 127.0) as i8)
+            .collect(),
+        b1: vec![100, -3, 0, 7],
+        w2: vec![5, -6, 7, -128],
+        b2: vec![-300],
+        v2: None,
+    }
+}
+
+#[test]
+fn legacy_quant_models_round_trip_byte_for_byte() {
+    let dir = fresh_dir("nnue_quant_writer_q01");
+    let model = legacy_model();
+    let path = dir.join("a.nnue");
+    model.save(&path).unwrap();
+    let loaded = QuantNnue::load_quantized(&path).unwrap();
+    assert!(loaded.v2.is_none());
+    assert_eq!(loaded.meta.input_dim, HALFKP_DIM);
+    assert_eq!((loaded.w1_scale, loaded.w2_scale), (0.25, 0.5));
+    assert_eq!(loaded.w1, model.w1);
+    assert_eq!(loaded.b1, model.b1);
+    assert_eq!(loaded.w2, model.w2);
+    assert_eq!(loaded.b2, model.b2);
+
+    let again = dir.join("b.nnue");
+    loaded.save(&again).unwrap();
+    assert_eq!(
+        std::fs::read(&path).unwrap(),
+        std::fs::read(&again).unwrap()
+    );
+
+    let mut broken = model;
+    broken.b1.pop();
+    assert!(broken.save(dir.join("c.nnue")).is_err());
+}
+
+#[test]
+fn v2_models_round_trip_through_the_wrapper() {
+    let dir = fresh_dir("nnue_quant_writer_q02");
+    let (v2, _) = quantize_v2(&FloatNnueV2::new_random(8, 3));
+    let direct = dir.join("direct.nnue");
+    v2.save(&direct).unwrap();
+    let loaded = QuantNnue::load_quantized(&direct).unwrap();
+    let wrapped = dir.join("wrapped.nnue");
+    loaded.save(&wrapped).unwrap();
+    assert_eq!(
+        std::fs::read(&direct).unwrap(),
+        std::fs::read(&wrapped).unwrap()
+    );
+    let board = Board::default();
+    let acc = quant_accumulators(&loaded, &board).unwrap();
+    assert_eq!(acc.len(), 16);
+}
+
+#[test]
+fn dense_models_quantize_to_matching_evals() {
+    let dir = fresh_dir("nnue_quant_writer_dense");
+    let path = dir.join("dense.nnue");
+    // b2 must fit i16 at w1_scale * w2_scale (about 1.2e-4 here).
+    write_dense(&path, 8, 0.05, 40.0, 3.0);
+    let dense = Nnue::load(&path).unwrap();
+    let (quant, stats) = quantize_dense(&dense, 100.0);
+    assert!(stats.iter().all(|s| s.clipped == 0), "{stats:?}");
+    let w1 = &stats[0];
+    assert_eq!(w1.values, 8 * HALFKP_DIM);
+    assert!(w1.max_rounding_error <= quant.w1_scale / 2.0 + 1e-6);
+
+    let out = dir.join("quant.nnue");
+    quant.save(&out).unwrap();
+    let network = QuantNetwork::new(QuantNnue::load_quantized(&out).unwrap());
+    for fen in FENS {
+        let board = Board::from_fen(fen, false).unwrap();
+        let float = dense.evaluate(&board) as f32;
+        let int = network.eval_full(&board) as f32;
+        assert!(
+            (float - int).abs() <= 3.0 + float.abs() * 0.02,
+            "{fen}: dense {float} vs quantized {int}"
+        );
+        let acc = quant_accumulators(&quant, &board).unwrap();
+        let pre = dense.hidden_preactivations(&board);
+        for (a, p) in acc.iter().zip(&pre) {
+            assert!((*a as f32 * quant.w1_scale - p).abs() < 0.1, "{a} vs {p}");
+        }
+    }
+}
+
+#[test]
+fn clip_statistics_count_saturated_values() {
+    let dir = fresh_dir("nnue_quant_writer_clip");
+    let path = dir.join("dense.nnue");
+    // An output bias far beyond i16 in the w1_scale * w2_scale domain.
+    write_dense(&path, 2, 0.05, 1.0, 1.0e6);
+    let dense = Nnue::load(&path).unwrap();
+    let (_, full) = quantize_dense(&dense, 100.0);
+    assert_eq!(full[0].clipped, 0);
+    assert_eq!(full[3].tensor, "b2");
+    assert_eq!(full[3].clipped, 1);
+    assert_eq!(full[3].clipped_fraction(), 1.0);
+
+    // At the 90th percentile the top tenth of |w1| saturates.
+    let (quant, clipped) = quantize_dense(&dense, 90.0);
+    let share = clipped[0].clipped_fraction();
+    assert!((0.08..=0.11).contains(&share), "clipped {share}");
+    assert!(quant.w1.iter().all(|&w| (-127..=127).contains(&w)));
+
+    let mut float = FloatNnueV2::new_random(4, 1);
+    float.w2[0] = 10.0;
+    float.b1[1] = -200.0;
+    let (_, v2_stats) = quantize_v2(&float);
+    let counts: Vec<usize> = v2_stats.iter().map(|s| s.clipped).collect();
+    assert_eq!(counts, vec![0, 1, 1, 0]);
+}
@@ -0,0 +1,191 @@
+use cozy_chess::Board;
+use piebot::eval::nnue::loader::{QuantNnue, QuantNnueV2};
+use piebot::eval::nnue::network::QuantNetwork;
+use piebot::eval::nnue::train::{
+    collect_data_files, load_samples, AdamConfig, FloatNnueV2, Sample, TargetParams, Trainer,
+};
+use piebot::selfplay::packed::{PackedPosition, PackedWriter};
+use std::path::{Path, PathBuf};
+
+const FENS: [&str; 6] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "4k3/8/8/3p4/4Q3/8/8/4K3 b - - 0 1",
+    "r3k2r/ppp2ppp/8/8/8/8/PPP2PPP/R3K2R b KQkq - 0 1",
+    "8/5pk1/8/8/8/8/1P3K2/8 w - - 0 1",
+    "2r3k1/5ppp/8/8/8/8/5PPP/3R2K1 b - - 0 1",
+];
+
+fn fresh_dir(name: &str) -> PathBuf {
+    let dir = Path::new("target").join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn position(fen: &str, score_cp: i16, result: i8) -> PackedPosition {
+    let mut p = PackedPosition::new(Board::from_fen(fen, false).unwrap());
+    p.score_cp = Some(score_cp);
+    p.result = result;
+    p.outcome_valid = true;
+    p
+}
+
+fn samples() -> Vec<Sample> {
+    let labels = [(30, 0), (60, 1), (-700, -1), (0, 0), (250, 1), (-180, -1)];
+    FENS.iter()
+        .zip(labels)
+        .map(|(fen, (cp, result))| {
+            Sample::from_position(&position(fen, cp, result), &TargetParams::default()).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn adam_fits_a_handful_of_positions() {
+    let samples = samples();
+    let mut trainer = Trainer::new(
+        FloatNnueV2::new_random(16, 7),
+        AdamConfig {
+            lr: 1e-2,
+            ..AdamConfig::default()
+        },
+    );
+    let before = trainer.loss(&samples, 400.0);
+    for _ in 0..200 {
+        trainer.train_batch(&samples, 400.0);
+    }
+    let after = trainer.loss(&samples, 400.0);
+    assert_eq!(trainer.step, 200);
+    assert!(after < before / 10.0, "loss {before} -> {after}");
+    let limit = 127.0 / QuantNnueV2::QB as f32;
+    assert!(trainer.model.w2.iter().all(|w| w.abs() <= limit));
+}
+
+#[test]
+fn exported_network_evaluates_like_the_float_model() {
+    let mut trainer = Trainer::new(FloatNnueV2::new_random(32, 11), AdamConfig::default());
+    for _ in 0..20 {
+        trainer.train_batch(&samples(), 400.0);
+    }
+    let quant = trainer.model.quantize();
+    assert_eq!(
+        (quant.qa, quant.qb, quant.scale),
+        (QuantNnueV2::QA, QuantNnueV2::QB, QuantNnueV2::SCALE)
+    );
+
+    let dir = fresh_dir("nnue_train_export");
+    let path = dir.join("net.nnue");
+    quant.save(&path).unwrap();
+    let loaded = QuantNnue::load_quantized(&path).unwrap();
+    let v2 = loaded.v2.as_ref().expect("PIENNQ02 model");
+    assert_eq!(v2.w1, quant.w1);
+    assert_eq!(v2.b1, quant.b1);
+    assert_eq!(v2.w2, quant.w2);
+    assert_eq!(v2.b2, quant.b2);
+
+    // The engine computes exactly what the float head computes on the
+    // rounded weights; only the final integer division loses anything.
+    let qa = QuantNnueV2::QA as f32;
+    let qb = QuantNnueV2::QB as f32;
+    let rounded = FloatNnueV2 {
+        w1: quant.w1.iter().map(|&w| f32::from(w) / qa).collect(),
+        b1: quant.b1.iter().map(|&b| f32::from(b) / qa).collect(),
+        w2: quant.w2.iter().map(|&w| f32::from(w) / qb).collect(),
+        b2: quant.b2 as f32 / (qa * qa * qb),
+        ..trainer.model.clone()
+    };
+    let network = QuantNetwork::new(loaded);
+    for fen in FENS {
+        let board = Board::from_fen(fen, false).unwrap();
+        let int = network.eval_full(&board) as f32;
+        let expected = rounded.evaluate(&board);
+        assert!(
+            (expected - int).abs() <= 1.5,
+            "{fen}: dequantized {expected} vs engine {int}"
+        );
+        // Rounding w2 to 1/64 steps costs a few cp on an untrained net.
+        let float = trainer.model.evaluate(&board);
+        assert!(
+            (float - int).abs() <= 10.0 + float.abs() * 0.1,
+            "{fen}: float {float} vs engine {int}"
+        );
+    }
+}
+
+#[test]
+fn checkpoints_resume_training_exactly() {
+    let samples = samples();
+    let adam = AdamConfig::default();
+    let mut trainer = Trainer::new(FloatNnueV2::new_random(8, 5), adam);
+    for _ in 0..3 {
+        trainer.train_batch(&samples, 400.0);
+    }
+    trainer.epoch = 2;
+    let dir = fresh_dir("nnue_train_checkpoint");
+    let path = dir.join("net.ckpt");
+    trainer.save_checkpoint(&path).unwrap();
+
+    let mut resumed = Trainer::load_checkpoint(&path, adam).unwrap();
+    assert_eq!(resumed.step, 3);
+    assert_eq!(resumed.epoch, 2);
+    assert_eq!(resumed.model, trainer.model);
+    assert_eq!(FloatNnueV2::load(&path).unwrap(), trainer.model);
+    trainer.train_batch(&samples, 400.0);
+    resumed.train_batch(&samples, 400.0);
+    assert_eq!(resumed.model, trainer.model);
+
+    // A version 1 checkpoint has no epoch counter: its weights load, but
+    // resuming from it is refused.
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[8..12].copy_from_slice(&1u32.to_le_bytes());
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(FloatNnueV2::load(&path).is_ok());
+    let error = Trainer::load_checkpoint(&path, adam).err().unwrap();
+    assert!(error.contains("version 1"), "{error}");
+
+    std::fs::write(&path, b"PIENNQ02").unwrap();
+    assert!(Trainer::load_checkpoint(&path, adam).is_err());
+}
+
+#[test]
+fn samples_load_from_jsonl_and_packed_shards() {
+    let dir = fresh_dir("nnue_train_data");
+    let lines = [
+        format!(
+            r#"{{"fen": "{}", "value_cp": 120.0, "result": 1}}"#,
+            FENS[0]
+        ),
+        // Black to move; a White-relative score is a loss for the mover.
+        format!(
+            r#"{{"fen": "{}", "value_cp": 400.0, "outcome_valid": false}}"#,
+            FENS[2]
+        ),
+        // No teacher score and no valid result.
+        format!(r#"{{"fen": "{}", "outcome_valid": false}}"#, FENS[1]),
+        "not json".to_string(),
+    ];
+    std::fs::write(dir.join("a.jsonl"), lines.join("\n")).unwrap();
+    let file = std::fs::File::create(dir.join("b.piepack")).unwrap();
+    let mut writer = PackedWriter::new(file).unwrap();
+    writer.write(&position(FENS[3], -50, -1)).unwrap();
+    writer.finish().unwrap();
+    std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+    let files = collect_data_files(&dir).unwrap();
+    assert_eq!(files.len(), 2);
+    let params = TargetParams {
+        eval_weight: 1.0,
+        ..TargetParams::default()
+    };
+    let loaded = load_samples(&files, &params, 0).unwrap();
+    assert_eq!(loaded.samples.len(), 3);
+    assert_eq!(loaded.skipped, 2);
+    let sigmoid = |x: f32| 1.0 / (1.0 + (-x).exp());
+    assert!((loaded.samples[0].target - sigmoid(0.3)).abs() < 1e-6);
+    assert!((loaded.samples[1].target - (1.0 - sigmoid(1.0))).abs() < 1e-6);
+    assert!(loaded.samples[2].target > 0.5);
+
+    let capped = load_samples(&files, &params, 2).unwrap();
+    assert_eq!(capped.samples.len(), 2);
+}
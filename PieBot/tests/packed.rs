@@ -0,0 +1,172 @@
+use cozy_chess::{Board, Move};
+use piebot::selfplay::packed::{
+    game_to_packed, shuffle_packed, write_packed_shards, PackedFile, PackedPosition, PackedView,
+    PackedWriter, PACKED_HEADER_SIZE, PACKED_RECORD_SIZE,
+};
+use piebot::selfplay::{GameRecord, GameTermination};
+use std::path::Path;
+
+fn board(fen: &str) -> Board {
+    Board::from_fen(fen, false)
+        .or_else(|_| Board::from_fen(fen, true))
+        .unwrap()
+}
+
+fn game(moves: &[&str]) -> GameRecord {
+    let n = moves.len();
+    GameRecord {
+        run_id: "packed-run".to_string(),
+        game_id: "packed-game".to_string(),
+        start_fen: Board::default().to_string(),
+        moves: moves.iter().map(|m| m.to_string()).collect(),
+        move_target_best: vec![Some("e2e4".to_string()); n],
+        move_value_cp: (0..n).map(|i| Some(i as f32 * 10.0)).collect(),
+        move_teacher_depth: vec![Some(8); n],
+        move_policy_top: vec![Vec::new(); n],
+        result: -1,
+        outcome_valid: true,
+        termination: GameTermination::Checkmate,
+        opening: None,
+    }
+}
+
+#[test]
+fn records_round_trip_boards_and_targets() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 7 42",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 2",
+        "bnrbkrqn/pppppppp/8/8/8/8/PPPPPPPP/BNRBKRQN w FCfc - 0 1",
+        "8/8/8/4k3/8/8/8/4K3 w - - 99 200",
+    ];
+    for fen in fens {
+        let board = board(fen);
+        let mut first = None;
+        board.generate_moves(|moves| {
+            first = moves.into_iter().next();
+            true
+        });
+        let mv = first.unwrap();
+        let position = PackedPosition {
+            score_cp: Some(-1234),
+            result: 1,
+            outcome_valid: true,
+            ply: 513,
+            best_move: Some(mv),
+            played_move: Some(mv),
+            teacher_depth: Some(12),
+            termination: Some(GameTermination::FiftyMove),
+            board: board.clone(),
+        };
+        let bytes = position.encode();
+        assert_eq!(bytes.len(), PACKED_RECORD_SIZE);
+        let decoded = PackedPosition::decode(&bytes).unwrap();
+        assert_eq!(decoded, position, "{fen}");
+        assert_eq!(decoded.board.to_string(), board.to_string());
+
+        let bare = PackedPosition::new(board);
+        assert_eq!(PackedPosition::decode(&bare.encode()).unwrap(), bare);
+    }
+}
+
+#[test]
+fn castling_moves_survive_the_move_encoding() {
+    let board = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let castle: Move = "e1h1".parse().unwrap();
+    assert!(board.is_legal(castle));
+    let mut position = PackedPosition::new(board);
+    position.best_move = Some(castle);
+    let decoded = PackedPosition::decode(&position.encode()).unwrap();
+    assert_eq!(decoded.best_move, Some(castle));
+}
+
+#[test]
+fn views_reject_bad_headers_and_truncated_records() {
+    let mut bytes = PackedWriter::new(Vec::new()).unwrap().finish().unwrap();
+    assert_eq!(bytes.len(), PACKED_HEADER_SIZE);
+    assert!(PackedView::parse(&bytes).unwrap().is_empty());
+
+    bytes.extend_from_slice(&[0u8; PACKED_RECORD_SIZE - 1]);
+    assert!(PackedView::parse(&bytes).is_err());
+
+    let mut bad_magic = bytes[..PACKED_HEADER_SIZE].to_vec();
+    bad_magic[0] = b'X';
+    assert!(PackedView::parse(&bad_magic).is_err());
+    assert!(PackedView::parse(&bad_magic[..4]).is_err());
+}
+
+#[test]
+fn games_pack_one_record_per_ply() {
+    let g = game(&["e2e4", "e7e5", "g1f3"]);
+    let positions = game_to_packed(&g);
+    assert_eq!(positions.len(), 3);
+    assert_eq!(positions[0].board, Board::default());
+    assert_eq!(positions[0].best_move, Some("e2e4".parse().unwrap()));
+    // The target is illegal for Black, so the played move stands in.
+    assert_eq!(positions[1].best_move, Some("e7e5".parse().unwrap()));
+    assert_eq!(positions[2].score_cp, Some(20));
+    assert_eq!(positions[2].ply, 2);
+    assert_eq!(positions[2].teacher_depth, Some(8));
+    assert!(positions.iter().all(|p| p.result == -1 && p.outcome_valid));
+
+    let mut writer = PackedWriter::new(Vec::new()).unwrap();
+    for p in &positions {
+        writer.write(p).unwrap();
+    }
+    assert_eq!(writer.records(), 3);
+    let bytes = writer.finish().unwrap();
+    let view = PackedView::parse(&bytes).unwrap();
+    let read: Vec<_> = view.iter().collect::<Result<_, _>>().unwrap();
+    assert_eq!(read, positions);
+}
+
+#[test]
+fn json_round_trip_keeps_the_training_fields() {
+    let positions = game_to_packed(&game(&["d2d4", "d7d5"]));
+    for p in positions {
+        let json = p.to_json();
+        assert_eq!(PackedPosition::from_json(&json).unwrap(), p);
+    }
+    let legacy = serde_json::json!({"fen": Board::default().to_string(), "result": 1});
+    let p = PackedPosition::from_json(legacy.as_object().unwrap()).unwrap();
+    assert!(p.outcome_valid);
+    assert_eq!(p.score_cp, None);
+    let illegal = serde_json::json!({"fen": Board::default().to_string(), "best_move": "e2e5"});
+    assert!(PackedPosition::from_json(illegal.as_object().unwrap()).is_err());
+}
+
+#[test]
+fn shuffle_keeps_every_record() {
+    let dir = Path::new("target/packed_shuffle_test");
+    let _ = std::fs::remove_dir_all(dir);
+    let games = [
+        game(&["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]),
+        game(&["d2d4", "g8f6", "c2c4", "e7e6"]),
+    ];
+    let inputs = write_packed_shards(&games, dir.join("in"), 3).unwrap();
+    assert_eq!(inputs.len(), 3);
+    let out = shuffle_packed(&inputs, &dir.join("out"), 4, 7).unwrap();
+    assert_eq!(out.len(), 3);
+
+    let records = |paths: &[std::path::PathBuf]| {
+        let mut all = Vec::new();
+        for path in paths {
+            let file = PackedFile::open(path).unwrap();
+            let view = file.view();
+            all.extend((0..view.len()).map(|i| view.raw(i).to_vec()));
+        }
+        all
+    };
+    let mut before = records(&inputs);
+    let mut after = records(&out);
+    assert_eq!(before.len(), 9);
+    before.sort();
+    after.sort();
+    assert_eq!(before, after);
+    // The scatter buckets are cleaned up.
+    for entry in std::fs::read_dir(dir.join("out")).unwrap() {
+        let name = entry.unwrap().file_name();
+        assert!(!name.to_string_lossy().starts_with('.'), "{name:?}");
+    }
+}
@@ -0,0 +1,202 @@
+use cozy_chess::{Board, Move};
+use piebot::io::pgn::{move_to_san, san_to_move, EvalComment, PgnGame, PgnReader};
+
+fn board(fen: &str) -> Board {
+    Board::from_fen(fen, false).unwrap()
+}
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut moves = Vec::new();
+    board.generate_moves(|ml| {
+        moves.extend(ml);
+        false
+    });
+    moves
+}
+
+fn read_all(text: &str) -> Vec<PgnGame> {
+    PgnReader::new(text.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .expect("valid PGN")
+}
+
+#[test]
+fn san_round_trips_every_legal_move() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "1k6/8/8/8/8/8/8/R3K2R b KQ - 0 1",
+    ] {
+        let b = board(fen);
+        for mv in legal_moves(&b) {
+            let san = move_to_san(&b, mv);
+            assert_eq!(san_to_move(&b, &san), Ok(mv), "{fen}: {san}");
+        }
+    }
+}
+
+#[test]
+fn san_writer_handles_castling_disambiguation_and_mate() {
+    let b = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert_eq!(move_to_san(&b, "e1h1".parse().unwrap()), "O-O");
+    assert_eq!(move_to_san(&b, "e1a1".parse().unwrap()), "O-O-O");
+    let knights = board("4k3/8/8/8/8/1N6/8/1N2K3 w - - 0 1");
+    assert_eq!(move_to_san(&knights, "b1d2".parse().unwrap()), "N1d2");
+    let promotion = board("8/P7/8/8/8/8/8/k6K w - - 0 1");
+    assert_eq!(move_to_san(&promotion, "a7a8q".parse().unwrap()), "a8=Q+");
+    let mate = board("7k/Q7/6K1/8/8/8/8/8 w - - 0 1");
+    assert_eq!(move_to_san(&mate, "a7g7".parse().unwrap()), "Qg7#");
+}
+
+#[test]
+fn san_parser_accepts_common_variants_and_rejects_bad_moves() {
+    let start = Board::default();
+    let e4: Move = "e2e4".parse().unwrap();
+    for san in ["e4", "e4!", "e2e4", "e2-e4", "Pe4"] {
+        let parsed = san_to_move(&start, san.strip_prefix('P').unwrap_or(san));
+        assert_eq!(parsed, Ok(e4), "{san}");
+    }
+    let castle = board("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    assert_eq!(san_to_move(&castle, "0-0+"), Ok("e1h1".parse().unwrap()));
+    let promotion = board("8/P7/8/8/8/8/8/k6K w - - 0 1");
+    assert_eq!(san_to_move(&promotion, "a8N"), Ok("a7a8n".parse().unwrap()));
+    assert!(san_to_move(&start, "e5").unwrap_err().contains("illegal"));
+    assert!(san_to_move(&start, "O-O").is_err());
+    assert!(san_to_move(&start, "Zz9").is_err());
+    let rooks = board("4k3/8/8/8/8/R7/8/R3K3 w - - 0 1");
+    assert!(san_to_move(&rooks, "Ra2")
+        .unwrap_err()
+        .contains("ambiguous"));
+}
+
+#[test]
+fn eval_comments_round_trip_centipawns_and_mates() {
+    for (text, cp, depth) in [
+        ("+0.35/12", 35, 12),
+        ("-1.07/9", -107, 9),
+        ("+0.00/1", 0, 1),
+        ("+M3/20", 30000 - 5, 20),
+        ("-M1/4", -(30000 - 1), 4),
+    ] {
+        let parsed = EvalComment::parse(text).unwrap();
+        assert_eq!(
+            parsed,
+            EvalComment {
+                score_cp: cp,
+                depth
+            },
+            "{text}"
+        );
+        assert_eq!(parsed.to_string(), text);
+    }
+    assert_eq!(
+        EvalComment::parse("0.5/7 0.12s").map(|e| e.score_cp),
+        Some(50)
+    );
+    assert_eq!(EvalComment::parse("book"), None);
+}
+
+#[test]
+fn reader_streams_games_with_comments_variations_and_nags() {
+    let text = r#"[Event "First"]
+[White "A \"quoted\" name"]
+[Result "1-0"]
+
+1. e4 {+0.30/10} e5 $1 2. Nf3 (2. f4 exf4 {gambit} (2... d5)) Nc6 ; rest of line
+3. Bb5 {multi-line
+comment} a6 1-0
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 30"]
+
+30... Kd7 31.e4 *
+[Event "Third"]
+
+1/2-1/2
+"#;
+    let games = read_all(text);
+    assert_eq!(games.len(), 3);
+
+    let first = &games[0];
+    assert_eq!(first.header("White"), Some("A \"quoted\" name"));
+    assert_eq!(first.result, "1-0");
+    assert_eq!(first.white_score(), Some(1));
+    let moves: Vec<String> = first.moves.iter().map(|m| m.mv.to_string()).collect();
+    assert_eq!(moves, ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6"]);
+    assert_eq!(first.moves[0].comment.as_deref(), Some("+0.30/10"));
+    assert_eq!(first.moves[2].comment, None);
+    assert_eq!(
+        first.moves[4].comment.as_deref(),
+        Some("multi-line comment")
+    );
+
+    let second = &games[1];
+    assert_eq!(second.start.side_to_move(), cozy_chess::Color::Black);
+    assert_eq!(second.moves.len(), 2);
+    assert_eq!(second.result, "*");
+    assert_eq!(second.white_score(), None);
+
+    assert!(games[2].moves.is_empty());
+    assert_eq!(games[2].white_score(), Some(0));
+}
+
+#[test]
+fn reader_reports_illegal_moves() {
+    let mut reader = PgnReader::new(&b"[Event \"Bad\"]\n\n1. e4 e4 *\n"[..]);
+    let error = reader.next().unwrap().unwrap_err();
+    assert!(error.contains("illegal move"), "{error}");
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn writer_output_reads_back_identically() {
+    let start = board("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 17");
+    let mut game = PgnGame::new(start.clone());
+    game.set_header("Event", "Round trip");
+    game.set_header("GameId", "g-1");
+    let mut b = start;
+    for (i, uci) in ["e8a8", "e1h1", "d8d1", "f1d1"].iter().enumerate() {
+        let mv = legal_moves(&b)
+            .into_iter()
+            .find(|m| m.to_string() == *uci)
+            .unwrap();
+        game.push(mv, (i % 2 == 0).then(|| format!("note {i}")));
+        b.play_unchecked(mv);
+    }
+    game.result = "1/2-1/2".to_string();
+    let text = game.to_pgn();
+    assert!(
+        text.starts_with("[Event \"Round trip\"]\n[Site \"?\"]\n"),
+        "{text}"
+    );
+    assert!(text.contains("[FEN \"r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 17\"]"));
+    assert!(text.contains("17... O-O-O {note 0} 18. O-O Rd1 {note 2} 19. Rfxd1 1/2-1/2"));
+    assert!(text.lines().all(|line| line.len() < 80));
+
+    let read = read_all(&text);
+    assert_eq!(read.len(), 1);
+    assert_eq!(read[0].moves, game.moves);
+    assert_eq!(read[0].header("GameId"), Some("g-1"));
+    assert_eq!(read[0].positions().last(), game.positions().last());
+}
+
+#[test]
+fn writer_wraps_long_games() {
+    let mut game = PgnGame::default();
+    let mut b = Board::default();
+    for _ in 0..30 {
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            let mv: Move = uci.parse().unwrap();
+            game.push(mv, Some("+0.12/8".to_string()));
+            b.play_unchecked(mv);
+        }
+    }
+    let text = game.to_pgn();
+    assert!(text.lines().count() > 10);
+    assert!(text.lines().all(|line| line.len() < 80));
+    assert_eq!(read_all(&text)[0].moves.len(), 120);
+}
@@ -0,0 +1,129 @@
+use cozy_chess::{Board, Move};
+use piebot::io::polyglot::{decode_move, encode_move, polyglot_key, BookSelection, PolyglotBook};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+fn after(moves: &[&str]) -> Board {
+    let mut board = Board::default();
+    for mv in moves {
+        board.play(mv.parse().unwrap());
+    }
+    board
+}
+
+fn entry(key: u64, mv: &str, weight: u16) -> Vec<u8> {
+    let mut bytes = key.to_be_bytes().to_vec();
+    bytes.extend(encode_move(mv.parse().unwrap()).to_be_bytes());
+    bytes.extend(weight.to_be_bytes());
+    bytes.extend(0u32.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn keys_match_the_polyglot_reference_positions() {
+    let cases: [(&[&str], u64); 9] = [
+        (&[], 0x463b96181691fc9c),
+        (&["e2e4"], 0x823c9b50fd114196),
+        (&["e2e4", "d7d5"], 0x0756b94461c50fb0),
+        (&["e2e4", "d7d5", "e4e5"], 0x662fafb965db29d4),
+        // f5 is next to the e5 pawn: the en passant file is part of the key.
+        (&["e2e4", "d7d5", "e4e5", "f7f5"], 0x22a48b5a8e47ff78),
+        (
+            &["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"],
+            0x652a607ca3f242c1,
+        ),
+        (
+            &["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"],
+            0x00fdd303c946bdd9,
+        ),
+        (
+            &["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"],
+            0x3c8123ea7b067637,
+        ),
+        (
+            &["a2a4", "b7b5", "h2h4", "b5b4", "c2c4", "b4c3", "a1a3"],
+            0x5c3f9b829b279560,
+        ),
+    ];
+    for (moves, key) in cases {
+        assert_eq!(polyglot_key(&after(moves)), Some(key), "{moves:?}");
+    }
+}
+
+#[test]
+fn moves_round_trip_including_castling_and_promotion() {
+    for raw in ["e2e4", "e1h1", "e8a8", "g7h8q", "b2a1n"] {
+        let mv: Move = raw.parse().unwrap();
+        assert_eq!(decode_move(encode_move(mv)), Some(mv));
+    }
+    // Polyglot castling: king e1 "captures" the h1 rook.
+    assert_eq!(encode_move("e1h1".parse().unwrap()), 0x0107);
+    assert_eq!(decode_move(7 << 12), None);
+}
+
+#[test]
+fn book_lookup_returns_legal_moves_and_selects_by_weight() {
+    let start = polyglot_key(&Board::default()).unwrap();
+    let e4 = polyglot_key(&after(&["e2e4"])).unwrap();
+    // Deliberately unsorted, with an illegal entry for the start position.
+    let mut bytes = entry(e4, "c7c5", 5);
+    bytes.extend(entry(start, "e2e4", 3));
+    bytes.extend(entry(start, "d2d4", 1));
+    bytes.extend(entry(start, "e2e5", 9));
+    let book = PolyglotBook::from_bytes(&bytes).unwrap();
+    assert_eq!(book.len(), 4);
+
+    let moves: Vec<String> = book
+        .moves(&Board::default())
+        .iter()
+        .map(|m| m.mv.to_string())
+        .collect();
+    assert_eq!(moves, ["e2e4", "d2d4"]);
+
+    let mut rng = SmallRng::seed_from_u64(7);
+    let best = book.choose(&Board::default(), BookSelection::Best, &mut rng);
+    assert_eq!(best.map(|mv| mv.to_string()).as_deref(), Some("e2e4"));
+
+    let e4_count = (0..400)
+        .filter(|_| {
+            book.choose(&Board::default(), BookSelection::Weighted, &mut rng)
+                .unwrap()
+                .to_string()
+                == "e2e4"
+        })
+        .count();
+    assert!((240..360).contains(&e4_count), "{e4_count}");
+
+    assert!(book
+        .choose(&after(&["d2d4"]), BookSelection::Best, &mut rng)
+        .is_none());
+}
+
+#[test]
+fn truncated_books_are_rejected() {
+    let bytes = entry(1, "e2e4", 1);
+    assert!(PolyglotBook::from_bytes(&bytes[..15]).is_err());
+    assert!(PolyglotBook::open(std::path::Path::new("/definitely/missing/book.bin")).is_err());
+}
+
+#[test]
+fn pgn_databases_become_books_weighted_by_play_count() {
+    let path = std::env::temp_dir().join(format!("piebot_pgn_book_{}.pgn", std::process::id()));
+    std::fs::write(
+        &path,
+        "1. e4 e5 2. Nf3 *\n\n1. e4 c5 *\n\n1. d4 d5 *\n\n1. O-O-O *\n",
+    )
+    .unwrap();
+    let err = PolyglotBook::from_pgn(&path, 2).expect_err("1. O-O-O is illegal");
+    assert!(err.contains("game 4"), "{err}");
+    std::fs::write(&path, "1. e4 e5 2. Nf3 *\n\n1. e4 c5 *\n\n1. d4 d5 *\n").unwrap();
+    let book = PolyglotBook::from_pgn(&path, 2).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let root = book.moves(&Board::default());
+    let summary: Vec<(String, u16)> = root.iter().map(|m| (m.mv.to_string(), m.weight)).collect();
+    assert_eq!(summary, [("e2e4".to_string(), 2), ("d2d4".to_string(), 1)]);
+    assert_eq!(2, book.moves(&after(&["e2e4"])).len());
+    // Only the first two plies of each game are kept.
+    assert!(book.moves(&after(&["e2e4", "e7e5"])).is_empty());
+}
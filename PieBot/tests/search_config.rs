@@ -0,0 +1,97 @@
+use cozy_chess::Board;
+use piebot::search::alphabeta::{SearchParams, SearchResult, Searcher};
+use piebot::search::config::SearchConfig;
+
+const MIDGAME: &str = "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ - 0 8";
+
+fn search(config: Option<SearchConfig>, depth: u32) -> SearchResult {
+    let board = Board::from_fen(MIDGAME, false).unwrap();
+    let mut searcher = Searcher::default();
+    if let Some(config) = config {
+        searcher.set_search_config(config);
+    }
+    let params = SearchParams {
+        depth,
+        use_tt: true,
+        order_captures: true,
+        use_history: true,
+        threads: 1,
+        use_aspiration: true,
+        aspiration_window_cp: 50,
+        use_lmr: true,
+        use_killers: true,
+        use_nullmove: true,
+        deterministic: true,
+        ..SearchParams::default()
+    };
+    searcher.search_with_params(&board, params)
+}
+
+#[test]
+fn the_default_config_is_the_unconfigured_search() {
+    let plain = search(None, 7);
+    let configured = search(Some(SearchConfig::default()), 7);
+    assert_eq!(configured.nodes, plain.nodes);
+    assert_eq!(configured.bestmove, plain.bestmove);
+    assert_eq!(configured.score_cp, plain.score_cp);
+}
+
+#[test]
+fn every_heuristic_knob_reaches_the_search() {
+    let baseline = search(None, 7).nodes;
+    let knobs: [(&str, SearchConfig); 7] = [
+        (
+            "lmr_divisor",
+            SearchConfig {
+                lmr_divisor: 1.5,
+                ..SearchConfig::default()
+            },
+        ),
+        (
+            "lmr_min_move_index",
+            SearchConfig {
+                lmr_min_move_index: 6,
+                ..SearchConfig::default()
+            },
+        ),
+        (
+            "futility_margins_cp",
+            SearchConfig {
+                futility_margins_cp: Vec::new(),
+                ..SearchConfig::default()
+            },
+        ),
+        (
+            "reverse_futility_max_depth",
+            SearchConfig {
+                reverse_futility_max_depth: 0,
+                ..SearchConfig::default()
+            },
+        ),
+        (
+            "null_move_capped_r",
+            SearchConfig {
+                null_move_capped_r: 1,
+                ..SearchConfig::default()
+            },
+        ),
+        (
+            "history_malus_percent",
+            SearchConfig {
+                history_malus_percent: 0,
+                ..SearchConfig::default()
+            },
+        ),
+        (
+            "good_capture_priority",
+            SearchConfig {
+                good_capture_priority: 0,
+                ..SearchConfig::default()
+            },
+        ),
+    ];
+    for (name, config) in knobs {
+        let nodes = search(Some(config), 7).nodes;
+        assert_ne!(nodes, baseline, "{name} did not change the search");
+    }
+}
@@ -0,0 +1,131 @@
+use cozy_chess::Board;
+use piebot::search::alphabeta::{InfoScore, SearchParams, Searcher};
+use piebot::search::eval::MATE_SCORE;
+use std::sync::mpsc;
+
+fn depth_params(depth: u32) -> SearchParams {
+    SearchParams {
+        depth,
+        use_tt: true,
+        order_captures: true,
+        use_history: true,
+        use_killers: true,
+        deterministic: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn every_completed_iteration_is_reported_with_a_legal_pv() {
+    let board = Board::default();
+    let mut searcher = Searcher::default();
+    let (tx, rx) = mpsc::channel();
+    searcher.set_info_sender(Some(tx));
+    let result = searcher.search_with_params(&board, depth_params(4));
+    searcher.set_info_sender(None);
+
+    let infos: Vec<_> = rx.try_iter().collect();
+    assert_eq!(
+        infos.iter().map(|info| info.depth).collect::<Vec<_>>(),
+        vec![1, 2, 3, 4]
+    );
+    for pair in infos.windows(2) {
+        assert!(pair[1].nodes >= pair[0].nodes, "node count went backwards");
+    }
+    let last = infos.last().unwrap();
+    assert_eq!(last.pv.first(), result.bestmove.as_ref());
+    assert_eq!(last.pv, result.pv);
+    assert_eq!(last.score, InfoScore::Cp(result.score_cp));
+    assert!(last.seldepth >= last.depth);
+    assert_eq!(last.multipv, 1);
+    assert_eq!(result.lines.len(), 1);
+    assert_eq!(result.lines[0].pv, result.pv);
+
+    let mut replay = board.clone();
+    for mv in &last.pv {
+        let mv = mv.parse().expect("pv move parses");
+        assert!(replay.is_legal(mv), "illegal pv move {mv}");
+        replay.play_unchecked(mv);
+    }
+}
+
+#[test]
+fn forced_mate_is_reported_in_moves_not_centipawns() {
+    let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", false).unwrap();
+    let mut searcher = Searcher::default();
+    let (tx, rx) = mpsc::channel();
+    searcher.set_info_sender(Some(tx));
+    searcher.search_with_params(&board, depth_params(3));
+
+    let last = rx.try_iter().last().expect("at least one iteration");
+    assert_eq!(last.score, InfoScore::Mate(1));
+    assert_eq!(last.pv, vec!["a1a8".to_string()]);
+}
+
+#[test]
+fn multipv_ranks_distinct_root_moves_best_first() {
+    let board = Board::default();
+    let mut searcher = Searcher::default();
+    let (tx, rx) = mpsc::channel();
+    searcher.set_info_sender(Some(tx));
+    let params = SearchParams {
+        multipv: 3,
+        ..depth_params(3)
+    };
+    let result = searcher.search_with_params(&board, params);
+    searcher.set_info_sender(None);
+
+    assert_eq!(result.depth, 3);
+    assert_eq!(result.lines.len(), 3);
+    assert_eq!(Some(&result.lines[0].mv), result.bestmove.as_ref());
+    assert_eq!(result.lines[0].pv, result.pv);
+    for pair in result.lines.windows(2) {
+        assert_ne!(pair[0].mv, pair[1].mv);
+        assert!(pair[0].score_cp >= pair[1].score_cp, "lines out of order");
+    }
+    for line in &result.lines {
+        assert_eq!(line.pv.first(), Some(&line.mv));
+        assert_eq!(line.depth, 3);
+    }
+
+    let infos: Vec<_> = rx.try_iter().filter(|info| info.depth == 3).collect();
+    assert_eq!(
+        infos.iter().map(|info| info.multipv).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    for (info, line) in infos.iter().zip(&result.lines) {
+        assert_eq!(info.pv, line.pv);
+        assert_eq!(info.score, InfoScore::from_score(line.score_cp));
+    }
+}
+
+#[test]
+fn multipv_stops_at_the_number_of_legal_moves() {
+    // The lone white king on a1 has exactly three moves.
+    let board = Board::from_fen("7k/8/8/8/8/8/8/K7 w - - 0 1", false).unwrap();
+    let mut searcher = Searcher::default();
+    let params = SearchParams {
+        multipv: 8,
+        ..depth_params(2)
+    };
+    let result = searcher.search_with_params(&board, params);
+
+    let mut moves: Vec<_> = result.lines.iter().map(|line| line.mv.as_str()).collect();
+    moves.sort_unstable();
+    assert_eq!(moves, vec!["a1a2", "a1b1", "a1b2"]);
+    assert_eq!(result.depth, 2);
+}
+
+#[test]
+fn mate_distances_convert_from_plies_to_moves() {
+    assert_eq!(InfoScore::from_score(MATE_SCORE - 1), InfoScore::Mate(1));
+    assert_eq!(InfoScore::from_score(MATE_SCORE - 5), InfoScore::Mate(3));
+    assert_eq!(InfoScore::from_score(-MATE_SCORE + 2), InfoScore::Mate(-1));
+    assert_eq!(InfoScore::from_score(-MATE_SCORE + 4), InfoScore::Mate(-2));
+    assert_eq!(InfoScore::from_score(-35), InfoScore::Cp(-35));
+    assert_eq!(format!("{}", InfoScore::Mate(-2)), "mate -2");
+    assert_eq!(format!("{}", InfoScore::Cp(12)), "cp 12");
+    assert_eq!(InfoScore::Mate(3).to_score(), MATE_SCORE - 5);
+    assert_eq!(InfoScore::Mate(-2).to_score(), -MATE_SCORE + 4);
+    assert_eq!(InfoScore::Cp(-35).to_score(), -35);
+}
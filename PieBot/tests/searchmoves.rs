@@ -0,0 +1,85 @@
+use cozy_chess::{Board, Move};
+use piebot::search::alphabeta::{RootMoveFilter, SearchParams, Searcher};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+fn restricted(depth: u32, moves: &[&str]) -> SearchParams {
+    let moves: Vec<Move> = moves.iter().map(|mv| mv.parse().unwrap()).collect();
+    SearchParams {
+        depth,
+        use_tt: true,
+        order_captures: true,
+        use_history: true,
+        use_killers: true,
+        deterministic: true,
+        searchmoves: Some(RootMoveFilter::new(&moves)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn search_only_considers_the_listed_root_moves() {
+    // Qxd8 wins the queen outright; restricted to quiet moves it is never played.
+    let board = Board::from_fen("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1", false).unwrap();
+    let mut searcher = Searcher::default();
+
+    let free = searcher.search_with_params(&board, restricted(3, &["d1d8"]));
+    assert_eq!(free.bestmove.as_deref(), Some("d1d8"));
+
+    let result = searcher.search_with_params(&board, restricted(3, &["e1f2", "e1e2"]));
+    let best = result.bestmove.unwrap();
+    assert!(best == "e1f2" || best == "e1e2", "{best}");
+    assert!(result.score_cp < free.score_cp);
+    assert!(result.pv.first() == Some(&best));
+}
+
+#[test]
+fn multipv_ranks_only_the_listed_root_moves() {
+    let board = Board::default();
+    let mut searcher = Searcher::default();
+    let params = SearchParams {
+        multipv: 4,
+        ..restricted(2, &["a2a3", "h2h3"])
+    };
+    let result = searcher.search_with_params(&board, params);
+
+    let mut moves: Vec<_> = result.lines.iter().map(|line| line.mv.as_str()).collect();
+    moves.sort_unstable();
+    assert_eq!(moves, vec!["a2a3", "h2h3"]);
+}
+
+#[test]
+fn a_filter_without_legal_moves_leaves_the_search_unrestricted() {
+    let board = Board::default();
+    let mut searcher = Searcher::default();
+    let result = searcher.search_with_params(&board, restricted(2, &["e2e5"]));
+    let best: Move = result.bestmove.unwrap().parse().unwrap();
+    assert!(board.is_legal(best));
+}
+
+#[test]
+fn uci_go_searchmoves_accepts_standard_castling_notation() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_uci"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("start UCI engine");
+    let mut stdin = child.stdin.take().expect("piped stdin");
+    let stdout = child.stdout.take().expect("piped stdout");
+    writeln!(
+        stdin,
+        "position fen r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1"
+    )
+    .unwrap();
+    writeln!(stdin, "go depth 3 searchmoves e1g1").unwrap();
+    stdin.flush().unwrap();
+
+    let bestmove = BufReader::new(stdout)
+        .lines()
+        .map_while(Result::ok)
+        .find(|line| line.starts_with("bestmove"))
+        .expect("bestmove");
+    writeln!(stdin, "quit").ok();
+    let _ = child.wait();
+    assert_eq!(bestmove, "bestmove e1g1");
+}
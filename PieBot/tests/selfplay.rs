@@ -1,5 +1,7 @@
+use piebot::selfplay::opening::{OpeningSource, OpeningStrategy};
 use piebot::selfplay::{
-    generate_games, AdjudicationVerdict, Adjudicator, GameTermination, SelfPlayParams,
+    game_jsonl_lines, generate_games, AdjudicationVerdict, Adjudicator, GameTermination,
+    SelfPlayParams,
 };
 
 #[test]
@@ -32,6 +34,9 @@ fn selfplay_generates_games_deterministically() {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     };
     let g1 = generate_games(&params).expect("selfplay games");
     let g2 = generate_games(&params).expect("selfplay games");
@@ -75,6 +80,9 @@ fn max_ply_cutoff_is_not_labeled_as_a_real_draw() {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     };
 
     let games = generate_games(&params).expect("selfplay games");
@@ -114,6 +122,9 @@ fn selfplay_noise_changes_moves_with_different_seeds() {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     };
     let g1 = generate_games(&p).expect("selfplay games");
     p.seed = 2;
@@ -154,6 +165,9 @@ fn selfplay_parallel_random_matches_serial_by_seed() {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     };
     let serial = generate_games(&params).expect("selfplay games");
     params.parallel_games = 4;
@@ -198,6 +212,9 @@ fn selfplay_parallel_engine_matches_serial_by_seed() {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     };
     let serial = generate_games(&params).expect("selfplay games");
     params.parallel_games = 4;
@@ -241,6 +258,9 @@ fn openings_params(openings_path: std::path::PathBuf) -> SelfPlayParams {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     }
 }
 
@@ -457,6 +477,9 @@ fn adjudication_params(openings_path: std::path::PathBuf) -> SelfPlayParams {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     }
 }
 
@@ -534,3 +557,145 @@ fn actor_budget_is_configurable_with_legacy_defaults() {
     let best = piebot::selfplay::bestmove_search_params(&raised, 2);
     assert_eq!(Some(100_000), best.max_nodes);
 }
+
+fn policy_params(policy_full_distribution: bool) -> SelfPlayParams {
+    let mut params = openings_params(std::path::PathBuf::new());
+    params.games = 1;
+    params.max_plies = 2;
+    params.use_engine = true;
+    params.depth = 2;
+    params.openings_path = None;
+    params.temperature_tau = 1.0;
+    params.temperature_moves = 2;
+    params.policy_node_cap = 200_000;
+    params.policy_full_distribution = policy_full_distribution;
+    params
+}
+
+#[test]
+fn policy_targets_come_from_one_multipv_search_over_all_root_moves() {
+    let top = generate_games(&policy_params(false)).expect("selfplay games");
+    let full = generate_games(&policy_params(true)).expect("selfplay games");
+    assert_eq!(
+        top[0].moves, full[0].moves,
+        "the option only changes output"
+    );
+
+    let first_top = &top[0].move_policy_top[0];
+    let first_full = &full[0].move_policy_top[0];
+    assert_eq!(8, first_top.len());
+    assert_eq!(20, first_full.len(), "every legal opening move is listed");
+    assert_eq!(&first_full[..8], &first_top[..]);
+    let mass: f32 = first_full.iter().map(|(_, p)| p).sum();
+    assert!(
+        (mass - 1.0).abs() < 1e-4,
+        "full distribution sums to {mass}"
+    );
+    assert!(first_full.windows(2).all(|w| w[0].1 >= w[1].1));
+    // The target is the highest-probability move.
+    assert_eq!(
+        Some(first_full[0].0.as_str()),
+        full[0].move_target_best[0].as_deref()
+    );
+    assert!(full[0].move_teacher_depth[0].is_some_and(|d| d >= 1));
+}
+
+#[test]
+fn policy_targets_score_every_move_when_the_budget_runs_out() {
+    let mut params = policy_params(true);
+    params.policy_node_cap = 1;
+    let games = generate_games(&params).expect("selfplay games");
+    let first = &games[0].move_policy_top[0];
+    assert_eq!(20, first.len());
+    // Scored at depth 1, the opening moves do not all tie.
+    assert!(first[0].1 > first[19].1);
+}
+
+fn opening_params(strategy: OpeningStrategy) -> SelfPlayParams {
+    let mut params = openings_params(std::path::PathBuf::new());
+    params.openings_path = None;
+    params.max_plies = 2;
+    params.opening_strategy = strategy;
+    params
+}
+
+#[test]
+fn random_walk_openings_stay_inside_the_eval_window() {
+    let params = opening_params(OpeningStrategy::RandomWalk {
+        plies: 6,
+        max_cp: 300,
+        depth: 2,
+    });
+    let games = generate_games(&params).expect("selfplay games");
+    let again = generate_games(&params).expect("selfplay games");
+    for (game, repeat) in games.iter().zip(&again) {
+        let opening = game.opening.as_ref().expect("provenance");
+        assert_eq!(OpeningSource::RandomWalk, opening.source);
+        assert_eq!(6, opening.moves.len());
+        let eval = opening.eval_cp.expect("filtered walks carry their eval");
+        assert!(eval.abs() <= 300, "eval {eval} outside the window");
+        let mut board = cozy_chess::Board::default();
+        for mv in &opening.moves {
+            board.play(mv.parse().unwrap());
+        }
+        assert_eq!(board.to_string(), game.start_fen);
+        assert_eq!(game.start_fen, repeat.start_fen, "openings follow the seed");
+
+        let line = &game_jsonl_lines(game).unwrap()[0];
+        let json: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!("random_walk", json["opening"]["source"]);
+        assert_eq!(6, json["opening"]["moves"].as_array().unwrap().len());
+    }
+    assert!(games.windows(2).any(|w| w[0].start_fen != w[1].start_fen));
+}
+
+#[test]
+fn paired_openings_play_each_opening_twice() {
+    let mut params = opening_params(OpeningStrategy::RandomWalk {
+        plies: 4,
+        max_cp: 0,
+        depth: 1,
+    });
+    params.paired_openings = true;
+    let games = generate_games(&params).expect("selfplay games");
+    assert_eq!(4, games.len());
+    for (pair, games) in games.chunks(2).enumerate() {
+        assert_eq!(games[0].start_fen, games[1].start_fen);
+        assert_eq!(games[0].opening, games[1].opening);
+        assert_eq!(Some(pair), games[0].opening.as_ref().unwrap().pair);
+    }
+    assert_ne!(games[0].start_fen, games[2].start_fen);
+}
+
+#[test]
+fn book_openings_follow_a_pgn_database() {
+    let path = std::env::temp_dir().join(format!("piebot_book_{}.pgn", std::process::id()));
+    std::fs::write(&path, "1. e4 e5 2. Nf3 Nc6 *\n\n1. e4 c5 2. Nf3 d6 *\n").unwrap();
+    let params = opening_params(OpeningStrategy::Book {
+        path: path.clone(),
+        max_plies: 4,
+    });
+    let games = generate_games(&params).expect("selfplay games");
+    std::fs::remove_file(&path).ok();
+    let lines = [
+        ["e2e4", "e7e5", "g1f3", "b8c6"],
+        ["e2e4", "c7c5", "g1f3", "d7d6"],
+    ];
+    for game in &games {
+        let opening = game.opening.as_ref().unwrap();
+        assert_eq!(OpeningSource::Book, opening.source);
+        assert!(opening.book.as_deref().unwrap().ends_with(".pgn"));
+        assert!(!opening.moves.is_empty());
+        let moves: Vec<&str> = opening.moves.iter().map(String::as_str).collect();
+        assert!(
+            lines.iter().any(|line| line.starts_with(&moves)),
+            "{moves:?} is not a book line"
+        );
+    }
+
+    let missing = opening_params(OpeningStrategy::Book {
+        path: "/nonexistent/piebot_book.bin".into(),
+        max_plies: 4,
+    });
+    assert!(generate_games(&missing).is_err());
+}
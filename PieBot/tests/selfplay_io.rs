@@ -1,6 +1,8 @@
+use piebot::io::pgn;
+use piebot::selfplay::opening::OpeningStrategy;
 use piebot::selfplay::{
-    flatten_game_to_records, generate_games, read_shard, write_jsonl_shards, write_shards,
-    GameRecord, GameTermination, SelfPlayParams,
+    flatten_game_to_records, game_jsonl_lines, game_record_from_pgn, generate_games, read_shard,
+    write_jsonl_shards, write_pgn, write_shards, GameRecord, GameTermination, SelfPlayParams,
 };
 use std::fs::create_dir_all;
 
@@ -23,6 +25,7 @@ fn write_and_read_shard() {
         result: -1,
         outcome_valid: true,
         termination: GameTermination::Checkmate,
+        opening: None,
     }];
     let outdir = std::path::Path::new("target/selfplay_test");
     create_dir_all(outdir).unwrap();
@@ -46,6 +49,7 @@ fn binary_records_omit_games_without_a_real_outcome() {
         result: 0,
         outcome_valid: false,
         termination: GameTermination::MaxPlies,
+        opening: None,
     };
 
     assert!(
@@ -84,6 +88,9 @@ fn write_jsonl_shard_contains_fen_result_best_move() {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     };
     let games = generate_games(&params).expect("selfplay games");
     let outdir = std::path::Path::new("target/selfplay_jsonl_test");
@@ -142,6 +149,9 @@ fn write_jsonl_shard_contains_ply_value_and_policy_top_for_engine_games() {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     };
     let games = generate_games(&params).expect("selfplay games");
     let outdir = std::path::Path::new("target/selfplay_jsonl_value_test");
@@ -196,8 +206,70 @@ fn selfplay_preserves_opening_start_fen() {
         actor_tt_mb: 0,
         policy_node_cap: 10_000,
         bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
     };
     let games = generate_games(&params).expect("selfplay games");
     assert_eq!(games.len(), 1);
     assert_eq!(games[0].start_fen, opening_fen);
 }
+
+#[test]
+fn pgn_output_reads_back_as_the_same_jsonl_records() {
+    let game = GameRecord {
+        run_id: "pgn-run".to_string(),
+        game_id: "pgn-run:0".to_string(),
+        start_fen: cozy_chess::Board::default().to_string(),
+        moves: vec![
+            "f2f3".to_string(),
+            "e7e5".to_string(),
+            "g2g4".to_string(),
+            "d8h4".to_string(),
+        ],
+        move_target_best: vec![None; 4],
+        move_value_cp: vec![Some(-20.0), Some(-35.0), None, Some(-29_999.0)],
+        move_teacher_depth: vec![Some(6), Some(6), None, Some(6)],
+        move_policy_top: vec![Vec::new(); 4],
+        result: -1,
+        outcome_valid: true,
+        termination: GameTermination::Checkmate,
+        opening: None,
+    };
+    let path = std::path::Path::new("target/selfplay_pgn_test/games.pgn");
+    write_pgn(std::slice::from_ref(&game), path).unwrap();
+    let text = std::fs::read_to_string(path).unwrap();
+    assert!(text.contains("[Result \"0-1\"]"), "{text}");
+    assert!(text.contains("[Termination \"checkmate\"]"), "{text}");
+    // Comments are from the mover: Black's -35 (White view) is +0.35.
+    assert!(
+        text.contains("1. f3 {-0.20/6} e5 {+0.35/6} 2. g4 Qh4# {+M1/6} 0-1"),
+        "{text}"
+    );
+
+    let games: Vec<_> = pgn::open(path).unwrap().map(Result::unwrap).collect();
+    assert_eq!(games.len(), 1);
+    let back = game_record_from_pgn(&games[0], "unused", "unused");
+    assert_eq!(back.run_id, "pgn-run");
+    assert_eq!(back.termination, GameTermination::Checkmate);
+    assert_eq!(
+        game_jsonl_lines(&back).unwrap(),
+        game_jsonl_lines(&game).unwrap()
+    );
+}
+
+#[test]
+fn pgn_games_without_tags_get_ids_and_termination_from_the_game() {
+    let text = "[Result \"1/2-1/2\"]\n\n1. e4 {+0.25/10 0.5s} e5 {book} 1/2-1/2\n";
+    let game = pgn::PgnReader::new(text.as_bytes())
+        .next()
+        .unwrap()
+        .unwrap();
+    let record = game_record_from_pgn(&game, "pgn:db", "pgn:db:7");
+    assert_eq!(record.game_id, "pgn:db:7");
+    assert_eq!(record.moves, ["e2e4", "e7e5"]);
+    assert_eq!(record.move_value_cp, [Some(25.0), None]);
+    assert_eq!(record.move_teacher_depth, [Some(10), None]);
+    assert_eq!((record.result, record.outcome_valid), (0, true));
+    assert_eq!(record.termination, GameTermination::AdjudicatedDraw);
+}
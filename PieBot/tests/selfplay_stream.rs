@@ -0,0 +1,161 @@
+use piebot::selfplay::opening::OpeningStrategy;
+use piebot::selfplay::packed::PackedFile;
+use piebot::selfplay::stream::{Manifest, StreamOutputs, StreamingShardWriter, MANIFEST_FILE};
+use piebot::selfplay::{generate_games, write_jsonl_shards, GameRecord, SelfPlayParams};
+use std::path::{Path, PathBuf};
+
+fn params(games: usize, seed: u64) -> SelfPlayParams {
+    SelfPlayParams {
+        games,
+        max_plies: 12,
+        threads: 1,
+        parallel_games: 2,
+        use_engine: false,
+        depth: 1,
+        movetime_ms: None,
+        seed,
+        temperature_tau: 0.0,
+        temp_cp_scale: 200.0,
+        dirichlet_alpha: 0.3,
+        dirichlet_epsilon: 0.0,
+        dirichlet_plies: 0,
+        temperature_moves: 0,
+        openings_path: None,
+        temperature_tau_final: 0.1,
+        nnue_quant_model: None,
+        nnue_blend_percent: 100,
+        resign_cp: 900.0,
+        resign_plies: 8,
+        no_resign_fraction: 0.15,
+        draw_adj_cp: 10.0,
+        draw_adj_plies: 40,
+        draw_adj_min_ply: 80,
+        actor_tt_mb: 0,
+        policy_node_cap: 10_000,
+        bestmove_node_cap: 20_000,
+        policy_full_distribution: false,
+        opening_strategy: OpeningStrategy::Suite,
+        paired_openings: false,
+    }
+}
+
+fn outputs(dir: &Path) -> StreamOutputs {
+    StreamOutputs {
+        bin_dir: Some(dir.join("bin")),
+        jsonl_dir: Some(dir.join("jsonl")),
+        packed_dir: Some(dir.join("packed")),
+        pgn_path: Some(dir.join("games.pgn")),
+        manifest_path: dir.join("jsonl").join(MANIFEST_FILE),
+        // Every game has 12 plies, so each shard holds two games.
+        max_records_per_shard: 20,
+    }
+}
+
+fn fresh_dir(name: &str) -> PathBuf {
+    let dir = Path::new("target").join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn sorted_jsonl_lines(dir: &Path) -> Vec<String> {
+    let mut lines = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|x| x.to_str()) == Some("jsonl") {
+            let text = std::fs::read_to_string(path).unwrap();
+            lines.extend(text.lines().map(str::to_string));
+        }
+    }
+    lines.sort();
+    lines
+}
+
+fn write_all(writer: &mut StreamingShardWriter, games: &[GameRecord], indices: &[usize]) {
+    for &i in indices {
+        writer.write_game(i, &games[i]).unwrap();
+    }
+}
+
+#[test]
+fn streamed_shards_hold_the_same_records_as_batch_shards() {
+    let dir = fresh_dir("selfplay_stream_same");
+    let games = generate_games(&params(5, 11)).unwrap();
+    assert!(games.iter().all(|g| g.moves.len() == 12));
+    write_jsonl_shards(&games, dir.join("batch"), 1000).unwrap();
+
+    let mut writer = StreamingShardWriter::create(outputs(&dir), 11, false).unwrap();
+    // Out of order, as parallel games finish.
+    write_all(&mut writer, &games, &[3, 0, 4, 1, 2]);
+    let shards = writer.finish().unwrap();
+    // Three rotations (two, two and one game) in three formats.
+    assert_eq!(shards.len(), 9);
+    assert!(shards.iter().all(|p| p.exists()));
+
+    assert_eq!(
+        sorted_jsonl_lines(&dir.join("jsonl")),
+        sorted_jsonl_lines(&dir.join("batch"))
+    );
+    let packed = PackedFile::open(&dir.join("packed/shard_000000.piepack")).unwrap();
+    assert_eq!(packed.view().len(), 24);
+    let pgn = std::fs::read_to_string(dir.join("games.pgn")).unwrap();
+    assert_eq!(pgn.matches("[Event ").count(), 5);
+
+    let manifest = Manifest::load(&dir.join("jsonl").join(MANIFEST_FILE))
+        .unwrap()
+        .unwrap();
+    assert_eq!(manifest.next_shard, 3);
+    assert_eq!(
+        manifest.completed_games.into_iter().collect::<Vec<_>>(),
+        [0, 1, 2, 3, 4]
+    );
+}
+
+#[test]
+fn resume_skips_finished_games_and_replays_the_open_shard() {
+    let dir = fresh_dir("selfplay_stream_resume");
+    let games = generate_games(&params(5, 23)).unwrap();
+
+    // A run that dies after game 2 went into a shard that never closed.
+    let mut writer = StreamingShardWriter::create(outputs(&dir), 23, false).unwrap();
+    write_all(&mut writer, &games, &[0, 1, 2]);
+    drop(writer);
+    assert!(dir.join("jsonl/shard_000001.jsonl.tmp").exists());
+    assert!(!dir.join("jsonl/shard_000001.jsonl").exists());
+
+    let mut writer = StreamingShardWriter::create(outputs(&dir), 23, true).unwrap();
+    let pending: Vec<usize> = (0..5).filter(|&i| !writer.is_completed(i)).collect();
+    assert_eq!(pending, [2, 3, 4]);
+    // The games played after resuming are the ones the first run would
+    // have played.
+    let replayed = generate_games(&params(5, 23)).unwrap();
+    assert_eq!(replayed[2].moves, games[2].moves);
+    write_all(&mut writer, &replayed, &pending);
+    writer.finish().unwrap();
+
+    let mut expected_dir = fresh_dir("selfplay_stream_resume_expected");
+    expected_dir.push("jsonl");
+    write_jsonl_shards(&games, &expected_dir, 1000).unwrap();
+    assert_eq!(
+        sorted_jsonl_lines(&dir.join("jsonl")),
+        sorted_jsonl_lines(&expected_dir)
+    );
+    let pgn = std::fs::read_to_string(dir.join("games.pgn")).unwrap();
+    assert_eq!(pgn.matches("[Event ").count(), 5);
+}
+
+#[test]
+fn resume_refuses_a_manifest_from_another_seed() {
+    let dir = fresh_dir("selfplay_stream_seed");
+    let games = generate_games(&params(2, 5)).unwrap();
+    let mut writer = StreamingShardWriter::create(outputs(&dir), 5, false).unwrap();
+    write_all(&mut writer, &games, &[0, 1]);
+    writer.finish().unwrap();
+
+    let err = StreamingShardWriter::create(outputs(&dir), 6, true)
+        .err()
+        .expect("seed mismatch");
+    assert!(err.contains("seed 5"), "{err}");
+    // Without --resume the run starts over.
+    let writer = StreamingShardWriter::create(outputs(&dir), 6, false).unwrap();
+    assert!(writer.manifest().completed_games.is_empty());
+}
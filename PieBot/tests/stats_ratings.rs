@@ -0,0 +1,103 @@
+use piebot::stats::{logistic_elo, ml_ratings, normal_quantile, ResultMatrix};
+
+/// `points` of `games` for `player` against `opponent`, as wins and draws.
+fn add_score(
+    results: &mut ResultMatrix,
+    player: usize,
+    opponent: usize,
+    games: usize,
+    points: f64,
+) {
+    let wins = points.floor() as usize;
+    let draws = ((points - points.floor()) * 2.0).round() as usize;
+    for game in 0..games {
+        let score = if game < wins {
+            1.0
+        } else if game < wins + draws {
+            0.5
+        } else {
+            0.0
+        };
+        results.add_game(player, opponent, score);
+    }
+}
+
+#[test]
+fn normal_quantiles_match_tables() {
+    assert!((normal_quantile(0.975) - 1.959_964).abs() < 1e-6);
+    assert!((normal_quantile(0.5)).abs() < 1e-12);
+    assert!((normal_quantile(0.01) + 2.326_348).abs() < 1e-6);
+    assert!((normal_quantile(0.999) - 3.090_232).abs() < 1e-6);
+}
+
+#[test]
+fn two_players_rate_at_the_logistic_elo_of_their_score_with_a_binomial_error() {
+    let mut results = ResultMatrix::new(2);
+    add_score(&mut results, 1, 0, 100, 60.0);
+    let fit = ml_ratings(&results, 0.0).unwrap();
+    let elo = fit.relative_to(Some(0));
+    assert!((elo[1] - logistic_elo(0.6)).abs() < 1e-6, "{elo:?}");
+    assert!(
+        (fit.elo()[0] + fit.elo()[1]).abs() < 1e-9,
+        "centred on the pool mean"
+    );
+
+    // Delta method: d(elo)/d(score) = 400 / (ln 10 * p * (1 - p)).
+    let expected = 400.0 / std::f64::consts::LN_10 / (100.0f64 * 0.6 * 0.4).sqrt();
+    assert!((fit.std_error(1, Some(0)) - expected).abs() < 1e-6);
+    assert_eq!(fit.std_error(0, Some(0)), 0.0);
+    let (lower, upper) = fit.interval(1, Some(0), 0.95);
+    assert!((upper - lower - 2.0 * 1.959_964 * expected).abs() < 1e-3);
+}
+
+#[test]
+fn ratings_chain_through_common_opponents() {
+    // A scores 75% against B and B 75% against C; A and C never meet.
+    let mut results = ResultMatrix::new(3);
+    add_score(&mut results, 0, 1, 40, 30.0);
+    add_score(&mut results, 1, 2, 40, 30.0);
+    let fit = ml_ratings(&results, 0.0).unwrap();
+    let elo = fit.relative_to(Some(2));
+    assert!((elo[0] - 2.0 * logistic_elo(0.75)).abs() < 1e-6, "{elo:?}");
+    assert!(
+        fit.std_error(0, Some(2)) > fit.std_error(1, Some(2)),
+        "the indirect comparison is the less certain one"
+    );
+}
+
+#[test]
+fn more_games_shrink_the_interval_like_one_over_root_n() {
+    let fit_for = |scale: usize| {
+        let mut results = ResultMatrix::new(3);
+        add_score(&mut results, 0, 1, 20 * scale, 11.0 * scale as f64);
+        add_score(&mut results, 1, 2, 20 * scale, 12.0 * scale as f64);
+        add_score(&mut results, 0, 2, 20 * scale, 13.0 * scale as f64);
+        ml_ratings(&results, 0.0).unwrap()
+    };
+    let (small, large) = (fit_for(1), fit_for(4));
+    for player in 0..3 {
+        assert!((small.elo()[player] - large.elo()[player]).abs() < 1e-6);
+        let ratio = small.std_error(player, None) / large.std_error(player, None);
+        assert!((ratio - 2.0).abs() < 1e-6, "player {player}: {ratio}");
+    }
+}
+
+#[test]
+fn unrateable_tournaments_are_rejected_and_a_prior_bounds_perfect_scores() {
+    let mut disconnected = ResultMatrix::new(4);
+    add_score(&mut disconnected, 0, 1, 10, 5.0);
+    add_score(&mut disconnected, 2, 3, 10, 5.0);
+    assert!(ml_ratings(&disconnected, 1.0).is_err());
+
+    let mut idle = ResultMatrix::new(3);
+    add_score(&mut idle, 0, 1, 10, 5.0);
+    assert!(ml_ratings(&idle, 1.0).is_err());
+
+    let mut perfect = ResultMatrix::new(2);
+    add_score(&mut perfect, 0, 1, 10, 10.0);
+    assert!(ml_ratings(&perfect, 0.0).is_err());
+    let fit = ml_ratings(&perfect, 1.0).unwrap();
+    // 10.5 of 11 after the virtual draw.
+    let elo = fit.relative_to(Some(1));
+    assert!((elo[0] - logistic_elo(10.5 / 11.0)).abs() < 1e-6, "{elo:?}");
+}
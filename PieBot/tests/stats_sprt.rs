@@ -0,0 +1,131 @@
+use piebot::stats::{
+    logistic_elo, logistic_score, paired_bootstrap, Pentanomial, Sprt, SprtConfig, SprtDecision,
+};
+
+const CONFIG: SprtConfig = SprtConfig {
+    elo0: 0.0,
+    elo1: 5.0,
+    alpha: 0.05,
+    beta: 0.05,
+};
+
+fn pentanomial(counts: [u64; 5]) -> Pentanomial {
+    Pentanomial { counts }
+}
+
+#[test]
+fn logistic_elo_round_trips() {
+    assert_eq!(logistic_score(0.0), 0.5);
+    for elo in [-400.0, -35.5, 1.0, 120.0] {
+        assert!((logistic_elo(logistic_score(elo)) - elo).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn pair_scores_must_be_half_point_steps_within_a_pair() {
+    let mut counts = Pentanomial::default();
+    for points in [0.0, 0.5, 1.0, 1.5, 2.0, 2.0] {
+        counts.add_pair(points).unwrap();
+    }
+    assert_eq!(counts.counts, [1, 1, 1, 1, 2]);
+    assert!(counts.add_pair(0.3).is_err());
+    assert!(counts.add_pair(2.5).is_err());
+    assert!(counts.add_pair(-0.5).is_err());
+}
+
+#[test]
+fn llr_matches_the_normal_approximation_on_a_large_sample() {
+    // With thousands of pairs the GSPRT and its normal approximation
+    // N (s1 - s0) (2 mu - s0 - s1) / (2 var) agree closely.
+    let counts = pentanomial([300, 1500, 3000, 1650, 350]);
+    let pairs = counts.pairs() as f64;
+    let mu = counts.score().unwrap();
+    let var = counts
+        .counts
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| n as f64 / pairs * (i as f64 / 4.0 - mu).powi(2))
+        .sum::<f64>();
+    let (s0, s1) = (logistic_score(0.0), logistic_score(5.0));
+    let approx = pairs * (s1 - s0) * (2.0 * mu - s0 - s1) / (2.0 * var);
+    let llr = counts.llr(0.0, 5.0);
+    assert!(approx > 1.0, "sample should favour H1: {approx}");
+    assert!(
+        (llr - approx).abs() < 0.02 * approx.abs(),
+        "{llr} vs {approx}"
+    );
+}
+
+#[test]
+fn llr_sign_follows_the_score() {
+    assert_eq!(Pentanomial::default().llr(0.0, 5.0), 0.0);
+    assert!(pentanomial([10, 40, 100, 60, 20]).llr(0.0, 5.0) > 0.0);
+    assert!(pentanomial([20, 60, 100, 40, 10]).llr(0.0, 5.0) < 0.0);
+    // Only drawn pairs: the mean sits on H0 and the variance is all
+    // pseudo-count, so a long enough run is decisive against H1.
+    assert!(pentanomial([0, 0, 500, 0, 0]).llr(0.0, 5.0) < -3.0);
+}
+
+#[test]
+fn sprt_keeps_its_first_decision_and_full_trajectory() {
+    let mut sprt = Sprt::new(CONFIG).unwrap();
+    let (lower, upper) = CONFIG.bounds();
+    assert!((lower + 2.944).abs() < 1e-3 && (upper - 2.944).abs() < 1e-3);
+
+    let mut decided_at = None;
+    for pair in 1..=400u64 {
+        // Three out of every four pairs are won 1.5-0.5, the rest drawn.
+        let points = if pair % 4 == 0 { 1.0 } else { 1.5 };
+        if sprt.record_pair(points).unwrap().is_some() && decided_at.is_none() {
+            decided_at = Some(pair);
+        }
+    }
+    let stop = sprt.stop().expect("a clear edge ends the test");
+    assert_eq!(stop.decision, SprtDecision::H1);
+    assert_eq!(Some(stop.pairs), decided_at);
+    assert!(stop.llr >= upper);
+    assert!(stop.pairs < 400);
+    assert_eq!(sprt.trajectory().len(), 400);
+    assert_eq!(sprt.pentanomial().pairs(), 400);
+
+    let mut losing = Sprt::new(CONFIG).unwrap();
+    let decision = (0..400).find_map(|pair| {
+        losing
+            .record_pair(if pair % 2 == 0 { 0.5 } else { 1.0 })
+            .unwrap()
+    });
+    assert_eq!(decision, Some(SprtDecision::H0));
+}
+
+#[test]
+fn sprt_config_rejects_unusable_hypotheses() {
+    let with = |elo0, elo1, alpha, beta| SprtConfig {
+        elo0,
+        elo1,
+        alpha,
+        beta,
+    };
+    assert!(with(0.0, 5.0, 0.05, 0.05).validate().is_ok());
+    assert!(with(5.0, 5.0, 0.05, 0.05).validate().is_err());
+    assert!(with(0.0, f64::NAN, 0.05, 0.05).validate().is_err());
+    assert!(with(0.0, 5.0, 0.0, 0.05).validate().is_err());
+    assert!(with(0.0, 5.0, 0.05, 0.5).validate().is_err());
+    assert!(Sprt::new(with(1.0, 0.0, 0.05, 0.05)).is_err());
+}
+
+#[test]
+fn paired_bootstrap_is_reproducible_and_brackets_the_mean() {
+    let deltas = [1.0, 0.0, -1.0, 0.5, 1.0, 0.0, 2.0, -0.5, 0.0, 1.0];
+    let a = paired_bootstrap(&deltas, 5_000, 0.95, 7).unwrap();
+    let b = paired_bootstrap(&deltas, 5_000, 0.95, 7).unwrap();
+    assert_eq!(a, b);
+    assert!((a.mean - 0.4).abs() < 1e-12);
+    assert!(a.lower < a.mean && a.mean < a.upper);
+
+    let narrow = paired_bootstrap(&deltas, 5_000, 0.5, 7).unwrap();
+    assert!(narrow.lower > a.lower && narrow.upper < a.upper);
+
+    let flat = paired_bootstrap(&[0.5; 6], 100, 0.95, 1).unwrap();
+    assert_eq!((flat.lower, flat.upper), (0.5, 0.5));
+    assert!(paired_bootstrap(&[], 100, 0.95, 1).is_none());
+}
@@ -0,0 +1,173 @@
+use cozy_chess::{Board, Move};
+use piebot::search::alphabeta::{SearchParams, Searcher};
+use piebot::search::syzygy::{AmbiguousWdl, Tablebases, Wdl, TB_WIN_SCORE};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Directory holding the 3/4-man test tables; see tests/data/syzygy/README.md.
+fn tables_dir() -> PathBuf {
+    std::env::var_os("PIEBOT_SYZYGY_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/syzygy"))
+}
+
+/// The test tables; panics when any of `needed` is missing.
+fn tables(needed: &[&str]) -> Tablebases {
+    let dir = tables_dir();
+    for name in needed {
+        for ext in ["rtbw", "rtbz"] {
+            let path = dir.join(format!("{name}.{ext}"));
+            assert!(
+                path.is_file(),
+                "Syzygy table {} is missing; run scripts/fetch_syzygy_tables.sh",
+                path.display()
+            );
+        }
+    }
+    Tablebases::open(dir.to_str().unwrap()).expect("test tables load")
+}
+
+fn board(fen: &str) -> Board {
+    Board::from_fen(fen, false).unwrap()
+}
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut moves = Vec::new();
+    board.generate_moves(|ml| {
+        moves.extend(ml);
+        false
+    });
+    moves
+}
+
+fn params(depth: u32) -> SearchParams {
+    SearchParams {
+        depth,
+        use_tt: true,
+        order_captures: true,
+        use_history: true,
+        use_killers: true,
+        deterministic: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn open_rejects_paths_without_tables() {
+    let empty = std::env::temp_dir().join(format!("piebot-syzygy-empty-{}", std::process::id()));
+    std::fs::create_dir_all(&empty).unwrap();
+    let error = Tablebases::open(empty.to_str().unwrap()).err();
+    let _ = std::fs::remove_dir(&empty);
+    assert!(error.unwrap().contains("no Syzygy tables"));
+    assert!(Tablebases::open("/definitely/missing/piebot-syzygy").is_err());
+}
+
+#[test]
+fn wdl_probes_match_known_endgames() {
+    let tb = tables(&["KQvK", "KBvK", "KQvKR"]);
+    assert_eq!(tb.max_pieces(), 4);
+    let kqk = board("8/8/8/8/8/8/1Q6/K6k w - - 0 1");
+    assert_eq!(tb.probe_wdl_after_zeroing(&kqk), Some(Wdl::Win));
+    assert!(tb.probe_dtz(&kqk).unwrap() > 0);
+    let kqk_black = board("8/8/8/8/8/8/1Q6/K6k b - - 0 1");
+    assert_eq!(tb.probe_wdl_after_zeroing(&kqk_black), Some(Wdl::Loss));
+    assert!(tb.probe_dtz(&kqk_black).unwrap() < 0);
+    let kbk = board("8/8/8/8/8/8/1B6/K6k w - - 0 1");
+    assert_eq!(tb.probe_wdl_after_zeroing(&kbk), Some(Wdl::Draw));
+    // KQvKR is a win for the queen side in general.
+    let kqkr = board("8/8/8/4k3/8/8/3r4/KQ6 w - - 0 1");
+    assert_eq!(tb.probe_wdl_after_zeroing(&kqkr), Some(Wdl::Win));
+    // Too many pieces or castling rights: the tables cannot answer.
+    assert_eq!(tb.probe_wdl_after_zeroing(&Board::default()), None);
+    assert_eq!(tb.probe_wdl(&board("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1")), None);
+}
+
+#[test]
+fn pawn_and_minor_piece_tables_probe() {
+    let tb = tables(&["KPvK", "KBNvK"]);
+    // White wins by escorting the pawn; Black to move is stalemated.
+    let white_to_move = board("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1");
+    let black_to_move = board("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1");
+    assert_eq!(tb.probe_wdl_after_zeroing(&white_to_move), Some(Wdl::Win));
+    assert!(tb.probe_dtz(&white_to_move).unwrap() > 0);
+    assert_eq!(tb.probe_wdl_after_zeroing(&black_to_move), Some(Wdl::Draw));
+    // Bishop and knight mate, but only after a long drive to the corner.
+    let kbnk = board("8/8/8/4k3/8/8/8/KBN5 w - - 0 1");
+    assert_eq!(tb.probe_wdl_after_zeroing(&kbnk), Some(Wdl::Win));
+    assert!(tb.probe_dtz(&kbnk).unwrap() > 20);
+}
+
+#[test]
+fn root_filter_keeps_only_the_fastest_conversion() {
+    let tb = tables(&["KQvK"]);
+    // Qb8 mates at once; every other winning move takes longer.
+    let mate_in_one = board("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1");
+    let probe = tb
+        .rank_root_moves(&mate_in_one, &legal_moves(&mate_in_one))
+        .unwrap();
+    assert_eq!(probe.wdl, AmbiguousWdl::Win);
+    let moves: Vec<String> = probe.moves.iter().map(|mv| mv.to_string()).collect();
+    assert_eq!(moves, ["b1b8"]);
+}
+
+#[test]
+fn root_filter_counts_a_winning_capture_as_one_ply() {
+    let tb = tables(&["KQvK", "KQvKR"]);
+    // Qxd2 zeroes the clock; every quiet win still has a capture to come.
+    let kqkr = board("8/8/8/8/3k4/8/3r4/K2Q4 w - - 0 1");
+    let probe = tb.rank_root_moves(&kqkr, &legal_moves(&kqkr)).unwrap();
+    assert_eq!(probe.wdl, AmbiguousWdl::Win);
+    let moves: Vec<String> = probe.moves.iter().map(|mv| mv.to_string()).collect();
+    assert_eq!(moves, ["d1d2"]);
+    assert_eq!(probe.score, TB_WIN_SCORE - 1);
+}
+
+#[test]
+fn root_filter_respects_the_fifty_move_rule() {
+    let tb = tables(&["KRvK"]);
+    // KRvK from far away needs more than the two plies left on the clock,
+    // so the win is only a cursed one and the search must not claim it.
+    let late = board("8/8/8/4k3/8/8/8/R6K w - - 98 120");
+    let early = board("8/8/8/4k3/8/8/8/R6K w - - 0 120");
+    let early_probe = tb.rank_root_moves(&early, &legal_moves(&early)).unwrap();
+    assert_eq!(early_probe.wdl, AmbiguousWdl::Win);
+    assert!(early_probe.score > TB_WIN_SCORE - 1_000);
+    let late_probe = tb.rank_root_moves(&late, &legal_moves(&late)).unwrap();
+    assert!(
+        late_probe.wdl < AmbiguousWdl::MaybeWin,
+        "{:?}",
+        late_probe.wdl
+    );
+    assert!(late_probe.score.abs() <= 1);
+}
+
+#[test]
+fn search_reports_tablebase_scores_and_hits() {
+    let tb = tables(&["KQvK", "KQvKR"]);
+    let mut searcher = Searcher::default();
+    searcher.set_tablebases(Some(Arc::new(tb)));
+
+    // Qxd2 reaches KQvK; every non-capture keeps the 4-man win.
+    let kqkr = board("8/8/8/8/3k4/8/3r4/K2Q4 w - - 0 1");
+    let result = searcher.search_with_params(&kqkr, params(4));
+    assert!(
+        result.score_cp > TB_WIN_SCORE - 1_000,
+        "{}",
+        result.score_cp
+    );
+    assert!(searcher.tb_hits() > 0);
+
+    // A tablebase draw is reported as one, whatever the evaluation says.
+    let kbk = board("8/8/8/8/8/8/1B6/K6k w - - 0 1");
+    let draw = searcher.search_with_params(&kbk, params(3));
+    assert_eq!(draw.score_cp, 0);
+}
+
+#[test]
+fn search_without_tables_counts_no_hits() {
+    let mut searcher = Searcher::default();
+    let kqk = board("8/8/8/8/8/8/1Q6/K6k w - - 0 1");
+    let result = searcher.search_with_params(&kqk, params(3));
+    assert!(result.bestmove.is_some());
+    assert_eq!(searcher.tb_hits(), 0);
+}
@@ -20,3 +20,212 @@ fn movetime_returns_quickly_with_move() {
         elapsed
     );
 }
+
+#[test]
+fn clock_limits_keep_soft_below_hard_and_hard_inside_the_clock() {
+    use piebot::search::time_manager::TimeLimits;
+    let b = Board::default();
+    for (remaining, inc, mtg) in [
+        (60_000, 0, None),
+        (60_000, 1_000, None),
+        (1_000, 0, None),
+        (60_000, 0, Some(30)),
+        (5_000, 2_000, Some(2)),
+        (3_000, 0, Some(1)),
+    ] {
+        let limits = TimeLimits::from_clock(remaining, inc, mtg, &b);
+        assert!(limits.soft <= limits.hard, "{limits:?}");
+        // Without an increment to win time back, at most half the clock.
+        let cap = if mtg == Some(1) || inc > 0 {
+            remaining - 10
+        } else {
+            remaining / 2
+        };
+        assert!(
+            limits.hard <= Duration::from_millis(cap),
+            "hard limit {limits:?} leaves too little of {remaining} ms"
+        );
+    }
+    let limits = TimeLimits::from_clock(60_000, 0, Some(30), &b);
+    assert_eq!(limits.soft, Duration::from_millis(1_999));
+    assert_eq!(limits.hard, Duration::from_millis(5_997));
+}
+
+#[test]
+fn stable_best_move_stops_before_the_soft_target() {
+    use piebot::search::time_manager::{TimeLimits, TimeManager};
+    let limits = TimeLimits {
+        soft: Duration::from_millis(1_000),
+        hard: Duration::from_millis(3_000),
+    };
+    let mut tm = TimeManager::new(limits, 20);
+    assert!(!tm.should_stop(Duration::from_secs(10)), "no iteration yet");
+    for depth in 1..=3 {
+        tm.on_iteration(depth, Some("e2e4"), 30);
+    }
+    assert_eq!(tm.soft_target(), limits.soft);
+    tm.on_iteration(4, Some("e2e4"), 30);
+    assert_eq!(tm.soft_target(), Duration::from_millis(500));
+    assert!(!tm.should_stop(Duration::from_millis(250)));
+    assert!(tm.should_stop(Duration::from_millis(300)));
+}
+
+#[test]
+fn score_drop_and_late_change_extend_up_to_the_hard_limit() {
+    use piebot::search::time_manager::{TimeLimits, TimeManager};
+    let limits = TimeLimits {
+        soft: Duration::from_millis(1_000),
+        hard: Duration::from_millis(3_000),
+    };
+    let mut tm = TimeManager::new(limits, 20);
+    for depth in 1..=5 {
+        tm.on_iteration(depth, Some("e2e4"), 40);
+    }
+    tm.on_iteration(6, Some("e2e4"), -35);
+    let dropped = tm.soft_target();
+    assert!(dropped > limits.soft, "{dropped:?}");
+    assert!(!tm.should_stop(Duration::from_millis(700)));
+
+    tm.on_iteration(7, Some("d2d4"), -300);
+    assert_eq!(tm.soft_target(), limits.hard);
+    assert!(tm.should_stop(Duration::from_millis(3_000)));
+}
+
+#[test]
+fn unresolved_fail_low_runs_to_the_hard_limit() {
+    use piebot::search::time_manager::{TimeLimits, TimeManager};
+    let limits = TimeLimits {
+        soft: Duration::from_millis(100),
+        hard: Duration::from_millis(400),
+    };
+    let mut tm = TimeManager::new(limits, 20);
+    tm.on_iteration(1, Some("e2e4"), 0);
+    assert_eq!(tm.iteration_limit(), limits.soft);
+    tm.on_fail_low();
+    assert_eq!(tm.iteration_limit(), limits.hard);
+    tm.on_iteration(2, Some("e2e4"), 0);
+    assert_eq!(tm.iteration_limit(), limits.soft);
+}
+
+#[test]
+fn clock_search_stays_between_soft_and_hard_bounds() {
+    use piebot::search::alphabeta::{SearchParams, Searcher};
+    use piebot::search::time_manager::TimeLimits;
+    let b = Board::default();
+    let mut searcher = Searcher::default();
+    let params = SearchParams {
+        use_tt: true,
+        order_captures: true,
+        use_history: true,
+        time: Some(TimeLimits {
+            soft: Duration::from_millis(40),
+            hard: Duration::from_millis(5_000),
+        }),
+        ..Default::default()
+    };
+    let t0 = Instant::now();
+    let res = searcher.search_with_params(&b, params);
+    let elapsed = t0.elapsed();
+    assert!(res.bestmove.is_some());
+    assert!(res.depth >= 1);
+    assert!(
+        elapsed < Duration::from_millis(1_000),
+        "ran toward the hard limit instead of the soft target: {elapsed:?}"
+    );
+
+    let capped = SearchParams {
+        time: Some(TimeLimits {
+            soft: Duration::from_secs(60),
+            hard: Duration::from_millis(50),
+        }),
+        ..params
+    };
+    let t0 = Instant::now();
+    let res = searcher.search_with_params(&b, capped);
+    assert!(res.bestmove.is_some());
+    assert!(
+        t0.elapsed() < Duration::from_millis(300),
+        "search exceeded the hard limit: {:?}",
+        t0.elapsed()
+    );
+}
+
+#[test]
+fn single_legal_move_returns_after_one_iteration() {
+    use piebot::search::alphabeta::{SearchParams, Searcher};
+    use piebot::search::time_manager::TimeLimits;
+    // The unprotected queen on g7 checks the h8 king; Kxg7 is the only move.
+    let b = Board::from_fen("7k/6Q1/8/8/8/8/8/K7 b - - 0 1", false).unwrap();
+    let mut searcher = Searcher::default();
+    let params = SearchParams {
+        use_tt: true,
+        time: Some(TimeLimits {
+            soft: Duration::from_secs(5),
+            hard: Duration::from_secs(10),
+        }),
+        ..Default::default()
+    };
+    let t0 = Instant::now();
+    let res = searcher.search_with_params(&b, params);
+    assert_eq!(res.bestmove.as_deref(), Some("h8g7"));
+    assert_eq!(res.depth, 1);
+    assert!(t0.elapsed() < Duration::from_secs(1), "{:?}", t0.elapsed());
+}
+
+#[test]
+fn time_controls_parse_cutechess_notation() {
+    use piebot::search::time_manager::TimeControl;
+    let tc: TimeControl = "60+0.5".parse().unwrap();
+    assert_eq!(
+        tc,
+        TimeControl {
+            base_ms: 60_000,
+            increment_ms: 500,
+            moves_per_period: None,
+        }
+    );
+    assert_eq!(tc.to_string(), "60+0.5");
+    let tc: TimeControl = "40/90".parse().unwrap();
+    assert_eq!(
+        (tc.moves_per_period, tc.base_ms, tc.increment_ms),
+        (Some(40), 90_000, 0)
+    );
+    assert_eq!(tc.to_string(), "40/90");
+    let tc: TimeControl = "40/90+30".parse().unwrap();
+    assert_eq!(tc.increment_ms, 30_000);
+    assert_eq!("10+0".parse::<TimeControl>().unwrap().to_string(), "10+0");
+    for bad in ["60", "0+1", "x+1", "60+-1", "0/60", "/60", "40/", "60+inf"] {
+        assert!(bad.parse::<TimeControl>().is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn clocks_charge_wall_time_and_refill_periods() {
+    use piebot::search::time_manager::{Clock, TimeLimits};
+    let b = Board::default();
+    let mut clock = Clock::new("1+0.1".parse().unwrap());
+    assert_eq!(
+        clock.limits(&b),
+        TimeLimits::from_clock(1_000, 100, None, &b)
+    );
+    assert!(clock.charge(Duration::from_millis(300)));
+    assert_eq!(clock.remaining(), Duration::from_millis(800));
+    // Using exactly what is left is still in time; one millisecond more flags.
+    assert!(clock.charge(Duration::from_millis(800)));
+    assert_eq!(clock.remaining(), Duration::from_millis(100));
+    assert!(!clock.charge(Duration::from_millis(101)));
+    assert_eq!(clock.remaining(), Duration::ZERO);
+
+    let mut clock = Clock::new("2/1".parse().unwrap());
+    assert_eq!(clock.moves_to_go(), Some(2));
+    assert!(clock.charge(Duration::from_millis(400)));
+    assert_eq!(clock.moves_to_go(), Some(1));
+    assert_eq!(
+        clock.limits(&b),
+        TimeLimits::from_clock(600, 0, Some(1), &b)
+    );
+    assert!(clock.charge(Duration::from_millis(500)));
+    // A new period starts with the base added to what was left.
+    assert_eq!(clock.moves_to_go(), Some(2));
+    assert_eq!(clock.remaining(), Duration::from_millis(1_100));
+}
@@ -0,0 +1,220 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!(
+        "piebot_tournament_{name}_{}_{nanos}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn mock(name: &str, extra: &str) -> String {
+    let mut spec = format!("name={name},cmd={},hash=16", env!("CARGO_BIN_EXE_mock_uci"));
+    for arg in extra.split_whitespace() {
+        spec.push_str(&format!(",arg={arg}"));
+    }
+    spec
+}
+
+fn run_tournament(players: &[&str], extra: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_tournament"));
+    for player in players {
+        command.args(["--player", player]);
+    }
+    command.args(extra).output().expect("run tournament")
+}
+
+fn read_report(path: &Path) -> serde_json::Value {
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "tournament failed:\n{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn round_robin_rates_every_player_and_resumes_with_more_games() {
+    let dir = temp_dir("round_robin");
+    let json = dir.join("report.json");
+    let state = dir.join("state.json");
+    // alpha's score claims win every game it plays; beta and gamma never
+    // agree with each other, so their games run to the ply limit.
+    let players = [
+        mock("alpha", "--score-cp 2000"),
+        mock("beta", "--score-cp -2000"),
+        mock("gamma", "--score-cp -2000"),
+    ];
+    let players: Vec<&str> = players.iter().map(String::as_str).collect();
+    let common = [
+        "--tc",
+        "5+0.05",
+        "--max-plies",
+        "30",
+        "--resign-cp",
+        "900",
+        "--resign-plies",
+        "4",
+        "--anchor",
+        "beta",
+        "--state-file",
+        state.to_str().unwrap(),
+        "--json-out",
+        json.to_str().unwrap(),
+    ];
+    let output = run_tournament(
+        &players,
+        &[&["--games-per-pairing", "2"][..], &common].concat(),
+    );
+    assert_success(&output);
+    let report = read_report(&json);
+    assert_eq!(report["schema"], "piebot-tournament-v1");
+    assert_eq!(report["games"], 6);
+    assert_eq!(report["head_to_head"].as_array().unwrap().len(), 3);
+
+    let output = run_tournament(
+        &players,
+        &[&["--games-per-pairing", "4"][..], &common].concat(),
+    );
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("resuming: 6 of 12 games already played"),
+        "{stdout}"
+    );
+    let report = read_report(&json);
+    assert_eq!(report["games"], 12);
+
+    let ratings = report["ratings"].as_array().unwrap();
+    assert_eq!(ratings.len(), 3);
+    assert_eq!(ratings[0]["name"], "alpha");
+    assert_eq!(ratings[0]["rank"], 1);
+    assert_eq!(ratings[0]["score_points"], 8.0);
+    let beta = ratings.iter().find(|r| r["name"] == "beta").unwrap();
+    assert_eq!(beta["elo"], 0.0);
+    assert_eq!(beta["config"]["hash"], 16);
+    let alpha_ci = ratings[0]["elo_95_ci"].as_array().unwrap();
+    let alpha_elo = ratings[0]["elo"].as_f64().unwrap();
+    assert!(alpha_ci[0].as_f64().unwrap() < alpha_elo && alpha_elo < alpha_ci[1].as_f64().unwrap());
+
+    for row in report["head_to_head"].as_array().unwrap() {
+        assert_eq!(row["games"], 4);
+        assert_eq!(row["complete_pairs"], 2);
+        if row["player"] == "alpha" {
+            assert_eq!(row["wins"], 4);
+            assert_eq!(row["score_rate"], 1.0);
+            assert!(
+                row["elo_difference"].is_null(),
+                "a perfect score has no finite Elo"
+            );
+        } else {
+            assert_eq!(row["draws"], 4);
+            assert_eq!(row["elo_difference"], 0.0);
+        }
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn a_gauntlet_only_pairs_the_first_player() {
+    let dir = temp_dir("gauntlet");
+    let json = dir.join("report.json");
+    let output = run_tournament(
+        &[
+            &mock("hero", "--random-seed 1"),
+            &mock("one", "--random-seed 2"),
+            &mock("two", "--random-seed 3"),
+        ],
+        &[
+            "--format",
+            "gauntlet",
+            "--games-per-pairing",
+            "2",
+            "--tc",
+            "5+0.05",
+            "--max-plies",
+            "20",
+            "--json-out",
+            json.to_str().unwrap(),
+        ],
+    );
+    assert_success(&output);
+    let report = read_report(&json);
+    assert_eq!(report["format"], "gauntlet");
+    assert_eq!(report["games"], 4);
+    for game in report["game_results"].as_array().unwrap() {
+        assert!(game["white"] == "hero" || game["black"] == "hero");
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn piebot_configurations_map_to_uci_options() {
+    let dir = temp_dir("piebot");
+    let json = dir.join("report.json");
+    let net = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/arch_v2_gold.nnue");
+    let output = run_tournament(
+        &[
+            &format!(
+                "name=pst,cmd={},eval=pst,hash=8,threads=1",
+                env!("CARGO_BIN_EXE_uci")
+            ),
+            &format!(
+                "name=gold,cmd={},eval=nnue,quant={net},blend=50,hash=8",
+                env!("CARGO_BIN_EXE_uci")
+            ),
+        ],
+        &[
+            "--games-per-pairing",
+            "2",
+            "--tc",
+            "2+0.02",
+            "--max-plies",
+            "16",
+            "--json-out",
+            json.to_str().unwrap(),
+        ],
+    );
+    assert_success(&output);
+    let report = read_report(&json);
+    let gold = report["ratings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["name"] == "gold")
+        .unwrap();
+    assert_eq!(gold["config"]["blend"], 50);
+    assert!(gold["config"]["net_hash"]
+        .as_str()
+        .is_some_and(|h| !h.is_empty()));
+    for game in report["game_results"].as_array().unwrap() {
+        let termination = game["termination"].as_str().unwrap();
+        assert!(
+            !matches!(termination, "crash" | "illegal_move" | "time_forfeit"),
+            "unexpected fault: {termination}"
+        );
+    }
+
+    let output = run_tournament(
+        &[
+            &format!("name=a,cmd={}", env!("CARGO_BIN_EXE_uci")),
+            "name=b,eval=nnue,quant=/nonexistent/net.nnue",
+        ],
+        &["--games-per-pairing", "2"],
+    );
+    assert!(
+        !output.status.success(),
+        "a missing net fails before any game"
+    );
+    let _ = std::fs::remove_dir_all(dir);
+}
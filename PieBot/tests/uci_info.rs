@@ -0,0 +1,112 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Run `commands` against a fresh engine and collect the `info depth` lines
+/// and the `bestmove` line of the search they start.
+fn run_search(commands: &[&str]) -> (Vec<String>, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_uci"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("start UCI engine");
+    let mut stdin = child.stdin.take().expect("piped stdin");
+    let stdout = child.stdout.take().expect("piped stdout");
+    let (tx, rx) = mpsc::channel();
+    let reader = std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    for command in commands {
+        writeln!(stdin, "{command}").unwrap();
+    }
+    stdin.flush().unwrap();
+
+    let mut infos = Vec::new();
+    let mut bestmove = None;
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(20) {
+        match rx.recv_timeout(Duration::from_millis(50)) {
+            Ok(line) if line.starts_with("bestmove ") => {
+                bestmove = Some(line);
+                break;
+            }
+            Ok(line) if line.starts_with("info depth ") => infos.push(line),
+            Ok(_) => {}
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    writeln!(stdin, "quit").ok();
+    stdin.flush().ok();
+    let _ = child.wait();
+    drop(stdin);
+    let _ = reader.join();
+
+    (infos, bestmove.expect("search finished with a bestmove"))
+}
+
+fn field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let mut tokens = line.split_whitespace();
+    tokens.find(|&t| t == name)?;
+    tokens.next()
+}
+
+#[test]
+fn go_streams_one_info_line_per_iteration_before_bestmove() {
+    let (infos, bestmove) = run_search(&["position startpos moves e2e4 e7e5", "go depth 4"]);
+
+    for depth in 1..=4 {
+        assert!(
+            infos
+                .iter()
+                .any(|line| line.starts_with(&format!("info depth {depth} "))),
+            "no info line for depth {depth}: {infos:?}"
+        );
+    }
+    let last = infos.last().unwrap();
+    for field in [
+        " seldepth ",
+        " multipv 1 ",
+        " score cp ",
+        " nodes ",
+        " nps ",
+        " time ",
+        " hashfull ",
+        " pv ",
+    ] {
+        assert!(last.contains(field), "missing {field:?} in {last}");
+    }
+    let best = bestmove.split_whitespace().nth(1).unwrap();
+    let pv_head = last.split(" pv ").nth(1).unwrap().split_whitespace().next();
+    assert_eq!(pv_head, Some(best));
+}
+
+#[test]
+fn multipv_option_reports_one_ranked_line_per_move() {
+    let (infos, bestmove) = run_search(&[
+        "setoption name MultiPV value 3",
+        "position startpos",
+        "go depth 3",
+    ]);
+
+    let final_lines: Vec<&String> = infos
+        .iter()
+        .filter(|line| field(line, "depth") == Some("3"))
+        .collect();
+    let ranks: Vec<_> = final_lines
+        .iter()
+        .map(|line| field(line, "multipv").unwrap())
+        .collect();
+    assert!(ranks.ends_with(&["1", "2", "3"]), "ranks {ranks:?}");
+
+    let tail = &final_lines[final_lines.len() - 3..];
+    let heads: Vec<_> = tail.iter().map(|line| field(line, "pv").unwrap()).collect();
+    assert_eq!(Some(heads[0]), bestmove.split_whitespace().nth(1));
+    assert!(heads[0] != heads[1] && heads[1] != heads[2] && heads[0] != heads[2]);
+}
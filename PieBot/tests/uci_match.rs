@@ -0,0 +1,316 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!(
+        "piebot_match_{name}_{}_{nanos}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn mock(name: &str, extra: &str) -> String {
+    let mut spec = format!("name={name},cmd={}", env!("CARGO_BIN_EXE_mock_uci"));
+    spec.push_str(&format!(",arg=--name,arg={name}"));
+    for arg in extra.split_whitespace() {
+        spec.push_str(&format!(",arg={arg}"));
+    }
+    spec
+}
+
+fn run_match(engines: [&str; 2], extra: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_match"))
+        .args(["--engine", engines[0], "--engine", engines[1]])
+        .args(extra)
+        .output()
+        .expect("run match")
+}
+
+fn read_summary(path: &Path) -> serde_json::Value {
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "match failed:\n{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn openings_are_replayed_with_colors_reversed_and_every_game_reaches_the_pgn() {
+    let dir = temp_dir("pairs");
+    let openings = dir.join("openings.epd");
+    std::fs::write(
+        &openings,
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq -\n\
+         r3k2r/pppq1ppp/2np1n2/2b1p3/2B1P3/2NP1N2/PPPQ1PPP/R3K2R w KQkq - 0 8\n",
+    )
+    .unwrap();
+    let json = dir.join("summary.json");
+    let pgn = dir.join("games.pgn");
+    let output = run_match(
+        [
+            &mock("alpha", "--random-seed 1"),
+            &mock("beta", "--random-seed 2"),
+        ],
+        &[
+            "--games",
+            "4",
+            "--concurrency",
+            "2",
+            "--tc",
+            "5+0.05",
+            "--max-plies",
+            "30",
+            "--openings-file",
+            openings.to_str().unwrap(),
+            "--pgn-out",
+            pgn.to_str().unwrap(),
+            "--json-out",
+            json.to_str().unwrap(),
+        ],
+    );
+    assert_success(&output);
+
+    let summary = read_summary(&json);
+    assert_eq!(summary["games"], 4);
+    let results = summary["game_results"].as_array().unwrap();
+    let colors: Vec<(u64, &str, &str)> = results
+        .iter()
+        .map(|g| {
+            (
+                g["opening"].as_u64().unwrap(),
+                g["white"].as_str().unwrap(),
+                g["black"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        colors,
+        vec![
+            (0, "alpha", "beta"),
+            (0, "beta", "alpha"),
+            (1, "alpha", "beta"),
+            (1, "beta", "alpha"),
+        ]
+    );
+    let wins = summary["wins"].as_u64().unwrap();
+    let draws = summary["draws"].as_u64().unwrap();
+    let losses = summary["losses"].as_u64().unwrap();
+    assert_eq!(wins + draws + losses, 4);
+    let pairs: u64 = summary["pentanomial"]["counts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.as_u64().unwrap())
+        .sum();
+    assert_eq!(pairs, 2);
+
+    let pgn = std::fs::read_to_string(&pgn).unwrap();
+    assert_eq!(pgn.matches("[Event \"PieBot match\"]").count(), 4);
+    assert_eq!(pgn.matches("[White \"beta\"]").count(), 2);
+    assert!(
+        pgn.contains("[SetUp \"1\"]"),
+        "suite openings are not the start position"
+    );
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn crashes_hangs_and_illegal_moves_lose_and_the_engine_is_restarted() {
+    let dir = temp_dir("faults");
+    let json = dir.join("summary.json");
+    // Each process crashes on its second `go`, so beta only finishes both
+    // games if it is restarted after the first crash.
+    let output = run_match(
+        [&mock("alpha", ""), &mock("beta", "--crash-on-go 2")],
+        &[
+            "--games",
+            "2",
+            "--tc",
+            "2+0",
+            "--json-out",
+            json.to_str().unwrap(),
+        ],
+    );
+    assert_success(&output);
+    let summary = read_summary(&json);
+    assert_eq!(summary["wins"], 2);
+    assert_eq!(summary["faults"]["beta"]["crash"], 2);
+    assert_eq!(summary["terminations"]["crash"], 2);
+
+    let output = run_match(
+        [&mock("alpha", ""), &mock("beta", "--hang-on-go 1")],
+        &[
+            "--games",
+            "2",
+            "--tc",
+            "0.3+0",
+            "--timeout-margin-ms",
+            "100",
+            "--json-out",
+            json.to_str().unwrap(),
+        ],
+    );
+    assert_success(&output);
+    let summary = read_summary(&json);
+    assert_eq!(summary["wins"], 2);
+    assert_eq!(summary["faults"]["beta"]["time_forfeit"], 2);
+
+    let output = run_match(
+        [&mock("alpha", "--illegal-on-go 1"), &mock("beta", "")],
+        &[
+            "--games",
+            "1",
+            "--tc",
+            "2+0",
+            "--json-out",
+            json.to_str().unwrap(),
+        ],
+    );
+    assert_success(&output);
+    let summary = read_summary(&json);
+    assert_eq!(summary["losses"], 1);
+    assert_eq!(summary["faults"]["alpha"]["illegal_move"], 1);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn reported_scores_drive_resign_adjudication() {
+    let dir = temp_dir("resign");
+    let json = dir.join("summary.json");
+    // Both engines agree alpha is winning, whichever colour it has.
+    let output = run_match(
+        [
+            &mock("alpha", "--score-cp 2000"),
+            &mock("beta", "--score-cp -2000"),
+        ],
+        &[
+            "--games",
+            "2",
+            "--tc",
+            "2+0",
+            "--resign-cp",
+            "900",
+            "--resign-plies",
+            "4",
+            "--json-out",
+            json.to_str().unwrap(),
+        ],
+    );
+    assert_success(&output);
+    let summary = read_summary(&json);
+    assert_eq!(summary["wins"], 2);
+    assert_eq!(summary["terminations"]["resigned"], 2);
+    for game in summary["game_results"].as_array().unwrap() {
+        assert_eq!(game["plies"], 4);
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn a_state_file_resumes_and_refuses_changed_settings() {
+    let dir = temp_dir("resume");
+    let state = dir.join("state.json");
+    let json = dir.join("summary.json");
+    let engines = [
+        mock("alpha", "--random-seed 3"),
+        mock("beta", "--random-seed 4"),
+    ];
+    let engines = [engines[0].as_str(), engines[1].as_str()];
+    let common = [
+        "--tc",
+        "5+0.05",
+        "--max-plies",
+        "20",
+        "--state-file",
+        state.to_str().unwrap(),
+        "--json-out",
+        json.to_str().unwrap(),
+    ];
+    let output = run_match(engines, &[&["--games", "2"][..], &common].concat());
+    assert_success(&output);
+    let first = read_summary(&json)["game_results"].clone();
+
+    let output = run_match(engines, &[&["--games", "4"][..], &common].concat());
+    assert_success(&output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("resuming: 2 of 4 games already played"),
+        "{stdout}"
+    );
+    assert_eq!(
+        stdout.matches("game ").count(),
+        2,
+        "only the new games are played"
+    );
+    let second = read_summary(&json);
+    assert_eq!(second["games"], 4);
+    assert_eq!(second["game_results"][0], first[0]);
+    assert_eq!(second["game_results"][1], first[1]);
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&state).unwrap()).unwrap();
+    assert_eq!(saved["games"].as_array().unwrap().len(), 4);
+
+    let output = run_match(
+        engines,
+        &[
+            "--games",
+            "4",
+            "--tc",
+            "6+0.05",
+            "--max-plies",
+            "20",
+            "--state-file",
+            state.to_str().unwrap(),
+        ],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("different match configuration"));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn piebot_plays_itself_through_the_uci_protocol() {
+    let dir = temp_dir("piebot");
+    let json = dir.join("summary.json");
+    let pgn = dir.join("games.pgn");
+    let uci = env!("CARGO_BIN_EXE_uci");
+    let output = run_match(
+        [
+            &format!("name=pie-a,cmd={uci},option.Hash=8"),
+            &format!("name=pie-b,cmd={uci},option.Hash=8"),
+        ],
+        &[
+            "--games",
+            "2",
+            "--tc",
+            "2+0.02",
+            "--max-plies",
+            "24",
+            "--json-out",
+            json.to_str().unwrap(),
+            "--pgn-out",
+            pgn.to_str().unwrap(),
+        ],
+    );
+    assert_success(&output);
+    let summary = read_summary(&json);
+    assert_eq!(summary["games"], 2);
+    for game in summary["game_results"].as_array().unwrap() {
+        let termination = game["termination"].as_str().unwrap();
+        assert!(
+            !matches!(termination, "crash" | "illegal_move" | "time_forfeit"),
+            "unexpected fault: {termination}"
+        );
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,97 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    rx: mpsc::Receiver<String>,
+}
+
+impl Engine {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_uci"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("start UCI engine");
+        let stdin = child.stdin.take().expect("piped stdin");
+        let stdout = child.stdout.take().expect("piped stdout");
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { child, stdin, rx }
+    }
+
+    fn send(&mut self, command: &str) {
+        writeln!(self.stdin, "{command}").unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    /// The next `bestmove` line printed within `timeout`, if any.
+    fn bestmove_within(&self, timeout: Duration) -> Option<String> {
+        let started = Instant::now();
+        while let Some(left) = timeout.checked_sub(started.elapsed()) {
+            match self.rx.recv_timeout(left) {
+                Ok(line) if line.starts_with("bestmove") => return Some(line),
+                Ok(_) => {}
+                Err(_) => return None,
+            }
+        }
+        None
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit");
+        let _ = self.stdin.flush();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn ponder_search_waits_for_ponderhit_then_uses_the_clock() {
+    let mut engine = Engine::start();
+    engine.send("setoption name Ponder value true");
+    engine.send("position startpos moves e2e4 e7e5");
+    engine.send("go ponder wtime 6000 btime 6000 movestogo 20");
+
+    assert_eq!(engine.bestmove_within(Duration::from_millis(800)), None);
+
+    let hit = Instant::now();
+    engine.send("ponderhit");
+    let bestmove = engine
+        .bestmove_within(Duration::from_secs(5))
+        .expect("bestmove after ponderhit");
+    // 6000 ms over 20 moves is about 300 ms from ponderhit onwards.
+    assert!(
+        hit.elapsed() < Duration::from_secs(2),
+        "{:?}",
+        hit.elapsed()
+    );
+    let tokens: Vec<_> = bestmove.split_whitespace().collect();
+    assert_eq!(tokens.len(), 4, "{bestmove}");
+    assert_eq!(tokens[2], "ponder");
+}
+
+#[test]
+fn finished_ponder_search_holds_bestmove_until_stop() {
+    let mut engine = Engine::start();
+    engine.send("position startpos");
+    engine.send("go ponder depth 2");
+
+    assert_eq!(engine.bestmove_within(Duration::from_millis(500)), None);
+    engine.send("stop");
+    let bestmove = engine
+        .bestmove_within(Duration::from_secs(5))
+        .expect("bestmove after stop");
+    // Without the Ponder option the reply is not suggested.
+    assert_eq!(bestmove.split_whitespace().count(), 2, "{bestmove}");
+}
@@ -0,0 +1,35 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+# Fetch the published Syzygy tables that PieBot/tests/syzygy.rs probes.
+#
+# Outputs:
+# - PieBot/tests/data/syzygy/<table>.rtbw and <table>.rtbz for KQvK, KRvK,
+#   KBvK, KNvK, KPvK, KQvKR and KBNvK
+#
+# Usage:
+#   scripts/fetch_syzygy_tables.sh
+
+ROOT_DIR="$(cd "$(dirname "$0")/.." && pwd)"
+OUT_DIR="$ROOT_DIR/PieBot/tests/data/syzygy"
+URL="https://tablebase.lichess.ovh/tables/standard/3-4-5"
+TABLES=(KQvK KRvK KBvK KNvK KPvK KQvKR KBNvK)
+
+mkdir -p "$OUT_DIR"
+
+for table in "${TABLES[@]}"; do
+  for ext in rtbw rtbz; do
+    file="$table.$ext"
+    echo "[fetch] $file"
+    if command -v curl >/dev/null 2>&1; then
+      curl -fL "$URL/$file" -o "$OUT_DIR/$file"
+    elif command -v wget >/dev/null 2>&1; then
+      wget -O "$OUT_DIR/$file" "$URL/$file"
+    else
+      echo "Error: neither curl nor wget is installed. Please install one and retry." >&2
+      exit 1
+    fi
+  done
+done
+
+echo "[done] Tables written under $OUT_DIR"